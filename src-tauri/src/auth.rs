@@ -1,7 +1,24 @@
-use crate::db::DbState;
+// Local user accounts: argon2id password hashes, session tokens that commands
+// acting for a user check, and recovery codes for resetting a password.
+#[cfg(feature = "gui")]
+use crate::db::DbPool;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::Engine;
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "gui")]
 use tauri::State;
 
+/// How long a session token stays valid.
+pub const SESSION_DAYS: i64 = 30;
+
+// Checked when the username is unknown, so a failed login takes as long
+// whether or not the account exists. Same parameters as `hash_password`.
+const DUMMY_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$wHsRCl73CLBXf1M+chZ64A$rWVwAs9Q/XL19ctC8636RPn4tejgF0J4Xjg0X4BsK18";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserInfo {
     pub id: i64,
@@ -9,19 +26,81 @@ pub struct UserInfo {
     pub email: String,
 }
 
+/// A logged-in user. `token` is passed to every command acting for them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Session {
+    pub token: String,
+    pub user: UserInfo,
+    pub expires_at: String,
+    /// Code for [`reset_user_password`], set only when a new one was issued;
+    /// it is not stored in a readable form and must be shown to the user now
+    pub recovery_code: Option<String>,
+}
+
+// Hashing takes a moment, so the account commands run off the main thread
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub fn register(
-    state: State<DbState>,
+pub async fn register(
+    state: State<'_, DbPool>,
     username: String,
     password: String,
     email: String,
+) -> Result<Session, String> {
+    state.run(move |conn| register_user(conn, &username, &password, &email)).await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn login(state: State<'_, DbPool>, username: String, password: String) -> Result<Session, String> {
+    state.run(move |conn| login_user(conn, &username, &password)).await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn logout(state: State<'_, DbPool>, token: String) -> Result<(), String> {
+    state.run(move |conn| logout_session(conn, &token)).await
+}
+
+// Restores a remembered session at startup
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn current_user(state: State<'_, DbPool>, token: String) -> Result<UserInfo, String> {
+    state.run(move |conn| session_user(conn, &token)).await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn update_profile(
+    state: State<'_, DbPool>,
+    token: String,
+    new_username: Option<String>,
+    new_password: Option<String>,
 ) -> Result<UserInfo, String> {
+    state
+        .run(move |conn| update_user(conn, &token, new_username.as_deref(), new_password.as_deref()))
+        .await
+}
+
+// Returns the new recovery code
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn reset_password(
+    state: State<'_, DbPool>,
+    username: String,
+    recovery_code: String,
+    new_password: String,
+) -> Result<String, String> {
+    state
+        .run(move |conn| reset_user_password(conn, &username, &recovery_code, &new_password))
+        .await
+}
+
+/// Creates an account and logs it in.
+pub fn register_user(conn: &Connection, username: &str, password: &str, email: &str) -> Result<Session, String> {
     if username.trim().is_empty() || password.trim().is_empty() || email.trim().is_empty() {
         return Err("All fields are required".to_string());
     }
 
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-
     // Check if username already exists
     let exists: bool = conn
         .query_row(
@@ -35,62 +114,133 @@ pub fn register(
         return Err("Username already exists".to_string());
     }
 
+    let recovery_code = new_recovery_code();
     conn.execute(
-        "INSERT INTO users (username, password, email) VALUES (?1, ?2, ?3)",
-        rusqlite::params![username, password, email],
+        "INSERT INTO users (username, password, email, recovery_code) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![username, hash_password(password)?, email, hash_password(&normalize_code(&recovery_code))?],
     )
     .map_err(|e| e.to_string())?;
 
-    let id = conn.last_insert_rowid();
-    println!("[auth] User registered: {} (id: {})", username, id);
-
-    Ok(UserInfo {
-        id,
-        username,
-        email,
-    })
+    let user = UserInfo {
+        id: conn.last_insert_rowid(),
+        username: username.to_string(),
+        email: email.to_string(),
+    };
+    println!("[auth] User registered: {} (id: {})", user.username, user.id);
+    let mut session = create_session(conn, user)?;
+    session.recovery_code = Some(recovery_code);
+    Ok(session)
 }
 
-#[tauri::command]
-pub fn login(
-    state: State<DbState>,
-    username: String,
-    password: String,
-) -> Result<UserInfo, String> {
+/// Checks the password and starts a session. Accounts from before password
+/// hashing have their plain text password replaced by a hash here, and
+/// accounts without a recovery code get one.
+pub fn login_user(conn: &Connection, username: &str, password: &str) -> Result<Session, String> {
     if username.trim().is_empty() || password.trim().is_empty() {
         return Err("Username and password are required".to_string());
     }
 
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-
-    let user = conn
+    let row: Option<(UserInfo, String, String)> = conn
         .query_row(
-            "SELECT id, username, email FROM users WHERE username = ?1 AND password = ?2",
-            rusqlite::params![username, password],
+            "SELECT id, username, email, password, recovery_code FROM users WHERE username = ?1",
+            rusqlite::params![username],
             |row| {
-                Ok(UserInfo {
+                let user = UserInfo {
                     id: row.get(0)?,
                     username: row.get(1)?,
                     email: row.get(2)?,
-                })
+                };
+                Ok((user, row.get(3)?, row.get::<_, Option<String>>(4)?.unwrap_or_default()))
             },
         )
-        .map_err(|_| "Invalid username or password".to_string())?;
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some((user, stored, recovery_hash)) = row else {
+        if let Ok(hash) = PasswordHash::new(DUMMY_HASH) {
+            let _ = Argon2::default().verify_password(password.as_bytes(), &hash);
+        }
+        return Err("Invalid username or password".to_string());
+    };
+
+    match PasswordHash::new(&stored) {
+        Ok(hash) => {
+            if Argon2::default().verify_password(password.as_bytes(), &hash).is_err() {
+                return Err("Invalid username or password".to_string());
+            }
+        }
+        Err(_) => {
+            if !constant_time_eq(stored.as_bytes(), password.as_bytes()) {
+                return Err("Invalid username or password".to_string());
+            }
+            conn.execute(
+                "UPDATE users SET password = ?1 WHERE id = ?2",
+                rusqlite::params![hash_password(password)?, user.id],
+            )
+            .map_err(|e| e.to_string())?;
+            println!("[auth] Migrated plain text password of user {} to a hash", user.id);
+        }
+    }
+
+    let recovery_code = if recovery_hash.is_empty() {
+        let code = new_recovery_code();
+        conn.execute(
+            "UPDATE users SET recovery_code = ?1 WHERE id = ?2",
+            rusqlite::params![hash_password(&normalize_code(&code))?, user.id],
+        )
+        .map_err(|e| e.to_string())?;
+        Some(code)
+    } else {
+        None
+    };
+
+    // Expired sessions of anyone are of no further use
+    conn.execute("DELETE FROM sessions WHERE expires_at <= datetime('now')", [])
+        .map_err(|e| e.to_string())?;
 
     println!("[auth] User logged in: {} (id: {})", user.username, user.id);
-    Ok(user)
+    let mut session = create_session(conn, user)?;
+    session.recovery_code = recovery_code;
+    Ok(session)
 }
 
-#[tauri::command]
-pub fn update_profile(
-    state: State<DbState>,
-    id: i64,
-    new_username: Option<String>,
-    new_password: Option<String>,
+/// The user a session token belongs to; fails once it expired or was revoked.
+pub fn session_user(conn: &Connection, token: &str) -> Result<UserInfo, String> {
+    conn.query_row(
+        "SELECT users.id, users.username, users.email FROM sessions
+         JOIN users ON users.id = sessions.user_id
+         WHERE sessions.token_hash = ?1 AND sessions.expires_at > datetime('now')",
+        rusqlite::params![token_hash(token)],
+        |row| {
+            Ok(UserInfo {
+                id: row.get(0)?,
+                username: row.get(1)?,
+                email: row.get(2)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Not logged in or the session has expired".to_string())
+}
+
+/// Ends the session of `token`.
+pub fn logout_session(conn: &Connection, token: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM sessions WHERE token_hash = ?1", rusqlite::params![token_hash(token)])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Renames the user of `token` and/or changes their password. A new password
+/// ends their other sessions.
+pub fn update_user(
+    conn: &Connection,
+    token: &str,
+    new_username: Option<&str>,
+    new_password: Option<&str>,
 ) -> Result<UserInfo, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    let id = session_user(conn, token)?.id;
 
-    if let Some(ref name) = new_username {
+    if let Some(name) = new_username {
         if name.trim().is_empty() {
             return Err("Username cannot be empty".to_string());
         }
@@ -112,32 +262,23 @@ pub fn update_profile(
         .map_err(|e| e.to_string())?;
     }
 
-    if let Some(ref pwd) = new_password {
+    if let Some(pwd) = new_password {
         if pwd.trim().is_empty() {
             return Err("Password cannot be empty".to_string());
         }
         conn.execute(
             "UPDATE users SET password = ?1 WHERE id = ?2",
-            rusqlite::params![pwd, id],
+            rusqlite::params![hash_password(pwd)?, id],
         )
         .map_err(|e| e.to_string())?;
-    }
-
-    // Return updated user info
-    let user = conn
-        .query_row(
-            "SELECT id, username, email FROM users WHERE id = ?1",
-            rusqlite::params![id],
-            |row| {
-                Ok(UserInfo {
-                    id: row.get(0)?,
-                    username: row.get(1)?,
-                    email: row.get(2)?,
-                })
-            },
+        conn.execute(
+            "DELETE FROM sessions WHERE user_id = ?1 AND token_hash != ?2",
+            rusqlite::params![id, token_hash(token)],
         )
         .map_err(|e| e.to_string())?;
+    }
 
+    let user = session_user(conn, token)?;
     println!(
         "[auth] Profile updated: {} (id: {})",
         user.username, user.id
@@ -145,30 +286,106 @@ pub fn update_profile(
     Ok(user)
 }
 
-#[tauri::command]
-pub fn reset_password(
-    state: State<DbState>,
-    username: String,
-    email: String,
-    new_password: String,
-) -> Result<(), String> {
-    if username.trim().is_empty() || email.trim().is_empty() || new_password.trim().is_empty() {
+/// Sets a new password with the recovery code issued at registration (or
+/// the last reset), ending every session of the user. Each code works once;
+/// the replacement is returned.
+pub fn reset_user_password(
+    conn: &Connection,
+    username: &str,
+    recovery_code: &str,
+    new_password: &str,
+) -> Result<String, String> {
+    if username.trim().is_empty() || recovery_code.trim().is_empty() || new_password.trim().is_empty() {
         return Err("All fields are required".to_string());
     }
 
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-
-    let affected = conn
-        .execute(
-            "UPDATE users SET password = ?1 WHERE username = ?2 AND email = ?3",
-            rusqlite::params![new_password, username, email],
+    let row: Option<(i64, Option<String>)> = conn
+        .query_row(
+            "SELECT id, recovery_code FROM users WHERE username = ?1",
+            rusqlite::params![username],
+            |row| Ok((row.get(0)?, row.get(1)?)),
         )
+        .optional()
         .map_err(|e| e.to_string())?;
+    let valid = row.as_ref().is_some_and(|(_, hash)| {
+        hash.as_deref()
+            .and_then(|hash| PasswordHash::new(hash).ok())
+            .is_some_and(|hash| {
+                Argon2::default()
+                    .verify_password(normalize_code(recovery_code).as_bytes(), &hash)
+                    .is_ok()
+            })
+    });
+    let Some((id, _)) = row.filter(|_| valid) else {
+        return Err("Invalid username or recovery code".to_string());
+    };
 
-    if affected == 0 {
-        return Err("User not found or email mismatch".to_string());
-    }
+    let code = new_recovery_code();
+    conn.execute(
+        "UPDATE users SET password = ?1, recovery_code = ?2 WHERE id = ?3",
+        rusqlite::params![hash_password(new_password)?, hash_password(&normalize_code(&code))?, id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM sessions WHERE user_id = ?1", rusqlite::params![id])
+        .map_err(|e| e.to_string())?;
 
     println!("[auth] Password reset for user: {}", username);
-    Ok(())
+    Ok(code)
+}
+
+/// An argon2id hash of `password` in PHC string format.
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn create_session(conn: &Connection, user: UserInfo) -> Result<Session, String> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+    // Only a hash is stored, so the database alone cannot be used to log in
+    let expires_at: String = conn
+        .query_row(
+            "INSERT INTO sessions (token_hash, user_id, expires_at) VALUES (?1, ?2, datetime('now', ?3))
+             RETURNING expires_at",
+            rusqlite::params![token_hash(&token), user.id, format!("+{} days", SESSION_DAYS)],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(Session {
+        token,
+        user,
+        expires_at,
+        recovery_code: None,
+    })
+}
+
+fn token_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// 16 characters in groups of four, e.g. `K7QD-2M9X-...`; no 0/O or 1/I
+fn new_recovery_code() -> String {
+    const ALPHABET: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let chars: Vec<char> = bytes.iter().map(|b| ALPHABET[*b as usize % ALPHABET.len()] as char).collect();
+    chars.chunks(4).map(|c| c.iter().collect::<String>()).collect::<Vec<_>>().join("-")
+}
+
+// Codes are accepted in any case and with or without separators
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Compares secrets without stopping at the first difference, so the time
+/// taken does not reveal how much of a guess was right.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
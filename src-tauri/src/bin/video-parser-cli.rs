@@ -0,0 +1,362 @@
+// Headless front end for batch jobs: parses share links and downloads the
+// results without launching the desktop GUI.
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use app_lib::downloader::{self, sanitize_file_name};
+use app_lib::models::VideoParseInfo;
+use app_lib::parser::{cookies, ParseContext, ParserRegistry};
+use app_lib::subtitles::ExportFormat;
+use clap::{Parser, Subcommand};
+use serde_json::json;
+
+#[derive(Parser)]
+#[command(name = "video-parser-cli", version, about = "Parse and download share links without the GUI")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Netscape cookies.txt export; each cookie is sent to the platform its
+    /// domain belongs to (e.g. a Bilibili login for higher qualities)
+    #[arg(long, global = true, value_name = "FILE")]
+    cookies: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the parse result of each share link as one JSON object per line
+    Parse {
+        /// Share links or share text; read one per line from stdin when omitted or `-`
+        urls: Vec<String>,
+        /// Pretty-print the JSON output
+        #[arg(long)]
+        pretty: bool,
+    },
+    /// Parse each share link and download its video, or its images for image posts
+    Download {
+        /// Share links or share text; read one per line from stdin when omitted or `-`
+        urls: Vec<String>,
+        /// Quality label to download (e.g. `1080p`); defaults to the parser's choice
+        #[arg(short, long)]
+        quality: Option<String>,
+        /// Output directory, or the output file when downloading a single video
+        #[arg(short, long, default_value = ".")]
+        out: PathBuf,
+        /// Parallel connections per file when the server supports range requests
+        #[arg(short, long, default_value_t = downloader::DEFAULT_DOWNLOAD_SEGMENTS)]
+        connections: usize,
+        /// Download every part of multi-part videos and collections, not just
+        /// the linked one (a single part is picked with `?p=N` in the link)
+        #[arg(long)]
+        all_parts: bool,
+        /// Also save every subtitle track next to the video, as `srt` or `vtt`
+        #[arg(long, value_name = "FORMAT", value_parser = subtitle_format)]
+        subtitles: Option<ExportFormat>,
+        /// Also save the danmaku (bullet comments) next to the video, as `ass` or `xml`
+        #[arg(long, value_name = "FORMAT", value_parser = danmaku_format)]
+        danmaku: Option<ExportFormat>,
+    },
+    /// Run the HTTP API server until interrupted
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8787")]
+        bind: std::net::SocketAddr,
+        /// API token clients must send; defaults to $VIDEO_PARSER_API_TOKEN
+        #[arg(long)]
+        token: Option<String>,
+        /// SQLite database for downloads and favorites
+        #[arg(long, default_value = "video-parser.db")]
+        db: PathBuf,
+        /// Directory that download requests write into
+        #[arg(long, default_value = "downloads")]
+        download_dir: PathBuf,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let registry = ParserRegistry::with_defaults();
+    let ctx = ParseContext::new();
+    if let Some(path) = &cli.cookies {
+        if let Err(e) = load_cookies(&registry, &ctx, path) {
+            return fail(&e);
+        }
+    }
+
+    let ok = match cli.command {
+        Command::Parse { urls, pretty } => {
+            let urls = match read_inputs(urls) {
+                Ok(urls) => urls,
+                Err(e) => return fail(&format!("failed to read stdin: {}", e)),
+            };
+            run_parse(&registry, &ctx, &urls, pretty).await
+        }
+        Command::Download { urls, quality, out, connections, all_parts, subtitles, danmaku } => {
+            let urls = match read_inputs(urls) {
+                Ok(urls) => urls,
+                Err(e) => return fail(&format!("failed to read stdin: {}", e)),
+            };
+            let options = DownloadOptions { quality: quality.as_deref(), connections, all_parts, subtitles, danmaku };
+            run_download(&registry, &ctx, &urls, &out, &options).await
+        }
+        #[cfg(feature = "server")]
+        Command::Serve { bind, token, db, download_dir } => {
+            let Some(token) = token.or_else(|| std::env::var("VIDEO_PARSER_API_TOKEN").ok()) else {
+                return fail("an API token is required (--token or VIDEO_PARSER_API_TOKEN)");
+            };
+            let config = app_lib::server::ApiConfig { bind, token, db_path: db, download_dir, ctx, downloads: None };
+            let server = match app_lib::server::ApiServer::start(config).await {
+                Ok(server) => server,
+                Err(e) => return fail(&format!("failed to start server: {}", e)),
+            };
+            let _ = tokio::signal::ctrl_c().await;
+            server.stop().await;
+            true
+        }
+    };
+
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn subtitle_format(s: &str) -> Result<ExportFormat, String> {
+    match s.parse()? {
+        format @ (ExportFormat::Srt | ExportFormat::Vtt) => Ok(format),
+        _ => Err("subtitles can be saved as srt or vtt".to_string()),
+    }
+}
+
+fn danmaku_format(s: &str) -> Result<ExportFormat, String> {
+    match s.parse()? {
+        format @ (ExportFormat::Ass | ExportFormat::Xml) => Ok(format),
+        _ => Err("danmaku can be saved as ass or xml".to_string()),
+    }
+}
+
+fn load_cookies(registry: &ParserRegistry, ctx: &ParseContext, path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    for (platform, cookie) in cookies::import(registry, &text, None)? {
+        ctx.set_cookies(&platform, &cookie).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn fail(message: &str) -> ExitCode {
+    eprintln!("error: {}", message);
+    ExitCode::FAILURE
+}
+
+// Arguments as given, or one share link per non-empty stdin line for `-` / no arguments
+fn read_inputs(urls: Vec<String>) -> io::Result<Vec<String>> {
+    if !urls.is_empty() && urls.iter().all(|u| u != "-") {
+        return Ok(urls);
+    }
+    let mut inputs = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            inputs.push(line.to_string());
+        }
+    }
+    Ok(inputs)
+}
+
+fn report_error(url: &str, error: serde_json::Value) {
+    eprintln!("{}", json!({ "url": url, "error": error }));
+}
+
+async fn run_parse(registry: &ParserRegistry, ctx: &ParseContext, urls: &[String], pretty: bool) -> bool {
+    let mut ok = true;
+    for url in urls {
+        match registry.parse(ctx, url).await {
+            Ok(info) => {
+                let out = if pretty {
+                    serde_json::to_string_pretty(&info)
+                } else {
+                    serde_json::to_string(&info)
+                };
+                println!("{}", out.unwrap_or_default());
+            }
+            Err(e) => {
+                report_error(url, serde_json::to_value(&e).unwrap_or_default());
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+struct DownloadOptions<'a> {
+    quality: Option<&'a str>,
+    connections: usize,
+    all_parts: bool,
+    subtitles: Option<ExportFormat>,
+    danmaku: Option<ExportFormat>,
+}
+
+// One file to fetch for a parse result
+struct Job {
+    url: String,
+    /// Audio stream to mux in
+    audio_url: Option<String>,
+    /// Subtitle or danmaku conversion
+    export: Option<ExportFormat>,
+    path: PathBuf,
+}
+
+async fn run_download(
+    registry: &ParserRegistry,
+    ctx: &ParseContext,
+    urls: &[String],
+    out: &Path,
+    options: &DownloadOptions<'_>,
+) -> bool {
+    let mut ok = true;
+    for url in urls {
+        let info = match registry.parse(ctx, url).await {
+            Ok(info) => info,
+            Err(e) => {
+                report_error(url, serde_json::to_value(&e).unwrap_or_default());
+                ok = false;
+                continue;
+            }
+        };
+
+        if !options.all_parts || info.parts.is_empty() {
+            ok &= download_and_report(ctx, url, &info, out, urls.len() > 1, options).await;
+            continue;
+        }
+        // Each part is a separate parse; the linked one is already done
+        for part in &info.parts {
+            let part_info = if part.current {
+                info.clone()
+            } else {
+                match registry.parse(ctx, &part.url).await {
+                    Ok(part_info) => part_info,
+                    Err(e) => {
+                        report_error(&part.url, serde_json::to_value(&e).unwrap_or_default());
+                        ok = false;
+                        continue;
+                    }
+                }
+            };
+            ok &= download_and_report(ctx, &part.url, &part_info, out, true, options).await;
+        }
+    }
+    ok
+}
+
+async fn download_and_report(
+    ctx: &ParseContext,
+    url: &str,
+    info: &VideoParseInfo,
+    out: &Path,
+    batch: bool,
+    options: &DownloadOptions<'_>,
+) -> bool {
+    match download_info(ctx, info, out, batch, options).await {
+        Ok(files) => {
+            println!("{}", json!({ "url": url, "title": info.title, "files": files }));
+            true
+        }
+        Err(e) => {
+            report_error(url, json!({ "code": "download_failed", "detail": e.to_string(), "retryable": true }));
+            false
+        }
+    }
+}
+
+async fn download_info(
+    ctx: &ParseContext,
+    info: &VideoParseInfo,
+    out: &Path,
+    batch: bool,
+    options: &DownloadOptions<'_>,
+) -> anyhow::Result<Vec<String>> {
+    let base_name = sanitize_file_name(&info.title);
+    let quality = options.quality;
+    let mut jobs: Vec<Job> = Vec::new();
+
+    if let Some((video_url, audio_url)) = downloader::stream_for_quality(info, quality) {
+        // A single video may be written straight to `--out`
+        let path = if out.is_dir() || batch {
+            std::fs::create_dir_all(out)?;
+            out.join(format!("{}.mp4", base_name))
+        } else {
+            out.to_path_buf()
+        };
+        // Subtitles and danmaku go next to the video, named after it
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or(base_name.clone());
+        if let Some(format) = options.subtitles {
+            for track in &info.subtitles {
+                let name = format!("{}.{}.{}", stem, sanitize_file_name(&track.lang), format.as_str());
+                jobs.push(Job { url: track.url.clone(), audio_url: None, export: Some(format), path: dir.join(name) });
+            }
+        }
+        if let (Some(format), Some(danmaku_url)) = (options.danmaku, &info.danmaku_url) {
+            let name = format!("{}.danmaku.{}", stem, format.as_str());
+            jobs.push(Job { url: danmaku_url.clone(), audio_url: None, export: Some(format), path: dir.join(name) });
+        }
+        jobs.insert(0, Job { url: video_url.to_string(), audio_url: audio_url.map(str::to_string), export: None, path });
+    } else if let Some(q) = quality.filter(|_| !info.video_qualities.is_empty()) {
+        let available: Vec<&str> = info.video_qualities.iter().map(|vq| vq.quality.as_str()).collect();
+        anyhow::bail!("quality {} not available (have: {})", q, available.join(", "));
+    } else if !info.images.is_empty() {
+        std::fs::create_dir_all(out)?;
+        for (i, img) in info.images.iter().enumerate() {
+            let path = out.join(format!("{}_{}.jpg", base_name, i + 1));
+            jobs.push(Job { url: img.url.clone(), audio_url: None, export: None, path });
+        }
+    } else {
+        anyhow::bail!("nothing to download");
+    }
+
+    let show_progress = io::stderr().is_terminal();
+    let mut files = Vec::new();
+    for Job { url, audio_url, export, path } in jobs {
+        let label = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let mut last_step = None;
+        let on_progress = |done: u64, total: Option<u64>| {
+            if !show_progress {
+                return;
+            }
+            // Redraw once per percent, or once per MiB when the size is unknown
+            let step = match total.filter(|t| *t > 0) {
+                Some(t) => done * 100 / t,
+                None => done >> 20,
+            };
+            if last_step != Some(step) {
+                last_step = Some(step);
+                match total.filter(|t| *t > 0) {
+                    Some(_) => eprint!("\r{} {:>3}%", label, step),
+                    None => eprint!("\r{} {} MiB", label, step),
+                }
+                let _ = io::stderr().flush();
+            }
+        };
+        let connections = options.connections;
+        match (audio_url, export) {
+            (_, Some(format)) => downloader::download_converted(ctx.client(), &url, &path, format, on_progress).await?,
+            (Some(audio_url), None) => {
+                let previous = Default::default();
+                downloader::download_muxed(ctx.client(), &url, &audio_url, &path, connections, &previous, |_, _| {}, on_progress)
+                    .await?
+            }
+            (None, None) => {
+                downloader::download_segmented(ctx.client(), &url, &path, connections, None, |_| {}, on_progress).await?
+            }
+        };
+        if show_progress {
+            eprintln!();
+        }
+        files.push(path.to_string_lossy().to_string());
+    }
+    Ok(files)
+}
@@ -0,0 +1,185 @@
+// Login cookies per platform, kept encrypted in the `secrets` table and
+// loaded into the shared `ParseContext` so every parser sends them.
+#[cfg(feature = "gui")]
+use crate::auth;
+#[cfg(feature = "gui")]
+use crate::db::DbPool;
+use crate::parser::cookies;
+use crate::parser::{ParseContext, ParserRegistry};
+#[cfg(feature = "gui")]
+use crate::secrets::SecretsState;
+use crate::secrets::SecretStore;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "gui")]
+use tauri::State;
+
+/// Secret name the cookies of a platform are stored under.
+pub const COOKIE_SECRET: &str = "cookie";
+
+/// What the UI gets to see of a platform's stored cookies; the values stay
+/// in the backend.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct StoredCookies {
+    pub platform: String,
+    /// Cookie names, e.g. to check that `SESSDATA` made it in
+    pub names: Vec<String>,
+    pub updated_at: String,
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn import_cookies(
+    state: State<'_, DbPool>,
+    secrets: State<'_, SecretsState>,
+    ctx: State<'_, ParseContext>,
+    registry: State<'_, ParserRegistry>,
+    token: String,
+    text: String,
+    platform: Option<String>,
+) -> Result<Vec<StoredCookies>, String> {
+    let secrets = secrets.unlocked()?;
+    let ctx = ctx.inner().clone();
+    let platform = platform.filter(|p| !p.is_empty());
+    // Parsing needs the registry but no connection, so it happens up front
+    let headers = cookies::import(&registry, &text, platform.as_deref())?;
+    state
+        .run(move |conn| {
+            auth::session_user(conn, &token)?;
+            store_cookies(conn, &secrets, &ctx, headers)?;
+            list_cookies(conn, &secrets)
+        })
+        .await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_cookies(
+    state: State<'_, DbPool>,
+    secrets: State<'_, SecretsState>,
+    token: String,
+) -> Result<Vec<StoredCookies>, String> {
+    let secrets = secrets.unlocked()?;
+    state
+        .run(move |conn| {
+            auth::session_user(conn, &token)?;
+            list_cookies(conn, &secrets)
+        })
+        .await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn delete_cookies(
+    state: State<'_, DbPool>,
+    secrets: State<'_, SecretsState>,
+    ctx: State<'_, ParseContext>,
+    token: String,
+    platform: String,
+) -> Result<(), String> {
+    let secrets = secrets.unlocked()?;
+    let ctx = ctx.inner().clone();
+    state
+        .run(move |conn| {
+            auth::session_user(conn, &token)?;
+            remove_cookies(conn, &secrets, &ctx, &platform)
+        })
+        .await
+}
+
+/// Parses pasted cookies (see [`cookies::import`]), stores them encrypted,
+/// replacing the previous cookies of each platform they cover, and starts
+/// sending them. Returns the updated platforms.
+pub fn import(
+    conn: &Connection,
+    secrets: &SecretStore,
+    ctx: &ParseContext,
+    registry: &ParserRegistry,
+    text: &str,
+    platform: Option<&str>,
+) -> Result<Vec<String>, String> {
+    store_cookies(conn, secrets, ctx, cookies::import(registry, text, platform)?)
+}
+
+/// Stores the cookie headers of [`cookies::import`] by platform and starts
+/// sending them. Returns the updated platforms.
+pub fn store_cookies(
+    conn: &Connection,
+    secrets: &SecretStore,
+    ctx: &ParseContext,
+    headers: BTreeMap<String, String>,
+) -> Result<Vec<String>, String> {
+    for (platform, cookie) in &headers {
+        secrets.add(conn, platform, COOKIE_SECRET, cookie)?;
+        ctx.set_cookies(platform, cookie).map_err(|e| e.to_string())?;
+        println!("[cookies] Imported cookies for {}", platform);
+    }
+    Ok(headers.into_keys().collect())
+}
+
+/// Forgets the cookies of `platform`, in the database and in `ctx`.
+pub fn remove_cookies(conn: &Connection, secrets: &SecretStore, ctx: &ParseContext, platform: &str) -> Result<(), String> {
+    secrets.delete(conn, platform, COOKIE_SECRET)?;
+    ctx.remove_cookies(platform);
+    println!("[cookies] Removed cookies for {}", platform);
+    Ok(())
+}
+
+/// Stored cookies by platform name.
+pub fn list_cookies(conn: &Connection, secrets: &SecretStore) -> Result<Vec<StoredCookies>, String> {
+    secrets
+        .list(conn, Some(COOKIE_SECRET))?
+        .into_iter()
+        .map(|info| {
+            let cookie = secrets.get(conn, &info.platform, COOKIE_SECRET)?.unwrap_or_default();
+            Ok(StoredCookies {
+                platform: info.platform,
+                names: cookies::parse_header(&cookie).into_iter().map(|(name, _)| name).collect(),
+                updated_at: info.updated_at,
+            })
+        })
+        .collect()
+}
+
+/// Sends the stored cookies with `ctx`'s requests; called at startup.
+/// Returns how many platforms have cookies.
+pub fn load_cookies(conn: &Connection, secrets: &SecretStore, ctx: &ParseContext) -> Result<usize, String> {
+    migrate_plaintext_cookies(conn, secrets)?;
+    let stored = secrets.list(conn, Some(COOKIE_SECRET))?;
+    for info in &stored {
+        let cookie = secrets.get(conn, &info.platform, COOKIE_SECRET)?.unwrap_or_default();
+        if let Err(e) = ctx.set_cookies(&info.platform, &cookie) {
+            println!("[cookies] Skipping stored cookies: {}", e);
+        }
+    }
+    Ok(stored.len())
+}
+
+// Cookies used to be kept in plain text in `platform_cookies`; encrypt them
+// and drop the table
+fn migrate_plaintext_cookies(conn: &Connection, secrets: &SecretStore) -> Result<(), String> {
+    let exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'platform_cookies'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        return Ok(());
+    }
+    let rows = conn
+        .prepare("SELECT platform, cookie FROM platform_cookies")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .map_err(|e| e.to_string())?;
+    for (platform, cookie) in &rows {
+        secrets.add(conn, platform, COOKIE_SECRET, cookie)?;
+    }
+    conn.execute_batch("DROP TABLE platform_cookies;").map_err(|e| e.to_string())?;
+    println!("[db] Migrating: encrypted {} stored cookies", rows.len());
+    Ok(())
+}
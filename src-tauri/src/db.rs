@@ -1,90 +1,583 @@
-use rusqlite::{Connection, Result};
-use std::sync::Mutex;
+use rusqlite::{Connection, Result, Transaction, TransactionBehavior};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
-pub struct DbState(pub Mutex<Connection>);
+/// Connections the app keeps open to its database; the UI commands, download
+/// workers and API server share them.
+pub const DEFAULT_POOL_SIZE: usize = 4;
 
-pub fn init_db(app: &tauri::AppHandle) -> Result<Connection, Box<dyn std::error::Error>> {
+// How long `DbPool::get` waits for a connection before giving up
+const POOL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A fixed-size pool of connections to one database in WAL mode, so readers
+/// never wait for the writer and no caller holds the only connection.
+/// Clones share the pool.
+#[derive(Clone)]
+pub struct DbPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    path: PathBuf,
+    max_size: usize,
+    // Idle connections and the number of connections open in total
+    state: Mutex<(Vec<Connection>, usize)>,
+    returned: Condvar,
+}
+
+impl DbPool {
+    /// Opens the database at `db_path`, migrating it, with room for up to
+    /// `max_size` connections. Further connections are opened on demand.
+    pub fn open(db_path: &Path, max_size: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        let first = open_db(db_path)?;
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                path: db_path.to_path_buf(),
+                max_size: max_size.max(1),
+                state: Mutex::new((vec![first], 1)),
+                returned: Condvar::new(),
+            }),
+        })
+    }
+
+    /// A connection for a short piece of work; it goes back to the pool when
+    /// dropped. Blocks while all connections are in use, so async code should
+    /// prefer [`DbPool::run`].
+    pub fn get(&self) -> Result<PooledConnection, String> {
+        let inner = &self.inner;
+        let mut state = inner.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(conn) = state.0.pop() {
+                return Ok(PooledConnection { conn: Some(conn), pool: inner.clone() });
+            }
+            if state.1 < inner.max_size {
+                state.1 += 1;
+                drop(state);
+                return match connect(&inner.path) {
+                    Ok(conn) => Ok(PooledConnection { conn: Some(conn), pool: inner.clone() }),
+                    Err(e) => {
+                        inner.state.lock().unwrap_or_else(|e| e.into_inner()).1 -= 1;
+                        inner.returned.notify_one();
+                        Err(e.to_string())
+                    }
+                };
+            }
+            let (next, timeout) = inner
+                .returned
+                .wait_timeout(state, POOL_TIMEOUT)
+                .unwrap_or_else(|e| e.into_inner());
+            if timeout.timed_out() && next.0.is_empty() && next.1 >= inner.max_size {
+                return Err("Database is busy; no connection became free".to_string());
+            }
+            state = next;
+        }
+    }
+
+    /// Runs `f` with a connection on Tokio's blocking thread pool, keeping
+    /// queries off the async workers.
+    pub async fn run<T, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, String> + Send + 'static,
+    {
+        let pool = self.clone();
+        tokio::task::spawn_blocking(move || f(&mut *pool.get()?))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+}
+
+/// A connection borrowed from a [`DbPool`].
+pub struct PooledConnection {
+    conn: Option<Connection>,
+    pool: Arc<PoolInner>,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection taken")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("connection taken")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            let mut state = self.pool.state.lock().unwrap_or_else(|e| e.into_inner());
+            // A connection left inside a transaction (e.g. by a panic) is
+            // closed rather than handed out again
+            if conn.is_autocommit() {
+                state.0.push(conn);
+            } else {
+                state.1 -= 1;
+            }
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+/// Location of the app database, creating its directory if needed.
+#[cfg(feature = "gui")]
+pub fn db_path(app: &tauri::AppHandle) -> Result<PathBuf, Box<dyn std::error::Error>> {
     use tauri::Manager;
     let app_data_dir = app
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
     std::fs::create_dir_all(&app_data_dir)?;
-    let db_path = app_data_dir.join("favorites.db");
+    Ok(app_data_dir.join("favorites.db"))
+}
+
+#[cfg(feature = "gui")]
+pub fn init_db(app: &tauri::AppHandle) -> Result<DbPool, Box<dyn std::error::Error>> {
+    let db_path = db_path(app)?;
     println!("[db] Database path: {:?}", db_path);
 
+    DbPool::open(&db_path, DEFAULT_POOL_SIZE)
+}
+
+/// Opens (creating if needed) the database at `db_path` and brings its schema up to date.
+pub fn open_db(db_path: &Path) -> Result<Connection, Box<dyn std::error::Error>> {
+    let mut conn = connect(db_path)?;
+    migrate(&mut conn, db_path)?;
+    println!("[db] Database initialized successfully");
+    Ok(conn)
+}
+
+// A connection to an already migrated database
+fn connect(db_path: &Path) -> Result<Connection> {
     let conn = Connection::open(db_path)?;
+    // Pools, the API server and tests may all hold connections to one file
+    conn.busy_timeout(Duration::from_secs(5))?;
+    // WAL lets readers go on while a write is in progress; NORMAL sync is
+    // safe with it and spares an fsync per commit
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(conn)
+}
 
-    // Migration: check if favorites table has user_id column
-    let has_user_id = conn
-        .prepare("PRAGMA table_info(favorites)")
-        .and_then(|mut stmt| {
-            let columns: Vec<String> = stmt
-                .query_map([], |row| row.get::<_, String>(1))
-                .unwrap()
-                .filter_map(|r| r.ok())
-                .collect();
-            Ok(columns.contains(&"user_id".to_string()))
-        })
-        .unwrap_or(false);
+/// Schema version of the current build; see [`MIGRATIONS`].
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
 
-    if !has_user_id {
-        // Drop old table and recreate with new schema
-        println!("[db] Migrating: recreating favorites table with user_id");
-        conn.execute_batch("DROP TABLE IF EXISTS favorites;")?;
-    }
+struct Migration {
+    description: &'static str,
+    apply: fn(&Transaction) -> Result<()>,
+}
 
-    // Migration: check if downloads table has user_id column
-    let has_download_user_id = conn
-        .prepare("PRAGMA table_info(downloads)")
-        .and_then(|mut stmt| {
-            let columns: Vec<String> = stmt
-                .query_map([], |row| row.get::<_, String>(1))
-                .unwrap()
-                .filter_map(|r| r.ok())
-                .collect();
-            Ok(columns.contains(&"user_id".to_string()))
-        })
-        .unwrap_or(false);
+/// Schema changes in order. A database at `PRAGMA user_version` N has had
+/// the first N applied. Databases from before versioning start at 0 with
+/// some of the changes already made, so the early steps check first.
+/// Append new steps; never edit or reorder released ones.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "create favorites, users and downloads",
+        apply: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS favorites (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    user_id INTEGER NOT NULL DEFAULT 0,
+                    url TEXT NOT NULL,
+                    title TEXT NOT NULL,
+                    platform TEXT NOT NULL,
+                    cover_url TEXT DEFAULT '',
+                    author_name TEXT DEFAULT '',
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    UNIQUE(user_id, url)
+                );
+                CREATE TABLE IF NOT EXISTS users (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    username TEXT NOT NULL UNIQUE,
+                    password TEXT NOT NULL,
+                    email TEXT NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );
+                CREATE TABLE IF NOT EXISTS downloads (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    user_id INTEGER NOT NULL DEFAULT 0,
+                    url TEXT NOT NULL,
+                    title TEXT NOT NULL,
+                    cover_url TEXT DEFAULT '',
+                    file_path TEXT NOT NULL,
+                    status TEXT NOT NULL,
+                    total_size INTEGER DEFAULT 0,
+                    downloaded_size INTEGER DEFAULT 0,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );",
+            )
+        },
+    },
+    Migration {
+        // Favorites and downloads from before accounts go to user 0
+        description: "add user_id to favorites and downloads",
+        apply: |tx| {
+            if !has_column(tx, "favorites", "user_id") {
+                rebuild_table(
+                    tx,
+                    "favorites",
+                    "CREATE TABLE favorites (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        user_id INTEGER NOT NULL DEFAULT 0,
+                        url TEXT NOT NULL,
+                        title TEXT NOT NULL,
+                        platform TEXT NOT NULL,
+                        cover_url TEXT DEFAULT '',
+                        author_name TEXT DEFAULT '',
+                        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                        UNIQUE(user_id, url)
+                    )",
+                )?;
+            }
+            if !has_column(tx, "downloads", "user_id") {
+                rebuild_table(
+                    tx,
+                    "downloads",
+                    "CREATE TABLE downloads (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        user_id INTEGER NOT NULL DEFAULT 0,
+                        url TEXT NOT NULL,
+                        title TEXT NOT NULL,
+                        cover_url TEXT DEFAULT '',
+                        file_path TEXT NOT NULL,
+                        status TEXT NOT NULL,
+                        total_size INTEGER DEFAULT 0,
+                        downloaded_size INTEGER DEFAULT 0,
+                        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                    )",
+                )?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        // Resumable downloads remember the server's ETag/Last-Modified and
+        // DASH downloads the audio stream muxed into the video; subtitle and
+        // danmaku downloads the format they are converted to
+        description: "add resume, audio and export columns to downloads",
+        apply: |tx| {
+            for column in ["etag", "last_modified", "audio_url", "export_format"] {
+                if !has_column(tx, "downloads", column) {
+                    tx.execute_batch(&format!("ALTER TABLE downloads ADD COLUMN {} TEXT DEFAULT '';", column))?;
+                }
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        description: "create encrypted secrets",
+        apply: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS secrets_meta (
+                    id INTEGER PRIMARY KEY CHECK (id = 1),
+                    kdf TEXT NOT NULL,
+                    salt BLOB NOT NULL,
+                    check_nonce BLOB NOT NULL,
+                    check_value BLOB NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS secrets (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    platform TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    nonce BLOB NOT NULL,
+                    ciphertext BLOB NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    UNIQUE(platform, name)
+                );",
+            )
+        },
+    },
+    Migration {
+        // Password resets need the hash of a recovery code; accounts from
+        // before get one at their next login
+        description: "add recovery codes and sessions",
+        apply: |tx| {
+            if !has_column(tx, "users", "recovery_code") {
+                tx.execute_batch("ALTER TABLE users ADD COLUMN recovery_code TEXT DEFAULT '';")?;
+            }
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS sessions (
+                    token_hash TEXT PRIMARY KEY,
+                    user_id INTEGER NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    expires_at DATETIME NOT NULL
+                );",
+            )
+        },
+    },
+    Migration {
+        // Full parse results, so favorites and downloads open without a new parse
+        description: "create parsed_items and link favorites and downloads to it",
+        apply: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS parsed_items (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    url TEXT NOT NULL UNIQUE,
+                    platform TEXT NOT NULL,
+                    title TEXT NOT NULL,
+                    author_name TEXT DEFAULT '',
+                    info TEXT NOT NULL,
+                    parsed_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );",
+            )?;
+            for table in ["favorites", "downloads"] {
+                if !has_column(tx, table, "parsed_item_id") {
+                    tx.execute_batch(&format!("ALTER TABLE {} ADD COLUMN parsed_item_id INTEGER;", table))?;
+                }
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        // The trigram index matches parts of words, which CJK titles need;
+        // the triggers keep it in step with the table
+        description: "create parse history with a full-text index",
+        apply: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS parse_history (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    user_id INTEGER NOT NULL,
+                    input TEXT NOT NULL,
+                    url TEXT NOT NULL,
+                    platform TEXT NOT NULL,
+                    title TEXT NOT NULL,
+                    author_name TEXT DEFAULT '',
+                    parsed_item_id INTEGER,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );
+                CREATE INDEX IF NOT EXISTS parse_history_user ON parse_history (user_id, id);
+                CREATE VIRTUAL TABLE IF NOT EXISTS parse_history_fts USING fts5 (
+                    input, url, title, author_name, platform,
+                    content = 'parse_history', content_rowid = 'id', tokenize = 'trigram'
+                );
+                CREATE TRIGGER IF NOT EXISTS parse_history_ai AFTER INSERT ON parse_history BEGIN
+                    INSERT INTO parse_history_fts (rowid, input, url, title, author_name, platform)
+                    VALUES (new.id, new.input, new.url, new.title, new.author_name, new.platform);
+                END;
+                CREATE TRIGGER IF NOT EXISTS parse_history_ad AFTER DELETE ON parse_history BEGIN
+                    INSERT INTO parse_history_fts (parse_history_fts, rowid, input, url, title, author_name, platform)
+                    VALUES ('delete', old.id, old.input, old.url, old.title, old.author_name, old.platform);
+                END;
+                CREATE TRIGGER IF NOT EXISTS parse_history_au AFTER UPDATE ON parse_history BEGIN
+                    INSERT INTO parse_history_fts (parse_history_fts, rowid, input, url, title, author_name, platform)
+                    VALUES ('delete', old.id, old.input, old.url, old.title, old.author_name, old.platform);
+                    INSERT INTO parse_history_fts (rowid, input, url, title, author_name, platform)
+                    VALUES (new.id, new.input, new.url, new.title, new.author_name, new.platform);
+                END;",
+            )
+        },
+    },
+    Migration {
+        // Favorites and downloads are also found by the tags and description
+        // of the parse result they link to, so their indexes keep a copy of
+        // those and are refreshed when the result changes
+        description: "index favorites, downloads and parsed items for full-text search",
+        apply: |tx| {
+            for column in ["cover_url", "tags", "description"] {
+                if !has_column(tx, "parsed_items", column) {
+                    tx.execute_batch(&format!("ALTER TABLE parsed_items ADD COLUMN {} TEXT DEFAULT '';", column))?;
+                }
+            }
+            tx.execute_batch(
+                "UPDATE parsed_items SET
+                    cover_url = COALESCE(json_extract(info, '$.cover_url'), ''),
+                    description = COALESCE(json_extract(info, '$.description'), ''),
+                    tags = COALESCE((SELECT group_concat(value, ' ') FROM json_each(info, '$.tags')), '');
+
+                CREATE VIRTUAL TABLE IF NOT EXISTS parsed_items_fts USING fts5 (
+                    title, author_name, url, tags, description, tokenize = 'trigram'
+                );
+                CREATE VIRTUAL TABLE IF NOT EXISTS favorites_fts USING fts5 (
+                    title, author_name, url, tags, description, tokenize = 'trigram'
+                );
+                CREATE VIRTUAL TABLE IF NOT EXISTS downloads_fts USING fts5 (
+                    title, author_name, url, tags, description, tokenize = 'trigram'
+                );
+
+                INSERT INTO parsed_items_fts (rowid, title, author_name, url, tags, description)
+                SELECT id, title, author_name, url, tags, description FROM parsed_items;
+                INSERT INTO favorites_fts (rowid, title, author_name, url, tags, description)
+                SELECT f.id, f.title, f.author_name, f.url, COALESCE(p.tags, ''), COALESCE(p.description, '')
+                FROM favorites f LEFT JOIN parsed_items p ON p.id = f.parsed_item_id;
+                INSERT INTO downloads_fts (rowid, title, author_name, url, tags, description)
+                SELECT d.id, d.title, COALESCE(p.author_name, ''), d.url, COALESCE(p.tags, ''), COALESCE(p.description, '')
+                FROM downloads d LEFT JOIN parsed_items p ON p.id = d.parsed_item_id;
+
+                CREATE TRIGGER IF NOT EXISTS parsed_items_ai AFTER INSERT ON parsed_items BEGIN
+                    INSERT INTO parsed_items_fts (rowid, title, author_name, url, tags, description)
+                    VALUES (new.id, new.title, new.author_name, new.url, new.tags, new.description);
+                END;
+                CREATE TRIGGER IF NOT EXISTS parsed_items_ad AFTER DELETE ON parsed_items BEGIN
+                    DELETE FROM parsed_items_fts WHERE rowid = old.id;
+                END;
+                CREATE TRIGGER IF NOT EXISTS parsed_items_au AFTER UPDATE ON parsed_items BEGIN
+                    UPDATE parsed_items_fts SET title = new.title, author_name = new.author_name, url = new.url,
+                        tags = new.tags, description = new.description
+                    WHERE rowid = new.id;
+                    UPDATE favorites_fts SET tags = new.tags, description = new.description
+                    WHERE rowid IN (SELECT id FROM favorites WHERE parsed_item_id = new.id);
+                    UPDATE downloads_fts SET author_name = new.author_name, tags = new.tags, description = new.description
+                    WHERE rowid IN (SELECT id FROM downloads WHERE parsed_item_id = new.id);
+                END;
 
-    if !has_download_user_id {
-        println!("[db] Migrating: recreating downloads table with user_id");
-        conn.execute_batch("DROP TABLE IF EXISTS downloads;")?;
+                CREATE TRIGGER IF NOT EXISTS favorites_ai AFTER INSERT ON favorites BEGIN
+                    INSERT INTO favorites_fts (rowid, title, author_name, url, tags, description)
+                    VALUES (new.id, new.title, new.author_name, new.url,
+                        COALESCE((SELECT tags FROM parsed_items WHERE id = new.parsed_item_id), ''),
+                        COALESCE((SELECT description FROM parsed_items WHERE id = new.parsed_item_id), ''));
+                END;
+                CREATE TRIGGER IF NOT EXISTS favorites_ad AFTER DELETE ON favorites BEGIN
+                    DELETE FROM favorites_fts WHERE rowid = old.id;
+                END;
+                CREATE TRIGGER IF NOT EXISTS favorites_au AFTER UPDATE ON favorites BEGIN
+                    UPDATE favorites_fts SET title = new.title, author_name = new.author_name, url = new.url,
+                        tags = COALESCE((SELECT tags FROM parsed_items WHERE id = new.parsed_item_id), ''),
+                        description = COALESCE((SELECT description FROM parsed_items WHERE id = new.parsed_item_id), '')
+                    WHERE rowid = new.id;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS downloads_ai AFTER INSERT ON downloads BEGIN
+                    INSERT INTO downloads_fts (rowid, title, author_name, url, tags, description)
+                    VALUES (new.id, new.title,
+                        COALESCE((SELECT author_name FROM parsed_items WHERE id = new.parsed_item_id), ''), new.url,
+                        COALESCE((SELECT tags FROM parsed_items WHERE id = new.parsed_item_id), ''),
+                        COALESCE((SELECT description FROM parsed_items WHERE id = new.parsed_item_id), ''));
+                END;
+                CREATE TRIGGER IF NOT EXISTS downloads_ad AFTER DELETE ON downloads BEGIN
+                    DELETE FROM downloads_fts WHERE rowid = old.id;
+                END;
+                -- Not on progress updates, which are frequent
+                CREATE TRIGGER IF NOT EXISTS downloads_au AFTER UPDATE OF title, url, parsed_item_id ON downloads BEGIN
+                    UPDATE downloads_fts SET title = new.title, url = new.url,
+                        author_name = COALESCE((SELECT author_name FROM parsed_items WHERE id = new.parsed_item_id), ''),
+                        tags = COALESCE((SELECT tags FROM parsed_items WHERE id = new.parsed_item_id), ''),
+                        description = COALESCE((SELECT description FROM parsed_items WHERE id = new.parsed_item_id), '')
+                    WHERE rowid = new.id;
+                END;",
+            )
+        },
+    },
+    Migration {
+        // Each stream of a muxed DASH download is resumed on its own, so
+        // each keeps its own validators
+        description: "create per-stream validators of muxed downloads",
+        apply: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS download_streams (
+                    download_id INTEGER NOT NULL,
+                    stream TEXT NOT NULL,
+                    etag TEXT DEFAULT '',
+                    last_modified TEXT DEFAULT '',
+                    total_size INTEGER DEFAULT 0,
+                    PRIMARY KEY (download_id, stream)
+                );
+                CREATE TRIGGER IF NOT EXISTS download_streams_ad AFTER DELETE ON downloads BEGIN
+                    DELETE FROM download_streams WHERE download_id = old.id;
+                END;",
+            )
+        },
+    },
+    Migration {
+        // Downloads are filtered by platform even when no parse result is
+        // linked to them; linked ones take the platform of their parse
+        description: "add platform to downloads",
+        apply: |tx| {
+            if !has_column(tx, "downloads", "platform") {
+                tx.execute_batch("ALTER TABLE downloads ADD COLUMN platform TEXT DEFAULT '';")?;
+            }
+            tx.execute_batch(
+                "UPDATE downloads SET platform = (SELECT platform FROM parsed_items WHERE id = parsed_item_id)
+                 WHERE parsed_item_id IN (SELECT id FROM parsed_items);",
+            )
+        },
+    },
+];
+
+/// The `PRAGMA user_version` of `conn`.
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+// Applies the pending migrations, each in its own transaction together with
+// the version bump, after copying an existing database aside
+fn migrate(conn: &mut Connection, db_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let version = schema_version(conn)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "Database schema version {} is newer than this build supports ({}); update the app",
+            version, SCHEMA_VERSION
+        )
+        .into());
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
     }
 
-    conn.execute_batch(
-        "CREATE TABLE IF NOT EXISTS favorites (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            user_id INTEGER NOT NULL DEFAULT 0,
-            url TEXT NOT NULL,
-            title TEXT NOT NULL,
-            platform TEXT NOT NULL,
-            cover_url TEXT DEFAULT '',
-            author_name TEXT DEFAULT '',
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            UNIQUE(user_id, url)
-        );
-        CREATE TABLE IF NOT EXISTS users (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            username TEXT NOT NULL UNIQUE,
-            password TEXT NOT NULL,
-            email TEXT NOT NULL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );
-        CREATE TABLE IF NOT EXISTS downloads (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            user_id INTEGER NOT NULL DEFAULT 0,
-            url TEXT NOT NULL,
-            title TEXT NOT NULL,
-            cover_url TEXT DEFAULT '',
-            file_path TEXT NOT NULL,
-            status TEXT NOT NULL,
-            total_size INTEGER DEFAULT 0,
-            downloaded_size INTEGER DEFAULT 0,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );",
+    let has_tables: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        [],
+        |row| row.get(0),
     )?;
+    if has_tables {
+        let backup = backup_path(db_path, version);
+        let _ = std::fs::remove_file(&backup);
+        conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+        println!("[db] Backed up schema version {} to {:?}", version, backup);
+    }
 
-    println!("[db] Database initialized successfully");
-    Ok(conn)
+    for (i, migration) in MIGRATIONS.iter().enumerate() {
+        let target = i as u32 + 1;
+        // Another connection (download workers, API server) may have
+        // migrated since; the write lock taken here makes the check stick
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if schema_version(&tx)? >= target {
+            continue;
+        }
+        println!("[db] Migrating to version {}: {}", target, migration.description);
+        (migration.apply)(&tx)?;
+        tx.pragma_update(None, "user_version", target)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Where the copy of a database taken before migrating it from `version` goes,
+/// e.g. `favorites.db.v2.bak`.
+pub fn backup_path(db_path: &Path, version: u32) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    db_path.with_file_name(name)
+}
+
+// Recreates `table` with `create_sql`, keeping the rows and every column the
+// old and new definitions share
+fn rebuild_table(tx: &Transaction, table: &str, create_sql: &str) -> Result<()> {
+    let old_columns = columns(tx, table)?;
+    tx.execute_batch(&format!("ALTER TABLE {table} RENAME TO {table}_old; {create_sql};"))?;
+    let shared: Vec<String> = columns(tx, table)?
+        .into_iter()
+        .filter(|c| old_columns.contains(c))
+        .collect();
+    let shared = shared.join(", ");
+    tx.execute_batch(&format!(
+        "INSERT INTO {table} ({shared}) SELECT {shared} FROM {table}_old; DROP TABLE {table}_old;"
+    ))
+}
+
+fn columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?.collect();
+    columns
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+    columns(conn, table).is_ok_and(|columns| columns.iter().any(|c| c == column))
 }
@@ -0,0 +1,530 @@
+// Download queue persisted in the `downloads` table and worked off by a
+// bounded pool of tasks. A record moves through
+// queued -> downloading -> completed | failed | paused | cancelled, and every
+// transition is reported through the manager's progress callback. Progress
+// of running downloads is written to the database in batches.
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use futures_util::future::{BoxFuture, Either};
+use rusqlite::{Connection, OptionalExtension};
+use tokio::sync::oneshot;
+
+use crate::db::DbPool;
+use crate::downloader;
+use crate::downloads::{self, DownloadProgress, DownloadRecord};
+use crate::subtitles::ExportFormat;
+
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+// How often the progress of running downloads is saved
+const PROGRESS_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy)]
+enum Stop {
+    Pause,
+    Cancel,
+}
+
+// Running downloads. The sender is taken once a stop has been requested.
+type ActiveJobs = HashMap<i64, Option<oneshot::Sender<Stop>>>;
+
+// Latest (downloaded, total) of running downloads not yet saved
+type PendingProgress = HashMap<i64, (u64, Option<u64>)>;
+
+struct Inner {
+    db: DbPool,
+    client: reqwest::Client,
+    max_concurrent: AtomicUsize,
+    segments: AtomicUsize,
+    // Lock order: `active` before `pending` before a `db` connection. Held
+    // across database calls so state changes of a download never interleave.
+    active: tokio::sync::Mutex<ActiveJobs>,
+    pending: Mutex<PendingProgress>,
+    on_progress: Box<dyn Fn(&DownloadProgress) + Send + Sync>,
+}
+
+/// Handle to the download queue; clones share the same queue and workers.
+///
+/// Database work runs on Tokio's blocking pool, so every method that touches
+/// the queue is async and must be awaited within a runtime.
+#[derive(Clone)]
+pub struct DownloadManager {
+    inner: Arc<Inner>,
+}
+
+impl DownloadManager {
+    /// The queue lives in the `downloads` table of the database behind `db`.
+    pub fn new<F>(db: DbPool, client: reqwest::Client, max_concurrent: usize, on_progress: F) -> Self
+    where
+        F: Fn(&DownloadProgress) + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(Inner {
+                db,
+                client,
+                max_concurrent: AtomicUsize::new(max_concurrent.max(1)),
+                segments: AtomicUsize::new(downloader::DEFAULT_DOWNLOAD_SEGMENTS),
+                active: tokio::sync::Mutex::new(HashMap::new()),
+                pending: Mutex::new(HashMap::new()),
+                on_progress: Box::new(on_progress),
+            }),
+        }
+    }
+
+    /// Requeues downloads that were running when the app last exited and
+    /// starts working the queue and saving progress.
+    pub async fn start(&self) -> Result<(), String> {
+        let requeued = self
+            .inner
+            .db
+            .run(|conn| {
+                conn.execute("UPDATE downloads SET status = 'queued' WHERE status = 'downloading'", [])
+                    .map_err(|e| e.to_string())
+            })
+            .await?;
+        if requeued > 0 {
+            println!("[downloads] Requeued {} interrupted downloads", requeued);
+        }
+        self.spawn_progress_flusher();
+        self.pump().await;
+        Ok(())
+    }
+
+    /// Saves the latest progress of running downloads in one transaction.
+    /// Runs every second once the queue is started.
+    pub async fn flush_progress(&self) -> Result<(), String> {
+        let pending = std::mem::take(&mut *self.pending()?);
+        if pending.is_empty() {
+            return Ok(());
+        }
+        self.inner
+            .db
+            .run(move |conn| {
+                let tx = conn.transaction().map_err(|e| e.to_string())?;
+                {
+                    // A download that has since stopped already has its final numbers
+                    let mut stmt = tx
+                        .prepare_cached(
+                            "UPDATE downloads SET downloaded_size = ?1, total_size = ?2
+                             WHERE id = ?3 AND status = 'downloading'",
+                        )
+                        .map_err(|e| e.to_string())?;
+                    for (id, (downloaded, total)) in pending {
+                        stmt.execute(rusqlite::params![downloaded as i64, total.unwrap_or(0) as i64, id])
+                            .map_err(|e| e.to_string())?;
+                    }
+                }
+                tx.commit().map_err(|e| e.to_string())
+            })
+            .await
+    }
+
+    // Flushes progress until the last handle to the manager is dropped
+    fn spawn_progress_flusher(&self) {
+        let inner = Arc::downgrade(&self.inner);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PROGRESS_FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(inner) = inner.upgrade() else { break };
+                if let Err(e) = (DownloadManager { inner }).flush_progress().await {
+                    println!("[downloads] Failed to save progress: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Adds a download to the end of the queue and returns its record id.
+    /// With `audio_url`, `url` is a video-only stream and the two are muxed
+    /// into one MP4.
+    pub async fn enqueue(
+        &self,
+        user_id: i64,
+        url: &str,
+        audio_url: Option<&str>,
+        save_path: &str,
+        title: &str,
+        cover_url: &str,
+    ) -> Result<i64, String> {
+        let record = (url.to_string(), title.to_string(), cover_url.to_string(), save_path.to_string());
+        let audio_url = audio_url.filter(|a| !a.is_empty()).map(str::to_string);
+        let id = self
+            .inner
+            .db
+            .run(move |conn| {
+                let (url, title, cover_url, save_path) = record;
+                let tx = conn.transaction().map_err(|e| e.to_string())?;
+                let id = downloads::create_download_record(&tx, user_id, &url, &title, &cover_url, &save_path, "queued")
+                    .map_err(|e| e.to_string())?;
+                if let Some(audio_url) = audio_url {
+                    downloads::set_download_audio_url(&tx, id, &audio_url).map_err(|e| e.to_string())?;
+                }
+                tx.commit().map_err(|e| e.to_string())?;
+                Ok(id)
+            })
+            .await?;
+        self.emit(id, 0, None, "queued");
+        self.pump().await;
+        Ok(id)
+    }
+
+    /// Adds a subtitle track or danmaku download that is saved converted to
+    /// `format`, and returns its record id.
+    pub async fn enqueue_export(
+        &self,
+        user_id: i64,
+        url: &str,
+        format: ExportFormat,
+        save_path: &str,
+        title: &str,
+        cover_url: &str,
+    ) -> Result<i64, String> {
+        let record = (url.to_string(), title.to_string(), cover_url.to_string(), save_path.to_string());
+        let id = self
+            .inner
+            .db
+            .run(move |conn| {
+                let (url, title, cover_url, save_path) = record;
+                let tx = conn.transaction().map_err(|e| e.to_string())?;
+                let id = downloads::create_download_record(&tx, user_id, &url, &title, &cover_url, &save_path, "queued")
+                    .map_err(|e| e.to_string())?;
+                downloads::set_download_export_format(&tx, id, format.as_str()).map_err(|e| e.to_string())?;
+                tx.commit().map_err(|e| e.to_string())?;
+                Ok(id)
+            })
+            .await?;
+        self.emit(id, 0, None, "queued");
+        self.pump().await;
+        Ok(id)
+    }
+
+    /// Stops a running download or holds back a queued one. The partial file
+    /// is kept so resuming can continue where it stopped.
+    pub async fn pause(&self, id: i64) -> Result<(), String> {
+        let mut active = self.inner.active.lock().await;
+        if let Some(slot) = active.get_mut(&id) {
+            return match slot.take() {
+                Some(stop) => {
+                    let _ = stop.send(Stop::Pause);
+                    Ok(())
+                }
+                None => Err("Download is already stopping".to_string()),
+            };
+        }
+
+        let paused = self
+            .inner
+            .db
+            .run(move |conn| {
+                let record = find(conn, id)?;
+                match record.status.as_str() {
+                    "queued" => {
+                        downloads::set_download_status(conn, id, "paused").map_err(|e| e.to_string())?;
+                        Ok(Some(record))
+                    }
+                    "paused" => Ok(None),
+                    status => Err(format!("Cannot pause a {} download", status)),
+                }
+            })
+            .await?;
+        if let Some(record) = paused {
+            self.emit_record(&record, "paused");
+        }
+        Ok(())
+    }
+
+    /// Puts a paused download back in the queue.
+    pub async fn resume(&self, id: i64) -> Result<(), String> {
+        self.requeue(id, &["paused"]).await
+    }
+
+    /// Requeues a failed download, continuing its partial file when possible,
+    /// or a cancelled one from scratch.
+    pub async fn retry(&self, id: i64) -> Result<(), String> {
+        self.requeue(id, &["failed", "cancelled"]).await
+    }
+
+    /// Stops a download for good and removes its partial file. The record is
+    /// kept with status `cancelled` so it can be retried.
+    pub async fn cancel(&self, id: i64) -> Result<(), String> {
+        let mut active = self.inner.active.lock().await;
+        if let Some(slot) = active.get_mut(&id) {
+            return match slot.take() {
+                Some(stop) => {
+                    let _ = stop.send(Stop::Cancel);
+                    Ok(())
+                }
+                None => Err("Download is already stopping".to_string()),
+            };
+        }
+
+        let cancelled = self
+            .inner
+            .db
+            .run(move |conn| {
+                let record = find(conn, id)?;
+                match record.status.as_str() {
+                    "queued" | "paused" => {
+                        downloads::set_download_status(conn, id, "cancelled").map_err(|e| e.to_string())?;
+                        remove_partial_files(&record.file_path);
+                        Ok(Some(record))
+                    }
+                    "cancelled" => Ok(None),
+                    status => Err(format!("Cannot cancel a {} download", status)),
+                }
+            })
+            .await?;
+        if let Some(record) = cancelled {
+            self.emit_record(&record, "cancelled");
+        }
+        Ok(())
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.inner.max_concurrent.load(Ordering::SeqCst)
+    }
+
+    /// Changes the worker limit. Lowering it lets running downloads finish.
+    pub async fn set_max_concurrent(&self, max_concurrent: usize) {
+        self.inner.max_concurrent.store(max_concurrent.max(1), Ordering::SeqCst);
+        self.pump().await;
+    }
+
+    /// Connections per download for servers that support range requests.
+    /// Applies to downloads started afterwards.
+    pub fn set_segments(&self, segments: usize) {
+        self.inner.segments.store(segments.max(1), Ordering::SeqCst);
+    }
+
+    /// Number of downloads currently transferring.
+    pub async fn active_count(&self) -> usize {
+        self.inner.active.lock().await.len()
+    }
+
+    async fn requeue(&self, id: i64, from: &'static [&'static str]) -> Result<(), String> {
+        {
+            let _active = self.inner.active.lock().await;
+            let record = self
+                .inner
+                .db
+                .run(move |conn| {
+                    let record = find(conn, id)?;
+                    if !from.contains(&record.status.as_str()) {
+                        return Err(format!("Cannot requeue a {} download", record.status));
+                    }
+                    // Cancelling removed the partial file, so there is nothing to continue
+                    if record.status == "cancelled" {
+                        downloads::update_download_progress(conn, id, 0, 0, "queued").map_err(|e| e.to_string())?;
+                    } else {
+                        downloads::set_download_status(conn, id, "queued").map_err(|e| e.to_string())?;
+                    }
+                    Ok(record)
+                })
+                .await?;
+            if record.status == "cancelled" {
+                self.emit(id, 0, None, "queued");
+            } else {
+                self.emit_record(&record, "queued");
+            }
+        }
+        self.pump().await;
+        Ok(())
+    }
+
+    // Starts queued downloads, oldest first, until the worker limit is reached.
+    async fn pump(&self) {
+        let mut active = self.inner.active.lock().await;
+        while active.len() < self.max_concurrent() {
+            let job = match self.claim_next().await {
+                Ok(Some(job)) => job,
+                Ok(None) => break,
+                Err(e) => {
+                    println!("[downloads] Failed to read queue: {}", e);
+                    break;
+                }
+            };
+            let (stop_tx, stop_rx) = oneshot::channel();
+            active.insert(job.id, Some(stop_tx));
+            tokio::spawn(self.clone().run(job, stop_rx));
+        }
+    }
+
+    async fn claim_next(&self) -> Result<Option<DownloadRecord>, String> {
+        self.inner
+            .db
+            .run(|conn| {
+                let id: Option<i64> = conn
+                    .query_row(
+                        "SELECT id FROM downloads WHERE status = 'queued' ORDER BY id LIMIT 1",
+                        [],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(|e| e.to_string())?;
+                let Some(id) = id else { return Ok(None) };
+                downloads::set_download_status(conn, id, "downloading").map_err(|e| e.to_string())?;
+                downloads::get_download(conn, id).map_err(|e| e.to_string())
+            })
+            .await
+    }
+
+    // Boxed because it ends by pumping the queue, which spawns `run` again
+    fn run(self, job: DownloadRecord, stop: oneshot::Receiver<Stop>) -> BoxFuture<'static, ()> {
+        Box::pin(async move { self.run_job(job, stop).await })
+    }
+
+    async fn run_job(self, job: DownloadRecord, stop: oneshot::Receiver<Stop>) {
+        let id = job.id;
+        self.emit(id, 0, None, "downloading");
+
+        // Interrupted transfers continue from the bytes already on disk
+        let (previous, previous_streams) = self
+            .inner
+            .db
+            .run(move |conn| {
+                let streams = downloads::get_stream_validators(conn, id).map_err(|e| e.to_string())?;
+                Ok((downloads::get_download_validators(conn, id).ok(), streams))
+            })
+            .await
+            .unwrap_or_default();
+
+        let mut downloaded: u64 = 0;
+        let mut total_size: Option<u64> = None;
+        let outcome = {
+            let on_progress = |done, total| {
+                downloaded = done;
+                total_size = total;
+                if let Ok(mut pending) = self.pending() {
+                    pending.insert(id, (done, total));
+                }
+                self.emit(id, done, total, "downloading");
+            };
+            let path = Path::new(&job.file_path);
+            let segments = self.inner.segments.load(Ordering::SeqCst);
+            let export_format = job.export_format.parse::<ExportFormat>().ok();
+            let download = if let Some(format) = export_format {
+                Either::Left(Either::Right(downloader::download_converted(
+                    &self.inner.client,
+                    &job.url,
+                    path,
+                    format,
+                    on_progress,
+                )))
+            } else if job.audio_url.is_empty() {
+                Either::Left(Either::Left(downloader::download_segmented(
+                    &self.inner.client,
+                    &job.url,
+                    path,
+                    segments,
+                    previous.as_ref(),
+                    |validators| self.save_validators(id, None, validators),
+                    on_progress,
+                )))
+            } else {
+                Either::Right(downloader::download_muxed(
+                    &self.inner.client,
+                    &job.url,
+                    &job.audio_url,
+                    path,
+                    segments,
+                    &previous_streams,
+                    |stream, validators| self.save_validators(id, Some(stream), validators),
+                    on_progress,
+                ))
+            };
+            tokio::select! {
+                result = download => Ok(result),
+                Ok(stop) = stop => Err(stop),
+            }
+        };
+
+        let status = match &outcome {
+            Ok(Ok(_)) => "completed",
+            Ok(Err(e)) => {
+                println!("[downloads] Download {} failed: {}", id, e);
+                "failed"
+            }
+            Err(Stop::Pause) => "paused",
+            Err(Stop::Cancel) => {
+                remove_partial_files(&job.file_path);
+                "cancelled"
+            }
+        };
+        let recorded_total = match (status, total_size) {
+            ("completed", None) => downloaded,
+            (_, total) => total.unwrap_or(0),
+        };
+
+        // Record and announce the final state before leaving the pool, so a
+        // resume or retry issued right after sees it
+        {
+            let mut active = self.inner.active.lock().await;
+            if let Ok(mut pending) = self.pending() {
+                pending.remove(&id);
+            }
+            let _ = self
+                .inner
+                .db
+                .run(move |conn| {
+                    downloads::update_download_progress(conn, id, downloaded as i64, recorded_total as i64, status)
+                        .map_err(|e| e.to_string())
+                })
+                .await;
+            self.emit(id, downloaded, total_size, status);
+            active.remove(&id);
+        }
+        self.pump().await;
+    }
+
+    // Saved in the background; the transfer does not wait for the database.
+    // `stream` names one stream of a muxed download.
+    fn save_validators(&self, id: i64, stream: Option<&str>, validators: &downloader::Validators) {
+        let validators = validators.clone();
+        let stream = stream.map(str::to_string);
+        let db = self.inner.db.clone();
+        tokio::spawn(async move {
+            let _ = db
+                .run(move |conn| {
+                    match stream {
+                        Some(stream) => downloads::set_stream_validators(conn, id, &stream, &validators),
+                        None => downloads::set_download_validators(conn, id, &validators),
+                    }
+                    .map_err(|e| e.to_string())
+                })
+                .await;
+        });
+    }
+
+    fn emit(&self, id: i64, downloaded: u64, total: Option<u64>, status: &str) {
+        (self.inner.on_progress)(&DownloadProgress {
+            id,
+            downloaded,
+            total,
+            status: status.to_string(),
+        });
+    }
+
+    fn emit_record(&self, record: &DownloadRecord, status: &str) {
+        let total = (record.total_size > 0).then_some(record.total_size as u64);
+        self.emit(record.id, record.downloaded_size.max(0) as u64, total, status);
+    }
+
+    fn pending(&self) -> Result<MutexGuard<'_, PendingProgress>, String> {
+        self.inner.pending.lock().map_err(|e| e.to_string())
+    }
+}
+
+fn find(conn: &Connection, id: i64) -> Result<DownloadRecord, String> {
+    downloads::get_download(conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Download {} not found", id))
+}
+
+fn remove_partial_files(file_path: &str) {
+    let _ = std::fs::remove_file(file_path);
+    downloader::remove_temp_files(Path::new(file_path));
+}
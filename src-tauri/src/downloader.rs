@@ -0,0 +1,569 @@
+// Streaming file downloads shared by the `download_file` command and the CLI.
+use crate::models::{StreamKind, VideoParseInfo, VideoQuality};
+use crate::subtitles::ExportFormat;
+use futures_util::StreamExt;
+use reqwest::{header, StatusCode};
+use std::collections::HashMap;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+pub const DOWNLOAD_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+// Media CDNs that refuse requests without the site's Referer
+const CDN_REFERERS: &[(&str, &str)] = &[
+    ("bilivideo.com", "https://www.bilibili.com/"),
+    ("bilivideo.cn", "https://www.bilibili.com/"),
+    ("mirrorakam.akamaized.net", "https://www.bilibili.com/"),
+    ("hdslb.com", "https://www.bilibili.com/"),
+    ("api.bilibili.com", "https://www.bilibili.com/"),
+    ("sinaimg.cn", "https://weibo.com/"),
+];
+
+// GET with the headers every media download needs
+fn media_request(client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+    let req = client.get(url).header("User-Agent", DOWNLOAD_USER_AGENT);
+    let host = url::Url::parse(url).ok().and_then(|u| u.host_str().map(|h| h.to_string()));
+    let referer = host.and_then(|host| {
+        CDN_REFERERS
+            .iter()
+            .find(|(suffix, _)| host == *suffix || host.ends_with(&format!(".{}", suffix)))
+            .map(|(_, referer)| *referer)
+    });
+    match referer {
+        Some(referer) => req.header(header::REFERER, referer),
+        None => req,
+    }
+}
+
+/// Streams `url` into `path`, calling `on_progress(downloaded, total)` after every chunk.
+/// Returns the number of bytes written.
+pub async fn download_to_file<F>(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    on_progress: F,
+) -> anyhow::Result<u64>
+where
+    F: FnMut(u64, Option<u64>),
+{
+    download_resumable(client, url, path, None, |_| {}, on_progress).await
+}
+
+/// What the server said about a file, kept with a partial download so a later
+/// attempt can check it is continuing the same file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub total: Option<u64>,
+}
+
+impl Validators {
+    fn from_response(res: &reqwest::Response, total: Option<u64>) -> Self {
+        let header = |name| {
+            res.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        Self {
+            etag: header(header::ETAG),
+            last_modified: header(header::LAST_MODIFIED),
+            total,
+        }
+    }
+
+    // Weak ETags are not allowed in If-Range
+    fn if_range(&self) -> Option<&str> {
+        match &self.etag {
+            Some(etag) if !etag.starts_with("W/") => Some(etag),
+            _ => self.last_modified.as_deref(),
+        }
+    }
+}
+
+/// Like [`download_to_file`], but continues the partial file at `path` when
+/// `previous` holds the validators of the transfer that wrote it. Starts over
+/// when the server ignores the range, the file changed, or nothing identifies
+/// the original. `on_start` receives the validators of the transfer actually
+/// made so callers can store them before any bytes arrive.
+pub async fn download_resumable<S, F>(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    previous: Option<&Validators>,
+    mut on_start: S,
+    mut on_progress: F,
+) -> anyhow::Result<u64>
+where
+    S: FnMut(&Validators),
+    F: FnMut(u64, Option<u64>),
+{
+    let offset = match previous {
+        Some(prev) if prev.if_range().is_some() => std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        _ => 0,
+    };
+    let resume = previous.filter(|_| offset > 0);
+
+    let mut res = send_download_request(client, url, resume.map(|prev| (offset, prev))).await?;
+    if let Some(prev) = resume {
+        match res.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                if let Some(total) = resumed_total(&res, offset, prev) {
+                    let fresh = Validators::from_response(&res, Some(total));
+                    on_start(&Validators {
+                        etag: fresh.etag.or_else(|| prev.etag.clone()),
+                        last_modified: fresh.last_modified.or_else(|| prev.last_modified.clone()),
+                        total: Some(total),
+                    });
+                    let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+                    file.set_len(offset)?;
+                    file.seek(SeekFrom::End(0))?;
+                    return stream_to_file(res, file, offset, Some(total), on_progress).await;
+                }
+                println!("[download] Range response does not match the partial file, starting over");
+                res = send_download_request(client, url, None).await?;
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE if prev.total == Some(offset) => {
+                // The previous attempt already wrote the whole file
+                on_start(prev);
+                on_progress(offset, Some(offset));
+                return Ok(offset);
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                res = send_download_request(client, url, None).await?;
+            }
+            // A 200 means the server ignored the range or the file changed
+            _ => {}
+        }
+    }
+
+    let res = res.error_for_status()?;
+    let total_size = res.content_length();
+    on_start(&Validators::from_response(&res, total_size));
+    let file = std::fs::File::create(path)?;
+    stream_to_file(res, file, 0, total_size, on_progress).await
+}
+
+async fn send_download_request(
+    client: &reqwest::Client,
+    url: &str,
+    range: Option<(u64, &Validators)>,
+) -> reqwest::Result<reqwest::Response> {
+    let mut req = media_request(client, url);
+    if let Some((offset, prev)) = range {
+        req = req.header(header::RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = prev.if_range() {
+            req = req.header(header::IF_RANGE, validator);
+        }
+    }
+    req.send().await
+}
+
+// Start offset and total size from a 206 response's `Content-Range`; the
+// total is `None` when the server sends `*`.
+fn content_range(res: &reqwest::Response) -> Option<(u64, Option<u64>)> {
+    let value = res.headers().get(header::CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    let total = match total.trim() {
+        "*" => None,
+        t => Some(t.parse().ok()?),
+    };
+    Some((start, total))
+}
+
+// Total size announced by a 206 response, if it continues exactly at `offset`
+// and still describes the file `prev` was recorded for.
+fn resumed_total(res: &reqwest::Response, offset: u64, prev: &Validators) -> Option<u64> {
+    let (start, total) = content_range(res)?;
+    if start != offset {
+        return None;
+    }
+    let total = total.or(prev.total)?;
+    if prev.total.is_some_and(|known| known != total) {
+        return None;
+    }
+    let etag = res.headers().get(header::ETAG).and_then(|v| v.to_str().ok());
+    if let (Some(etag), Some(known)) = (etag, prev.etag.as_deref()) {
+        if etag != known {
+            return None;
+        }
+    }
+    Some(total)
+}
+
+async fn stream_to_file<F>(
+    res: reqwest::Response,
+    mut file: std::fs::File,
+    start: u64,
+    total_size: Option<u64>,
+    mut on_progress: F,
+) -> anyhow::Result<u64>
+where
+    F: FnMut(u64, Option<u64>),
+{
+    let mut downloaded = start;
+    let mut stream = res.bytes_stream();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk)?;
+        downloaded += chunk.len() as u64;
+        on_progress(downloaded, total_size);
+    }
+
+    file.flush()?;
+    Ok(downloaded)
+}
+
+/// Segment count used by the app and CLI unless configured otherwise.
+pub const DEFAULT_DOWNLOAD_SEGMENTS: usize = 4;
+/// Files smaller than this per segment are fetched over a single connection.
+pub const MIN_SEGMENT_SIZE: u64 = 1024 * 1024;
+
+/// Fetches `url` as up to `segments` byte ranges over parallel connections,
+/// each written to its own `.part` file next to `path` and joined into `path`
+/// once all have finished. Parts left by an interrupted attempt are continued
+/// when `previous` still matches the server's validators.
+///
+/// Falls back to [`download_resumable`] when the server does not support
+/// ranges or the file is too small to be worth splitting. Progress is the sum
+/// over all segments, reported a few times per second.
+pub async fn download_segmented<S, F>(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    segments: usize,
+    previous: Option<&Validators>,
+    mut on_start: S,
+    mut on_progress: F,
+) -> anyhow::Result<u64>
+where
+    S: FnMut(&Validators),
+    F: FnMut(u64, Option<u64>),
+{
+    let probe = if segments > 1 {
+        let res = media_request(client, url)
+            .header(header::RANGE, "bytes=0-0")
+            .send()
+            .await?;
+        match content_range(&res) {
+            Some((0, Some(total))) if res.status() == StatusCode::PARTIAL_CONTENT => {
+                Some(Validators::from_response(&res, Some(total)))
+            }
+            _ => None,
+        }
+    } else {
+        None
+    };
+    let Some(validators) = probe.filter(|v| v.total.unwrap_or(0) >= MIN_SEGMENT_SIZE * 2) else {
+        remove_segment_files(path);
+        return download_resumable(client, url, path, previous, on_start, on_progress).await;
+    };
+    let total = validators.total.unwrap_or(0);
+    let segments = (segments as u64).min(total / MIN_SEGMENT_SIZE).max(1);
+
+    // Parts are only trusted when a strong validator proves the file is unchanged
+    let if_range = validators.if_range().map(|v| v.to_string());
+    let same_file = if_range.is_some()
+        && previous.is_some_and(|prev| {
+            prev.total == validators.total
+                && prev.etag == validators.etag
+                && prev.last_modified == validators.last_modified
+        });
+    if !same_file {
+        remove_segment_files(path);
+    }
+    on_start(&validators);
+
+    let segment_len = total.div_ceil(segments);
+    let parts: Vec<(PathBuf, u64, u64)> = (0..segments)
+        .map(|i| {
+            let start = i * segment_len;
+            let end = ((i + 1) * segment_len).min(total) - 1;
+            (segment_path(path, i + 1, segments), start, end)
+        })
+        .collect();
+
+    let done = AtomicU64::new(0);
+    let work = futures_util::future::try_join_all(
+        parts
+            .iter()
+            .map(|(part, start, end)| fetch_segment(client, url, part, *start, *end, if_range.as_deref(), &done)),
+    );
+    tokio::pin!(work);
+    let mut ticker = tokio::time::interval(Duration::from_millis(250));
+    loop {
+        tokio::select! {
+            result = &mut work => {
+                result?;
+                break;
+            }
+            _ = ticker.tick() => on_progress(done.load(Ordering::Relaxed), Some(total)),
+        }
+    }
+
+    let mut file = std::fs::File::create(path)?;
+    for (part, _, _) in &parts {
+        std::io::copy(&mut std::fs::File::open(part)?, &mut file)?;
+    }
+    file.flush()?;
+    remove_segment_files(path);
+    on_progress(total, Some(total));
+    Ok(total)
+}
+
+// Downloads bytes `start..=end` into `part`, continuing whatever it already holds.
+async fn fetch_segment(
+    client: &reqwest::Client,
+    url: &str,
+    part: &Path,
+    start: u64,
+    end: u64,
+    if_range: Option<&str>,
+    done: &AtomicU64,
+) -> anyhow::Result<()> {
+    let len = end - start + 1;
+    let mut have = std::fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+    if have > len {
+        have = 0;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).write(true).truncate(false).open(part)?;
+    file.set_len(have)?;
+    file.seek(SeekFrom::End(0))?;
+    done.fetch_add(have, Ordering::Relaxed);
+    if have == len {
+        return Ok(());
+    }
+
+    let mut req = media_request(client, url).header(header::RANGE, format!("bytes={}-{}", start + have, end));
+    if let Some(validator) = if_range {
+        req = req.header(header::IF_RANGE, validator);
+    }
+    let res = req.send().await?.error_for_status()?;
+    if res.status() != StatusCode::PARTIAL_CONTENT || content_range(&res).map(|(s, _)| s) != Some(start + have) {
+        anyhow::bail!("server did not honour the range request for bytes {}-{}", start + have, end);
+    }
+
+    let mut stream = res.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        // Never write past the segment, even if the server sends more
+        let take = chunk.len().min((len - have) as usize);
+        file.write_all(&chunk[..take])?;
+        have += take as u64;
+        done.fetch_add(take as u64, Ordering::Relaxed);
+        if have == len {
+            break;
+        }
+    }
+    file.flush()?;
+    if have != len {
+        anyhow::bail!("connection closed after {} of {} bytes for bytes {}-{}", have, len, start, end);
+    }
+    Ok(())
+}
+
+fn segment_path(path: &Path, index: u64, count: u64) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!("{}.{}-of-{}.part", name, index, count))
+}
+
+/// Removes the `.part` files [`download_segmented`] keeps next to `path`.
+pub fn remove_segment_files(path: &Path) {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else { return };
+    let prefix = format!("{}.", name.to_string_lossy());
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let is_part = file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".part"))
+            .is_some_and(|rest| rest.contains("-of-"));
+        if is_part {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Downloads a DASH video stream and its audio track side by side (each via
+/// [`download_segmented`]) and muxes them into the MP4 at `path`. Progress is
+/// the sum of both streams; the muxed file size is returned.
+///
+/// The separate streams are kept next to `path` until muxing succeeds.
+/// `previous` holds the validators `on_start` reported for each stream
+/// (`"video"`, `"audio"`) in an earlier attempt: a stream already complete is
+/// not fetched again and a partial one is continued.
+#[allow(clippy::too_many_arguments)]
+pub async fn download_muxed<S, F>(
+    client: &reqwest::Client,
+    video_url: &str,
+    audio_url: &str,
+    path: &Path,
+    segments: usize,
+    previous: &HashMap<String, Validators>,
+    on_start: S,
+    mut on_progress: F,
+) -> anyhow::Result<u64>
+where
+    S: Fn(&str, &Validators),
+    F: FnMut(u64, Option<u64>),
+{
+    let video_path = stream_path(path, "video");
+    let audio_path = stream_path(path, "audio");
+    // (done, total) per stream; a total of 0 means not known yet
+    let video = [AtomicU64::new(0), AtomicU64::new(0)];
+    let audio = [AtomicU64::new(0), AtomicU64::new(0)];
+
+    let work = async {
+        tokio::try_join!(
+            fetch_stream(client, video_url, &video_path, segments, previous.get("video"), |v| on_start("video", v), &video),
+            fetch_stream(client, audio_url, &audio_path, segments, previous.get("audio"), |v| on_start("audio", v), &audio),
+        )
+    };
+    tokio::pin!(work);
+    let mut ticker = tokio::time::interval(Duration::from_millis(250));
+    let report = |on_progress: &mut F| {
+        let done = video[0].load(Ordering::Relaxed) + audio[0].load(Ordering::Relaxed);
+        let totals = (video[1].load(Ordering::Relaxed), audio[1].load(Ordering::Relaxed));
+        let total = (totals.0 > 0 && totals.1 > 0).then_some(totals.0 + totals.1);
+        on_progress(done, total);
+    };
+    loop {
+        tokio::select! {
+            result = &mut work => {
+                result?;
+                break;
+            }
+            _ = ticker.tick() => report(&mut on_progress),
+        }
+    }
+    report(&mut on_progress);
+
+    let (video_path, audio_path, out) = (video_path.clone(), audio_path.clone(), path.to_path_buf());
+    let size = tokio::task::spawn_blocking(move || crate::mux::mux_av(&video_path, &audio_path, &out)).await??;
+    remove_temp_files(path);
+    Ok(size)
+}
+
+/// Downloads a subtitle track or danmaku file and saves it converted to
+/// `format` (see [`crate::subtitles::convert`]). Returns the size written.
+pub async fn download_converted<F>(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    format: ExportFormat,
+    on_progress: F,
+) -> anyhow::Result<u64>
+where
+    F: FnMut(u64, Option<u64>),
+{
+    let source_path = source_path(path);
+    download_to_file(client, url, &source_path, on_progress).await?;
+    let source = tokio::fs::read(&source_path).await?;
+    let converted = crate::subtitles::convert(&source, format)?;
+    tokio::fs::write(path, &converted).await?;
+    let _ = tokio::fs::remove_file(&source_path).await;
+    Ok(converted.len() as u64)
+}
+
+// Where [`download_converted`] keeps the file as served
+fn source_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!("{}.src", name))
+}
+
+// One stream of [`download_muxed`]; a stream joined by an earlier attempt is
+// left as it is
+async fn fetch_stream<S>(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    segments: usize,
+    previous: Option<&Validators>,
+    on_start: S,
+    progress: &[AtomicU64; 2],
+) -> anyhow::Result<u64>
+where
+    S: FnMut(&Validators),
+{
+    let finished = previous
+        .and_then(|prev| prev.total)
+        .filter(|total| *total > 0 && std::fs::metadata(path).is_ok_and(|m| m.len() == *total));
+    if let Some(total) = finished {
+        store_progress(progress)(total, Some(total));
+        return Ok(total);
+    }
+    download_segmented(client, url, path, segments, previous, on_start, store_progress(progress)).await
+}
+
+fn store_progress(state: &[AtomicU64; 2]) -> impl FnMut(u64, Option<u64>) + '_ {
+    move |done, total| {
+        state[0].store(done, Ordering::Relaxed);
+        state[1].store(total.unwrap_or(0), Ordering::Relaxed);
+    }
+}
+
+// Where [`download_muxed`] keeps one of its streams before muxing
+fn stream_path(path: &Path, stream: &str) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!("{}.{}.m4s", name, stream))
+}
+
+/// Removes every temporary file a download of `path` may leave behind:
+/// segment parts, the separate streams of a muxed download and the source of
+/// a converted one.
+pub fn remove_temp_files(path: &Path) {
+    remove_segment_files(path);
+    let _ = std::fs::remove_file(source_path(path));
+    for stream in ["video", "audio"] {
+        let stream_path = stream_path(path, stream);
+        let _ = std::fs::remove_file(&stream_path);
+        remove_segment_files(&stream_path);
+    }
+}
+
+/// Picks the stream URL for `quality` (matched case-insensitively against
+/// `video_qualities`), or the parser's default when `quality` is `None`.
+pub fn video_url_for_quality<'a>(info: &'a VideoParseInfo, quality: Option<&str>) -> Option<&'a str> {
+    stream_for_quality(info, quality).map(|(video, _)| video)
+}
+
+/// Like [`video_url_for_quality`], plus the audio stream to mux in when the
+/// chosen quality is a DASH video stream. Audio-only entries are never picked.
+pub fn stream_for_quality<'a>(info: &'a VideoParseInfo, quality: Option<&str>) -> Option<(&'a str, Option<&'a str>)> {
+    let playable = |vq: &&VideoQuality| vq.kind != StreamKind::Audio;
+    let chosen = match quality {
+        Some(q) => info
+            .video_qualities
+            .iter()
+            .filter(playable)
+            .find(|vq| vq.quality.eq_ignore_ascii_case(q)),
+        None if !info.video_url.is_empty() => return Some((info.video_url.as_str(), None)),
+        None => info.video_qualities.iter().find(playable),
+    }?;
+    Some((chosen.video_url.as_str(), chosen.audio_url.as_deref()))
+}
+
+/// Turns a post title into something usable as a file name.
+pub fn sanitize_file_name(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(80)
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').to_string();
+    if cleaned.is_empty() {
+        "video".to_string()
+    } else {
+        cleaned
+    }
+}
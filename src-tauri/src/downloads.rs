@@ -1,7 +1,13 @@
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
-use tauri::{State, Manager};
-use crate::db::DbState;
+use rusqlite::Connection;
+use std::collections::HashMap;
+#[cfg(feature = "gui")]
+use tauri::State;
+#[cfg(feature = "gui")]
+use crate::auth;
+#[cfg(feature = "gui")]
+use crate::db::DbPool;
+use crate::downloader;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DownloadRecord {
@@ -14,83 +20,121 @@ pub struct DownloadRecord {
     pub status: String,
     pub total_size: i64,
     pub downloaded_size: i64,
+    /// Separate audio stream muxed into the file, empty for single-stream downloads
+    pub audio_url: String,
+    /// Subtitle or danmaku format the file is converted to, empty for media
+    pub export_format: String,
+    /// Cached parse result of the page the download came from, see [`crate::parsed_items`]
+    pub parsed_item_id: Option<i64>,
     pub created_at: String,
+    /// Platform of the page the download came from, empty when unknown
+    pub platform: String,
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn get_downloads(
-    state: State<'_, DbState>,
-    user_id: i64,
+    state: State<'_, DbPool>,
+    token: String,
 ) -> Result<Vec<DownloadRecord>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    
-    let mut stmt = conn
-        .prepare("SELECT id, user_id, url, title, cover_url, file_path, status, total_size, downloaded_size, created_at FROM downloads WHERE user_id = ? ORDER BY id DESC")
-        .map_err(|e| e.to_string())?;
-        
-    let iter = stmt
-        .query_map([user_id], |row| {
-            Ok(DownloadRecord {
-                id: row.get(0)?,
-                user_id: row.get(1)?,
-                url: row.get(2)?,
-                title: row.get(3)?,
-                cover_url: row.get(4)?,
-                file_path: row.get(5)?,
-                status: row.get(6)?,
-                total_size: row.get(7)?,
-                downloaded_size: row.get(8)?,
-                created_at: row.get(9)?,
-            })
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            list_downloads(conn, user_id).map_err(|e| e.to_string())
         })
-        .map_err(|e| e.to_string())?;
+        .await
+}
 
-    let mut downloads = Vec::new();
-    for download in iter {
-        if let Ok(dl) = download {
-            downloads.push(dl);
-        }
-    }
-    Ok(downloads)
+const DOWNLOAD_COLUMNS: &str =
+    "id, user_id, url, title, cover_url, file_path, status, total_size, downloaded_size, audio_url, export_format, parsed_item_id, created_at, platform";
+
+fn record_from_row(row: &rusqlite::Row) -> rusqlite::Result<DownloadRecord> {
+    Ok(DownloadRecord {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        url: row.get(2)?,
+        title: row.get(3)?,
+        cover_url: row.get(4)?,
+        file_path: row.get(5)?,
+        status: row.get(6)?,
+        total_size: row.get(7)?,
+        downloaded_size: row.get(8)?,
+        audio_url: row.get::<_, Option<String>>(9)?.unwrap_or_default(),
+        export_format: row.get::<_, Option<String>>(10)?.unwrap_or_default(),
+        parsed_item_id: row.get(11)?,
+        created_at: row.get(12)?,
+        platform: row.get::<_, Option<String>>(13)?.unwrap_or_default(),
+    })
 }
 
+/// Download records of `user_id`, newest first. Rows that fail to decode are skipped.
+pub fn list_downloads(conn: &Connection, user_id: i64) -> rusqlite::Result<Vec<DownloadRecord>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM downloads WHERE user_id = ? ORDER BY id DESC",
+        DOWNLOAD_COLUMNS
+    ))?;
+    let iter = stmt.query_map([user_id], record_from_row)?;
+
+    Ok(iter.filter_map(|download| download.ok()).collect())
+}
+
+pub fn get_download(conn: &Connection, id: i64) -> rusqlite::Result<Option<DownloadRecord>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM downloads WHERE id = ?", DOWNLOAD_COLUMNS))?;
+    let mut rows = stmt.query_map([id], record_from_row)?;
+    rows.next().transpose()
+}
+
+/// Download `id` if it belongs to `user_id`. Another user's download is
+/// reported as not found, like a missing one.
+pub fn owned_download(conn: &Connection, user_id: i64, id: i64) -> Result<DownloadRecord, String> {
+    get_download(conn, id)
+        .map_err(|e| e.to_string())?
+        .filter(|record| record.user_id == user_id)
+        .ok_or_else(|| format!("Download {} not found", id))
+}
+
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub async fn remove_download_record(
-    state: State<'_, DbState>,
+    state: State<'_, DbPool>,
+    manager: State<'_, crate::download_manager::DownloadManager>,
+    token: String,
     id: i64,
     delete_file: bool,
 ) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    
-    
-    if delete_file {
-        // First get the file_path
-        let file_path: Result<String, _> = conn.query_row(
-            "SELECT file_path FROM downloads WHERE id = ?",
-            [id],
-            |row| row.get(0),
-        );
-        
-        if let Ok(path_str) = file_path {
-            if !path_str.is_empty() {
-                // Ignore error if file doesn't exist or we don't have permission
-                let _ = std::fs::remove_file(path_str);
-            }
-        }
+    let user_id = state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            owned_download(conn, user_id, id)?;
+            Ok(user_id)
+        })
+        .await?;
+    // Stop the transfer first; it is fine if the download is not running
+    let _ = manager.cancel(id).await;
+    state.run(move |conn| delete_download(conn, user_id, id, delete_file)).await
+}
+
+/// Removes download record `id` of `user_id`, and with `delete_file` the
+/// downloaded file too.
+pub fn delete_download(conn: &Connection, user_id: i64, id: i64, delete_file: bool) -> Result<(), String> {
+    let record = owned_download(conn, user_id, id)?;
+    if delete_file && !record.file_path.is_empty() {
+        // Ignore error if file doesn't exist or we don't have permission
+        let _ = std::fs::remove_file(&record.file_path);
     }
 
     conn.execute(
-        "DELETE FROM downloads WHERE id = ?",
-        [id],
+        "DELETE FROM downloads WHERE id = ?1 AND user_id = ?2",
+        rusqlite::params![id, user_id],
     )
     .map_err(|e| e.to_string())?;
-    
+
     Ok(())
 }
 
 // Helper function to insert a new download record
 pub fn create_download_record(
-    conn: &mut std::sync::MutexGuard<'_, rusqlite::Connection>,
+    conn: &rusqlite::Connection,
     user_id: i64,
     url: &str,
     title: &str,
@@ -107,7 +151,7 @@ pub fn create_download_record(
 
 // Helper function to update download progress
 pub fn update_download_progress(
-    conn: &mut std::sync::MutexGuard<'_, rusqlite::Connection>,
+    conn: &rusqlite::Connection,
     id: i64,
     downloaded_size: i64,
     total_size: i64,
@@ -119,3 +163,106 @@ pub fn update_download_progress(
     )?;
     Ok(())
 }
+
+/// Progress of a recorded download, as broadcast on `download://progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub id: i64,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    pub status: String,
+}
+
+pub fn set_download_audio_url(conn: &Connection, id: i64, audio_url: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE downloads SET audio_url = ?1 WHERE id = ?2",
+        rusqlite::params![audio_url, id],
+    )?;
+    Ok(())
+}
+
+pub fn set_download_platform(conn: &Connection, id: i64, platform: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE downloads SET platform = ?1 WHERE id = ?2",
+        rusqlite::params![platform, id],
+    )?;
+    Ok(())
+}
+
+pub fn set_download_export_format(conn: &Connection, id: i64, format: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE downloads SET export_format = ?1 WHERE id = ?2",
+        rusqlite::params![format, id],
+    )?;
+    Ok(())
+}
+
+pub fn set_download_status(conn: &Connection, id: i64, status: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE downloads SET status = ?1 WHERE id = ?2",
+        rusqlite::params![status, id],
+    )?;
+    Ok(())
+}
+
+/// Validators stored for a download by [`set_download_validators`].
+pub fn get_download_validators(conn: &Connection, id: i64) -> rusqlite::Result<downloader::Validators> {
+    conn.query_row(
+        "SELECT etag, last_modified, total_size FROM downloads WHERE id = ?",
+        [id],
+        |row| validators_from_row(row, 0),
+    )
+}
+
+// etag, last_modified and total size, starting at column `first`
+fn validators_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<downloader::Validators> {
+    let non_empty = |s: Option<String>| s.filter(|s| !s.is_empty());
+    Ok(downloader::Validators {
+        etag: non_empty(row.get(first)?),
+        last_modified: non_empty(row.get(first + 1)?),
+        total: row.get::<_, Option<i64>>(first + 2)?.filter(|t| *t > 0).map(|t| t as u64),
+    })
+}
+
+/// Validators stored for each stream of a muxed download by
+/// [`set_stream_validators`], by stream name.
+pub fn get_stream_validators(conn: &Connection, id: i64) -> rusqlite::Result<HashMap<String, downloader::Validators>> {
+    let mut stmt = conn.prepare("SELECT stream, etag, last_modified, total_size FROM download_streams WHERE download_id = ?")?;
+    let rows = stmt.query_map([id], |row| Ok((row.get(0)?, validators_from_row(row, 1)?)))?;
+    rows.collect()
+}
+
+pub fn set_stream_validators(
+    conn: &Connection,
+    id: i64,
+    stream: &str,
+    validators: &downloader::Validators,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO download_streams (download_id, stream, etag, last_modified, total_size)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT (download_id, stream) DO UPDATE SET
+            etag = excluded.etag, last_modified = excluded.last_modified, total_size = excluded.total_size",
+        rusqlite::params![
+            id,
+            stream,
+            validators.etag.as_deref().unwrap_or(""),
+            validators.last_modified.as_deref().unwrap_or(""),
+            validators.total.unwrap_or(0) as i64,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn set_download_validators(conn: &Connection, id: i64, validators: &downloader::Validators) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE downloads SET etag = ?1, last_modified = ?2, total_size = ?3 WHERE id = ?4",
+        rusqlite::params![
+            validators.etag.as_deref().unwrap_or(""),
+            validators.last_modified.as_deref().unwrap_or(""),
+            validators.total.unwrap_or(0) as i64,
+            id
+        ],
+    )?;
+    Ok(())
+}
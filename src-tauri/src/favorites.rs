@@ -1,5 +1,10 @@
-use crate::db::DbState;
+#[cfg(feature = "gui")]
+use crate::auth;
+#[cfg(feature = "gui")]
+use crate::db::DbPool;
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
 use tauri::State;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -10,28 +15,60 @@ pub struct Favorite {
     pub platform: String,
     pub cover_url: String,
     pub author_name: String,
+    /// Cached parse result to reopen the favorite from, see [`crate::parsed_items`]
+    pub parsed_item_id: Option<i64>,
     pub created_at: String,
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub fn add_favorite(
-    state: State<DbState>,
-    user_id: i64,
+pub async fn add_favorite(
+    state: State<'_, DbPool>,
+    token: String,
     url: String,
     title: String,
     platform: String,
     cover_url: String,
     author_name: String,
 ) -> Result<Favorite, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            insert_favorite(conn, user_id, &url, &title, &platform, &cover_url, &author_name).map_err(|e| e.to_string())
+        })
+        .await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn remove_favorite(state: State<'_, DbPool>, token: String, id: i64) -> Result<(), String> {
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            delete_favorite(conn, user_id, id)
+        })
+        .await
+}
+
+/// Adds `url` to the favorites of `user_id`, or returns the existing favorite.
+pub fn insert_favorite(
+    conn: &Connection,
+    user_id: i64,
+    url: &str,
+    title: &str,
+    platform: &str,
+    cover_url: &str,
+    author_name: &str,
+) -> rusqlite::Result<Favorite> {
     conn.execute(
-        "INSERT OR IGNORE INTO favorites (user_id, url, title, platform, cover_url, author_name) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT OR IGNORE INTO favorites (user_id, url, title, platform, cover_url, author_name, parsed_item_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT id FROM parsed_items WHERE url = ?2))",
         rusqlite::params![user_id, url, title, platform, cover_url, author_name],
-    ).map_err(|e| e.to_string())?;
+    )?;
 
     // Return the inserted/existing favorite
     let fav = conn.query_row(
-        "SELECT id, url, title, platform, cover_url, author_name, created_at FROM favorites WHERE user_id = ?1 AND url = ?2",
+        "SELECT id, url, title, platform, cover_url, author_name, parsed_item_id, created_at FROM favorites WHERE user_id = ?1 AND url = ?2",
         rusqlite::params![user_id, url],
         |row| {
             Ok(Favorite {
@@ -41,10 +78,11 @@ pub fn add_favorite(
                 platform: row.get(3)?,
                 cover_url: row.get(4)?,
                 author_name: row.get(5)?,
-                created_at: row.get(6)?,
+                parsed_item_id: row.get(6)?,
+                created_at: row.get(7)?,
             })
         },
-    ).map_err(|e| e.to_string())?;
+    )?;
 
     println!(
         "[favorites] Added favorite for user {}: {} ({})",
@@ -53,35 +91,55 @@ pub fn add_favorite(
     Ok(fav)
 }
 
-#[tauri::command]
-pub fn remove_favorite(state: State<DbState>, id: i64) -> Result<(), String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM favorites WHERE id = ?1", rusqlite::params![id])
+/// Removes favorite `id` of `user_id`. Another user's favorite is reported as
+/// not found, like a missing one.
+pub fn delete_favorite(conn: &Connection, user_id: i64, id: i64) -> Result<(), String> {
+    let deleted = conn
+        .execute(
+            "DELETE FROM favorites WHERE id = ?1 AND user_id = ?2",
+            rusqlite::params![id, user_id],
+        )
         .map_err(|e| e.to_string())?;
+    if deleted == 0 {
+        return Err(format!("Favorite {} not found", id));
+    }
     println!("[favorites] Removed favorite id: {}", id);
     Ok(())
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub fn get_favorites(
-    state: State<DbState>,
-    user_id: i64,
+pub async fn get_favorites(
+    state: State<'_, DbPool>,
+    token: String,
     platform: Option<String>,
 ) -> Result<Vec<Favorite>, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            list_favorites(conn, user_id, platform.as_deref()).map_err(|e| e.to_string())
+        })
+        .await
+}
 
-    let (sql, params): (&str, Vec<Box<dyn rusqlite::types::ToSql>>) = match &platform {
+/// Favorites of `user_id`, newest first; `platform` of `None`, `""` or `"all"` returns every platform.
+pub fn list_favorites(
+    conn: &Connection,
+    user_id: i64,
+    platform: Option<&str>,
+) -> rusqlite::Result<Vec<Favorite>> {
+    let (sql, params): (&str, Vec<Box<dyn rusqlite::types::ToSql>>) = match platform {
         Some(p) if !p.is_empty() && p != "all" => (
-            "SELECT id, url, title, platform, cover_url, author_name, created_at FROM favorites WHERE user_id = ?1 AND platform = ?2 ORDER BY created_at DESC",
-            vec![Box::new(user_id), Box::new(p.clone())],
+            "SELECT id, url, title, platform, cover_url, author_name, parsed_item_id, created_at FROM favorites WHERE user_id = ?1 AND platform = ?2 ORDER BY created_at DESC",
+            vec![Box::new(user_id), Box::new(p.to_string())],
         ),
         _ => (
-            "SELECT id, url, title, platform, cover_url, author_name, created_at FROM favorites WHERE user_id = ?1 ORDER BY created_at DESC",
+            "SELECT id, url, title, platform, cover_url, author_name, parsed_item_id, created_at FROM favorites WHERE user_id = ?1 ORDER BY created_at DESC",
             vec![Box::new(user_id)],
         ),
     };
 
-    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(sql)?;
     let params_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
     let favorites = stmt
         .query_map(params_refs.as_slice(), |row| {
@@ -92,25 +150,31 @@ pub fn get_favorites(
                 platform: row.get(3)?,
                 cover_url: row.get(4)?,
                 author_name: row.get(5)?,
-                created_at: row.get(6)?,
+                parsed_item_id: row.get(6)?,
+                created_at: row.get(7)?,
             })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
 
     Ok(favorites)
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub fn is_favorited(state: State<DbState>, user_id: i64, url: String) -> Result<bool, String> {
-    let conn = state.0.lock().map_err(|e| e.to_string())?;
-    let count: i64 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM favorites WHERE user_id = ?1 AND url = ?2",
-            rusqlite::params![user_id, url],
-            |row| row.get(0),
-        )
-        .map_err(|e| e.to_string())?;
+pub async fn is_favorited(state: State<'_, DbPool>, token: String, url: String) -> Result<bool, String> {
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            is_favorite(conn, user_id, &url).map_err(|e| e.to_string())
+        })
+        .await
+}
+
+pub fn is_favorite(conn: &Connection, user_id: i64, url: &str) -> rusqlite::Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM favorites WHERE user_id = ?1 AND url = ?2",
+        rusqlite::params![user_id, url],
+        |row| row.get(0),
+    )?;
     Ok(count > 0)
 }
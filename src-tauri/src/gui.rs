@@ -0,0 +1,642 @@
+// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+use crate::download_manager::{DownloadManager, DEFAULT_MAX_CONCURRENT_DOWNLOADS};
+use crate::models::VideoParseInfo;
+use crate::parser::batch::{BatchItem, BatchSummary, DEFAULT_BATCH_CONCURRENCY};
+use crate::parser::{ParseContext, ParseError, ParserRegistry};
+use crate::secrets::{self, KeySource, SecretStore, SecretsState};
+use crate::server::{ApiConfig, ApiServer};
+use crate::subtitles::ExportFormat;
+use crate::{auth, cookie_store, db, downloader, downloads, favorites, history, parsed_items, search};
+use tauri::{Manager, Emitter};
+
+// With the `token` of a logged-in user the parse goes into their history
+#[tauri::command]
+async fn parse_video(
+    _app: tauri::AppHandle,
+    registry: tauri::State<'_, ParserRegistry>,
+    ctx: tauri::State<'_, ParseContext>,
+    db: tauri::State<'_, db::DbPool>,
+    url: String,
+    token: Option<String>,
+) -> Result<VideoParseInfo, ParseError> {
+    let info = registry.parse(&ctx, &url).await?;
+    cache_parse(&db, url, &info, token).await;
+    Ok(info)
+}
+
+// Parses the input of history entry `id` again; the new result is recorded
+// as a new entry
+#[tauri::command]
+async fn reparse_history_entry(
+    registry: tauri::State<'_, ParserRegistry>,
+    ctx: tauri::State<'_, ParseContext>,
+    db: tauri::State<'_, db::DbPool>,
+    token: String,
+    id: i64,
+) -> Result<VideoParseInfo, String> {
+    let entry = {
+        let token = token.clone();
+        db.run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            history::owned_history_entry(conn, user_id, id)
+        })
+        .await?
+    };
+    let info = registry.parse(&ctx, &entry.input).await.map_err(|e| e.to_string())?;
+    cache_parse(&db, entry.input, &info, Some(token)).await;
+    Ok(info)
+}
+
+// Keeps the result so favorites, downloads and history of `url` reopen
+// without a new parse, and records it in the history of the user logged in
+// as `token`. Neither failing fails the parse.
+async fn cache_parse(db: &db::DbPool, url: String, info: &VideoParseInfo, token: Option<String>) {
+    let info = info.clone();
+    let saved = db
+        .run(move |conn| {
+            let item_id = parsed_items::save_parsed_item(conn, &url, &info)?;
+            // Parsing does not need an account; only users get a history
+            let Some(user) = token.and_then(|token| auth::session_user(conn, &token).ok()) else {
+                return Ok(());
+            };
+            history::record_history(conn, user.id, &url, &info, Some(item_id)).map(|_| ())
+        })
+        .await;
+    if let Err(e) = saved {
+        println!("[parse] Failed to cache parse result: {}", e);
+    }
+}
+
+// Links download `id` to the cached parse of the page it came from, and
+// records the platform of that page even when it has no cached parse
+async fn link_download(db: &db::DbPool, registry: &ParserRegistry, id: i64, page_url: Option<String>) {
+    let Some(page_url) = page_url.filter(|u| !u.is_empty()) else { return };
+    let platform = registry.find(&page_url).map(|p| p.platform());
+    let linked = db
+        .run(move |conn| {
+            if let Some(platform) = platform {
+                downloads::set_download_platform(conn, id, platform).map_err(|e| e.to_string())?;
+            }
+            parsed_items::link_download(conn, id, &page_url)
+        })
+        .await;
+    if let Err(e) = linked {
+        println!("[downloads] Failed to link download {} to its parse result: {}", id, e);
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
+struct BatchItemPayload {
+    batch_id: String,
+    #[serde(flatten)]
+    item: BatchItem,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct BatchDonePayload {
+    batch_id: String,
+    #[serde(flatten)]
+    summary: BatchSummary,
+}
+
+// Results stream through `parse://batch-item` events as each link finishes;
+// `parse://batch-done` follows with the same summary this command returns.
+#[tauri::command]
+async fn parse_batch(
+    app: tauri::AppHandle,
+    registry: tauri::State<'_, ParserRegistry>,
+    ctx: tauri::State<'_, ParseContext>,
+    text: String,
+    concurrency: Option<usize>,
+    batch_id: Option<String>,
+) -> Result<BatchDonePayload, String> {
+    let batch_id = batch_id.unwrap_or_else(|| chrono::Utc::now().timestamp_millis().to_string());
+    let concurrency = concurrency.unwrap_or(DEFAULT_BATCH_CONCURRENCY);
+
+    let summary = registry
+        .parse_batch(&ctx, &text, concurrency, |item| {
+            let _ = app.emit("parse://batch-item", BatchItemPayload {
+                batch_id: batch_id.clone(),
+                item,
+            });
+        })
+        .await;
+
+    let done = BatchDonePayload { batch_id, summary };
+    let _ = app.emit("parse://batch-done", done.clone());
+    Ok(done)
+}
+
+// The user a download command acts for
+async fn session_user_id(db: &db::DbPool, token: String) -> Result<i64, String> {
+    db.run(move |conn| Ok(auth::session_user(conn, &token)?.id)).await
+}
+
+// Downloads can only be controlled by the user who queued them
+async fn check_download_owner(db: &db::DbPool, token: String, id: i64) -> Result<(), String> {
+    db.run(move |conn| {
+        let user_id = auth::session_user(conn, &token)?.id;
+        downloads::owned_download(conn, user_id, id).map(|_| ())
+    })
+    .await
+}
+
+// Queues the download and returns its record id; progress and the final
+// status arrive as `download://progress` events. `audio_url` is set for DASH
+// qualities, whose video stream has no sound of its own. `page_url` is the
+// parsed link the media came from.
+#[tauri::command]
+async fn download_file(
+    registry: tauri::State<'_, ParserRegistry>,
+    db: tauri::State<'_, db::DbPool>,
+    manager: tauri::State<'_, DownloadManager>,
+    token: String,
+    url: String,
+    audio_url: Option<String>,
+    save_path: String,
+    title: String,
+    cover_url: String,
+    page_url: Option<String>,
+) -> Result<i64, String> {
+    let user_id = session_user_id(&db, token).await?;
+    let id = manager.enqueue(user_id, &url, audio_url.as_deref(), &save_path, &title, &cover_url).await?;
+    link_download(&db, &registry, id, page_url).await;
+    Ok(id)
+}
+
+// Queues a subtitle track or the danmaku of a video; the file is saved in
+// `format` (srt/vtt for subtitles, ass/xml for danmaku)
+#[tauri::command]
+async fn download_export(
+    registry: tauri::State<'_, ParserRegistry>,
+    db: tauri::State<'_, db::DbPool>,
+    manager: tauri::State<'_, DownloadManager>,
+    token: String,
+    url: String,
+    format: String,
+    save_path: String,
+    title: String,
+    cover_url: String,
+    page_url: Option<String>,
+) -> Result<i64, String> {
+    let format: ExportFormat = format.parse()?;
+    let user_id = session_user_id(&db, token).await?;
+    let id = manager.enqueue_export(user_id, &url, format, &save_path, &title, &cover_url).await?;
+    link_download(&db, &registry, id, page_url).await;
+    Ok(id)
+}
+
+// Queues every part of the multi-part video or collection at `url` into
+// `dir`, in `quality` where a part has it. Parts that fail to parse are
+// skipped; returns the record ids of the queued parts.
+#[tauri::command]
+async fn download_parts(
+    registry: tauri::State<'_, ParserRegistry>,
+    ctx: tauri::State<'_, ParseContext>,
+    db: tauri::State<'_, db::DbPool>,
+    manager: tauri::State<'_, DownloadManager>,
+    token: String,
+    url: String,
+    dir: String,
+    quality: Option<String>,
+) -> Result<Vec<i64>, String> {
+    let user_id = session_user_id(&db, token).await?;
+    let info = registry.parse(&ctx, &url).await.map_err(|e| e.to_string())?;
+    if info.parts.is_empty() {
+        return Err("Not a multi-part video or collection".to_string());
+    }
+
+    let mut ids = Vec::new();
+    for part in &info.parts {
+        let part_info = if part.current {
+            info.clone()
+        } else {
+            match registry.parse(&ctx, &part.url).await {
+                Ok(part_info) => part_info,
+                Err(e) => {
+                    println!("[parts] Skipping {}: {}", part.url, e);
+                    continue;
+                }
+            }
+        };
+        cache_parse(&db, part.url.clone(), &part_info, None).await;
+        let stream = downloader::stream_for_quality(&part_info, quality.as_deref())
+            .or_else(|| downloader::stream_for_quality(&part_info, None));
+        let Some((video_url, audio_url)) = stream else {
+            println!("[parts] Skipping {}: no video stream", part.url);
+            continue;
+        };
+        let path = std::path::Path::new(&dir).join(format!("{}.mp4", downloader::sanitize_file_name(&part_info.title)));
+        let id = manager
+            .enqueue(
+                user_id,
+                video_url,
+                audio_url,
+                &path.to_string_lossy(),
+                &part_info.title,
+                &part_info.cover_url,
+            )
+            .await?;
+        link_download(&db, &registry, id, Some(part.url.clone())).await;
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+#[tauri::command]
+async fn pause_download(
+    db: tauri::State<'_, db::DbPool>,
+    manager: tauri::State<'_, DownloadManager>,
+    token: String,
+    id: i64,
+) -> Result<(), String> {
+    check_download_owner(&db, token, id).await?;
+    manager.pause(id).await
+}
+
+#[tauri::command]
+async fn resume_download(
+    db: tauri::State<'_, db::DbPool>,
+    manager: tauri::State<'_, DownloadManager>,
+    token: String,
+    id: i64,
+) -> Result<(), String> {
+    check_download_owner(&db, token, id).await?;
+    manager.resume(id).await
+}
+
+#[tauri::command]
+async fn cancel_download(
+    db: tauri::State<'_, db::DbPool>,
+    manager: tauri::State<'_, DownloadManager>,
+    token: String,
+    id: i64,
+) -> Result<(), String> {
+    check_download_owner(&db, token, id).await?;
+    manager.cancel(id).await
+}
+
+#[tauri::command]
+async fn retry_download(
+    db: tauri::State<'_, db::DbPool>,
+    manager: tauri::State<'_, DownloadManager>,
+    token: String,
+    id: i64,
+) -> Result<(), String> {
+    check_download_owner(&db, token, id).await?;
+    manager.retry(id).await
+}
+
+#[tauri::command]
+async fn set_max_concurrent_downloads(
+    db: tauri::State<'_, db::DbPool>,
+    manager: tauri::State<'_, DownloadManager>,
+    token: String,
+    max_concurrent: usize,
+) -> Result<(), String> {
+    session_user_id(&db, token).await?;
+    manager.set_max_concurrent(max_concurrent).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_download_segments(
+    db: tauri::State<'_, db::DbPool>,
+    manager: tauri::State<'_, DownloadManager>,
+    token: String,
+    segments: usize,
+) -> Result<(), String> {
+    session_user_id(&db, token).await?;
+    manager.set_segments(segments);
+    Ok(())
+}
+
+#[tauri::command]
+fn open_path(path: String) -> Result<(), String> {
+    use std::process::Command;
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    #[cfg(target_os = "windows")]
+    {
+         Command::new("explorer")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn reveal_path(path: String) -> Result<(), String> {
+    use std::process::Command;
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg("-R")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    #[cfg(target_os = "windows")]
+    {
+         Command::new("explorer")
+            .arg("/select,")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Proxy image through backend to bypass hotlink protection
+// Returns base64 data URL that can be used in img src
+#[tauri::command]
+async fn proxy_image(url: String) -> Result<String, String> {
+    use base64::{Engine as _, engine::general_purpose};
+    
+    println!("[proxy_image] Proxying URL: {}", url);
+    
+    let client = reqwest::Client::new();
+    let res = client.get(&url)
+        .header("User-Agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+        .header("Referer", "https://weibo.com/")
+        .send()
+        .await
+        .map_err(|e| {
+            println!("[proxy_image] Request failed: {}", e);
+            e.to_string()
+        })?;
+
+    let status = res.status();
+    println!("[proxy_image] Response status: {}", status);
+    
+    if !status.is_success() {
+        return Err(format!("HTTP error: {}", status));
+    }
+
+    let content_type = res.headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("image/jpeg")
+        .to_string();
+
+    let bytes = res.bytes().await.map_err(|e| e.to_string())?;
+    println!("[proxy_image] Downloaded {} bytes, content-type: {}", bytes.len(), content_type);
+    
+    let base64_data = general_purpose::STANDARD.encode(&bytes);
+    
+    // Return as data URL
+    Ok(format!("data:{};base64,{}", content_type, base64_data))
+}
+
+#[tauri::command]
+async fn cache_video(app: tauri::AppHandle, url: String) -> Result<String, String> {
+    use std::io::Write;
+    use tauri::Manager;
+    
+    println!("[cache_video] Caching URL: {}", url);
+    
+    // Create a temp directory for videos if not exists
+    let temp_dir = app.path().temp_dir().map_err(|e| e.to_string())?.join("tauri_video_cache");
+    if !temp_dir.exists() {
+        std::fs::create_dir_all(&temp_dir).map_err(|e| e.to_string())?;
+    }
+    
+    // Generate filename from URL hash
+    use md5::{Md5, Digest};
+    let mut hasher = Md5::new();
+    hasher.update(url.as_bytes());
+    let result = hasher.finalize();
+    let filename = format!("{:x}.mp4", result);
+    let file_path = temp_dir.join(&filename);
+    
+    // Return existing file if already cached
+    if file_path.exists() {
+        println!("[cache_video] Video already cached: {:?}", file_path);
+        return Ok(file_path.to_string_lossy().to_string());
+    }
+    
+    let client = reqwest::Client::new();
+    let res = client.get(&url)
+        .header("User-Agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+        .header("Referer", "https://weibo.com/")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !res.status().is_success() {
+        return Err(format!("Download failed: {}", res.status()));
+    }
+    
+    let content = res.bytes().await.map_err(|e| e.to_string())?;
+    let mut file = std::fs::File::create(&file_path).map_err(|e| e.to_string())?;
+    file.write_all(&content).map_err(|e| e.to_string())?;
+    
+    println!("[cache_video] Video cached to: {:?}", file_path);
+    Ok(file_path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+async fn get_weather() -> Result<serde_json::Value, String> {
+    let url = "https://weathernew.pae.baidu.com/weathernew/pc?query=%E5%B1%B1%E4%B8%9C%E6%B5%8E%E5%AE%81%E5%A4%A9%E6%B0%94&srcid=4982&forecast=long_day_forecast";
+    let client = reqwest::Client::new();
+    let res = client.get(url)
+        .header("User-Agent", "Mozilla/5.0 (Macintosh; M1 Mac OS X) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    let html = res.text().await.map_err(|e| e.to_string())?;
+    
+    let start_marker = "window.tplData = ";
+    if let Some(start_idx) = html.find(start_marker) {
+        let json_start = start_idx + start_marker.len();
+        if let Some(end_idx) = html[json_start..].find("};") {
+            let json_str = &html[json_start..json_start + end_idx + 1];
+            let parsed: serde_json::Value = serde_json::from_str(json_str).map_err(|e| e.to_string())?;
+            return Ok(parsed);
+        }
+    }
+    Err("Could not find weather data".to_string())
+}
+
+
+/// Handle to the embedded API server while it is running.
+#[derive(Default)]
+struct ApiServerState(tokio::sync::Mutex<Option<ApiServer>>);
+
+async fn start_server(
+    app: &tauri::AppHandle,
+    bind: &str,
+    token: String,
+    download_dir: Option<String>,
+) -> Result<String, String> {
+    let state = app.state::<ApiServerState>();
+    let mut running = state.0.lock().await;
+    if let Some(server) = running.as_ref() {
+        return Err(format!("API server already running on {}", server.local_addr()));
+    }
+
+    let download_dir = match download_dir {
+        Some(dir) if !dir.is_empty() => std::path::PathBuf::from(dir),
+        _ => app.path().download_dir().map_err(|e| e.to_string())?.join("VideoParser"),
+    };
+    let config = ApiConfig {
+        bind: bind.parse().map_err(|e| format!("Invalid bind address {}: {}", bind, e))?,
+        token,
+        db_path: db::db_path(app).map_err(|e| e.to_string())?,
+        download_dir,
+        ctx: app.state::<ParseContext>().inner().clone(),
+        // API downloads join the app's queue and show up in its downloads list
+        downloads: Some(app.state::<DownloadManager>().inner().clone()),
+    };
+    let server = ApiServer::start(config).await.map_err(|e| e.to_string())?;
+    let addr = server.local_addr().to_string();
+    *running = Some(server);
+    Ok(addr)
+}
+
+#[tauri::command]
+async fn start_api_server(
+    app: tauri::AppHandle,
+    bind: String,
+    token: String,
+    download_dir: Option<String>,
+) -> Result<String, String> {
+    start_server(&app, &bind, token, download_dir).await
+}
+
+#[tauri::command]
+async fn stop_api_server(state: tauri::State<'_, ApiServerState>) -> Result<(), String> {
+    let server = state.0.lock().await.take();
+    if let Some(server) = server {
+        server.stop().await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_api_server_status(state: tauri::State<'_, ApiServerState>) -> Result<Option<String>, String> {
+    Ok(state.0.lock().await.as_ref().map(|s| s.local_addr().to_string()))
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
+        .setup(|app| {
+            let pool = db::init_db(&app.handle()).expect("Failed to initialize database");
+            let conn = pool.get()?;
+            let ctx = ParseContext::new();
+            // With a passphrase key the secrets stay locked until the user enters it
+            let secrets = match SecretStore::unlock(&conn, &KeySource::default_for(&db::db_path(app.handle())?)) {
+                Ok(secrets) => Some(secrets),
+                Err(e) => {
+                    println!("[secrets] Secrets are locked: {}", e);
+                    None
+                }
+            };
+            if let Some(secrets) = &secrets {
+                match cookie_store::load_cookies(&conn, secrets, &ctx) {
+                    Ok(count) if count > 0 => println!("[cookies] Loaded cookies for {} platforms", count),
+                    Ok(_) => {}
+                    Err(e) => println!("[cookies] Failed to load cookies: {}", e),
+                }
+            }
+            app.manage(SecretsState(std::sync::Mutex::new(secrets)));
+            drop(conn);
+            app.manage(pool.clone());
+            app.manage(ParserRegistry::with_defaults());
+            app.manage(ctx);
+            app.manage(ApiServerState::default());
+
+            // Download workers share the pool; WAL keeps their writes from blocking UI reads
+            let handle = app.handle().clone();
+            let manager = DownloadManager::new(
+                pool,
+                reqwest::Client::new(),
+                DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+                move |progress| {
+                    let _ = handle.emit("download://progress", progress);
+                },
+            );
+            app.manage(manager.clone());
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = manager.start().await {
+                    println!("[downloads] Failed to start download queue: {}", e);
+                }
+            });
+
+            // VIDEO_PARSER_API_BIND + VIDEO_PARSER_API_TOKEN start the API server with the app
+            if let (Ok(bind), Ok(token)) = (
+                std::env::var("VIDEO_PARSER_API_BIND"),
+                std::env::var("VIDEO_PARSER_API_TOKEN"),
+            ) {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let download_dir = std::env::var("VIDEO_PARSER_API_DOWNLOAD_DIR").ok();
+                    if let Err(e) = start_server(&handle, &bind, token, download_dir).await {
+                        println!("[server] Failed to start: {}", e);
+                    }
+                });
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            parse_video,
+            parse_batch,
+            download_file,
+            download_parts,
+            download_export,
+            pause_download,
+            resume_download,
+            cancel_download,
+            retry_download,
+            set_max_concurrent_downloads,
+            set_download_segments,
+            proxy_image,
+            cache_video,
+            favorites::add_favorite,
+            favorites::remove_favorite,
+            favorites::get_favorites,
+            favorites::is_favorited,
+            parsed_items::get_parsed_item,
+            history::get_history,
+            history::remove_history_entry,
+            history::clear_history,
+            search::search_library,
+            reparse_history_entry,
+            cookie_store::import_cookies,
+            cookie_store::get_cookies,
+            cookie_store::delete_cookies,
+            secrets::unlock_secrets,
+            secrets::set_secrets_passphrase,
+            secrets::list_credentials,
+            secrets::add_credential,
+            secrets::rotate_credential,
+            secrets::delete_credential,
+            auth::register,
+            auth::login,
+            auth::update_profile,
+            auth::reset_password,
+            auth::logout,
+            auth::current_user,
+            downloads::get_downloads,
+            downloads::remove_download_record,
+            open_path,
+            reveal_path,
+            start_api_server,
+            stop_api_server,
+            get_api_server_status,
+            get_weather
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
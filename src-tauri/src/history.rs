@@ -0,0 +1,208 @@
+// Per-user record of successful parses, searchable through the
+// `parse_history_fts` index that triggers keep in step with the table.
+#[cfg(feature = "gui")]
+use crate::auth;
+#[cfg(feature = "gui")]
+use crate::db::DbPool;
+use crate::models::VideoParseInfo;
+use crate::parser::utils::regexp_match_url_from_string;
+use crate::search::TextQuery;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use tauri::State;
+
+pub const DEFAULT_HISTORY_PAGE_SIZE: i64 = 20;
+const MAX_HISTORY_PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub id: i64,
+    /// What was pasted into the parser, share text included
+    pub input: String,
+    /// The post `input` resolved to, short links followed
+    pub url: String,
+    pub platform: String,
+    pub title: String,
+    pub author_name: String,
+    /// Cached parse result, see [`crate::parsed_items`]
+    pub parsed_item_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// Entries matching the query across all pages
+    pub total: i64,
+}
+
+// With a `query`, entries are ranked by relevance; otherwise newest first
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_history(
+    state: State<'_, DbPool>,
+    token: String,
+    query: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<HistoryPage, String> {
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            list_history(
+                conn,
+                user_id,
+                query.as_deref(),
+                limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE),
+                offset.unwrap_or(0),
+            )
+        })
+        .await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn remove_history_entry(state: State<'_, DbPool>, token: String, id: i64) -> Result<(), String> {
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            delete_history_entry(conn, user_id, id)
+        })
+        .await
+}
+
+// Returns the number of entries removed
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn clear_history(state: State<'_, DbPool>, token: String) -> Result<usize, String> {
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            delete_all_history(conn, user_id)
+        })
+        .await
+}
+
+/// Records that `user_id` parsed `input` into `info`, cached as `parsed_item_id`.
+/// The entry's `url` is the resolved `page_url` of the result, so short links
+/// are stored as the post they led to; `input` keeps what was typed.
+pub fn record_history(
+    conn: &Connection,
+    user_id: i64,
+    input: &str,
+    info: &VideoParseInfo,
+    parsed_item_id: Option<i64>,
+) -> Result<i64, String> {
+    let input = input.trim();
+    let url = info
+        .page_url
+        .clone()
+        .or_else(|| regexp_match_url_from_string(input))
+        .unwrap_or_else(|| input.to_string());
+    conn.execute(
+        "INSERT INTO parse_history (user_id, input, url, platform, title, author_name, parsed_item_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![user_id, input, url, info.platform, info.title, info.author.name, parsed_item_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+const HISTORY_COLUMNS: &str =
+    "h.id, h.input, h.url, h.platform, h.title, h.author_name, h.parsed_item_id, h.created_at";
+
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        input: row.get(1)?,
+        url: row.get(2)?,
+        platform: row.get(3)?,
+        title: row.get(4)?,
+        author_name: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+        parsed_item_id: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+/// One page of the history of `user_id`. A `query` matches its terms
+/// anywhere in the input, link, title, author or platform, best matches
+/// first; without one the newest entries come first.
+pub fn list_history(
+    conn: &Connection,
+    user_id: i64,
+    query: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<HistoryPage, String> {
+    let text = TextQuery::parse(query.unwrap_or(""));
+    let mut from = "FROM parse_history h".to_string();
+    let mut conditions = vec!["h.user_id = ?".to_string()];
+    let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(user_id)];
+    if let Some(fts) = &text.fts {
+        from.push_str(" JOIN parse_history_fts ON parse_history_fts.rowid = h.id");
+        conditions.push("parse_history_fts MATCH ?".to_string());
+        params.push(Box::new(fts.clone()));
+    }
+    if !text.like.is_empty() {
+        let columns = ["h.input", "h.url", "h.title", "h.author_name", "h.platform"];
+        conditions.push(text.like_sql(&columns));
+        for pattern in text.like_params(columns.len()) {
+            params.push(Box::new(pattern));
+        }
+    }
+    let filter = format!("{} WHERE {}", from, conditions.join(" AND "));
+    let order = if text.fts.is_some() { "bm25(parse_history_fts), h.id DESC" } else { "h.id DESC" };
+
+    let params_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let total: i64 = conn
+        .query_row(&format!("SELECT COUNT(*) {}", filter), params_refs.as_slice(), |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    let limit = limit.clamp(1, MAX_HISTORY_PAGE_SIZE);
+    let offset = offset.max(0);
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} {} ORDER BY {} LIMIT {} OFFSET {}",
+            HISTORY_COLUMNS, filter, order, limit, offset
+        ))
+        .map_err(|e| e.to_string())?;
+    let entries = stmt
+        .query_map(params_refs.as_slice(), entry_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(HistoryPage { entries, total })
+}
+
+/// History entry `id` if it belongs to `user_id`. Another user's entry is
+/// reported as not found, like a missing one.
+pub fn owned_history_entry(conn: &Connection, user_id: i64, id: i64) -> Result<HistoryEntry, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM parse_history h WHERE h.id = ?1 AND h.user_id = ?2", HISTORY_COLUMNS),
+        rusqlite::params![id, user_id],
+        entry_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("History entry {} not found", id))
+}
+
+pub fn delete_history_entry(conn: &Connection, user_id: i64, id: i64) -> Result<(), String> {
+    let deleted = conn
+        .execute(
+            "DELETE FROM parse_history WHERE id = ?1 AND user_id = ?2",
+            rusqlite::params![id, user_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if deleted == 0 {
+        return Err(format!("History entry {} not found", id));
+    }
+    Ok(())
+}
+
+/// Removes the whole history of `user_id` and returns how many entries it had.
+pub fn delete_all_history(conn: &Connection, user_id: i64) -> Result<usize, String> {
+    conn.execute("DELETE FROM parse_history WHERE user_id = ?1", [user_id])
+        .map_err(|e| e.to_string())
+}
@@ -13,11 +13,44 @@ pub struct ImgInfo {
     pub live_photo_url: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// What a [`VideoQuality`] URL points at.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    /// A regular file with both picture and sound
+    #[default]
+    Muxed,
+    /// Picture only (DASH); pair with `audio_url` when downloading
+    Video,
+    /// Sound only (DASH), listed so clients can pick a track
+    Audio,
+}
+
+impl StreamKind {
+    fn is_muxed(&self) -> bool {
+        *self == StreamKind::Muxed
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct VideoQuality {
     pub quality: String,
     pub video_url: String,
     pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "StreamKind::is_muxed")]
+    pub kind: StreamKind,
+    /// Audio stream to mux with a `Video` stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Bits per second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -27,6 +60,8 @@ pub struct VideoStatistics {
     pub favorites: Option<u64>,
     pub shares: Option<u64>,
     pub comments: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coins: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -37,6 +72,37 @@ pub struct MusicInfo {
     pub cover_url: String,
 }
 
+/// One part of a multi-part upload or one episode of a collection.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VideoPart {
+    /// 1-based position in the set
+    pub index: u32,
+    pub title: String,
+    /// Platform id of the part's stream (Bilibili cid)
+    pub id: String,
+    /// Seconds
+    pub duration: u64,
+    /// Share link that parses to this part
+    pub url: String,
+    /// Whether this is the part the parse result describes
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub current: bool,
+}
+
+/// A subtitle track offered for a video.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubtitleTrack {
+    /// Language code, e.g. `zh-CN` or `ai-zh`
+    pub lang: String,
+    /// Display name of the language
+    pub label: String,
+    /// The track in the platform's own format (Bilibili: JSON)
+    pub url: String,
+    /// Machine generated rather than uploaded
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ai_generated: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VideoParseInfo {
     pub author: Author,
@@ -51,6 +117,22 @@ pub struct VideoParseInfo {
     pub tags: Option<Vec<String>>,
     pub music_info: Option<MusicInfo>,
     pub create_time: Option<u64>,
+    /// Seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Every part of a multi-part upload or collection, empty for single videos
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<VideoPart>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subtitles: Vec<SubtitleTrack>,
+    /// Bullet comments (danmaku) as XML
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub danmaku_url: Option<String>,
+    /// Canonical link of the parsed post, with short links resolved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -0,0 +1,352 @@
+// Joins a DASH video stream and its audio stream into one MP4 without
+// ffmpeg. Both inputs must be fragmented MP4 holding a single track each (what
+// Bilibili serves as `.m4s`); the output is a fragmented MP4 with both tracks
+// and the fragments interleaved by decode time. Only the `moov`/`moof` boxes
+// are rewritten; media data is copied straight from the inputs, so large
+// files are muxed without loading them into memory.
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+// Header boxes larger than this are not something we expect from a DASH stream
+const MAX_HEADER_BOX: u64 = 64 * 1024 * 1024;
+
+/// Muxes the fragmented MP4 streams at `video` and `audio` into `out`.
+/// Returns the size of the written file.
+pub fn mux_av(video: &Path, audio: &Path, out: &Path) -> Result<u64> {
+    let mut inputs = [
+        Input::scan(video).context("reading video stream")?,
+        Input::scan(audio).context("reading audio stream")?,
+    ];
+    let tracks = [
+        Track::from_moov(&inputs[0].moov).context("reading video stream")?,
+        Track::from_moov(&inputs[1].moov).context("reading audio stream")?,
+    ];
+
+    let mut w = BufWriter::new(File::create(out)?);
+    let mut written = 0u64;
+
+    let ftyp = make_box(b"ftyp", &[b"isom".as_slice(), &512u32.to_be_bytes(), b"isom", b"iso6", b"mp41"].concat());
+    let moov = build_moov(&inputs, &tracks)?;
+    for header in [ftyp, moov] {
+        w.write_all(&header)?;
+        written += header.len() as u64;
+    }
+
+    // Interleave fragments of both tracks by their start time in seconds
+    let mut order: Vec<(usize, usize, f64)> = Vec::new();
+    for (t, input) in inputs.iter().enumerate() {
+        let timescale = tracks[t].timescale.max(1) as f64;
+        for (i, fragment) in input.fragments.iter().enumerate() {
+            order.push((t, i, fragment.decode_time as f64 / timescale));
+        }
+    }
+    order.sort_by(|a, b| a.2.total_cmp(&b.2).then(a.0.cmp(&b.0)));
+
+    for (sequence, (t, i, _)) in order.into_iter().enumerate() {
+        let input = &mut inputs[t];
+        let fragment = &input.fragments[i];
+        let moof = rewrite_moof(
+            &fragment.moof,
+            sequence as u32 + 1,
+            t as u32 + 1,
+            written as i64 - fragment.pos as i64,
+        )?;
+        w.write_all(&moof)?;
+
+        // Everything up to the end of the fragment's media data is copied as
+        // is, so data offsets relative to the moof stay valid
+        let rest_start = fragment.pos + moof.len() as u64;
+        let rest_len = fragment.end - rest_start;
+        input.file.seek(SeekFrom::Start(rest_start))?;
+        let copied = io::copy(&mut (&mut input.file).take(rest_len), &mut w)?;
+        if copied != rest_len {
+            bail!("stream ended inside a fragment");
+        }
+        written += moof.len() as u64 + rest_len;
+    }
+
+    w.flush()?;
+    Ok(written)
+}
+
+struct Input {
+    file: File,
+    moov: Vec<u8>,
+    fragments: Vec<Fragment>,
+}
+
+struct Fragment {
+    moof: Vec<u8>,
+    // File offset of the moof and the end of its last mdat
+    pos: u64,
+    end: u64,
+    decode_time: u64,
+}
+
+impl Input {
+    fn scan(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut moov = None;
+        let mut fragments: Vec<Fragment> = Vec::new();
+
+        let mut pos = 0u64;
+        while pos + 8 <= len {
+            file.seek(SeekFrom::Start(pos))?;
+            let mut head = [0u8; 16];
+            file.read_exact(&mut head[..8])?;
+            let kind: [u8; 4] = head[4..8].try_into().unwrap();
+            let (size, header) = match u32::from_be_bytes(head[..4].try_into().unwrap()) {
+                0 => (len - pos, 8),
+                1 => {
+                    file.read_exact(&mut head[8..16])?;
+                    (u64::from_be_bytes(head[8..16].try_into().unwrap()), 16)
+                }
+                n => (n as u64, 8),
+            };
+            if size < header || pos + size > len {
+                bail!("truncated {} box at offset {}", fourcc(&kind), pos);
+            }
+
+            match &kind {
+                b"moov" => moov = Some(read_box(&mut file, pos, size)?),
+                b"moof" => {
+                    let moof = read_box(&mut file, pos, size)?;
+                    let decode_time = moof_decode_time(&moof)?;
+                    fragments.push(Fragment { moof, pos, end: pos + size, decode_time });
+                }
+                b"mdat" => match fragments.last_mut() {
+                    Some(fragment) => fragment.end = pos + size,
+                    None => bail!("media data before the first fragment; not a fragmented MP4"),
+                },
+                // ftyp is rebuilt; sidx/styp/free only describe the input layout
+                _ => {}
+            }
+            pos += size;
+        }
+
+        let Some(moov) = moov else { bail!("no moov box; not an MP4 stream") };
+        if fragments.is_empty() {
+            bail!("no fragments; not a fragmented MP4");
+        }
+        Ok(Self { file, moov, fragments })
+    }
+}
+
+fn read_box(file: &mut File, pos: u64, size: u64) -> Result<Vec<u8>> {
+    if size > MAX_HEADER_BOX {
+        bail!("header box of {} bytes is too large", size);
+    }
+    let mut buf = vec![0u8; size as usize];
+    file.seek(SeekFrom::Start(pos))?;
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+// Offsets of one box inside a buffer
+#[derive(Clone, Copy)]
+struct Span {
+    kind: [u8; 4],
+    start: usize,
+    header: usize,
+    end: usize,
+}
+
+impl Span {
+    fn body(&self) -> usize {
+        self.start + self.header
+    }
+}
+
+// The boxes directly inside `data[start..end]`
+fn children(data: &[u8], start: usize, end: usize) -> Result<Vec<Span>> {
+    let mut out = Vec::new();
+    let mut pos = start;
+    while pos + 8 <= end {
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap();
+        let (size, header) = match read_u32(data, pos) {
+            0 => (end - pos, 8),
+            1 if pos + 16 <= end => (read_u64(data, pos + 8) as usize, 16),
+            1 => bail!("truncated {} box", fourcc(&kind)),
+            n => (n as usize, 8),
+        };
+        if size < header || size > end - pos {
+            bail!("truncated {} box", fourcc(&kind));
+        }
+        out.push(Span { kind, start: pos, header, end: pos + size });
+        pos += size;
+    }
+    Ok(out)
+}
+
+fn root(data: &[u8]) -> Result<Span> {
+    children(data, 0, data.len())?
+        .into_iter()
+        .next()
+        .context("empty box")
+}
+
+fn child(data: &[u8], parent: Span, kind: &[u8; 4]) -> Result<Span> {
+    children(data, parent.body(), parent.end)?
+        .into_iter()
+        .find(|c| &c.kind == kind)
+        .with_context(|| format!("missing {} in {}", fourcc(kind), fourcc(&parent.kind)))
+}
+
+// Version of a full box, the first byte of its body
+fn version(data: &[u8], span: Span) -> Result<u8> {
+    let body = span.body();
+    match data.get(body) {
+        Some(&version) if body < span.end => Ok(version),
+        _ => bail!("{} box is too short", fourcc(&span.kind)),
+    }
+}
+
+// Byte offset of a field inside a full box whose layout depends on its version
+fn field(data: &[u8], span: Span, v0: usize, v1: usize, width: usize) -> Result<usize> {
+    let body = span.body();
+    let offset = body + if data.get(body) == Some(&1) { v1 } else { v0 };
+    if offset + width > span.end {
+        bail!("{} box is too short", fourcc(&span.kind));
+    }
+    Ok(offset)
+}
+
+struct Track {
+    mvhd: Span,
+    trak: Span,
+    trex: Span,
+    timescale: u32,
+    movie_timescale: u32,
+    movie_duration: u64,
+}
+
+impl Track {
+    fn from_moov(moov: &[u8]) -> Result<Self> {
+        let root = root(moov)?;
+        let mvhd = child(moov, root, b"mvhd")?;
+        let traks: Vec<Span> = children(moov, root.body(), root.end)?
+            .into_iter()
+            .filter(|c| &c.kind == b"trak")
+            .collect();
+        if traks.len() != 1 {
+            bail!("expected one track per stream, found {}", traks.len());
+        }
+        let trak = traks[0];
+        let mvex = child(moov, root, b"mvex").context("not a fragmented MP4")?;
+        let trex = child(moov, mvex, b"trex")?;
+        let mdhd = child(moov, child(moov, trak, b"mdia")?, b"mdhd")?;
+
+        let timescale = read_u32(moov, field(moov, mdhd, 12, 20, 4)?);
+        let movie_timescale = read_u32(moov, field(moov, mvhd, 12, 20, 4)?);
+        let movie_duration = if version(moov, mvhd)? == 1 {
+            read_u64(moov, field(moov, mvhd, 16, 24, 8)?)
+        } else {
+            read_u32(moov, field(moov, mvhd, 16, 24, 4)?) as u64
+        };
+        Ok(Self { mvhd, trak, trex, timescale, movie_timescale, movie_duration })
+    }
+}
+
+// moov with the video's movie header, both tracks (IDs 1 and 2) and their trex
+fn build_moov(inputs: &[Input; 2], tracks: &[Track; 2]) -> Result<Vec<u8>> {
+    let movie_timescale = tracks[0].movie_timescale.max(1) as u64;
+    let duration = tracks
+        .iter()
+        .map(|t| t.movie_duration * movie_timescale / t.movie_timescale.max(1) as u64)
+        .max()
+        .unwrap_or(0);
+
+    let video_moov = &inputs[0].moov;
+    let mvhd_span = tracks[0].mvhd;
+    let mut mvhd = video_moov[mvhd_span.start..mvhd_span.end].to_vec();
+    let local = Span { start: 0, end: mvhd.len(), ..mvhd_span };
+    if version(&mvhd, local)? == 1 {
+        let at = field(&mvhd, local, 16, 24, 8)?;
+        mvhd[at..at + 8].copy_from_slice(&duration.to_be_bytes());
+    } else {
+        let at = field(&mvhd, local, 16, 24, 4)?;
+        mvhd[at..at + 4].copy_from_slice(&(duration.min(u32::MAX as u64) as u32).to_be_bytes());
+    }
+    let next_track_id = field(&mvhd, local, 96, 108, 4)?;
+    mvhd[next_track_id..next_track_id + 4].copy_from_slice(&3u32.to_be_bytes());
+
+    let mut body = mvhd;
+    let mut trexes = Vec::new();
+    for (i, (input, track)) in inputs.iter().zip(tracks).enumerate() {
+        let id = (i as u32 + 1).to_be_bytes();
+
+        let mut trak = input.moov[track.trak.start..track.trak.end].to_vec();
+        let trak_root = root(&trak)?;
+        let tkhd = child(&trak, trak_root, b"tkhd")?;
+        let at = field(&trak, tkhd, 12, 20, 4)?;
+        trak[at..at + 4].copy_from_slice(&id);
+        body.extend_from_slice(&trak);
+
+        let mut trex = input.moov[track.trex.start..track.trex.end].to_vec();
+        let trex_span = Span { start: 0, end: trex.len(), ..track.trex };
+        let at = field(&trex, trex_span, 4, 4, 4)?;
+        trex[at..at + 4].copy_from_slice(&id);
+        trexes.extend_from_slice(&trex);
+    }
+    body.extend_from_slice(&make_box(b"mvex", &trexes));
+    Ok(make_box(b"moov", &body))
+}
+
+// Renumbers a fragment and its track, and shifts explicit base data offsets
+// by how far the fragment moved
+fn rewrite_moof(moof: &[u8], sequence: u32, track_id: u32, moved: i64) -> Result<Vec<u8>> {
+    let mut out = moof.to_vec();
+    let root = root(moof)?;
+    let mfhd = child(moof, root, b"mfhd")?;
+    let at = field(moof, mfhd, 4, 4, 4)?;
+    out[at..at + 4].copy_from_slice(&sequence.to_be_bytes());
+
+    for traf in children(moof, root.body(), root.end)?.into_iter().filter(|c| &c.kind == b"traf") {
+        let tfhd = child(moof, traf, b"tfhd")?;
+        let flags = read_u32(moof, field(moof, tfhd, 0, 0, 4)?) & 0x00ff_ffff;
+        let at = field(moof, tfhd, 4, 4, 4)?;
+        out[at..at + 4].copy_from_slice(&track_id.to_be_bytes());
+        if flags & 0x1 != 0 {
+            let at = field(moof, tfhd, 8, 8, 8)?;
+            let base = read_u64(moof, at) as i64 + moved;
+            out[at..at + 8].copy_from_slice(&(base as u64).to_be_bytes());
+        }
+    }
+    Ok(out)
+}
+
+// Decode time of a fragment's first sample, in its track's timescale
+fn moof_decode_time(moof: &[u8]) -> Result<u64> {
+    let root = root(moof)?;
+    let traf = child(moof, root, b"traf")?;
+    let Ok(tfdt) = child(moof, traf, b"tfdt") else { return Ok(0) };
+    Ok(if version(moof, tfdt)? == 1 {
+        read_u64(moof, field(moof, tfdt, 4, 4, 8)?)
+    } else {
+        read_u32(moof, field(moof, tfdt, 4, 4, 4)?) as u64
+    })
+}
+
+fn make_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 8);
+    out.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(body);
+    out
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+fn fourcc(kind: &[u8; 4]) -> String {
+    String::from_utf8_lossy(kind).to_string()
+}
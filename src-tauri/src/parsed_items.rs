@@ -0,0 +1,133 @@
+// Cache of full parse results, so favorites, downloads and history can be
+// reopened without parsing the link again. One row per parsed URL, replaced
+// by newer parses; the others point at it through `parsed_item_id`.
+#[cfg(feature = "gui")]
+use crate::auth;
+#[cfg(feature = "gui")]
+use crate::db::DbPool;
+use crate::models::VideoParseInfo;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use tauri::State;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ParsedItem {
+    pub id: i64,
+    /// The link that was parsed
+    pub url: String,
+    pub info: VideoParseInfo,
+    pub parsed_at: String,
+}
+
+// Returns the cached parse result `id` if one of the user's favorites,
+// downloads or history entries links to it
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_parsed_item(state: State<'_, DbPool>, token: String, id: i64) -> Result<ParsedItem, String> {
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            owned_parsed_item(conn, user_id, id)
+        })
+        .await
+}
+
+/// Stores `info` as the latest parse result for `url` and returns its id.
+/// Favorites of `url` saved before it was cached are linked to it.
+pub fn save_parsed_item(conn: &Connection, url: &str, info: &VideoParseInfo) -> Result<i64, String> {
+    let json = serde_json::to_string(info).map_err(|e| e.to_string())?;
+    let id: i64 = conn
+        .query_row(
+            "INSERT INTO parsed_items (url, platform, title, author_name, cover_url, tags, description, info)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(url) DO UPDATE SET platform = excluded.platform, title = excluded.title,
+                 author_name = excluded.author_name, cover_url = excluded.cover_url, tags = excluded.tags,
+                 description = excluded.description, info = excluded.info, parsed_at = CURRENT_TIMESTAMP
+             RETURNING id",
+            rusqlite::params![
+                url,
+                info.platform,
+                info.title,
+                info.author.name,
+                info.cover_url,
+                info.tags.as_deref().unwrap_or_default().join(" "),
+                info.description.as_deref().unwrap_or_default(),
+                json
+            ],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE favorites SET parsed_item_id = ?1 WHERE url = ?2 AND parsed_item_id IS NULL",
+        rusqlite::params![id, url],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+const PARSED_ITEM_COLUMNS: &str = "id, url, info, parsed_at";
+
+fn item_from_row(row: &rusqlite::Row) -> rusqlite::Result<ParsedItem> {
+    let json: String = row.get(2)?;
+    let info = serde_json::from_str(&json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?;
+    Ok(ParsedItem {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        info,
+        parsed_at: row.get(3)?,
+    })
+}
+
+/// Cached parse result `id`, whoever it belongs to.
+pub fn load_parsed_item(conn: &Connection, id: i64) -> Result<Option<ParsedItem>, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM parsed_items WHERE id = ?1", PARSED_ITEM_COLUMNS),
+        [id],
+        item_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// The cached parse result for `url`, if it has been parsed before.
+pub fn find_parsed_item(conn: &Connection, url: &str) -> Result<Option<ParsedItem>, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM parsed_items WHERE url = ?1", PARSED_ITEM_COLUMNS),
+        [url],
+        item_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Parse result `id` if a favorite, download or history entry of `user_id`
+/// links to it.
+/// Anything else is reported as not found, like a missing item.
+pub fn owned_parsed_item(conn: &Connection, user_id: i64, id: i64) -> Result<ParsedItem, String> {
+    let linked: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM favorites WHERE user_id = ?1 AND parsed_item_id = ?2)
+                 OR EXISTS (SELECT 1 FROM downloads WHERE user_id = ?1 AND parsed_item_id = ?2)
+                 OR EXISTS (SELECT 1 FROM parse_history WHERE user_id = ?1 AND parsed_item_id = ?2)",
+            rusqlite::params![user_id, id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let item = if linked { load_parsed_item(conn, id)? } else { None };
+    item.ok_or_else(|| format!("Parsed item {} not found", id))
+}
+
+/// Points download `download_id` at the cached parse result for `url`, if
+/// there is one, and gives it the platform of that result.
+pub fn link_download(conn: &Connection, download_id: i64, url: &str) -> Result<(), String> {
+    conn.execute(
+        "UPDATE downloads SET parsed_item_id = (SELECT id FROM parsed_items WHERE url = ?1),
+            platform = COALESCE((SELECT platform FROM parsed_items WHERE url = ?1), platform)
+         WHERE id = ?2",
+        rusqlite::params![url, download_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use crate::models::VideoParseInfo;
+use crate::parser::{utils, ParseContext, ParseError, ParserRegistry};
+use futures_util::stream::{self, StreamExt};
+use serde::Serialize;
+
+/// Parses in flight at once when the caller does not choose a limit.
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+/// Upper bound on the concurrency a caller may request.
+pub const MAX_BATCH_CONCURRENCY: usize = 16;
+
+/// Outcome of one link in a batch; exactly one of `info` and `error` is set.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchItem {
+    /// Position of the link among the supported links of the batch.
+    pub index: usize,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<VideoParseInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ParseError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchSummary {
+    /// Supported links that were parsed, in order of appearance.
+    pub urls: Vec<String>,
+    /// Links found in the text that no parser handles.
+    pub skipped: Vec<String>,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+impl ParserRegistry {
+    /// Extracts every share link from `text` and parses the supported ones with at
+    /// most `concurrency` requests in flight. `on_item` is called as each link
+    /// finishes, so items arrive in completion order rather than input order.
+    pub async fn parse_batch<F>(
+        &self,
+        ctx: &ParseContext,
+        text: &str,
+        concurrency: usize,
+        mut on_item: F,
+    ) -> BatchSummary
+    where
+        F: FnMut(BatchItem),
+    {
+        let (urls, skipped): (Vec<String>, Vec<String>) = utils::regexp_match_urls_from_string(text)
+            .into_iter()
+            .partition(|url| self.find(url).is_some());
+
+        let concurrency = concurrency.clamp(1, MAX_BATCH_CONCURRENCY);
+        let mut results = stream::iter(urls.iter().enumerate())
+            .map(|(index, url)| async move { (index, url, self.parse(ctx, url).await) })
+            .buffer_unordered(concurrency);
+
+        let mut succeeded = 0;
+        let mut failed = 0;
+        while let Some((index, url, result)) = results.next().await {
+            let (info, error) = match result {
+                Ok(info) => {
+                    succeeded += 1;
+                    (Some(info), None)
+                }
+                Err(e) => {
+                    failed += 1;
+                    (None, Some(e))
+                }
+            };
+            on_item(BatchItem { index, url: url.clone(), info, error });
+        }
+        drop(results);
+
+        BatchSummary { urls, skipped, succeeded, failed }
+    }
+}
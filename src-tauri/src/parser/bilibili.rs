@@ -1,85 +1,554 @@
-use crate::models::{Author, ImgInfo, VideoParseInfo};
-use crate::parser::utils;
-use anyhow::{anyhow, Result};
+use crate::models::{
+    Author, StreamKind, SubtitleTrack, VideoParseInfo, VideoPart, VideoQuality, VideoStatistics,
+};
+use crate::parser::{utils, ParseContext, Parser};
+use crate::parser::error::{check_status, ParseError, Result};
+use async_trait::async_trait;
 use reqwest::header::{USER_AGENT, REFERER};
-use reqwest::Client;
 use serde_json::Value; // Make sure to use Value from serde_json
-use std::time::Duration;
 use url::Url;
 
 pub struct Bilibili;
 
+/// What a bangumi (PGC) link points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BangumiId {
+    Episode(u64),
+    Season(u64),
+}
+
 impl Bilibili {
     const BILI_USER_AGENT: &'static str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/108.0.0.0 Safari/537.36";
 
-    pub async fn parse_share_url(share_url: &str) -> Result<VideoParseInfo> {
+    pub async fn parse_share_url(ctx: &ParseContext, share_url: &str) -> Result<VideoParseInfo> {
         let url_str = if let Some(u) = utils::regexp_match_url_from_string(share_url) {
             u
         } else {
             share_url.to_string()
         };
         
-        let mut bvid = String::new();
-        
         // 1. Handle b23.tv redirects
-        if url_str.contains("b23.tv") {
-           bvid = Self::get_bvid_from_short_url(&url_str).await?;
+        let full_url = if url_str.contains("b23.tv") {
+           Self::resolve_short_url(ctx, &url_str).await?
         } else {
-           bvid = Self::get_bvid_from_url(&url_str)?;
+           url_str
+        };
+        if let Some(id) = Self::bangumi_id_from_url(&full_url) {
+            return Self::parse_bangumi(ctx, id).await;
         }
+        let bvid = Self::get_bvid_from_url(&full_url)?;
+        let page = Self::page_from_url(&full_url);
         
         if bvid.is_empty() {
-            return Err(anyhow!("Could not find BVID in URL"));
+            return Err(ParseError::UnsupportedUrl("Could not find BVID in URL".to_string()));
         }
 
-        let client = Client::new();
+        let client = ctx.client();
 
         // 2. Get Video Metadata (View API)
-        let view_api = format!("https://api.bilibili.com/x/web-interface/view?bvid={}", bvid);
+        let view_api = ctx.url(&format!("https://api.bilibili.com/x/web-interface/view?bvid={}", bvid));
         let view_res = client.get(&view_api)
             .header(USER_AGENT, Self::BILI_USER_AGENT)
+            .headers(ctx.headers("bilibili"))
             .send()
             .await?;
+        let view_res = check_status(view_res, "Bilibili view API")?;
             
         let view_json: Value = view_res.json().await?;
+        Self::check_api_code(&view_json)?;
         
-        if view_json["code"].as_i64().unwrap_or(-1) != 0 {
-             return Err(anyhow!("Bilibili API Error: {}", view_json["message"]));
-        }
-        
-        let data = &view_json["data"];
-        let title = data["title"].as_str().unwrap_or("").to_string();
-        let pic = data["pic"].as_str().unwrap_or("").to_string();
-        let cid = data["cid"].as_i64().or_else(|| data["pages"][0]["cid"].as_i64()).unwrap_or(0);
-        let owner = &data["owner"];
-        let author_name = owner["name"].as_str().unwrap_or("").to_string();
-        let author_avatar = owner["face"].as_str().unwrap_or("").to_string();
-        let _mid = owner["mid"].as_i64().unwrap_or(0).to_string();
+        let cid = Self::selected_cid(&view_json["data"], page)?;
 
         // 3. Get Video Stream URL (Play API)
         // qn=80 (1080P), platform=html5
-        let play_api = format!(
+        let play_api = ctx.url(&format!(
             "https://api.bilibili.com/x/player/playurl?otype=json&fnver=0&fnval=0&qn=80&bvid={}&cid={}&platform=html5",
             bvid, cid
-        );
+        ));
         
         let play_res = client.get(&play_api)
              .header(USER_AGENT, Self::BILI_USER_AGENT)
              .header(REFERER, "https://www.bilibili.com/") // Important for some videos
+             .headers(ctx.headers("bilibili"))
              .send()
              .await?;
              
         let play_json: Value = play_res.json().await?;
-        
+        let mut info = Self::parse_part_responses(&view_json, &play_json, page)?;
+
+        // Tags live behind their own endpoint; a failure only loses the tags
+        let tags_api = ctx.url(&format!("https://api.bilibili.com/x/tag/archive/tags?bvid={}", bvid));
+        let tags_json: Result<Value> = async {
+            let res = client.get(&tags_api)
+                .header(USER_AGENT, Self::BILI_USER_AGENT)
+                .header(REFERER, "https://www.bilibili.com/")
+                .headers(ctx.headers("bilibili"))
+                .send()
+                .await?;
+            Ok(check_status(res, "Bilibili tags API")?.json().await?)
+        }
+        .await;
+        match tags_json.and_then(|json| Self::parse_tags(&json)) {
+            Ok(tags) if !tags.is_empty() => info.tags = Some(tags),
+            Ok(_) => {}
+            Err(e) => println!("[bilibili] Tags unavailable: {}", e),
+        }
+
+        // 4. DASH streams for the quality list. The html5 MP4 above stays the
+        // default since it has sound and plays anywhere.
+        let dash_api = ctx.url(&format!(
+            "https://api.bilibili.com/x/player/playurl?bvid={}&cid={}&qn=127&fnver=0&fnval={}&fourk=1",
+            bvid, cid, Self::DASH_FNVAL
+        ));
+        if let Some(qualities) = Self::fetch_dash_qualities(ctx, &dash_api).await {
+            info.video_qualities = qualities;
+        }
+        info.subtitles = Self::fetch_subtitle_tracks(ctx, &bvid, cid).await;
+        info.page_url = Some(match page {
+            Some(p) => format!("https://www.bilibili.com/video/{}?p={}", bvid, p),
+            None => format!("https://www.bilibili.com/video/{}", bvid),
+        });
+        Ok(info)
+    }
+
+    // Subtitle tracks of a video part; failures only cost the subtitles
+    async fn fetch_subtitle_tracks(ctx: &ParseContext, bvid: &str, cid: i64) -> Vec<SubtitleTrack> {
+        let player_api = ctx.url(&format!("https://api.bilibili.com/x/player/v2?bvid={}&cid={}", bvid, cid));
+        let player_json: Result<Value> = async {
+            let res = ctx.client().get(&player_api)
+                .header(USER_AGENT, Self::BILI_USER_AGENT)
+                .header(REFERER, "https://www.bilibili.com/")
+                .headers(ctx.headers("bilibili"))
+                .send()
+                .await?;
+            Ok(check_status(res, "Bilibili player API")?.json().await?)
+        }
+        .await;
+        match player_json.and_then(|json| Self::parse_subtitle_tracks(&json)) {
+            Ok(tracks) => tracks,
+            Err(e) => {
+                println!("[bilibili] Subtitles unavailable: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Subtitle tracks listed in a `x/player/v2` response. Their URLs point
+    /// at Bilibili's subtitle JSON; see [`crate::subtitles`] for conversion.
+    pub fn parse_subtitle_tracks(player_json: &Value) -> Result<Vec<SubtitleTrack>> {
+        Self::check_api_code(player_json)?;
+        Ok(player_json["data"]["subtitle"]["subtitles"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|track| {
+                let url = track["subtitle_url"].as_str().filter(|u| !u.is_empty())?;
+                let lang = track["lan"].as_str().unwrap_or("").to_string();
+                Some(SubtitleTrack {
+                    ai_generated: lang.starts_with("ai-") || track["type"].as_i64() == Some(1),
+                    label: track["lan_doc"].as_str().unwrap_or(&lang).to_string(),
+                    lang,
+                    url: if url.starts_with("//") { format!("https:{}", url) } else { url.to_string() },
+                })
+            })
+            .collect())
+    }
+
+    fn danmaku_url(cid: i64) -> String {
+        format!("https://api.bilibili.com/x/v1/dm/list.so?oid={}", cid)
+    }
+
+    // DASH qualities from a playurl endpoint; failures only cost the quality list
+    async fn fetch_dash_qualities(ctx: &ParseContext, api: &str) -> Option<Vec<VideoQuality>> {
+        let dash_json: Result<Value> = async {
+            let res = ctx.client().get(api)
+                .header(USER_AGENT, Self::BILI_USER_AGENT)
+                .header(REFERER, "https://www.bilibili.com/")
+                .headers(ctx.headers("bilibili"))
+                .send()
+                .await?;
+            Ok(check_status(res, "Bilibili playurl API")?.json().await?)
+        }
+        .await;
+        match dash_json.and_then(|json| Self::parse_dash_qualities(&json)) {
+            Ok(qualities) => Some(qualities),
+            Err(e) => {
+                println!("[bilibili] DASH streams unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn parse_bangumi(ctx: &ParseContext, id: BangumiId) -> Result<VideoParseInfo> {
+        let client = ctx.client();
+        let query = match id {
+            BangumiId::Episode(ep_id) => format!("ep_id={}", ep_id),
+            BangumiId::Season(season_id) => format!("season_id={}", season_id),
+        };
+        let season_api = ctx.url(&format!("https://api.bilibili.com/pgc/view/web/season?{}", query));
+        let season_res = client.get(&season_api)
+            .header(USER_AGENT, Self::BILI_USER_AGENT)
+            .header(REFERER, "https://www.bilibili.com/")
+            .headers(ctx.headers("bilibili"))
+            .send()
+            .await?;
+        let season_json: Value = check_status(season_res, "Bilibili season API")?.json().await?;
+        Self::check_api_code(&season_json)?;
+
+        let episode = Self::select_episode(&season_json, id)?;
+        let ep_id = episode["id"].as_i64().unwrap_or(0);
+        let cid = episode["cid"].as_i64().unwrap_or(0);
+
+        let play_api = ctx.url(&format!(
+            "https://api.bilibili.com/pgc/player/web/playurl?ep_id={}&cid={}&qn=80&fnver=0&fnval=1&fourk=1",
+            ep_id, cid
+        ));
+        let play_res = client.get(&play_api)
+            .header(USER_AGENT, Self::BILI_USER_AGENT)
+            .header(REFERER, "https://www.bilibili.com/")
+            .headers(ctx.headers("bilibili"))
+            .send()
+            .await?;
+        let play_json: Value = check_status(play_res, "Bilibili PGC playurl API")?.json().await?;
+        let mut info = Self::parse_bangumi_responses(&season_json, &play_json, id)?;
+        if let Some(bvid) = episode["bvid"].as_str() {
+            info.subtitles = Self::fetch_subtitle_tracks(ctx, bvid, cid).await;
+        }
+
+        let dash_api = ctx.url(&format!(
+            "https://api.bilibili.com/pgc/player/web/playurl?ep_id={}&cid={}&qn=127&fnver=0&fnval={}&fourk=1",
+            ep_id, cid, Self::DASH_FNVAL
+        ));
+        if let Some(qualities) = Self::fetch_dash_qualities(ctx, &dash_api).await {
+            info.video_qualities = qualities;
+        }
+        info.page_url = Some(format!("https://www.bilibili.com/bangumi/play/ep{}", ep_id));
+        Ok(info)
+    }
+
+    /// Builds the parse result for a bangumi episode from
+    /// `pgc/view/web/season` and `pgc/player/web/playurl` responses. A season
+    /// id selects its first episode. Every episode is listed in `parts`.
+    pub fn parse_bangumi_responses(season_json: &Value, play_json: &Value, id: BangumiId) -> Result<VideoParseInfo> {
+        Self::check_api_code(season_json)?;
+        Self::check_api_code(play_json)?;
+
+        let season = &season_json["result"];
+        let episode = Self::select_episode(season_json, id)?;
+        let ep_id = episode["id"].as_i64().unwrap_or(0);
+
+        let video_url = play_json["result"]["durl"][0]["url"].as_str().unwrap_or("").to_string();
+        if video_url.is_empty() {
+            return Err(ParseError::LayoutChanged("Could not find episode stream URL".to_string()));
+        }
+
+        let season_title = season["season_title"]
+            .as_str()
+            .or_else(|| season["title"].as_str())
+            .unwrap_or("");
+        let title = format!("{} {}", season_title, Self::episode_label(episode)).trim().to_string();
+        let cover = episode["cover"]
+            .as_str()
+            .filter(|c| !c.is_empty())
+            .or_else(|| season["cover"].as_str())
+            .unwrap_or("")
+            .to_string();
+
+        let up = &season["up_info"];
+        let tags: Vec<String> = season["styles"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|style| style.as_str())
+            .map(|style| style.to_string())
+            .collect();
+        let parts = season["episodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, ep)| {
+                let id = ep["id"].as_i64().unwrap_or(0);
+                VideoPart {
+                    index: i as u32 + 1,
+                    title: Self::episode_label(ep),
+                    id: ep["cid"].as_i64().unwrap_or(0).to_string(),
+                    // Episode durations are in milliseconds
+                    duration: ep["duration"].as_u64().unwrap_or(0) / 1000,
+                    url: format!("https://www.bilibili.com/bangumi/play/ep{}", id),
+                    current: id == ep_id,
+                }
+            })
+            .collect();
+
+        Ok(VideoParseInfo {
+            author: Author {
+                uid: up["mid"].as_i64().map(|m| m.to_string()).unwrap_or_default(),
+                name: up["uname"].as_str().unwrap_or("").to_string(),
+                avatar: up["avatar"].as_str().unwrap_or("").to_string(),
+            },
+            title,
+            video_url,
+            music_url: "".to_string(),
+            cover_url: cover,
+            images: vec![],
+            platform: "bilibili".to_string(),
+            video_qualities: vec![],
+            statistics: Self::parse_stat(&season["stat"]),
+            tags: (!tags.is_empty()).then_some(tags),
+            music_info: None,
+            create_time: episode["pub_time"].as_u64(),
+            duration: episode["duration"].as_u64().map(|ms| ms / 1000),
+            description: season["evaluate"].as_str().filter(|d| !d.is_empty()).map(|d| d.to_string()),
+            parts,
+            subtitles: vec![],
+            danmaku_url: episode["cid"].as_i64().map(Self::danmaku_url),
+            page_url: None,
+        })
+    }
+
+    fn select_episode(season_json: &Value, id: BangumiId) -> Result<&Value> {
+        let episodes = season_json["result"]["episodes"].as_array().map(|e| e.as_slice()).unwrap_or_default();
+        let episode = match id {
+            BangumiId::Episode(ep_id) => episodes.iter().find(|ep| ep["id"].as_u64() == Some(ep_id)),
+            BangumiId::Season(_) => episodes.first(),
+        };
+        episode.ok_or_else(|| ParseError::NotFound("Episode not found in season".to_string()))
+    }
+
+    // "第1话 标题", from the newer `show_title` or the number and long title
+    fn episode_label(ep: &Value) -> String {
+        if let Some(show_title) = ep["show_title"].as_str().filter(|t| !t.is_empty()) {
+            return show_title.to_string();
+        }
+        let number = ep["title"].as_str().unwrap_or("");
+        let long_title = ep["long_title"].as_str().unwrap_or("");
+        let number = if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+            format!("第{}话", number)
+        } else {
+            number.to_string()
+        };
+        format!("{} {}", number, long_title).trim().to_string()
+    }
+
+    /// The episode or season a `/bangumi/play/ep…` or `/bangumi/play/ss…` link points at.
+    pub fn bangumi_id_from_url(full_url: &str) -> Option<BangumiId> {
+        let parsed = Url::parse(full_url).ok()?;
+        let segments: Vec<&str> = parsed.path_segments()?.collect();
+        let play = segments.windows(3).find(|w| w[0] == "bangumi" && w[1] == "play")?[2];
+        let (prefix, number) = play.split_at(play.len().min(2));
+        let number: u64 = number.parse().ok()?;
+        match prefix {
+            "ep" => Some(BangumiId::Episode(number)),
+            "ss" => Some(BangumiId::Season(number)),
+            _ => None,
+        }
+    }
+
+    /// Converts a legacy av number to its BV id.
+    pub fn av_to_bv(aid: u64) -> String {
+        const ALPHABET: &[u8] = b"FcwAPNKTMug3GV5Lj7EJnHpWsx4tb8haYeviqBz6rkCy12mUSDQX9RdoZf";
+        const XOR_CODE: u64 = 23442827791579;
+        const MAX_AID: u64 = 1 << 51;
+
+        let mut bv = *b"BV1000000000";
+        let mut tmp = (MAX_AID | aid) ^ XOR_CODE;
+        let mut i = bv.len() - 1;
+        while tmp > 0 {
+            bv[i] = ALPHABET[(tmp % 58) as usize];
+            tmp /= 58;
+            i -= 1;
+        }
+        bv.swap(3, 9);
+        bv.swap(4, 7);
+        String::from_utf8_lossy(&bv).to_string()
+    }
+
+    // DASH | HDR | 4K | Dolby audio | Dolby Vision | 8K | AV1
+    const DASH_FNVAL: u32 = 16 | 64 | 128 | 256 | 512 | 1024 | 2048;
+
+    /// Lists every DASH video representation of a `x/player/playurl` or
+    /// `pgc/player/web/playurl` response (fnval with the DASH bit), best
+    /// first, each paired with the best AAC audio track, followed by the
+    /// audio tracks themselves.
+    pub fn parse_dash_qualities(play_json: &Value) -> Result<Vec<VideoQuality>> {
+        Self::check_api_code(play_json)?;
+        // PGC responses carry the same payload under `result`
+        let data = if play_json["data"].is_object() { &play_json["data"] } else { &play_json["result"] };
+        let dash = &data["dash"];
+        let Some(videos) = dash["video"].as_array() else {
+            return Err(ParseError::LayoutChanged("playurl response has no DASH streams".to_string()));
+        };
+
+        let stream_url = |stream: &Value| {
+            stream["baseUrl"]
+                .as_str()
+                .or_else(|| stream["base_url"].as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+
+        // Regular AAC tracks, then Dolby and Hi-Res which fewer players handle
+        let mut audios: Vec<(String, &Value)> = Vec::new();
+        for audio in dash["audio"].as_array().into_iter().flatten() {
+            let label = match audio["id"].as_i64().unwrap_or(0) {
+                30280 => "192K".to_string(),
+                30232 => "132K".to_string(),
+                30216 => "64K".to_string(),
+                _ => format!("{}K", audio["bandwidth"].as_u64().unwrap_or(0) / 1000),
+            };
+            audios.push((label, audio));
+        }
+        audios.sort_by_key(|(_, a)| std::cmp::Reverse(a["bandwidth"].as_u64().unwrap_or(0)));
+        let best_audio = audios.first().map(|(_, a)| stream_url(a)).filter(|u| !u.is_empty());
+        for audio in dash["dolby"]["audio"].as_array().into_iter().flatten() {
+            audios.push(("Dolby Atmos".to_string(), audio));
+        }
+        if dash["flac"]["audio"].is_object() {
+            audios.push(("Hi-Res".to_string(), &dash["flac"]["audio"]));
+        }
+
+        let mut video_streams: Vec<&Value> = videos.iter().collect();
+        video_streams.sort_by_key(|v| {
+            let codec_rank = match v["codecid"].as_i64() {
+                Some(7) => 0,
+                Some(12) => 1,
+                Some(13) => 2,
+                _ => 3,
+            };
+            (
+                std::cmp::Reverse(v["id"].as_i64().unwrap_or(0)),
+                codec_rank,
+                std::cmp::Reverse(v["bandwidth"].as_u64().unwrap_or(0)),
+            )
+        });
+
+        let mut qualities: Vec<VideoQuality> = Vec::new();
+        for video in video_streams {
+            let url = stream_url(video);
+            if url.is_empty() {
+                continue;
+            }
+            // The preferred codec keeps the plain label, others are told apart by codec
+            let codec = Self::codec_name(video);
+            let mut quality = Self::quality_label(video["id"].as_i64().unwrap_or(0), data);
+            if qualities.iter().any(|q| q.quality == quality) {
+                quality = format!("{} {}", quality, codec.as_deref().unwrap_or("alt"));
+            }
+            if qualities.iter().any(|q| q.quality == quality) {
+                continue;
+            }
+            qualities.push(VideoQuality {
+                quality,
+                video_url: url,
+                size: None,
+                kind: StreamKind::Video,
+                audio_url: best_audio.clone(),
+                codec,
+                width: video["width"].as_u64().map(|w| w as u32),
+                height: video["height"].as_u64().map(|h| h as u32),
+                bitrate: video["bandwidth"].as_u64(),
+            });
+        }
+        if qualities.is_empty() {
+            return Err(ParseError::LayoutChanged("DASH response lists no video streams".to_string()));
+        }
+
+        for (label, audio) in audios {
+            let url = stream_url(audio);
+            if url.is_empty() {
+                continue;
+            }
+            qualities.push(VideoQuality {
+                quality: format!("Audio {}", label),
+                video_url: url,
+                size: None,
+                kind: StreamKind::Audio,
+                codec: audio["codecs"].as_str().map(|c| c.to_string()),
+                bitrate: audio["bandwidth"].as_u64(),
+                ..Default::default()
+            });
+        }
+        Ok(qualities)
+    }
+
+    // Short label for a quality number; falls back to the API's own description
+    fn quality_label(qn: i64, data: &Value) -> String {
+        let known = match qn {
+            127 => "8K",
+            126 => "Dolby Vision",
+            125 => "HDR",
+            120 => "4K",
+            116 => "1080P60",
+            112 => "1080P+",
+            80 => "1080P",
+            74 => "720P60",
+            64 => "720P",
+            32 => "480P",
+            16 => "360P",
+            6 => "240P",
+            _ => "",
+        };
+        if !known.is_empty() {
+            return known.to_string();
+        }
+        data["support_formats"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|f| f["quality"].as_i64() == Some(qn))
+            .and_then(|f| f["new_description"].as_str())
+            .map(|d| d.to_string())
+            .unwrap_or_else(|| format!("qn{}", qn))
+    }
+
+    fn codec_name(video: &Value) -> Option<String> {
+        match video["codecid"].as_i64() {
+            Some(7) => Some("AVC".to_string()),
+            Some(12) => Some("HEVC".to_string()),
+            Some(13) => Some("AV1".to_string()),
+            _ => video["codecs"].as_str().map(|c| c.to_string()),
+        }
+    }
+
+    /// Builds the parse result from `x/web-interface/view` and `x/player/playurl` responses.
+    pub fn parse_api_responses(view_json: &Value, play_json: &Value) -> Result<VideoParseInfo> {
+        Self::parse_part_responses(view_json, play_json, None)
+    }
+
+    /// Like [`Self::parse_api_responses`] for part `page` (1-based) of a
+    /// multi-part upload, whose stream `play_json` must describe. Parts are
+    /// titled "<video title> P<n> <part title>" so downloads of a whole set
+    /// get distinct names.
+    pub fn parse_part_responses(view_json: &Value, play_json: &Value, page: Option<u32>) -> Result<VideoParseInfo> {
+        Self::check_api_code(view_json)?;
+        Self::check_api_code(play_json)?;
+
+        let data = &view_json["data"];
+        let mut title = data["title"].as_str().unwrap_or("").to_string();
+        let mut duration = data["duration"].as_u64();
+        let parts = Self::parse_parts(data, page);
+        if data["pages"].as_array().is_some_and(|pages| pages.len() > 1) {
+            if let Some(part) = parts.iter().find(|p| p.current) {
+                title = format!("{} P{} {}", title, part.index, part.title);
+                duration = Some(part.duration);
+            }
+        }
+        let pic = data["pic"].as_str().unwrap_or("").to_string();
+        let owner = &data["owner"];
+        let author_name = owner["name"].as_str().unwrap_or("").to_string();
+        let author_avatar = owner["face"].as_str().unwrap_or("").to_string();
+        let _mid = owner["mid"].as_i64().unwrap_or(0).to_string();
+
         let mut video_url = String::new();
         if let Some(durl) = play_json["data"]["durl"].as_array() {
-            if let Some(first_url) = durl.get(0) {
+            if let Some(first_url) = durl.first() {
                  video_url = first_url["url"].as_str().unwrap_or("").to_string();
             }
         }
         
         if video_url.is_empty() {
-             return Err(anyhow!("Could not find video stream URL"));
+             return Err(ParseError::LayoutChanged("Could not find video stream URL".to_string()));
         }
 
         Ok(VideoParseInfo {
@@ -95,19 +564,133 @@ impl Bilibili {
             images: vec![],
             platform: "bilibili".to_string(),
             video_qualities: vec![],
-            statistics: None,
+            statistics: Self::parse_stat(&data["stat"]),
             tags: None,
             music_info: None,
-            create_time: None,
+            create_time: data["pubdate"].as_u64(),
+            duration,
+            description: data["desc"].as_str().filter(|d| !d.is_empty()).map(|d| d.to_string()),
+            parts,
+            subtitles: vec![],
+            danmaku_url: Self::selected_cid(data, page).ok().filter(|cid| *cid > 0).map(Self::danmaku_url),
+            page_url: None,
+        })
+    }
+
+    // `stat` of a view or season response; seasons spell some counters differently
+    fn parse_stat(stat: &Value) -> Option<VideoStatistics> {
+        if !stat.is_object() {
+            return None;
+        }
+        let count = |keys: &[&str]| keys.iter().find_map(|k| stat[*k].as_u64());
+        Some(VideoStatistics {
+            likes: count(&["like", "likes"]),
+            views: count(&["view", "views"]),
+            favorites: count(&["favorite", "favorites"]),
+            shares: count(&["share"]),
+            comments: count(&["reply"]),
+            coins: count(&["coin", "coins"]),
         })
     }
+
+    /// Tag names from a `x/tag/archive/tags` response.
+    pub fn parse_tags(tags_json: &Value) -> Result<Vec<String>> {
+        Self::check_api_code(tags_json)?;
+        Ok(tags_json["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|tag| tag["tag_name"].as_str())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string())
+            .collect())
+    }
+
+    /// Parts of the video described by a `x/web-interface/view` `data`
+    /// object: its pages when there are several, otherwise the episodes of
+    /// the collection (`ugc_season`) it belongs to. Empty for a lone video.
+    pub fn parse_parts(data: &Value, page: Option<u32>) -> Vec<VideoPart> {
+        let bvid = data["bvid"].as_str().unwrap_or("");
+        let pages = data["pages"].as_array().map(|p| p.as_slice()).unwrap_or_default();
+        if pages.len() > 1 {
+            let current = page.unwrap_or(1);
+            return pages
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let index = p["page"].as_u64().map(|n| n as u32).unwrap_or(i as u32 + 1);
+                    VideoPart {
+                        index,
+                        title: p["part"].as_str().unwrap_or("").to_string(),
+                        id: p["cid"].as_i64().unwrap_or(0).to_string(),
+                        duration: p["duration"].as_u64().unwrap_or(0),
+                        url: format!("https://www.bilibili.com/video/{}?p={}", bvid, index),
+                        current: index == current,
+                    }
+                })
+                .collect();
+        }
+
+        let episodes = data["ugc_season"]["sections"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|section| section["episodes"].as_array().into_iter().flatten());
+        episodes
+            .enumerate()
+            .map(|(i, ep)| {
+                let ep_bvid = ep["bvid"].as_str().unwrap_or("");
+                VideoPart {
+                    index: i as u32 + 1,
+                    title: ep["title"].as_str().unwrap_or("").to_string(),
+                    id: ep["cid"].as_i64().unwrap_or(0).to_string(),
+                    duration: ep["arc"]["duration"]
+                        .as_u64()
+                        .or_else(|| ep["page"]["duration"].as_u64())
+                        .unwrap_or(0),
+                    url: format!("https://www.bilibili.com/video/{}", ep_bvid),
+                    current: ep_bvid == bvid,
+                }
+            })
+            .collect()
+    }
+
+    // cid of the requested page, or of the video's default page
+    fn selected_cid(data: &Value, page: Option<u32>) -> Result<i64> {
+        if let Some(page) = page {
+            let pages = data["pages"].as_array().map(|p| p.as_slice()).unwrap_or_default();
+            return pages
+                .iter()
+                .find(|p| p["page"].as_u64() == Some(page as u64))
+                .or_else(|| pages.get(page as usize - 1))
+                .and_then(|p| p["cid"].as_i64())
+                .ok_or_else(|| ParseError::NotFound(format!("Part {} does not exist ({} parts)", page, pages.len())));
+        }
+        Ok(data["cid"].as_i64().or_else(|| data["pages"][0]["cid"].as_i64()).unwrap_or(0))
+    }
     
-    // Extracted from reference: b23.tv redirection
-    async fn get_bvid_from_short_url(short_url: &str) -> Result<String> {
-         let client = Client::builder()
-            .redirect(reqwest::redirect::Policy::none())
-            .build()?;
-            
+    // Maps Bilibili's `code` field onto a ParseError category
+    fn check_api_code(json: &Value) -> Result<()> {
+        let code = json["code"].as_i64().unwrap_or(-1);
+        if code == 0 {
+            return Ok(());
+        }
+        let detail = format!("Bilibili API Error {}: {}", code, json["message"].as_str().unwrap_or(""));
+        Err(match code {
+            -404 | 62002 | 62004 | 62012 => ParseError::NotFound(detail),
+            -101 => ParseError::LoginRequired(detail),
+            -412 | -509 | -799 => ParseError::RateLimited(detail),
+            // -10403 covers both "area restricted" and "members only"
+            -10403 if detail.contains("地区") => ParseError::RegionBlocked(detail),
+            -10403 => ParseError::LoginRequired(detail),
+            6002003 => ParseError::RegionBlocked(detail),
+            _ => ParseError::Internal(detail),
+        })
+    }
+
+    // Extracted from reference: b23.tv redirection. Returns the full video URL,
+    // which keeps the `?p=` of links to a specific part.
+    async fn resolve_short_url(ctx: &ParseContext, short_url: &str) -> Result<String> {
          // Check if it's already a full URL? No, ensure we have a protocol
          let url = if !short_url.starts_with("http") {
              format!("https://{}", short_url)
@@ -115,22 +698,29 @@ impl Bilibili {
              short_url.to_string()
          };
 
-         let res = client.get(&url)
+         let res = ctx.no_redirect_client().get(&url)
             .header(USER_AGENT, Self::BILI_USER_AGENT)
+            .headers(ctx.headers("bilibili"))
             .send()
             .await?;
             
          if res.status().is_redirection() {
              if let Some(loc) = res.headers().get("location") {
-                 let full_url = loc.to_str()?.to_string();
-                 return Self::get_bvid_from_url(&full_url);
+                 return Ok(loc.to_str()?.to_string());
              }
          }
          
-         Err(anyhow!("Could not resolve b23.tv short link"))
+         Err(ParseError::NotFound("Could not resolve b23.tv short link".to_string()))
     }
     
-    fn get_bvid_from_url(full_url: &str) -> Result<String> {
+    /// The part selected by a `?p=N` query, if any.
+    pub fn page_from_url(full_url: &str) -> Option<u32> {
+        let parsed = Url::parse(full_url).ok()?;
+        let page = parsed.query_pairs().find(|(k, _)| k == "p")?.1.parse::<u32>().ok()?;
+        (page > 0).then_some(page)
+    }
+
+    pub fn get_bvid_from_url(full_url: &str) -> Result<String> {
         let parsed = Url::parse(full_url)?;
         // Path should be like /video/BVxxxxxxxx
         if let Some(path_segments) = parsed.path_segments() {
@@ -148,13 +738,39 @@ impl Bilibili {
             }
             
             // Sometimes path is just /BVxxxx (if mobile?)
-            for segment in segments {
+            for segment in &segments {
                 if segment.starts_with("BV") {
                     return Ok(segment.to_string());
                 }
             }
+
+            // Legacy /video/av170001 links
+            for segment in segments {
+                let aid = segment.strip_prefix("av").or_else(|| segment.strip_prefix("AV"));
+                if let Some(aid) = aid.and_then(|n| n.parse::<u64>().ok()) {
+                    return Ok(Self::av_to_bv(aid));
+                }
+            }
+        }
+        if let Some(aid) = parsed.query_pairs().find(|(k, _)| k == "aid").and_then(|(_, v)| v.parse::<u64>().ok()) {
+            return Ok(Self::av_to_bv(aid));
         }
         
-        Err(anyhow!("Could not find BVID in path"))
+        Err(ParseError::UnsupportedUrl("Could not find BVID in path".to_string()))
+    }
+}
+
+#[async_trait]
+impl Parser for Bilibili {
+    fn platform(&self) -> &'static str {
+        "bilibili"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["bilibili.com", "b23.tv"]
+    }
+
+    async fn parse(&self, ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo> {
+        Self::parse_share_url(ctx, share_text).await
     }
 }
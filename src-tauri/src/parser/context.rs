@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, COOKIE};
+use reqwest::redirect::Policy;
+use reqwest::{Client, ClientBuilder};
+
+use crate::parser::error::{ParseError, Result};
+
+/// HTTP configuration shared by every parser: the clients used for requests,
+/// base URL overrides, extra per-platform headers and login cookies.
+///
+/// Base URLs are keyed by the origin a parser would normally call (e.g.
+/// `https://api.bilibili.com`), since several platforms talk to more than one host.
+#[derive(Clone)]
+pub struct ParseContext {
+    client: Client,
+    no_redirect_client: Client,
+    base_urls: HashMap<String, String>,
+    headers: HashMap<String, HeaderMap>,
+    // Shared between clones, so cookies imported at runtime reach every parser
+    cookies: Arc<RwLock<HashMap<String, HeaderValue>>>,
+}
+
+impl ParseContext {
+    /// Creates a context with default clients and no overrides.
+    pub fn new() -> Self {
+        Self::with_client_config(|builder| builder).expect("failed to build default HTTP client")
+    }
+
+    /// Creates a context whose clients are built through `configure`,
+    /// e.g. to set a proxy, timeout or extra root certificate.
+    pub fn with_client_config<F>(configure: F) -> Result<Self>
+    where
+        F: Fn(ClientBuilder) -> ClientBuilder,
+    {
+        Ok(Self {
+            client: configure(Client::builder()).build()?,
+            no_redirect_client: configure(Client::builder()).redirect(Policy::none()).build()?,
+            base_urls: HashMap::new(),
+            headers: HashMap::new(),
+            cookies: Arc::default(),
+        })
+    }
+
+    /// Sends requests for `origin` to `base_url` instead, e.g. a mock or mirror server.
+    pub fn with_base_url(mut self, origin: &str, base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into();
+        self.base_urls.insert(
+            origin.trim_end_matches('/').to_string(),
+            base_url.trim_end_matches('/').to_string(),
+        );
+        self
+    }
+
+    /// Adds a header to every request made for `platform`. It replaces a
+    /// parser default of the same name.
+    pub fn with_header(mut self, platform: &str, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers
+            .entry(platform.to_string())
+            .or_default()
+            .insert(name, value);
+        self
+    }
+
+    /// Sends `cookie` (a `Cookie` header value) with every request made for
+    /// `platform`, replacing any cookies the parser would send itself. Takes
+    /// effect for this context and all its clones.
+    pub fn set_cookies(&self, platform: &str, cookie: &str) -> Result<()> {
+        let value = HeaderValue::from_str(cookie)
+            .map_err(|e| ParseError::Internal(format!("Invalid cookie for {}: {}", platform, e)))?;
+        if let Ok(mut cookies) = self.cookies.write() {
+            cookies.insert(platform.to_string(), value);
+        }
+        Ok(())
+    }
+
+    /// Stops sending stored cookies for `platform`.
+    pub fn remove_cookies(&self, platform: &str) {
+        if let Ok(mut cookies) = self.cookies.write() {
+            cookies.remove(platform);
+        }
+    }
+
+    /// Platforms that currently have cookies set.
+    pub fn cookie_platforms(&self) -> Vec<String> {
+        let mut platforms: Vec<String> = self.cookies.read().map(|c| c.keys().cloned().collect()).unwrap_or_default();
+        platforms.sort();
+        platforms
+    }
+
+    /// Client that follows redirects.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Client that returns 3xx responses as-is, for reading `Location` headers.
+    pub fn no_redirect_client(&self) -> &Client {
+        &self.no_redirect_client
+    }
+
+    /// Rewrites `url` onto its configured base URL, if its origin has one.
+    pub fn url(&self, url: &str) -> String {
+        for (origin, base_url) in &self.base_urls {
+            if let Some(rest) = url.strip_prefix(origin.as_str()) {
+                if rest.is_empty() || rest.starts_with('/') || rest.starts_with('?') {
+                    return format!("{}{}", base_url, rest);
+                }
+            }
+        }
+        url.to_string()
+    }
+
+    /// Extra headers configured for `platform`, including its cookies; pass to
+    /// `RequestBuilder::headers` after the parser's own headers so they take
+    /// precedence. A `Cookie` set through [`Self::with_header`] wins over
+    /// stored cookies.
+    pub fn headers(&self, platform: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(cookie) = self.cookies.read().ok().and_then(|c| c.get(platform).cloned()) {
+            headers.insert(COOKIE, cookie);
+        }
+        if let Some(extra) = self.headers.get(platform) {
+            headers.extend(extra.clone());
+        }
+        headers
+    }
+}
+
+impl Default for ParseContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
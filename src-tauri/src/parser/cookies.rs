@@ -0,0 +1,119 @@
+// Login cookies imported by the user, either a Netscape `cookies.txt` export
+// or a `Cookie:` header copied from the browser's developer tools.
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::parser::ParserRegistry;
+
+/// One cookie from a `cookies.txt` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    /// Domain without the leading dot
+    pub domain: String,
+    pub name: String,
+    pub value: String,
+    /// Unix time; `None` for session cookies
+    pub expires: Option<i64>,
+}
+
+/// Reads the cookies of a Netscape `cookies.txt` file, as written by curl,
+/// yt-dlp and the "Get cookies.txt" browser extensions. Expired cookies and
+/// malformed lines are skipped.
+pub fn parse_netscape(text: &str) -> Vec<Cookie> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    text.lines()
+        .filter_map(|line| {
+            // HttpOnly cookies are written as comments with this prefix
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.starts_with('#') {
+                return None;
+            }
+            let fields: Vec<&str> = line.trim_end_matches('\r').split('\t').collect();
+            let [domain, _, _, _, expires, name, value] = fields[..] else { return None };
+            let expires = expires.parse::<i64>().ok().filter(|e| *e > 0);
+            if name.is_empty() || expires.is_some_and(|e| e < now) {
+                return None;
+            }
+            Some(Cookie {
+                domain: domain.trim_start_matches('.').to_lowercase(),
+                name: name.to_string(),
+                value: value.to_string(),
+                expires,
+            })
+        })
+        .collect()
+}
+
+/// Reads `name=value` pairs from a pasted `Cookie` header, with or without
+/// the `Cookie:` prefix.
+pub fn parse_header(text: &str) -> Vec<(String, String)> {
+    let text = text.trim();
+    let text = match text.split_once(':') {
+        Some((prefix, rest)) if prefix.trim().eq_ignore_ascii_case("cookie") => rest,
+        _ => text,
+    };
+    text.split(';')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Turns pasted cookies into one `Cookie` header per platform.
+///
+/// A `cookies.txt` export is split by the platform whose hosts match each
+/// cookie's domain; cookies for other sites are dropped. A pasted header
+/// belongs to `platform`, which must then be given. When `platform` is given
+/// for a `cookies.txt` file, only that platform's cookies are kept.
+pub fn import(registry: &ParserRegistry, text: &str, platform: Option<&str>) -> Result<BTreeMap<String, String>, String> {
+    let mut pairs: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    if is_netscape(text) {
+        for cookie in parse_netscape(text) {
+            let Some(owner) = registry.platform_for_host(&cookie.domain) else { continue };
+            if platform.is_none_or(|p| p == owner) {
+                let entry = pairs.entry(owner.to_string()).or_default();
+                // A later line for the same name, e.g. a more specific domain, wins
+                entry.retain(|(name, _)| *name != cookie.name);
+                entry.push((cookie.name, cookie.value));
+            }
+        }
+    } else {
+        let platform = platform.ok_or("Choose the platform a pasted Cookie header belongs to")?;
+        if !registry.platforms().contains(&platform) {
+            return Err(format!("Unknown platform: {}", platform));
+        }
+        pairs.insert(platform.to_string(), parse_header(text));
+    }
+
+    let headers: BTreeMap<String, String> = pairs
+        .into_iter()
+        .filter(|(_, pairs)| !pairs.is_empty())
+        .map(|(platform, pairs)| (platform, to_header(&pairs)))
+        .collect();
+    if headers.is_empty() {
+        return Err("No cookies for a supported platform found".to_string());
+    }
+    Ok(headers)
+}
+
+/// Joins `name=value` pairs into a `Cookie` header value.
+pub fn to_header(pairs: &[(String, String)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Either the usual header comment or a line with the seven tab-separated fields
+fn is_netscape(text: &str) -> bool {
+    text.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with("# Netscape HTTP Cookie File")
+            || line.starts_with("# HTTP Cookie File")
+            || line.starts_with("#HttpOnly_")
+            || (!line.starts_with('#') && line.split('\t').count() == 7)
+    })
+}
@@ -1,16 +1,16 @@
 use crate::models::{Author, ImgInfo, VideoParseInfo, VideoPreview};
-use crate::parser::utils;
-use anyhow::{anyhow, Result};
+use crate::parser::{utils, ParseContext, Parser};
+use crate::parser::error::{check_status, ParseError, Result};
+use async_trait::async_trait;
 use regex::Regex;
 use reqwest::header::USER_AGENT;
-use reqwest::Client;
 use scraper::{Html, Selector};
 use serde_json::Value;
 
 pub struct DouYin;
 
 impl DouYin {
-    pub async fn parse_share_url(share_url: &str) -> Result<VideoParseInfo> {
+    pub async fn parse_share_url(ctx: &ParseContext, share_url: &str) -> Result<VideoParseInfo> {
         let url_str = if let Some(u) = utils::regexp_match_url_from_string(share_url) {
             u
         } else {
@@ -21,45 +21,43 @@ impl DouYin {
         let host = url_parsed.host_str().unwrap_or("");
 
         if host.contains("v.douyin.com") {
-            return Self::parse_app_share_url(&url_str).await;
+            return Self::parse_app_share_url(ctx, &url_str).await;
         } else if host.contains("iesdouyin.com") || host.contains("douyin.com") {
-            return Self::parse_pc_share_url(&url_str).await;
+            return Self::parse_pc_share_url(ctx, &url_str).await;
         }
 
-        Err(anyhow!("douyin not support this host: {}", host))
+        Err(ParseError::UnsupportedUrl(format!("douyin not support this host: {}", host)))
     }
 
-    async fn parse_app_share_url(share_url: &str) -> Result<VideoParseInfo> {
-        let client = Client::builder()
-            .redirect(reqwest::redirect::Policy::none())
-            .build()?;
-        
+    async fn parse_app_share_url(ctx: &ParseContext, share_url: &str) -> Result<VideoParseInfo> {
         // App share URL usually redirects.
-        let res = client
+        let res = ctx
+            .no_redirect_client()
             .get(share_url)
             .header(USER_AGENT, utils::DEFAULT_USER_AGENT)
+            .headers(ctx.headers("douyin"))
             .send()
             .await?;
 
         let location = res
             .headers()
             .get("location")
-            .ok_or_else(|| anyhow!("No location header found"))?
+            .ok_or_else(|| ParseError::NotFound("No location header found".to_string()))?
             .to_str()?;
 
         let video_id = Self::parse_video_id_from_path(location)?;
         
         // TODO: Handle ixigua logic if needed, but for now focus on Douyin.
         
-        Self::parse_video_id(&video_id).await
+        Self::parse_video_id(ctx, &video_id).await
     }
 
-    async fn parse_pc_share_url(share_url: &str) -> Result<VideoParseInfo> {
+    async fn parse_pc_share_url(ctx: &ParseContext, share_url: &str) -> Result<VideoParseInfo> {
         let video_id = Self::parse_video_id_from_path(share_url)?;
-        Self::parse_video_id(&video_id).await
+        Self::parse_video_id(ctx, &video_id).await
     }
 
-    fn parse_video_id_from_path(url_path: &str) -> Result<String> {
+    pub fn parse_video_id_from_path(url_path: &str) -> Result<String> {
         let url_parsed = url::Url::parse(url_path)?;
         
         // Check for modal_id
@@ -78,48 +76,28 @@ impl DouYin {
             }
         }
 
-        Err(anyhow!("parse video id from path fail"))
+        Err(ParseError::UnsupportedUrl("parse video id from path fail".to_string()))
     }
 
-    pub async fn parse_video_id(video_id: &str) -> Result<VideoParseInfo> {
-        let req_url = format!("https://www.douyin.com/share/video/{}", video_id);
-        let client = Client::new();
+    pub async fn parse_video_id(ctx: &ParseContext, video_id: &str) -> Result<VideoParseInfo> {
+        let req_url = ctx.url(&format!("https://www.douyin.com/share/video/{}", video_id));
+        let client = ctx.client();
         
         let res = client
             .get(&req_url)
             .header(USER_AGENT, utils::DEFAULT_USER_AGENT)
+            .headers(ctx.headers("douyin"))
             .send()
             .await?;
+        let res = check_status(res, "Douyin share page")?;
             
         let res_body = res.text().await?;
         
         // Try to parse from window._ROUTER_DATA first for both video and note types
-        let re = Regex::new(r"window._ROUTER_DATA\s*=\s*(.*?)</script>").unwrap();
-        let mut json_data = Value::Null;
-
-        if let Some(caps) = re.captures(&res_body) {
-            if let Some(json_str) = caps.get(1) {
-                let json_text = json_str.as_str().trim();
-                if let Ok(full_data) = serde_json::from_str::<Value>(json_text) {
-                    if let Some(loader_data) = full_data.get("loaderData").and_then(|v| v.as_object()) {
-                        // Find key corresponding to video or note
-                        // e.g. "video_(id)/page" or "note_(id)/page"
-                        for (key, value) in loader_data {
-                            if key.contains("/page") && (key.starts_with("video_") || key.starts_with("note_")) {
-                                if let Some(item) = value.get("videoInfoRes")
-                                    .and_then(|v| v.get("item_list"))
-                                    .and_then(|v| v.as_array())
-                                    .and_then(|arr| arr.get(0)) 
-                                {
-                                    json_data = item.clone();
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        let mut json_data = Self::extract_router_data(&res_body)
+            .and_then(|json_text| serde_json::from_str::<Value>(&json_text).ok())
+            .and_then(|full_data| Self::find_router_item(&full_data).cloned())
+            .unwrap_or(Value::Null);
 
         // Fallback to API if JSON parsing failed and it's a note
         if json_data.is_null() {
@@ -133,20 +111,21 @@ impl DouYin {
             if is_note_from_canonical {
                 let web_id = format!("75{}", utils::generate_fixed_length_numeric_id(15));
                 let a_bogus = utils::rand_seq(64);
-                let api_url = format!(
+                let api_url = ctx.url(&format!(
                     "https://www.douyin.com/web/api/v2/aweme/slidesinfo/?reflow_source=reflow_page&web_id={}&device_id={}&aweme_ids=%5B{}%5D&request_source=200&a_bogus={}",
                     web_id, web_id, video_id, a_bogus
-                );
+                ));
                 
                 let api_res = client
                     .get(&api_url)
                     .header(USER_AGENT, utils::DEFAULT_USER_AGENT)
+                    .headers(ctx.headers("douyin"))
                     .send()
                     .await?
                     .json::<Value>()
                     .await?;
                     
-                let details = api_res.get("aweme_details").and_then(|v| v.as_array()).and_then(|arr| arr.get(0));
+                let details = api_res.get("aweme_details").and_then(|v| v.as_array()).and_then(|arr| arr.first());
                 if let Some(d) = details {
                     json_data = d.clone();
                 }
@@ -154,9 +133,45 @@ impl DouYin {
         }
 
         if json_data.is_null() {
-            return Err(anyhow!("Failed to parse video/note data from HTML or API"));
+            return Err(ParseError::LayoutChanged("Failed to parse video/note data from HTML or API".to_string()));
         }
 
+        let mut result = Self::parse_item(&json_data);
+
+          if !result.video_url.is_empty() {
+              Self::get_redirect_url(ctx, &mut result).await;
+          }
+          for q in &mut result.video_qualities {
+              let mut temp_info = VideoParseInfo {
+                  video_url: q.video_url.clone(),
+                  author: result.author.clone(),
+                  title: String::new(),
+                  music_url: String::new(),
+                  cover_url: String::new(),
+                  images: vec![],
+                  platform: String::new(),
+                  video_qualities: vec![],
+                  statistics: None,
+                  tags: None,
+                  music_info: None,
+                  create_time: None,
+                  duration: None,
+                  description: None,
+                  parts: vec![],
+                  subtitles: vec![],
+                  danmaku_url: None,
+                  page_url: None,
+              };
+              Self::get_redirect_url(ctx, &mut temp_info).await;
+              q.video_url = temp_info.video_url;
+          }
+          let kind = if result.images.is_empty() { "video" } else { "note" };
+          result.page_url = Some(format!("https://www.douyin.com/{}/{}", kind, video_id));
+
+          Ok(result)
+    }
+
+    fn parse_item(json_data: &Value) -> VideoParseInfo {
         // Detect if it is a note from the data if not already known
         let is_note = json_data.get("images").and_then(|v| v.as_array()).map(|a| !a.is_empty()).unwrap_or(false);
 
@@ -169,7 +184,7 @@ impl DouYin {
              for img in imgs {
                  if let Some(url_list) = img.get("url_list").and_then(|v| v.as_array()) {
                      let url = Self::get_no_webp_url(url_list);
-                     let live_photo_url = img.get("video").and_then(|v| v.get("play_addr")).and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|arr| arr.get(0)).and_then(|v| v.as_str()).map(|s| s.to_string());
+                     let live_photo_url = img.get("video").and_then(|v| v.get("play_addr")).and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|arr| arr.first()).and_then(|v| v.as_str()).map(|s| s.to_string());
                      
                      if !url.is_empty() {
                          images.push(ImgInfo { url, live_photo_url });
@@ -185,7 +200,7 @@ impl DouYin {
              // Extract qualities
              if let Some(video) = json_data.get("video") {
                  // The default video URL
-                 if let Some(v_url) = video.get("play_addr").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|arr| arr.get(0)).and_then(|v| v.as_str()) {
+                 if let Some(v_url) = video.get("play_addr").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|arr| arr.first()).and_then(|v| v.as_str()) {
                      video_url = v_url.replace("playwm", "play");
                  }
 
@@ -196,7 +211,7 @@ impl DouYin {
                          let size_bytes = br.get("play_addr").and_then(|v| v.get("data_size")).and_then(|v| v.as_u64());
                          
                          if let Some(url_list) = br.get("play_addr").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()) {
-                             if let Some(v_url) = url_list.get(0).and_then(|v| v.as_str()) {
+                             if let Some(v_url) = url_list.first().and_then(|v| v.as_str()) {
                                  let clean_url = v_url.replace("playwm", "play");
                                  // Only add if not empty
                                  if !clean_url.is_empty() {
@@ -204,6 +219,7 @@ impl DouYin {
                                          quality: quality_desc,
                                          video_url: clean_url,
                                          size: size_bytes,
+                                         ..Default::default()
                                      });
                                  }
                              }
@@ -228,7 +244,7 @@ impl DouYin {
           let author = Author {
               uid: json_data.get("author").and_then(|v| v.get("sec_uid")).and_then(|v| v.as_str()).unwrap_or("").to_string(),
               name: json_data.get("author").and_then(|v| v.get("nickname")).and_then(|v| v.as_str()).unwrap_or("").to_string(),
-              avatar: json_data.get("author").and_then(|v| v.get("avatar_thumb")).and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|arr| arr.get(0)).and_then(|v| v.as_str()).unwrap_or("").to_string(),
+              avatar: json_data.get("author").and_then(|v| v.get("avatar_thumb")).and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|arr| arr.first()).and_then(|v| v.as_str()).unwrap_or("").to_string(),
           };
 
           let statistics = json_data.get("statistics").map(|v| crate::models::VideoStatistics {
@@ -237,6 +253,7 @@ impl DouYin {
               favorites: v.get("collect_count").and_then(|c| c.as_u64()),
               shares: v.get("share_count").and_then(|c| c.as_u64()),
               comments: v.get("comment_count").and_then(|c| c.as_u64()),
+              coins: None,
           });
 
           let mut tags = Vec::new();
@@ -256,8 +273,8 @@ impl DouYin {
           if let Some(music) = json_data.get("music") {
               let m_title = music.get("title").and_then(|v| v.as_str()).unwrap_or("").to_string();
               let m_author = music.get("author").and_then(|v| v.as_str()).unwrap_or("").to_string();
-              let m_url = music.get("play_url").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|a| a.get(0)).and_then(|v| v.as_str()).unwrap_or("").to_string();
-              let m_cover = music.get("cover_large").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|a| a.get(0)).and_then(|v| v.as_str()).unwrap_or("").to_string();
+              let m_url = music.get("play_url").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|a| a.first()).and_then(|v| v.as_str()).unwrap_or("").to_string();
+              let m_cover = music.get("cover_large").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|a| a.first()).and_then(|v| v.as_str()).unwrap_or("").to_string();
               
               if !m_url.is_empty() {
                   music_info = Some(crate::models::MusicInfo {
@@ -269,7 +286,7 @@ impl DouYin {
               }
           }
 
-          let mut result = VideoParseInfo {
+          VideoParseInfo {
               author,
               title: desc,
               video_url,
@@ -282,31 +299,13 @@ impl DouYin {
               tags: Some(tags),
               music_info,
               create_time,
-          };
-          
-          if !result.video_url.is_empty() {
-              Self::get_redirect_url(&mut result).await;
+              duration: None,
+              description: None,
+              parts: vec![],
+              subtitles: vec![],
+              danmaku_url: None,
+              page_url: None,
           }
-          for q in &mut result.video_qualities {
-              let mut temp_info = VideoParseInfo {
-                  video_url: q.video_url.clone(),
-                  author: result.author.clone(),
-                  title: String::new(),
-                  music_url: String::new(),
-                  cover_url: String::new(),
-                  images: vec![],
-                  platform: String::new(),
-                  video_qualities: vec![],
-                  statistics: None,
-                  tags: None,
-                  music_info: None,
-                  create_time: None,
-              };
-              Self::get_redirect_url(&mut temp_info).await;
-              q.video_url = temp_info.video_url;
-          }
-          
-          Ok(result)
     }
     
     fn get_no_webp_url(url_list: &Vec<Value>) -> String {
@@ -325,34 +324,33 @@ impl DouYin {
     
     fn get_canonical_from_html(html: &str) -> Result<String> {
         let document = Html::parse_document(html);
-        let selector = Selector::parse("link[rel='canonical']").map_err(|e| anyhow!("{:?}", e))?;
+        let selector = Selector::parse("link[rel='canonical']").map_err(|e| ParseError::Internal(format!("{:?}", e)))?;
         
         if let Some(element) = document.select(&selector).next() {
             if let Some(href) = element.value().attr("href") {
                 return Ok(href.to_string());
             }
         }
-        Err(anyhow!("canonical not found"))
+        Err(ParseError::LayoutChanged("canonical not found".to_string()))
     }
     
-    async fn get_redirect_url(info: &mut VideoParseInfo) {
-        let client = Client::builder()
-            .redirect(reqwest::redirect::Policy::none())
-            .build();
-            
-        if let Ok(c) = client {
-             if let Ok(res) = c.get(&info.video_url).header(USER_AGENT, utils::DEFAULT_USER_AGENT).send().await {
-                 if let Some(loc) = res.headers().get("location") {
-                     if let Ok(loc_str) = loc.to_str() {
-                         info.video_url = loc_str.to_string();
-                     }
-                 }
-             }
+    async fn get_redirect_url(ctx: &ParseContext, info: &mut VideoParseInfo) {
+        let req = ctx
+            .no_redirect_client()
+            .get(&info.video_url)
+            .header(USER_AGENT, utils::DEFAULT_USER_AGENT)
+            .headers(ctx.headers("douyin"));
+        if let Ok(res) = req.send().await {
+            if let Some(loc) = res.headers().get("location") {
+                if let Ok(loc_str) = loc.to_str() {
+                    info.video_url = loc_str.to_string();
+                }
+            }
         }
     }
 
-    pub fn parse_router_data(json_str: &str) -> Result<Vec<VideoPreview>, String> {
-        let data: Value = serde_json::from_str(json_str).map_err(|e| format!("JSON parse error: {}", e))?;
+    pub fn parse_router_data(json_str: &str) -> Result<Vec<VideoPreview>> {
+        let data: Value = serde_json::from_str(json_str)?;
         
         let mut previews = Vec::new();
         
@@ -390,164 +388,57 @@ impl DouYin {
         Ok(previews)
     }
 
-    pub fn parse_video_data_from_json(json_str: &str) -> Result<VideoParseInfo, String> {
-         let data: Value = serde_json::from_str(json_str).map_err(|e| format!("JSON parse error: {}", e))?;
-         
-         let mut json_data = Value::Null;
-         
-         if let Some(loader_data) = data.get("loaderData").and_then(|v| v.as_object()) {
-             for (key, value) in loader_data {
-                 if key.contains("/page") && (key.starts_with("video_") || key.starts_with("note_")) {
-                      if let Some(item) = value.get("videoInfoRes")
-                                    .and_then(|v| v.get("item_list"))
-                                    .and_then(|v| v.as_array())
-                                    .and_then(|arr| arr.get(0)) 
-                      {
-                            json_data = item.clone();
-                            break;
-                      }
-                 }
-             }
-         }
-         
-         if json_data.is_null() {
-             return Err("Could not find video info in router data".to_string());
-         }
-
-        let is_note = json_data.get("images").and_then(|v| v.as_array()).map(|a| !a.is_empty()).unwrap_or(false);
-
-         // Construct VideoParseInfo
-         let desc = json_data.get("desc").and_then(|v| v.as_str()).unwrap_or("").to_string();
-         
-         let mut images = Vec::new();
-         if let Some(imgs) = json_data.get("images").and_then(|v| v.as_array()) {
-             for img in imgs {
-                 if let Some(url_list) = img.get("url_list").and_then(|v| v.as_array()) {
-                     let url = Self::get_no_webp_url(url_list);
-                     let live_photo_url = img.get("video").and_then(|v| v.get("play_addr")).and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|arr| arr.get(0)).and_then(|v| v.as_str()).map(|s| s.to_string());
-                     
-                     if !url.is_empty() {
-                         images.push(ImgInfo { url, live_photo_url });
-                     }
-                 }
-             }
-         }
-         
-         let mut video_url = String::new();
-         let mut video_qualities = Vec::new();
-
-         if !is_note {
-             if let Some(video) = json_data.get("video") {
-                 if let Some(v_url) = video.get("play_addr").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|arr| arr.get(0)).and_then(|v| v.as_str()) {
-                     video_url = v_url.replace("playwm", "play");
-                 }
-
-                 if let Some(bit_rates) = video.get("bit_rate").and_then(|v| v.as_array()) {
-                     for br in bit_rates {
-                         let quality_desc = br.get("gear_name").and_then(|v| v.as_str()).unwrap_or("未知").to_string();
-                         let size_bytes = br.get("play_addr").and_then(|v| v.get("data_size")).and_then(|v| v.as_u64());
-                         
-                         if let Some(url_list) = br.get("play_addr").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()) {
-                             if let Some(v_url) = url_list.get(0).and_then(|v| v.as_str()) {
-                                 let clean_url = v_url.replace("playwm", "play");
-                                 if !clean_url.is_empty() {
-                                     video_qualities.push(crate::models::VideoQuality {
-                                         quality: quality_desc,
-                                         video_url: clean_url,
-                                         size: size_bytes,
-                                     });
-                                 }
-                             }
-                         }
-                     }
-                 }
-             }
-         }
-         
-         if !images.is_empty() {
-             video_url = "".to_string();
-             video_qualities.clear();
-         }
-
-          let cover_url_list = json_data.get("video").and_then(|v| v.get("cover")).and_then(|v| v.get("url_list")).and_then(|v| v.as_array());
-          let cover_url = if let Some(list) = cover_url_list {
-              Self::get_no_webp_url(list)
-          } else {
-              "".to_string()
-          };
-
-          let author = Author {
-              uid: json_data.get("author").and_then(|v| v.get("sec_uid")).and_then(|v| v.as_str()).unwrap_or("").to_string(),
-              name: json_data.get("author").and_then(|v| v.get("nickname")).and_then(|v| v.as_str()).unwrap_or("").to_string(),
-              avatar: json_data.get("author").and_then(|v| v.get("avatar_thumb")).and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|arr| arr.get(0)).and_then(|v| v.as_str()).unwrap_or("").to_string(),
-          };
+    pub fn parse_video_data_from_json(json_str: &str) -> Result<VideoParseInfo> {
+        let data: Value = serde_json::from_str(json_str)?;
+        let json_data = Self::find_router_item(&data)
+            .ok_or_else(|| ParseError::LayoutChanged("Could not find video info in router data".to_string()))?;
+        Ok(Self::parse_item(json_data))
+    }
 
-          let statistics = json_data.get("statistics").map(|v| crate::models::VideoStatistics {
-              likes: v.get("digg_count").and_then(|c| c.as_u64()),
-              views: v.get("play_count").and_then(|c| c.as_u64()),
-              favorites: v.get("collect_count").and_then(|c| c.as_u64()),
-              shares: v.get("share_count").and_then(|c| c.as_u64()),
-              comments: v.get("comment_count").and_then(|c| c.as_u64()),
-          });
+    /// Returns the raw `window._ROUTER_DATA` JSON embedded in a share page.
+    pub fn extract_router_data(html: &str) -> Option<String> {
+        let re = Regex::new(r"window._ROUTER_DATA\s*=\s*(.*?)</script>").unwrap();
+        re.captures(html)
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str().trim().to_string())
+    }
 
-          let mut tags = Vec::new();
-          if let Some(text_extras) = json_data.get("text_extra").and_then(|v| v.as_array()) {
-              for extra in text_extras {
-                  if let Some(tag_name) = extra.get("hashtag_name").and_then(|v| v.as_str()) {
-                       if !tag_name.is_empty() {
-                           tags.push(tag_name.to_string());
-                       }
-                  }
-              }
-          }
+    // Find key corresponding to video or note
+    // e.g. "video_(id)/page" or "note_(id)/page"
+    fn find_router_item(data: &Value) -> Option<&Value> {
+        let loader_data = data.get("loaderData").and_then(|v| v.as_object())?;
+        for (key, value) in loader_data {
+            if key.contains("/page") && (key.starts_with("video_") || key.starts_with("note_")) {
+                if let Some(item) = value.get("videoInfoRes")
+                    .and_then(|v| v.get("item_list"))
+                    .and_then(|v| v.as_array())
+                    .and_then(|arr| arr.first())
+                {
+                    return Some(item);
+                }
+            }
+        }
+        None
+    }
 
-          let create_time = json_data.get("create_time").and_then(|v| v.as_u64());
+    /// Profile posts are collected by the app's window fetcher through
+    /// [`DouYin::parse_router_data`]; there is no HTTP implementation.
+    pub async fn fetch_posts(_sec_uid: &str) -> Result<Vec<VideoPreview>> {
+        Err(ParseError::Internal("Please use window fetcher".to_string()))
+    }
+}
 
-          let mut music_info = None;
-          if let Some(music) = json_data.get("music") {
-              let m_title = music.get("title").and_then(|v| v.as_str()).unwrap_or("").to_string();
-              let m_author = music.get("author").and_then(|v| v.as_str()).unwrap_or("").to_string();
-              let m_url = music.get("play_url").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|a| a.get(0)).and_then(|v| v.as_str()).unwrap_or("").to_string();
-              let m_cover = music.get("cover_large").and_then(|v| v.get("url_list")).and_then(|v| v.as_array()).and_then(|a| a.get(0)).and_then(|v| v.as_str()).unwrap_or("").to_string();
-              
-              if !m_url.is_empty() {
-                  music_info = Some(crate::models::MusicInfo {
-                      title: m_title,
-                      author: m_author,
-                      url: m_url,
-                      cover_url: m_cover,
-                  });
-              }
-          }
+#[async_trait]
+impl Parser for DouYin {
+    fn platform(&self) -> &'static str {
+        "douyin"
+    }
 
-          Ok(VideoParseInfo {
-              author,
-              title: desc,
-              video_url,
-              music_url: "".to_string(), 
-              cover_url,
-              images,
-              platform: "douyin".to_string(),
-              video_qualities,
-              statistics,
-              tags: Some(tags),
-              music_info,
-              create_time,
-          })
+    fn hosts(&self) -> &'static [&'static str] {
+        &["douyin.com", "iesdouyin.com"]
     }
 
-    pub async fn fetch_posts(sec_uid: &str) -> Result<Vec<VideoPreview>, String> {
-        // Kept for reference but likely unused if using window fetcher
-        // ... (existing code, but we can deprecate or empty it)
-        // Let's keep existing implementation in case we revert or use it for testing
-        // For brevity in diff I will NOT remove it, but I AM adding parse_router_data
-        
-        // Actually, the user asked to FIX it. If I redirect to window fetch, this function
-        // is bypassed in lib.rs.
-        // So I'll just append parse_router_data before it. or replace it?
-        // lib.rs calls DouYin::parse_router_data
-        // lib.rs NO LONGER calls DouYin::fetch_posts for douyin platform.
-        
-        Err("Please use window fetcher".to_string())
+    async fn parse(&self, ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo> {
+        Self::parse_share_url(ctx, share_text).await
     }
 }
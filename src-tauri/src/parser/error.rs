@@ -0,0 +1,133 @@
+use reqwest::StatusCode;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+pub type Result<T> = std::result::Result<T, ParseError>;
+
+/// Failure reasons shared by every platform parser.
+///
+/// Serialized to the frontend as `{ "code": "...", "detail": "...", "retryable": bool }`
+/// so the UI can pick a localized message and decide whether to offer a retry.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ParseError {
+    #[error("Unsupported URL: {0}")]
+    UnsupportedUrl(String),
+    #[error("Content not found or deleted: {0}")]
+    NotFound(String),
+    #[error("Login required: {0}")]
+    LoginRequired(String),
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+    #[error("Region blocked: {0}")]
+    RegionBlocked(String),
+    #[error("Upstream layout changed: {0}")]
+    LayoutChanged(String),
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl ParseError {
+    /// Stable machine-readable code sent to the frontend.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnsupportedUrl(_) => "unsupported_url",
+            ParseError::NotFound(_) => "not_found",
+            ParseError::LoginRequired(_) => "login_required",
+            ParseError::RateLimited(_) => "rate_limited",
+            ParseError::RegionBlocked(_) => "region_blocked",
+            ParseError::LayoutChanged(_) => "layout_changed",
+            ParseError::Network(_) => "network",
+            ParseError::Internal(_) => "internal",
+        }
+    }
+
+    /// Human-readable detail without the category prefix.
+    pub fn detail(&self) -> &str {
+        match self {
+            ParseError::UnsupportedUrl(d)
+            | ParseError::NotFound(d)
+            | ParseError::LoginRequired(d)
+            | ParseError::RateLimited(d)
+            | ParseError::RegionBlocked(d)
+            | ParseError::LayoutChanged(d)
+            | ParseError::Network(d)
+            | ParseError::Internal(d) => d,
+        }
+    }
+
+    /// Whether repeating the same request later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ParseError::Network(_) | ParseError::RateLimited(_))
+    }
+
+    /// Maps an unsuccessful HTTP status to the closest error category.
+    pub fn from_status(status: StatusCode, context: &str) -> Self {
+        let detail = format!("{} returned HTTP {}", context, status);
+        match status.as_u16() {
+            404 | 410 => ParseError::NotFound(detail),
+            // Platforms answer 403 for content behind a login or membership;
+            // repeating the request does not help
+            401 | 403 => ParseError::LoginRequired(detail),
+            429 => ParseError::RateLimited(detail),
+            451 => ParseError::RegionBlocked(detail),
+            _ => ParseError::Network(detail),
+        }
+    }
+}
+
+/// Fails with the mapped `ParseError` when the response has a 4xx/5xx status.
+pub fn check_status(res: reqwest::Response, context: &str) -> Result<reqwest::Response> {
+    let status = res.status();
+    if status.is_client_error() || status.is_server_error() {
+        return Err(ParseError::from_status(status, context));
+    }
+    Ok(res)
+}
+
+impl Serialize for ParseError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("ParseError", 3)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("detail", self.detail())?;
+        s.serialize_field("retryable", &self.is_retryable())?;
+        s.end()
+    }
+}
+
+impl From<reqwest::Error> for ParseError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            // Body was not the JSON we expected: usually a captcha or a changed API.
+            ParseError::LayoutChanged(e.to_string())
+        } else if let Some(status) = e.status() {
+            ParseError::from_status(status, "upstream")
+        } else {
+            ParseError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(e: serde_json::Error) -> Self {
+        ParseError::LayoutChanged(format!("JSON decode error: {}", e))
+    }
+}
+
+impl From<url::ParseError> for ParseError {
+    fn from(e: url::ParseError) -> Self {
+        ParseError::UnsupportedUrl(e.to_string())
+    }
+}
+
+impl From<reqwest::header::ToStrError> for ParseError {
+    fn from(e: reqwest::header::ToStrError) -> Self {
+        ParseError::LayoutChanged(format!("Invalid header value: {}", e))
+    }
+}
+
+impl From<regex::Error> for ParseError {
+    fn from(e: regex::Error) -> Self {
+        ParseError::Internal(e.to_string())
+    }
+}
@@ -1,9 +1,9 @@
 use crate::models::{Author, ImgInfo, VideoParseInfo};
-use crate::parser::utils;
-use anyhow::{anyhow, Result};
+use crate::parser::{utils, ParseContext, Parser};
+use crate::parser::error::{check_status, ParseError, Result};
+use async_trait::async_trait;
 use regex::Regex;
 use reqwest::header::{USER_AGENT, ACCEPT, COOKIE};
-use reqwest::Client;
 use serde_json::Value;
 use std::time::Duration;
 
@@ -12,7 +12,7 @@ pub struct Kuaishou;
 impl Kuaishou {
     const KUAISHOU_USER_AGENT: &'static str = "Mozilla/5.0 (iPhone; CPU iPhone OS 26_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/26.0 Mobile/15E148 Safari/604.1";
 
-    pub async fn parse_share_url(share_url: &str) -> Result<VideoParseInfo> {
+    pub async fn parse_share_url(ctx: &ParseContext, share_url: &str) -> Result<VideoParseInfo> {
         let url_str = if let Some(u) = utils::regexp_match_url_from_string(share_url) {
             u
         } else {
@@ -26,10 +26,7 @@ impl Kuaishou {
         // Or disable redirects and loop manually.
         // Kuaishou 'v.kuaishou.com' usually redirects 302.
         
-        let client = Client::builder()
-            .timeout(Duration::from_secs(10))
-            .redirect(reqwest::redirect::Policy::none()) // Handle redirects manually
-            .build()?;
+        let client = ctx.no_redirect_client(); // Handle redirects manually
 
         let mut current_url = url_str.clone();
         let mut final_url = String::new();
@@ -38,7 +35,9 @@ impl Kuaishou {
         // or just to catch the 'fs/long-video' case before final fetch.
         for _ in 0..5 {
             let res = client.get(&current_url)
+                .timeout(Duration::from_secs(10))
                 .header(USER_AGENT, Self::KUAISHOU_USER_AGENT)
+                .headers(ctx.headers("kuaishou"))
                 .send()
                 .await?;
 
@@ -67,22 +66,29 @@ impl Kuaishou {
 
         // 2. Fetch the final page content
         let res = client.get(&final_url)
+            .timeout(Duration::from_secs(10))
             .header(USER_AGENT, Self::KUAISHOU_USER_AGENT)
             .header(ACCEPT, "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7")
             .header(COOKIE, "did=web_d1326127361a7a02596e1e273063544d; didv=1686713337000;") // Basic cookie often helps
+            .headers(ctx.headers("kuaishou"))
             .send()
             .await?;
 
+        let res = check_status(res, "Kuaishou page")?;
+
         let html = res.text().await?;
         
-        Self::parse_html(&html)
+        let mut info = Self::parse_html(&html)?;
+        info.page_url = Some(final_url);
+        Ok(info)
     }
 
-    fn parse_html(html: &str) -> Result<VideoParseInfo> {
+    /// Extracts video/atlas info from the `window.INIT_STATE` script of a photo page.
+    pub fn parse_html(html: &str) -> Result<VideoParseInfo> {
         // Extract window.INIT_STATE JSON
         // Regex: window.INIT_STATE\s*=\s*(.*?)</script>
         let re = Regex::new(r"window\.INIT_STATE\s*=\s*(.*?)</script>")?;
-        let cap = re.captures(html).ok_or_else(|| anyhow!("Could not find window.INIT_STATE in Kuaishou page"))?;
+        let cap = re.captures(html).ok_or_else(|| ParseError::LayoutChanged("Could not find window.INIT_STATE in Kuaishou page".to_string()))?;
         
         let json_str = cap.get(1).unwrap().as_str().trim();
         let data: Value = serde_json::from_str(json_str)?;
@@ -113,25 +119,25 @@ impl Kuaishou {
              if let Some(v) = data.get("visionVideoDetail") {
                  video_data = v;
              } else {
-                 return Err(anyhow!("Could not find video info in Kuaishou JSON"));
+                 return Err(ParseError::LayoutChanged("Could not find video info in Kuaishou JSON".to_string()));
              }
         }
         
-        let photo = video_data.get("photo").ok_or_else(|| anyhow!("No photo field in Kuaishou data"))?;
+        let photo = video_data.get("photo").ok_or_else(|| ParseError::NotFound("No photo field in Kuaishou data".to_string()))?;
         
         let title = photo.get("caption").and_then(|v| v.as_str()).unwrap_or("").to_string();
         let author_name = photo.get("userName").and_then(|v| v.as_str()).unwrap_or("").to_string();
         let author_avatar = photo.get("headUrl").and_then(|v| v.as_str()).unwrap_or("").to_string();
         
         let video_url = photo.get("mainMvUrls").and_then(|v| v.as_array())
-            .and_then(|arr| arr.get(0))
+            .and_then(|arr| arr.first())
             .and_then(|v| v.get("url"))
             .and_then(|v| v.as_str())
             .unwrap_or("")
             .to_string();
             
         let cover_url = photo.get("coverUrls").and_then(|v| v.as_array())
-             .and_then(|arr| arr.get(0))
+             .and_then(|arr| arr.first())
              .and_then(|v| v.get("url"))
              .and_then(|v| v.as_str())
              .unwrap_or("")
@@ -140,13 +146,13 @@ impl Kuaishou {
         // Images (Atlas)
         let mut images = Vec::new();
         let image_cdn = photo.get("ext_params").and_then(|v| v.get("atlas")).and_then(|v| v.get("cdn")).and_then(|v| v.as_array())
-            .and_then(|arr| arr.get(0)).and_then(|v| v.as_str()).unwrap_or("");
+            .and_then(|arr| arr.first()).and_then(|v| v.as_str()).unwrap_or("");
             
         if let Some(list) = photo.get("ext_params").and_then(|v| v.get("atlas")).and_then(|v| v.get("list")).and_then(|v| v.as_array()) {
             if !image_cdn.is_empty() {
                 for item in list {
                     if let Some(path) = item.as_str() {
-                        let full_url = format!("https://{}/{}", image_cdn, path);
+                        let full_url = format!("https://{}/{}", image_cdn, path.trim_start_matches('/'));
                         images.push(ImgInfo {
                             url: full_url,
                             live_photo_url: None
@@ -173,6 +179,27 @@ impl Kuaishou {
             tags: None,
             music_info: None,
             create_time: None,
+            duration: None,
+            description: None,
+            parts: vec![],
+            subtitles: vec![],
+            danmaku_url: None,
+            page_url: None,
         })
     }
 }
+
+#[async_trait]
+impl Parser for Kuaishou {
+    fn platform(&self) -> &'static str {
+        "kuaishou"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["kuaishou.com", "chenzhongtech.com"]
+    }
+
+    async fn parse(&self, ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo> {
+        Self::parse_share_url(ctx, share_text).await
+    }
+}
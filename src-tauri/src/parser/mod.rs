@@ -1,5 +1,9 @@
+pub mod batch;
 pub mod bilibili;
+pub mod context;
+pub mod cookies;
 pub mod douyin;
+pub mod error;
 pub mod kuaishou;
 pub mod pipixia;
 pub mod utils;
@@ -7,3 +11,117 @@ pub mod weibo;
 pub mod xb;
 pub mod xhs;
 pub mod xigua;
+
+use crate::models::VideoParseInfo;
+use async_trait::async_trait;
+
+pub use context::ParseContext;
+pub use error::{ParseError, Result};
+
+/// Common interface implemented by every platform parser.
+#[async_trait]
+pub trait Parser: Send + Sync {
+    /// Platform id reported in `VideoParseInfo::platform`, e.g. `"douyin"`.
+    fn platform(&self) -> &'static str;
+
+    /// Host names (and their subdomains) this parser accepts.
+    fn hosts(&self) -> &'static [&'static str];
+
+    /// Returns true if the first URL found in `share_text` belongs to this platform.
+    fn matches(&self, share_text: &str) -> bool {
+        let url_str = utils::regexp_match_url_from_string(share_text)
+            .unwrap_or_else(|| share_text.to_string());
+        let host = match url::Url::parse(&url_str) {
+            Ok(u) => u.host_str().unwrap_or("").to_lowercase(),
+            Err(_) => return false,
+        };
+        self.owns_host(&host)
+    }
+
+    /// Returns true if `host` is one of [`Parser::hosts`] or a subdomain of one.
+    fn owns_host(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.hosts()
+            .iter()
+            .any(|h| host == *h || host.ends_with(&format!(".{}", h)))
+    }
+
+    /// Parses a share link (or a block of share text containing one).
+    async fn parse(&self, ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo>;
+}
+
+/// Ordered set of parsers consulted by `parse_video`.
+pub struct ParserRegistry {
+    parsers: Vec<Box<dyn Parser>>,
+}
+
+impl ParserRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self { parsers: Vec::new() }
+    }
+
+    /// Creates a registry with every built-in platform enabled.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(douyin::DouYin);
+        registry.register(xhs::Xiaohongshu);
+        registry.register(pipixia::PiPiXia);
+        registry.register(weibo::Weibo);
+        registry.register(kuaishou::Kuaishou);
+        registry.register(bilibili::Bilibili);
+        registry.register(xigua::XiGua);
+        registry
+    }
+
+    /// Adds a parser. A parser registered later replaces one with the same platform id.
+    pub fn register<P: Parser + 'static>(&mut self, parser: P) {
+        self.unregister(parser.platform());
+        self.parsers.push(Box::new(parser));
+    }
+
+    /// Removes the parser for `platform`, returning true if one was registered.
+    pub fn unregister(&mut self, platform: &str) -> bool {
+        let before = self.parsers.len();
+        self.parsers.retain(|p| p.platform() != platform);
+        self.parsers.len() != before
+    }
+
+    /// Platform ids of all registered parsers, in lookup order.
+    pub fn platforms(&self) -> Vec<&'static str> {
+        self.parsers.iter().map(|p| p.platform()).collect()
+    }
+
+    /// Platform id of the parser whose hosts include `host`, e.g. the owner
+    /// of a cookie domain.
+    pub fn platform_for_host(&self, host: &str) -> Option<&'static str> {
+        self.parsers.iter().find(|p| p.owns_host(host)).map(|p| p.platform())
+    }
+
+    /// Finds the parser responsible for `share_text`.
+    pub fn find(&self, share_text: &str) -> Option<&dyn Parser> {
+        self.parsers
+            .iter()
+            .find(|p| p.matches(share_text))
+            .map(|p| p.as_ref())
+    }
+
+    /// Dispatches `share_text` to the matching parser. A result without a
+    /// `page_url` gets the link found in `share_text`.
+    pub async fn parse(&self, ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo> {
+        let parser = self
+            .find(share_text)
+            .ok_or_else(|| ParseError::UnsupportedUrl(share_text.trim().to_string()))?;
+        let mut info = parser.parse(ctx, share_text).await?;
+        if info.page_url.is_none() {
+            info.page_url = utils::regexp_match_url_from_string(share_text);
+        }
+        Ok(info)
+    }
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
@@ -1,14 +1,14 @@
 use crate::models::{Author, ImgInfo, VideoParseInfo};
-use crate::parser::utils;
-use anyhow::{anyhow, Result};
+use crate::parser::{utils, ParseContext, Parser};
+use crate::parser::error::{check_status, ParseError, Result};
+use async_trait::async_trait;
 use reqwest::header::USER_AGENT;
-use reqwest::Client;
 use serde_json::Value;
 
 pub struct PiPiXia;
 
 impl PiPiXia {
-    pub async fn parse_share_url(share_url: &str) -> Result<VideoParseInfo> {
+    pub async fn parse_share_url(ctx: &ParseContext, share_url: &str) -> Result<VideoParseInfo> {
         // Extract URL from share text if needed
         let url_str = if let Some(u) = utils::regexp_match_url_from_string(share_url) {
             u
@@ -17,20 +17,18 @@ impl PiPiXia {
         };
 
         // Follow redirect to get video ID
-        let client = Client::builder()
-            .redirect(reqwest::redirect::Policy::none())
-            .build()?;
-
-        let res = client
+        let res = ctx
+            .no_redirect_client()
             .get(&url_str)
             .header(USER_AGENT, utils::DEFAULT_USER_AGENT)
+            .headers(ctx.headers("pipixia"))
             .send()
             .await?;
 
         let location = res
             .headers()
             .get("location")
-            .ok_or_else(|| anyhow!("No location header found"))?
+            .ok_or_else(|| ParseError::NotFound("No location header found".to_string()))?
             .to_str()?;
 
         // Parse video ID from path: /ppx/item/{videoId} or /item/{videoId}
@@ -45,36 +43,45 @@ impl PiPiXia {
         println!("[Pipixia] Path: {}", path);
 
         if video_id.is_empty() {
-            return Err(anyhow!("Failed to parse video ID from URL"));
+            return Err(ParseError::UnsupportedUrl("Failed to parse video ID from URL".to_string()));
         }
 
         println!("[Pipixia] Video ID: {}", video_id);
-        Self::parse_video_id(&video_id).await
+        let mut info = Self::parse_video_id(ctx, &video_id).await?;
+        info.page_url = Some(location.to_string());
+        Ok(info)
     }
 
-    async fn parse_video_id(video_id: &str) -> Result<VideoParseInfo> {
-        let api_url = format!(
+    async fn parse_video_id(ctx: &ParseContext, video_id: &str) -> Result<VideoParseInfo> {
+        let api_url = ctx.url(&format!(
             "https://api.pipix.com/bds/cell/cell_comment/?offset=0&cell_type=1&api_version=1&cell_id={}&ac=wifi&channel=huawei_1319_64&aid=1319&app_name=super",
             video_id
-        );
+        ));
 
         println!("[Pipixia] API URL: {}", api_url);
 
-        let client = Client::new();
-        let res = client
+        let res = ctx
+            .client()
             .get(&api_url)
             .header(USER_AGENT, utils::DEFAULT_USER_AGENT)
+            .headers(ctx.headers("pipixia"))
             .send()
             .await?;
+        let res = check_status(res, "Pipixia API")?;
 
         let json: Value = res.json().await?;
 
         println!("[Pipixia] API Response: {}", serde_json::to_string_pretty(&json).unwrap_or_default());
 
+        Self::parse_api_response(&json)
+    }
+
+    /// Extracts item info from a `bds/cell/cell_comment` API response.
+    pub fn parse_api_response(json: &Value) -> Result<VideoParseInfo> {
         // data.cell_comments.0.comment_info.item
         let data = json
             .pointer("/data/cell_comments/0/comment_info/item")
-            .ok_or_else(|| anyhow!("Failed to get item data from API response"))?;
+            .ok_or_else(|| ParseError::NotFound("Failed to get item data from API response".to_string()))?;
 
         let author_id = data
             .pointer("/author/id")
@@ -170,6 +177,27 @@ impl PiPiXia {
             tags: None,
             music_info: None,
             create_time: None,
+            duration: None,
+            description: None,
+            parts: vec![],
+            subtitles: vec![],
+            danmaku_url: None,
+            page_url: None,
         })
     }
 }
+
+#[async_trait]
+impl Parser for PiPiXia {
+    fn platform(&self) -> &'static str {
+        "pipixia"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["pipix.com"]
+    }
+
+    async fn parse(&self, ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo> {
+        Self::parse_share_url(ctx, share_text).await
+    }
+}
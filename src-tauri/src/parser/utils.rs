@@ -24,7 +24,24 @@ pub fn generate_fixed_length_numeric_id(length: usize) -> String {
     format!("{:0width$}", random_num, width = length)
 }
 
+// `:` keeps an explicit port (`http://127.0.0.1:8080/...`) as part of the
+// link; without it the match stopped at the host and dropped port and path.
+const URL_PATTERN: &str = r"http[s]?://[a-zA-Z0-9\.\-_/\?=&%:]+";
+
 pub fn regexp_match_url_from_string(share_msg: &str) -> Option<String> {
-    let re = regex::Regex::new(r"http[s]?://[a-zA-Z0-9\.\-_/\?=&%]+").unwrap();
+    let re = regex::Regex::new(URL_PATTERN).unwrap();
     re.find(share_msg).map(|m| m.as_str().to_string())
 }
+
+/// Every URL in `text`, in order of first appearance, without duplicates.
+pub fn regexp_match_urls_from_string(text: &str) -> Vec<String> {
+    let re = regex::Regex::new(URL_PATTERN).unwrap();
+    let mut urls: Vec<String> = Vec::new();
+    for m in re.find_iter(text) {
+        let url = m.as_str().trim_end_matches('.').to_string();
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
@@ -1,16 +1,16 @@
 use crate::models::{Author, ImgInfo, VideoParseInfo};
-use crate::parser::utils;
-use anyhow::{anyhow, Result};
+use crate::parser::{utils, ParseContext, Parser};
+use crate::parser::error::{check_status, ParseError, Result};
+use async_trait::async_trait;
 use regex::Regex;
-use reqwest::header::{CONTENT_TYPE, COOKIE, REFERER, USER_AGENT};
-use reqwest::Client;
+use reqwest::header::{CONTENT_TYPE, REFERER, USER_AGENT};
 use serde_json::Value;
 use url::Url;
 
 pub struct Weibo;
 
 impl Weibo {
-    pub async fn parse_share_url(share_url: &str) -> Result<VideoParseInfo> {
+    pub async fn parse_share_url(ctx: &ParseContext, share_url: &str) -> Result<VideoParseInfo> {
         let url_str = if let Some(u) = utils::regexp_match_url_from_string(share_url) {
             u
         } else {
@@ -25,44 +25,62 @@ impl Weibo {
                 .query_pairs()
                 .find(|(k, _)| k == "fid")
                 .map(|(_, v)| v.to_string())
-                .ok_or_else(|| anyhow!("Cannot parse video id from share url"))?;
-            return Self::parse_video_id(&video_id).await;
+                .ok_or_else(|| ParseError::UnsupportedUrl("Cannot parse video id from share url".to_string()))?;
+            return Self::parse_video_page(ctx, &video_id).await;
         } else if url_str.contains("/tv/show/") {
             let video_id = url_info.path().replace("/tv/show/", "");
-            return Self::parse_video_id(&video_id).await;
+            return Self::parse_video_page(ctx, &video_id).await;
         } else {
             // Handle regular post URLs
             let path_parts: Vec<&str> = url_info.path().trim_matches('/').split('/').collect();
             if path_parts.len() >= 2 {
                 let post_id = path_parts.last().unwrap().to_string();
-                return Self::parse_post_url(&post_id, &url_str).await;
+                let mut info = Self::parse_post_url(ctx, &post_id, &url_str).await?;
+                info.page_url = Some(format!("https://m.weibo.cn/status/{}", post_id));
+                return Ok(info);
             }
         }
 
-        Err(anyhow!("Unsupported weibo url format"))
+        Err(ParseError::UnsupportedUrl("Unsupported weibo url format".to_string()))
     }
 
-    async fn parse_video_id(video_id: &str) -> Result<VideoParseInfo> {
-        let req_url = format!(
+    async fn parse_video_page(ctx: &ParseContext, video_id: &str) -> Result<VideoParseInfo> {
+        let mut info = Self::parse_video_id(ctx, video_id).await?;
+        info.page_url = Some(format!("https://h5.video.weibo.com/show/{}", video_id));
+        Ok(info)
+    }
+
+    async fn parse_video_id(ctx: &ParseContext, video_id: &str) -> Result<VideoParseInfo> {
+        let req_url = ctx.url(&format!(
             "https://h5.video.weibo.com/api/component?page=/show/{}",
             video_id
-        );
+        ));
 
-        let client = Client::new();
-        let res = client
+        let res = ctx
+            .client()
             .post(&req_url)
-            .header(COOKIE, "login_sid_t=6b652c77c1a4bc50cb9d06b24923210d; cross_origin_proto=SSL; WBStorage=2ceabba76d81138d|undefined; _s_tentry=passport.weibo.com; Apache=7330066378690.048.1625663522444; SINAGLOBAL=7330066378690.048.1625663522444; ULV=1625663522450:1:1:1:7330066378690.048.1625663522444:; TC-V-WEIBO-G0=35846f552801987f8c1e8f7cec0e2230; SUB=_2AkMXuScYf8NxqwJRmf8RzmnhaoxwzwDEieKh5dbDJRMxHRl-yT9jqhALtRB6PDkJ9w8OaqJAbsgjdEWtIcilcZxHG7rw; SUBP=0033WrSXqPxfM72-Ws9jqgMF55529P9D9W5Qx3Mf.RCfFAKC3smW0px0; XSRF-TOKEN=JQSK02Ijtm4Fri-YIRu0-vNj")
             .header(REFERER, format!("https://h5.video.weibo.com/show/{}", video_id))
             .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
             .header(USER_AGENT, utils::DEFAULT_USER_AGENT)
+            .headers(ctx.headers("weibo"))
             .body(format!(r#"data={{"Component_Play_Playinfo":{{"oid":"{}"}}}}"#, video_id))
             .send()
             .await?;
+        let res = check_status(res, "Weibo video API")?;
 
-        let json: Value = res.json().await?;
+        // Without a session Weibo answers with its visitor-check page instead of JSON
+        let body = res.text().await?;
+        let json: Value = serde_json::from_str(&body).map_err(|_| {
+            ParseError::LoginRequired("Weibo requires a login for this video; import Weibo cookies".to_string())
+        })?;
+        Self::parse_component_data(&json)
+    }
+
+    /// Extracts video info from an `h5.video.weibo.com/api/component` response.
+    pub fn parse_component_data(json: &Value) -> Result<VideoParseInfo> {
         let data = json
             .pointer("/data/Component_Play_Playinfo")
-            .ok_or_else(|| anyhow!("Failed to parse video response"))?;
+            .ok_or_else(|| ParseError::NotFound("Failed to parse video response".to_string()))?;
 
         // Get highest quality video URL
         let mut video_url = String::new();
@@ -111,13 +129,19 @@ impl Weibo {
             tags: None,
             music_info: None,
             create_time: None,
+            duration: None,
+            description: None,
+            parts: vec![],
+            subtitles: vec![],
+            danmaku_url: None,
+            page_url: None,
         })
     }
 
-    async fn parse_post_url(post_id: &str, original_url: &str) -> Result<VideoParseInfo> {
+    async fn parse_post_url(ctx: &ParseContext, post_id: &str, original_url: &str) -> Result<VideoParseInfo> {
         // Try mobile API first
-        let req_url = format!("https://m.weibo.cn/statuses/show?id={}", post_id);
-        let client = Client::new();
+        let req_url = ctx.url(&format!("https://m.weibo.cn/statuses/show?id={}", post_id));
+        let client = ctx.client();
 
         let res = client
             .get(&req_url)
@@ -125,6 +149,7 @@ impl Weibo {
             .header(REFERER, "https://m.weibo.cn/")
             .header(CONTENT_TYPE, "application/json;charset=UTF-8")
             .header("X-Requested-With", "XMLHttpRequest")
+            .headers(ctx.headers("weibo"))
             .send()
             .await;
 
@@ -141,7 +166,7 @@ impl Weibo {
                         if let Some(video_id) = caps.get(1) {
                             println!("[Weibo] Calling video API for: {}", video_id.as_str());
                             // Call video API to get actual stream URL
-                            if let Ok(video_info) = Self::parse_video_id(video_id.as_str()).await {
+                            if let Ok(video_info) = Self::parse_video_id(ctx, video_id.as_str()).await {
                                 result.video_url = video_info.video_url;
                                 if result.cover_url.is_empty() {
                                     result.cover_url = video_info.cover_url;
@@ -159,14 +184,17 @@ impl Weibo {
         let res = client
             .get(original_url)
             .header(USER_AGENT, "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
+            .headers(ctx.headers("weibo"))
             .send()
             .await?;
 
+        let res = check_status(res, "Weibo page")?;
         let html = res.text().await?;
         Self::parse_html_page(&html)
     }
 
-    fn parse_mobile_api_data(data: &Value) -> Result<VideoParseInfo> {
+    /// Extracts post info from the `data` field of an `m.weibo.cn/statuses/show` response.
+    pub fn parse_mobile_api_data(data: &Value) -> Result<VideoParseInfo> {
         let raw_text = data.get("text")
             .and_then(|v| v.as_str())
             .unwrap_or("");
@@ -293,18 +321,27 @@ impl Weibo {
             tags: None,
             music_info: None,
             create_time: None,
+            duration: None,
+            description: None,
+            parts: vec![],
+            subtitles: vec![],
+            danmaku_url: None,
+            page_url: None,
         })
     }
 
-    fn parse_html_page(html: &str) -> Result<VideoParseInfo> {
-        // Try to extract data from $render_data script
-        let re = Regex::new(r#"\$render_data\s*=\s*(.*?)\[0\]"#)?;
+    /// Extracts post info from the `$render_data` script of a desktop page.
+    pub fn parse_html_page(html: &str) -> Result<VideoParseInfo> {
+        // The script is `var $render_data = [{...}][0] || {};`, possibly
+        // spanning lines. Only the array literal is JSON; a page whose array
+        // does not decode has changed layout rather than having no content.
+        let re = Regex::new(r#"(?s)\$render_data\s*=\s*(\[.*?\])\[0\]"#)?;
         let captures = re
             .captures(html)
-            .ok_or_else(|| anyhow!("Failed to parse weibo html page"))?;
+            .ok_or_else(|| ParseError::LayoutChanged("Failed to parse weibo html page".to_string()))?;
 
-        let json_str = format!("{}[0]", captures.get(1).unwrap().as_str());
-        let data: Value = serde_json::from_str(&json_str).unwrap_or_default();
+        let render_data: Value = serde_json::from_str(captures.get(1).unwrap().as_str())?;
+        let data = render_data.get(0).cloned().unwrap_or_default();
 
         let title = Self::clean_text(
             data.pointer("/status/text")
@@ -363,6 +400,12 @@ impl Weibo {
             tags: None,
             music_info: None,
             create_time: None,
+            duration: None,
+            description: None,
+            parts: vec![],
+            subtitles: vec![],
+            danmaku_url: None,
+            page_url: None,
         })
     }
 
@@ -399,3 +442,18 @@ impl Weibo {
             .replace("tva1.sinaimg.cn", "ww1.sinaimg.cn")
     }
 }
+
+#[async_trait]
+impl Parser for Weibo {
+    fn platform(&self) -> &'static str {
+        "weibo"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["weibo.com", "weibo.cn"]
+    }
+
+    async fn parse(&self, ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo> {
+        Self::parse_share_url(ctx, share_text).await
+    }
+}
@@ -7,12 +7,6 @@ fn md5_hex(data: &[u8]) -> String {
     format!("{:x}", hasher.finalize())
 }
 
-fn md5_bytes(data: &[u8]) -> Vec<u8> {
-    let mut hasher = Md5::new();
-    hasher.update(data);
-    hasher.finalize().to_vec()
-}
-
 // Port of M(aa)
 fn m_transform(aa: &str) -> Vec<u8> {
     let r = [
@@ -150,16 +144,12 @@ fn h_transform(params: Vec<u8>) -> Vec<u8> {
 
 // Port of C(r)
 fn c_transform(r: &[u8]) -> u8 {
-    let mut a = r[0] ^ r[1];
-    for i in 2..r.len() {
-        a = a ^ r[i];
-    }
-    a
+    r[2..].iter().fold(r[0] ^ r[1], |a, b| a ^ b)
 }
 
 // Port of f(...)
 fn f_transform(aa: u8, l1: &[u8], l2: &[u8], l3: &[u8]) -> Vec<u8> {
-    let d = 1 >> 8 & 255; // 1/256 in int is 0
+    let d = 0; // 1 >> 8 & 255, i.e. 1/256 in int
     let e = 1 & 255;
     
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
@@ -198,13 +188,9 @@ fn f_transform(aa: u8, l1: &[u8], l2: &[u8], l3: &[u8]) -> Vec<u8> {
     let mut r_final = Vec::new();
     // Logic: [r[i] for i in range(len(r)) if i % 2 == 0] + [v] + [r[i] for i in range(len(r)) if i % 2 == 1]
     
-    for index in 0..r.len() {
-        if index % 2 == 0 { r_final.push(r[index]); }
-    }
+    r_final.extend(r.iter().step_by(2));
     r_final.push(v);
-    for index in 0..r.len() {
-        if index % 2 == 1 { r_final.push(r[index]); }
-    }
+    r_final.extend(r.iter().skip(1).step_by(2));
     
     r_final
 }
@@ -247,6 +233,5 @@ pub fn get_x_b(url: &str, ua: &str) -> String {
     // So c is a string. `u(c)` treats inputs as chars.
     // In Rust, we work with bytes. 
     
-    let d = u_transform(&c_bytes, None);
-    d
+    u_transform(&c_bytes, None)
 }
@@ -1,38 +1,46 @@
 use crate::models::{Author, ImgInfo, VideoParseInfo, VideoPreview};
+use crate::parser::error::{check_status, ParseError, Result};
+use async_trait::async_trait;
 use regex::Regex;
 use serde_json::Value;
 
-use crate::parser::utils;
+use crate::parser::{utils, ParseContext, Parser};
 
 pub struct Xiaohongshu;
 
 impl Xiaohongshu {
-    pub async fn parse_share_url(share_url: &str) -> Result<VideoParseInfo, String> {
+    pub async fn parse_share_url(ctx: &ParseContext, share_url: &str) -> Result<VideoParseInfo> {
         let url = if let Some(u) = utils::regexp_match_url_from_string(share_url) {
             u
         } else {
             share_url.to_string()
         };
 
-        let client = reqwest::Client::new();
-        let res = client.get(&url)
+        let res = ctx.client().get(&url)
             .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/121.0.0.0 Safari/537.36")
-            .header("Cookie", "abRequestId=0000; webId=0000; gibberish=0000;") // Sometimes needed
+            .headers(ctx.headers("xhs"))
             .send()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        let html = res.text().await.map_err(|e| e.to_string())?;
+            .await?;
+        let res = check_status(res, "Xiaohongshu page")?;
+        let final_url = res.url().to_string();
+
+        let html = res.text().await?;
+        let mut info = Self::parse_html(&html)?;
+        info.page_url = Some(final_url);
+        Ok(info)
+    }
 
+    /// Extracts note info from the `window.__INITIAL_STATE__` script of a note page.
+    pub fn parse_html(html: &str) -> Result<VideoParseInfo> {
         // Extract __INITIAL_STATE__
-        let re = Regex::new(r"window\.__INITIAL_STATE__\s*=\s*(\{.*?\})\s*</script>").map_err(|e| e.to_string())?;
-        let captures = re.captures(&html).ok_or("Could not find __INITIAL_STATE__ in page")?;
-        let json_str = captures.get(1).map(|m| m.as_str()).ok_or("Could not find JSON content")?;
+        let re = Regex::new(r"window\.__INITIAL_STATE__\s*=\s*(\{.*?\})\s*</script>")?;
+        let captures = re.captures(html).ok_or_else(|| ParseError::LayoutChanged("Could not find __INITIAL_STATE__ in page".to_string()))?;
+        let json_str = captures.get(1).map(|m| m.as_str()).ok_or_else(|| ParseError::LayoutChanged("Could not find JSON content".to_string()))?;
         
         // Replace "undefined" which is invalid JSON but often in JS objects
         let clean_json = json_str.replace("undefined", "null");
 
-        let data: Value = serde_json::from_str(&clean_json).map_err(|e| format!("JSON decode error: {}, content snippet: {}", e, &clean_json.chars().take(100).collect::<String>()))?;
+        let data: Value = serde_json::from_str(&clean_json).map_err(|e| ParseError::LayoutChanged(format!("JSON decode error: {}, content snippet: {}", e, &clean_json.chars().take(100).collect::<String>())))?;
 
         // Navigate JSON path directly to note data
         // Typically: note.noteDetailMap[noteId].note
@@ -45,12 +53,12 @@ impl Xiaohongshu {
         } else if let Some(note_detail_map) = data.get("note").and_then(|n| n.get("noteDetailMap")) {
             // Map structure, need first key
             if let Some(first_key) = note_detail_map.as_object().and_then(|m| m.keys().next()) {
-                note_detail_map.get(first_key).and_then(|v| v.get("note")).ok_or("Empty note detail")?
+                note_detail_map.get(first_key).and_then(|v| v.get("note")).ok_or_else(|| ParseError::NotFound("Empty note detail".to_string()))?
             } else {
-                return Err("Note detail map is empty".to_string());
+                return Err(ParseError::NotFound("Note detail map is empty".to_string()));
             }
         } else {
-             return Err("Could not find note data in JSON".to_string());
+             return Err(ParseError::LayoutChanged("Could not find note data in JSON".to_string()));
         };
 
         // Extract Title
@@ -59,7 +67,7 @@ impl Xiaohongshu {
         let full_title = if title.is_empty() { desc.chars().take(30).collect() } else { title };
 
         // Extract Author
-        let user = note_data.get("user").ok_or("No user info")?;
+        let user = note_data.get("user").ok_or_else(|| ParseError::LayoutChanged("No user info".to_string()))?;
         let author = Author {
             uid: user.get("userId").and_then(|v| v.as_str()).unwrap_or("").to_string(),
             name: user.get("nickname").and_then(|v| v.as_str()).unwrap_or("Unknown").to_string(),
@@ -93,7 +101,7 @@ impl Xiaohongshu {
                  
                  // Strategy 1: consumer generic
                  if let Some(consumer) = video.get("consumer") {
-                     if let Some(_) = consumer.get("originVideoKey").and_then(|v| v.as_str()) {
+                     if consumer.get("originVideoKey").and_then(|v| v.as_str()).is_some() {
                          // This is often just a key, requires host CDN construction? 
                          // Actually consumer often has `http...` url in recent versions?
                          // Let's look for `media` field for direct URLs.
@@ -141,28 +149,33 @@ impl Xiaohongshu {
             tags: None,
             music_info: None,
             create_time: None,
+            duration: None,
+            description: None,
+            parts: vec![],
+            subtitles: vec![],
+            danmaku_url: None,
+            page_url: None,
         })
     }
 
-    pub async fn fetch_posts(user_id: &str) -> Result<Vec<VideoPreview>, String> {
-        let url = format!("https://www.xiaohongshu.com/user/profile/{}", user_id);
+    pub async fn fetch_posts(ctx: &ParseContext, user_id: &str) -> Result<Vec<VideoPreview>> {
+        let url = ctx.url(&format!("https://www.xiaohongshu.com/user/profile/{}", user_id));
         
-        let client = reqwest::Client::new();
-        let res = client.get(&url)
+        let res = ctx.client().get(&url)
             .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/121.0.0.0 Safari/537.36")
-            .header("Cookie", "abRequestId=0000; webId=0000; gibberish=0000;") 
+            .headers(ctx.headers("xhs"))
             .send()
-            .await
-            .map_err(|e| e.to_string())?;
+            .await?;
+        let res = check_status(res, "Xiaohongshu profile page")?;
 
-        let html = res.text().await.map_err(|e| e.to_string())?;
+        let html = res.text().await?;
 
-        let re = Regex::new(r"window\.__INITIAL_STATE__\s*=\s*(\{.*?\})\s*</script>").map_err(|e| e.to_string())?;
-        let captures = re.captures(&html).ok_or("Could not find __INITIAL_STATE__ in page")?;
-        let json_str = captures.get(1).map(|m| m.as_str()).ok_or("Could not find JSON content")?;
+        let re = Regex::new(r"window\.__INITIAL_STATE__\s*=\s*(\{.*?\})\s*</script>")?;
+        let captures = re.captures(&html).ok_or_else(|| ParseError::LayoutChanged("Could not find __INITIAL_STATE__ in page".to_string()))?;
+        let json_str = captures.get(1).map(|m| m.as_str()).ok_or_else(|| ParseError::LayoutChanged("Could not find JSON content".to_string()))?;
         
         let clean_json = json_str.replace("undefined", "null");
-        let data: Value = serde_json::from_str(&clean_json).map_err(|e| format!("JSON decode error: {}, content snippet: {}", e, &clean_json.chars().take(100).collect::<String>()))?;
+        let data: Value = serde_json::from_str(&clean_json).map_err(|e| ParseError::LayoutChanged(format!("JSON decode error: {}, content snippet: {}", e, &clean_json.chars().take(100).collect::<String>())))?;
 
         let mut previews = Vec::new();
         
@@ -195,3 +208,18 @@ impl Xiaohongshu {
         Ok(previews)
     }
 }
+
+#[async_trait]
+impl Parser for Xiaohongshu {
+    fn platform(&self) -> &'static str {
+        "xhs"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["xhslink.com", "xiaohongshu.com"]
+    }
+
+    async fn parse(&self, ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo> {
+        Self::parse_share_url(ctx, share_text).await
+    }
+}
@@ -1,16 +1,15 @@
 use crate::models::{Author, VideoParseInfo, VideoQuality};
-use crate::parser::utils;
-use anyhow::{anyhow, Result};
+use crate::parser::{utils, ParseContext, Parser};
+use crate::parser::error::{check_status, ParseError, Result};
+use async_trait::async_trait;
 use base64::Engine;
 use reqwest::header::USER_AGENT;
-use reqwest::redirect::Policy;
-use reqwest::Client;
 use serde_json::Value;
 
 pub struct XiGua;
 
 impl XiGua {
-    pub async fn parse_share_url(share_url: &str) -> Result<VideoParseInfo> {
+    pub async fn parse_share_url(ctx: &ParseContext, share_url: &str) -> Result<VideoParseInfo> {
         let url_str = if let Some(u) = utils::regexp_match_url_from_string(share_url) {
             u
         } else {
@@ -18,13 +17,11 @@ impl XiGua {
         };
 
         // Step 1: Follow redirect to get the numeric item_id
-        let client = Client::builder()
-            .redirect(Policy::none())
-            .build()?;
-
-        let res = client
+        let res = ctx
+            .no_redirect_client()
             .get(&url_str)
             .header(USER_AGENT, utils::DEFAULT_USER_AGENT)
+            .headers(ctx.headers("xigua"))
             .send()
             .await?;
 
@@ -35,7 +32,7 @@ impl XiGua {
                 let path = path.trim_end_matches('/');
                 path.rsplit('/').next().unwrap_or("").to_string()
             } else {
-                return Err(anyhow!("No redirect location found"));
+                return Err(ParseError::NotFound("No redirect location found".to_string()));
             }
         } else {
             let path = url_str.split('?').next().unwrap_or(&url_str);
@@ -44,88 +41,104 @@ impl XiGua {
         };
 
         if item_id.is_empty() {
-            return Err(anyhow!("Could not parse video ID from URL"));
+            return Err(ParseError::UnsupportedUrl("Could not parse video ID from URL".to_string()));
         }
 
-        Self::parse_video_id(&item_id).await
+        let mut info = Self::parse_video_id(ctx, &item_id).await?;
+        info.page_url = Some(format!("https://www.ixigua.com/{}", item_id));
+        Ok(info)
     }
 
-    async fn parse_video_id(item_id: &str) -> Result<VideoParseInfo> {
-        let client = Client::new();
+    async fn parse_video_id(ctx: &ParseContext, item_id: &str) -> Result<VideoParseInfo> {
+        let client = ctx.client();
 
         // Step 1: Get metadata + play_auth_token from Toutiao API
-        let api_url = format!("https://m.toutiao.com/i{}/info/", item_id);
+        let api_url = ctx.url(&format!("https://m.toutiao.com/i{}/info/", item_id));
         let res = client
             .get(&api_url)
             .header(USER_AGENT, "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+            .headers(ctx.headers("xigua"))
             .send()
             .await?;
+        let res = check_status(res, "Toutiao API")?;
 
         let json: Value = res.json().await?;
-        if json.get("success").and_then(|v| v.as_bool()) != Some(true) {
-            return Err(anyhow!("Toutiao API returned error"));
+
+        // Step 2: Use play_auth_token_v2 to call Bytedance VOD API
+        let mut vod_json = None;
+        if let Some(get_play_info_token) = json
+            .pointer("/data/play_auth_token_v2")
+            .and_then(|v| v.as_str())
+            .and_then(Self::decode_play_info_token)
+        {
+            let vod_url = ctx.url(&format!("https://vod.bytedanceapi.com/?{}", get_play_info_token));
+            if let Ok(vod_res) = client
+                .get(&vod_url)
+                .header(USER_AGENT, "Mozilla/5.0")
+                .headers(ctx.headers("xigua"))
+                .send()
+                .await
+            {
+                vod_json = vod_res.json::<Value>().await.ok();
+            }
+        }
+
+        Self::parse_api_responses(&json, vod_json.as_ref())
+    }
+
+    // play_auth_token_v2 is base64 JSON carrying the signed VOD query string
+    fn decode_play_info_token(play_auth_token: &str) -> Option<String> {
+        let decoded = base64::engine::general_purpose::STANDARD.decode(play_auth_token).ok()?;
+        let token_str = String::from_utf8(decoded).ok()?;
+        let token_json = serde_json::from_str::<Value>(&token_str).ok()?;
+        token_json.get("GetPlayInfoToken").and_then(|v| v.as_str()).map(|s| s.to_string())
+    }
+
+    /// Builds the parse result from the Toutiao `info` response and the optional VOD `GetPlayInfo` response.
+    pub fn parse_api_responses(info_json: &Value, vod_json: Option<&Value>) -> Result<VideoParseInfo> {
+        if info_json.get("success").and_then(|v| v.as_bool()) != Some(true) {
+            return Err(ParseError::NotFound("Toutiao API returned error".to_string()));
         }
 
-        let data = json.get("data").ok_or_else(|| anyhow!("No data in response"))?;
+        let data = info_json.get("data").ok_or_else(|| ParseError::LayoutChanged("No data in response".to_string()))?;
         let title = data.get("title").and_then(|v| v.as_str()).unwrap_or("").to_string();
         let cover_url = data.get("poster_url").and_then(|v| v.as_str()).unwrap_or("").to_string();
         let source = data.get("detail_source").and_then(|v| v.as_str()).unwrap_or("").to_string();
 
-        // Step 2: Use play_auth_token_v2 to call Bytedance VOD API
         let mut video_url = String::new();
         let mut video_qualities = Vec::new();
 
-        if let Some(play_auth_token) = data.get("play_auth_token_v2").and_then(|v| v.as_str()) {
-            if let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(play_auth_token) {
-                if let Ok(token_str) = String::from_utf8(decoded) {
-                    if let Ok(token_json) = serde_json::from_str::<Value>(&token_str) {
-                        if let Some(get_play_info_token) = token_json.get("GetPlayInfoToken").and_then(|v| v.as_str()) {
-                            let vod_url = format!("https://vod.bytedanceapi.com/?{}", get_play_info_token);
-                            if let Ok(vod_res) = client
-                                .get(&vod_url)
-                                .header(USER_AGENT, "Mozilla/5.0")
-                                .send()
-                                .await
-                            {
-                                if let Ok(vod_json) = vod_res.json::<Value>().await {
-                                    if let Some(play_info_list) = vod_json.get("Result")
-                                        .and_then(|v| v.get("Data"))
-                                        .and_then(|v| v.get("PlayInfoList"))
-                                        .and_then(|v| v.as_array())
-                                    {
-                                        // Collect all qualities
-                                        for info in play_info_list {
-                                            let definition = info.get("Definition").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                            let main_play_url = info.get("MainPlayUrl").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                            
-                                            if !main_play_url.is_empty() {
-                                                video_qualities.push(VideoQuality {
-                                                    quality: definition,
-                                                    video_url: main_play_url,
-                                                    size: None,
-                                                });
-                                            }
-                                        }
-                                        
-                                        // Set the best quality as default video_url
-                                        // Sort by height (resolution) descending
-                                        let mut sorted_infos: Vec<&Value> = play_info_list.iter().collect();
-                                        sorted_infos.sort_by(|a, b| {
-                                            let h_b = b.get("Height").and_then(|v| v.as_i64()).unwrap_or(0);
-                                            let h_a = a.get("Height").and_then(|v| v.as_i64()).unwrap_or(0);
-                                            h_b.cmp(&h_a)
-                                        });
-                                        
-                                        if let Some(best) = sorted_infos.first() {
-                                            video_url = best.get("MainPlayUrl").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+        if let Some(play_info_list) = vod_json
+            .and_then(|v| v.pointer("/Result/Data/PlayInfoList"))
+            .and_then(|v| v.as_array())
+        {
+            // Collect all qualities
+            for info in play_info_list {
+                let definition = info.get("Definition").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                let main_play_url = info.get("MainPlayUrl").and_then(|v| v.as_str()).unwrap_or("").to_string();
+
+                if !main_play_url.is_empty() {
+                    video_qualities.push(VideoQuality {
+                        quality: definition,
+                        video_url: main_play_url,
+                        size: None,
+                        ..Default::default()
+                    });
                 }
             }
+
+            // Set the best quality as default video_url
+            // Sort by height (resolution) descending
+            let mut sorted_infos: Vec<&Value> = play_info_list.iter().collect();
+            sorted_infos.sort_by(|a, b| {
+                let h_b = b.get("Height").and_then(|v| v.as_i64()).unwrap_or(0);
+                let h_a = a.get("Height").and_then(|v| v.as_i64()).unwrap_or(0);
+                h_b.cmp(&h_a)
+            });
+
+            if let Some(best) = sorted_infos.first() {
+                video_url = best.get("MainPlayUrl").and_then(|v| v.as_str()).unwrap_or("").to_string();
+            }
         }
 
         // Get author info from media_user
@@ -156,6 +169,27 @@ impl XiGua {
             tags: None,
             music_info: None,
             create_time: None,
+            duration: None,
+            description: None,
+            parts: vec![],
+            subtitles: vec![],
+            danmaku_url: None,
+            page_url: None,
         })
     }
 }
+
+#[async_trait]
+impl Parser for XiGua {
+    fn platform(&self) -> &'static str {
+        "xigua"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["ixigua.com"]
+    }
+
+    async fn parse(&self, ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo> {
+        Self::parse_share_url(ctx, share_text).await
+    }
+}
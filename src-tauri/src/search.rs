@@ -0,0 +1,323 @@
+// Search over a user's favorites, downloads and cached parse results, and
+// turning what a user types into a search box into SQL. Text columns are
+// indexed with FTS5's trigram tokenizer, which matches any part of a word
+// and so works for Chinese titles that have no spaces, but only for terms of
+// three characters or more; shorter terms fall back to LIKE.
+#[cfg(feature = "gui")]
+use crate::auth;
+#[cfg(feature = "gui")]
+use crate::db::DbPool;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use tauri::State;
+
+/// A search box query split into whitespace-separated terms, all of which
+/// must match.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TextQuery {
+    /// FTS5 `MATCH` expression for the terms long enough for the index
+    pub fts: Option<String>,
+    /// Terms too short for the index, as `LIKE` patterns escaped with `\`
+    pub like: Vec<String>,
+}
+
+// The trigram tokenizer cannot match anything shorter
+const MIN_INDEXED_CHARS: usize = 3;
+
+impl TextQuery {
+    pub fn parse(text: &str) -> Self {
+        let mut phrases = Vec::new();
+        let mut like = Vec::new();
+        for term in text.split_whitespace() {
+            if term.chars().count() >= MIN_INDEXED_CHARS {
+                // A quoted phrase keeps FTS5 from reading the term as query syntax
+                phrases.push(format!("\"{}\"", term.replace('"', "\"\"")));
+            } else {
+                let escaped = term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+                like.push(format!("%{}%", escaped));
+            }
+        }
+        Self {
+            fts: (!phrases.is_empty()).then(|| phrases.join(" AND ")),
+            like,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fts.is_none() && self.like.is_empty()
+    }
+
+    /// SQL requiring every short term to appear in one of `columns`, with a
+    /// `?` placeholder per column and term; bind [`TextQuery::like_params`].
+    pub fn like_sql(&self, columns: &[&str]) -> String {
+        let any_column = format!(
+            "({})",
+            columns
+                .iter()
+                .map(|c| format!("{} LIKE ? ESCAPE '\\'", c))
+                .collect::<Vec<_>>()
+                .join(" OR ")
+        );
+        vec![any_column; self.like.len()].join(" AND ")
+    }
+
+    /// Parameters for [`TextQuery::like_sql`] over `columns` columns.
+    pub fn like_params(&self, columns: usize) -> Vec<String> {
+        self.like
+            .iter()
+            .flat_map(|pattern| std::iter::repeat_n(pattern.clone(), columns))
+            .collect()
+    }
+}
+
+pub const DEFAULT_SEARCH_PAGE_SIZE: i64 = 20;
+const MAX_SEARCH_PAGE_SIZE: i64 = 100;
+
+/// What a search result is.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchKind {
+    Favorite,
+    Download,
+    /// A cached parse result from the user's history
+    Parsed,
+}
+
+/// Narrows a search; every field is optional.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SearchFilters {
+    /// Kinds to search, all when empty
+    pub kinds: Vec<SearchKind>,
+    pub platform: Option<String>,
+    /// First day to include, `YYYY-MM-DD`
+    pub since: Option<String>,
+    /// Last day to include, `YYYY-MM-DD`
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchHit {
+    pub kind: SearchKind,
+    /// Id of the favorite, download or parsed item
+    pub id: i64,
+    pub title: String,
+    pub url: String,
+    pub platform: String,
+    pub author_name: String,
+    pub cover_url: String,
+    pub parsed_item_id: Option<i64>,
+    pub created_at: String,
+    /// BM25 relevance among hits of the same kind, lower is better; 0 when
+    /// only short terms were searched
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    /// Hits across all pages
+    pub total: i64,
+}
+
+// Searches favorites, downloads and cached parse results at once. Without a
+// query every item matching the filters is listed, newest first.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn search_library(
+    state: State<'_, DbPool>,
+    token: String,
+    query: Option<String>,
+    filters: Option<SearchFilters>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<SearchPage, String> {
+    state
+        .run(move |conn| {
+            let user_id = auth::session_user(conn, &token)?.id;
+            find_in_library(
+                conn,
+                user_id,
+                query.as_deref().unwrap_or(""),
+                &filters.unwrap_or_default(),
+                limit.unwrap_or(DEFAULT_SEARCH_PAGE_SIZE),
+                offset.unwrap_or(0),
+            )
+        })
+        .await
+}
+
+// Where one kind of result comes from; `index` is its FTS5 table
+struct Source {
+    kind: SearchKind,
+    columns: &'static str,
+    from: &'static str,
+    index: &'static str,
+    owner: &'static str,
+    platform: &'static str,
+    date: &'static str,
+}
+
+const SOURCES: [Source; 3] = [
+    Source {
+        kind: SearchKind::Favorite,
+        columns: "'favorite' AS kind, f.id AS id, f.title, f.url, f.platform, f.author_name, f.cover_url,
+                  f.parsed_item_id, f.created_at AS created_at",
+        from: "favorites f JOIN favorites_fts ON favorites_fts.rowid = f.id",
+        index: "favorites_fts",
+        owner: "f.user_id = ?",
+        platform: "f.platform",
+        date: "f.created_at",
+    },
+    Source {
+        kind: SearchKind::Download,
+        columns: "'download' AS kind, d.id AS id, d.title, d.url, COALESCE(d.platform, ''),
+                  COALESCE(p.author_name, ''), d.cover_url, d.parsed_item_id, d.created_at AS created_at",
+        from: "downloads d JOIN downloads_fts ON downloads_fts.rowid = d.id LEFT JOIN parsed_items p ON p.id = d.parsed_item_id",
+        index: "downloads_fts",
+        owner: "d.user_id = ?",
+        platform: "d.platform",
+        date: "d.created_at",
+    },
+    Source {
+        kind: SearchKind::Parsed,
+        columns: "'parsed' AS kind, p.id AS id, p.title, p.url, p.platform, p.author_name, p.cover_url,
+                  p.id, p.parsed_at AS created_at",
+        from: "parsed_items p JOIN parsed_items_fts ON parsed_items_fts.rowid = p.id",
+        index: "parsed_items_fts",
+        owner: "p.id IN (SELECT parsed_item_id FROM parse_history WHERE user_id = ?)",
+        platform: "p.platform",
+        date: "p.parsed_at",
+    },
+];
+
+const INDEXED_COLUMNS: [&str; 5] = ["title", "author_name", "url", "tags", "description"];
+
+impl SearchFilters {
+    /// Checks the dates, the only part of the filters that can be malformed.
+    pub fn validate(&self) -> Result<(), String> {
+        parse_day(self.since.as_deref())?;
+        parse_day(self.until.as_deref())?;
+        Ok(())
+    }
+}
+
+fn parse_day(day: Option<&str>) -> Result<Option<String>, String> {
+    match day.map(str::trim).filter(|d| !d.is_empty()) {
+        Some(day) => chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d")
+            .map(|date| Some(date.to_string()))
+            .map_err(|_| format!("Invalid date {:?}, expected YYYY-MM-DD", day)),
+        None => Ok(None),
+    }
+}
+
+/// One page of the favorites, downloads and history parse results of
+/// `user_id` matching `query` in their title, author, link, tags or
+/// description. The best match of each kind comes first, then the second
+/// best of each kind, and so on.
+pub fn find_in_library(
+    conn: &Connection,
+    user_id: i64,
+    query: &str,
+    filters: &SearchFilters,
+    limit: i64,
+    offset: i64,
+) -> Result<SearchPage, String> {
+    let text = TextQuery::parse(query);
+    let platform = filters.platform.as_deref().filter(|p| !p.is_empty() && *p != "all");
+    let since = parse_day(filters.since.as_deref())?;
+    let until = parse_day(filters.until.as_deref())?;
+
+    let mut matches = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+    for source in SOURCES.iter().filter(|s| filters.kinds.is_empty() || filters.kinds.contains(&s.kind)) {
+        let mut conditions = vec![source.owner.to_string()];
+        params.push(Box::new(user_id));
+        if let Some(fts) = &text.fts {
+            conditions.push(format!("{} MATCH ?", source.index));
+            params.push(Box::new(fts.clone()));
+        }
+        if !text.like.is_empty() {
+            let columns: Vec<String> = INDEXED_COLUMNS.iter().map(|c| format!("{}.{}", source.index, c)).collect();
+            let columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+            conditions.push(text.like_sql(&columns));
+            for pattern in text.like_params(columns.len()) {
+                params.push(Box::new(pattern));
+            }
+        }
+        if let Some(platform) = platform {
+            conditions.push(format!("{} = ?", source.platform));
+            params.push(Box::new(platform.to_string()));
+        }
+        if let Some(since) = &since {
+            conditions.push(format!("date({}) >= ?", source.date));
+            params.push(Box::new(since.clone()));
+        }
+        if let Some(until) = &until {
+            conditions.push(format!("date({}) <= ?", source.date));
+            params.push(Box::new(until.clone()));
+        }
+        // bm25() only works in the query that runs the MATCH, hence a
+        // materialized step before the matches are ranked
+        let score = if text.fts.is_some() { format!("bm25({})", source.index) } else { "0.0".to_string() };
+        matches.push(format!(
+            "m{} AS MATERIALIZED (SELECT {}, {} AS score FROM {} WHERE {})",
+            matches.len(),
+            source.columns,
+            score,
+            source.from,
+            conditions.join(" AND ")
+        ));
+    }
+    if matches.is_empty() {
+        return Ok(SearchPage { hits: Vec::new(), total: 0 });
+    }
+    // BM25 depends on the statistics of its own index, so scores are only
+    // compared within a source; the sources are merged by their ranks
+    let ranked: Vec<String> = (0..matches.len())
+        .map(|i| {
+            format!("SELECT *, ROW_NUMBER() OVER (ORDER BY score, created_at DESC, id DESC) AS source_rank FROM m{}", i)
+        })
+        .collect();
+    let with = format!("WITH {}", matches.join(", "));
+    let union = ranked.join(" UNION ALL ");
+
+    let params_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let total: i64 = conn
+        .query_row(&format!("{} SELECT COUNT(*) FROM ({})", with, union), params_refs.as_slice(), |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    let limit = limit.clamp(1, MAX_SEARCH_PAGE_SIZE);
+    let offset = offset.max(0);
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} SELECT * FROM ({}) ORDER BY source_rank, created_at DESC, kind, id DESC LIMIT {} OFFSET {}",
+            with, union, limit, offset
+        ))
+        .map_err(|e| e.to_string())?;
+    let hits = stmt
+        .query_map(params_refs.as_slice(), |row| {
+            let kind = match row.get::<_, String>(0)?.as_str() {
+                "favorite" => SearchKind::Favorite,
+                "download" => SearchKind::Download,
+                _ => SearchKind::Parsed,
+            };
+            Ok(SearchHit {
+                kind,
+                id: row.get(1)?,
+                title: row.get(2)?,
+                url: row.get(3)?,
+                platform: row.get(4)?,
+                author_name: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                cover_url: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+                parsed_item_id: row.get(7)?,
+                created_at: row.get(8)?,
+                score: row.get(9)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(SearchPage { hits, total })
+}
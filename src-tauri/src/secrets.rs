@@ -0,0 +1,598 @@
+// Encrypted credentials (login cookies, tokens) in the `secrets` table.
+//
+// Values are sealed with XChaCha20-Poly1305 under a 256-bit master key that
+// never touches the database. The key is either derived from a passphrase
+// with Argon2id (salt kept in `secrets_meta`) or generated once and kept in a
+// `KeyStore`: the OS keyring on macOS and Windows when built with the
+// `keyring` feature, else a key file next to the database.
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+#[cfg(all(feature = "keyring", any(target_os = "macos", target_os = "windows")))]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "gui")]
+use tauri::{Manager, State};
+
+#[cfg(feature = "gui")]
+use crate::auth;
+#[cfg(feature = "gui")]
+use crate::cookie_store;
+#[cfg(feature = "gui")]
+use crate::db::DbPool;
+#[cfg(feature = "gui")]
+use crate::parser::ParseContext;
+
+/// Environment variable that, when set, unlocks the secrets with a passphrase
+/// instead of a stored key.
+pub const PASSPHRASE_ENV: &str = "VIDEO_PARSER_PASSPHRASE";
+
+const KEY_LEN: usize = 32;
+// Sealed into `secrets_meta` so a wrong passphrase or key is caught up front
+const CHECK_PLAINTEXT: &[u8] = b"video-parser secrets";
+
+/// Somewhere a randomly generated master key can be kept.
+pub trait KeyStore: Send + Sync {
+    /// Shown in logs, e.g. `"OS keyring"`.
+    fn name(&self) -> String;
+    /// The stored key, or `None` if none has been stored yet.
+    fn load(&self) -> Result<Option<Vec<u8>>, String>;
+    fn store(&self, key: &[u8]) -> Result<(), String>;
+}
+
+/// Keeps the key base64-encoded in a file readable only by the current user.
+pub struct FileKeyStore {
+    path: PathBuf,
+}
+
+impl FileKeyStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `secrets.key` in the database's directory.
+    pub fn next_to(db_path: &Path) -> Self {
+        Self::new(db_path.with_file_name("secrets.key"))
+    }
+}
+
+impl KeyStore for FileKeyStore {
+    fn name(&self) -> String {
+        format!("key file {}", self.path.display())
+    }
+
+    fn load(&self) -> Result<Option<Vec<u8>>, String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => BASE64.decode(text.trim()).map(Some).map_err(|e| format!("Corrupt key file: {}", e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn store(&self, key: &[u8]) -> Result<(), String> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&self.path).map_err(|e| e.to_string())?;
+        std::io::Write::write_all(&mut file, BASE64.encode(key).as_bytes()).map_err(|e| e.to_string())
+    }
+}
+
+/// Keeps the key in the platform keyring: Keychain on macOS, Credential
+/// Manager on Windows. Linux has no keyring here since the kernel one is
+/// cleared at reboot, taking the key with it.
+#[cfg(all(feature = "keyring", any(target_os = "macos", target_os = "windows")))]
+pub struct OsKeyStore {
+    entry: keyring::Entry,
+    // The entry older versions shared between all databases, read only
+    legacy: Option<keyring::Entry>,
+}
+
+#[cfg(all(feature = "keyring", any(target_os = "macos", target_os = "windows")))]
+impl OsKeyStore {
+    const SERVICE: &'static str = "video-parser";
+
+    pub fn new(service: &str, user: &str) -> Result<Self, String> {
+        Ok(Self { entry: keyring::Entry::new(service, user).map_err(|e| e.to_string())?, legacy: None })
+    }
+
+    /// The entry of the database at `db_path`, so rotating the key of one
+    /// database leaves the others alone. A key only found in the entry that
+    /// older versions shared is still loaded.
+    pub fn for_db(db_path: &Path) -> Result<Self, String> {
+        let path = db_path.canonicalize().unwrap_or_else(|_| db_path.to_path_buf());
+        let digest = format!("{:x}", Sha256::digest(path.to_string_lossy().as_bytes()));
+        let mut store = Self::new(Self::SERVICE, &format!("secrets-key-{}", &digest[..16]))?;
+        store.legacy = Some(keyring::Entry::new(Self::SERVICE, "secrets-key").map_err(|e| e.to_string())?);
+        Ok(store)
+    }
+
+    fn read(entry: &keyring::Entry) -> Result<Option<Vec<u8>>, String> {
+        match entry.get_password() {
+            Ok(text) => BASE64.decode(text.trim()).map(Some).map_err(|e| format!("Corrupt keyring entry: {}", e)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(all(feature = "keyring", any(target_os = "macos", target_os = "windows")))]
+impl KeyStore for OsKeyStore {
+    fn name(&self) -> String {
+        "OS keyring".to_string()
+    }
+
+    fn load(&self) -> Result<Option<Vec<u8>>, String> {
+        match (Self::read(&self.entry)?, &self.legacy) {
+            (None, Some(legacy)) => Self::read(legacy),
+            (key, _) => Ok(key),
+        }
+    }
+
+    fn store(&self, key: &[u8]) -> Result<(), String> {
+        self.entry.set_password(&BASE64.encode(key)).map_err(|e| e.to_string())
+    }
+}
+
+/// Where the master key comes from.
+pub enum KeySource {
+    /// Derived from a passphrase the user types or sets in [`PASSPHRASE_ENV`].
+    Passphrase(String),
+    /// Generated on first use and kept in a [`KeyStore`].
+    Stored(Box<dyn KeyStore>),
+}
+
+impl KeySource {
+    /// The passphrase in [`PASSPHRASE_ENV`] if set, otherwise
+    /// [`KeySource::stored_for`].
+    pub fn default_for(db_path: &Path) -> Self {
+        match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => KeySource::Passphrase(passphrase),
+            _ => Self::stored_for(db_path),
+        }
+    }
+
+    /// The OS keyring entry of `db_path` when built with it and usable,
+    /// otherwise a key file next to `db_path`.
+    pub fn stored_for(db_path: &Path) -> Self {
+        #[cfg(all(feature = "keyring", any(target_os = "macos", target_os = "windows")))]
+        {
+            // Probe the keyring before relying on it
+            match OsKeyStore::for_db(db_path).and_then(|store| store.load().map(|_| store)) {
+                Ok(store) => return KeySource::Stored(Box::new(store)),
+                Err(e) => println!("[secrets] OS keyring unavailable, using a key file: {}", e),
+            }
+        }
+        KeySource::Stored(Box::new(FileKeyStore::next_to(db_path)))
+    }
+
+    fn kdf(&self) -> &'static str {
+        match self {
+            KeySource::Passphrase(_) => "argon2id",
+            KeySource::Stored(_) => "stored",
+        }
+    }
+}
+
+/// Secrets of the desktop app; `None` while locked, i.e. until the
+/// passphrase is entered when the key comes from one.
+pub struct SecretsState(pub Mutex<Option<SecretStore>>);
+
+impl SecretsState {
+    pub fn unlocked(&self) -> Result<SecretStore, String> {
+        self.0
+            .lock()
+            .map_err(|e| e.to_string())?
+            .clone()
+            .ok_or_else(|| "Secrets are locked; enter the passphrase to unlock them".to_string())
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn unlock_secrets(
+    state: State<'_, DbPool>,
+    secrets: State<'_, SecretsState>,
+    ctx: State<'_, ParseContext>,
+    passphrase: String,
+) -> Result<(), String> {
+    let ctx = ctx.inner().clone();
+    let store = state
+        .run(move |conn| {
+            let store = SecretStore::unlock(conn, &KeySource::Passphrase(passphrase))?;
+            cookie_store::load_cookies(conn, &store, &ctx)?;
+            Ok(store)
+        })
+        .await?;
+    *secrets.0.lock().map_err(|e| e.to_string())? = Some(store);
+    Ok(())
+}
+
+/// Re-encrypts the secrets under `passphrase`, or under a stored key again
+/// when `passphrase` is empty or missing.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn set_secrets_passphrase(
+    app: tauri::AppHandle,
+    state: State<'_, DbPool>,
+    token: String,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    let db_path = crate::db::db_path(&app).map_err(|e| e.to_string())?;
+    state
+        .run(move |conn| {
+            auth::session_user(conn, &token)?;
+            let source = match passphrase.filter(|p| !p.is_empty()) {
+                Some(passphrase) => KeySource::Passphrase(passphrase),
+                None => KeySource::stored_for(&db_path),
+            };
+            // Held while re-encrypting so nothing is sealed under the old key meanwhile
+            let secrets = app.state::<SecretsState>();
+            let mut guard = secrets.0.lock().map_err(|e| e.to_string())?;
+            let current = guard.as_ref().ok_or("Secrets are locked; enter the passphrase to unlock them")?;
+            *guard = Some(current.rotate_key(conn, &source)?);
+            Ok(())
+        })
+        .await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn list_credentials(
+    state: State<'_, DbPool>,
+    secrets: State<'_, SecretsState>,
+    token: String,
+) -> Result<Vec<SecretInfo>, String> {
+    let secrets = secrets.unlocked()?;
+    state
+        .run(move |conn| {
+            auth::session_user(conn, &token)?;
+            secrets.list(conn, None)
+        })
+        .await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn add_credential(
+    state: State<'_, DbPool>,
+    secrets: State<'_, SecretsState>,
+    ctx: State<'_, ParseContext>,
+    token: String,
+    platform: String,
+    name: String,
+    value: String,
+) -> Result<i64, String> {
+    let secrets = secrets.unlocked()?;
+    let ctx = ctx.inner().clone();
+    state
+        .run(move |conn| {
+            auth::session_user(conn, &token)?;
+            let id = secrets.add(conn, &platform, &name, &value)?;
+            if name == cookie_store::COOKIE_SECRET {
+                ctx.set_cookies(&platform, &value).map_err(|e| e.to_string())?;
+            }
+            Ok(id)
+        })
+        .await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn rotate_credential(
+    state: State<'_, DbPool>,
+    secrets: State<'_, SecretsState>,
+    ctx: State<'_, ParseContext>,
+    token: String,
+    platform: String,
+    name: String,
+    value: String,
+) -> Result<(), String> {
+    let secrets = secrets.unlocked()?;
+    let ctx = ctx.inner().clone();
+    state
+        .run(move |conn| {
+            auth::session_user(conn, &token)?;
+            secrets.rotate(conn, &platform, &name, &value)?;
+            if name == cookie_store::COOKIE_SECRET {
+                ctx.set_cookies(&platform, &value).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        })
+        .await
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn delete_credential(
+    state: State<'_, DbPool>,
+    secrets: State<'_, SecretsState>,
+    ctx: State<'_, ParseContext>,
+    token: String,
+    platform: String,
+    name: String,
+) -> Result<bool, String> {
+    let secrets = secrets.unlocked()?;
+    let ctx = ctx.inner().clone();
+    state
+        .run(move |conn| {
+            auth::session_user(conn, &token)?;
+            let deleted = secrets.delete(conn, &platform, &name)?;
+            if name == cookie_store::COOKIE_SECRET {
+                ctx.remove_cookies(&platform);
+            }
+            Ok(deleted)
+        })
+        .await
+}
+
+/// A credential as listed; the value stays encrypted.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SecretInfo {
+    pub id: i64,
+    /// Platform the credential is for, e.g. `"bilibili"`
+    pub platform: String,
+    /// What it is, e.g. `"cookie"`
+    pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// kdf, salt, check nonce and check value of `secrets_meta`
+type Meta = (String, Vec<u8>, Vec<u8>, Vec<u8>);
+
+/// Unlocked secrets; holds the master key in memory.
+#[derive(Clone)]
+pub struct SecretStore {
+    key: [u8; KEY_LEN],
+    cipher: XChaCha20Poly1305,
+}
+
+// Keeps the key out of logs
+impl std::fmt::Debug for SecretStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretStore").finish_non_exhaustive()
+    }
+}
+
+impl SecretStore {
+    /// Unlocks the secrets of `conn` with `source`. The first unlock of a
+    /// database sets up its key; later ones fail on a wrong passphrase or key.
+    pub fn unlock(conn: &Connection, source: &KeySource) -> Result<Self, String> {
+        let meta: Option<Meta> = conn
+            .query_row("SELECT kdf, salt, check_nonce, check_value FROM secrets_meta WHERE id = 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .optional()
+            .map_err(|e| e.to_string())?;
+
+        let Some((kdf, salt, check_nonce, check_value)) = meta else {
+            let (mut store, salt) = Self::create_key(source)?;
+            if let KeySource::Stored(key_store) = source {
+                // A key already in the store (e.g. the keyring entry older
+                // versions shared) is kept rather than overwritten
+                match key_store.load()? {
+                    Some(existing) => store = Self::from_key(existing.try_into().map_err(|_| invalid_key(key_store.as_ref()))?),
+                    None => {
+                        key_store.store(&store.key)?;
+                        println!("[secrets] Stored a new secrets key in the {}", key_store.name());
+                    }
+                }
+            }
+            store.write_meta(conn, source.kdf(), &salt)?;
+            return Ok(store);
+        };
+        if kdf != source.kdf() {
+            return Err(match kdf.as_str() {
+                "argon2id" => "Secrets are protected by a passphrase".to_string(),
+                _ => "Secrets are protected by a stored key, not a passphrase".to_string(),
+            });
+        }
+        let key = match source {
+            KeySource::Passphrase(passphrase) => derive_key(passphrase, &salt)?,
+            KeySource::Stored(key_store) => {
+                let key = key_store.load()?.ok_or_else(|| format!("No secrets key in the {}", key_store.name()))?;
+                key.try_into().map_err(|_| invalid_key(key_store.as_ref()))?
+            }
+        };
+        let store = Self::from_key(key);
+        store
+            .open(&check_nonce, &check_value, b"check")
+            .map_err(|_| "Wrong passphrase or secrets key".to_string())?;
+        Ok(store)
+    }
+
+    /// Stores `value` as the `name` credential of `platform`, replacing any
+    /// previous value. Returns its id.
+    pub fn add(&self, conn: &Connection, platform: &str, name: &str, value: &str) -> Result<i64, String> {
+        let (nonce, ciphertext) = self.seal(value.as_bytes(), &associated_data(platform, name))?;
+        conn.query_row(
+            "INSERT INTO secrets (platform, name, nonce, ciphertext) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(platform, name) DO UPDATE SET
+                 nonce = excluded.nonce, ciphertext = excluded.ciphertext, updated_at = CURRENT_TIMESTAMP
+             RETURNING id",
+            rusqlite::params![platform, name, nonce, ciphertext],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    /// Replaces the value of an existing credential, e.g. after logging in again.
+    pub fn rotate(&self, conn: &Connection, platform: &str, name: &str, value: &str) -> Result<(), String> {
+        if self.get(conn, platform, name)?.is_none() {
+            return Err(format!("No {} credential for {}", name, platform));
+        }
+        self.add(conn, platform, name, value).map(|_| ())
+    }
+
+    /// The decrypted value of a credential.
+    pub fn get(&self, conn: &Connection, platform: &str, name: &str) -> Result<Option<String>, String> {
+        let row: Option<(Vec<u8>, Vec<u8>)> = conn
+            .query_row(
+                "SELECT nonce, ciphertext FROM secrets WHERE platform = ?1 AND name = ?2",
+                rusqlite::params![platform, name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let Some((nonce, ciphertext)) = row else { return Ok(None) };
+        let plaintext = self.open(&nonce, &ciphertext, &associated_data(platform, name))?;
+        String::from_utf8(plaintext).map(Some).map_err(|e| e.to_string())
+    }
+
+    /// Every credential, optionally only those named `name`, by platform.
+    pub fn list(&self, conn: &Connection, name: Option<&str>) -> Result<Vec<SecretInfo>, String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, platform, name, created_at, updated_at FROM secrets
+                 WHERE ?1 IS NULL OR name = ?1 ORDER BY platform, name",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([name], |row| {
+                Ok(SecretInfo {
+                    id: row.get(0)?,
+                    platform: row.get(1)?,
+                    name: row.get(2)?,
+                    created_at: row.get(3)?,
+                    updated_at: row.get(4)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(|e| e.to_string())
+    }
+
+    /// Removes a credential; returns false if there was none.
+    pub fn delete(&self, conn: &Connection, platform: &str, name: &str) -> Result<bool, String> {
+        conn.execute(
+            "DELETE FROM secrets WHERE platform = ?1 AND name = ?2",
+            rusqlite::params![platform, name],
+        )
+        .map(|n| n > 0)
+        .map_err(|e| e.to_string())
+    }
+
+    /// Re-encrypts every credential under a new master key from `source`,
+    /// e.g. to set or change a passphrase. Returns the store for the new key;
+    /// `self` no longer opens anything.
+    pub fn rotate_key(&self, conn: &mut Connection, source: &KeySource) -> Result<SecretStore, String> {
+        let (next, salt) = Self::create_key(source)?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut stmt = tx
+                .prepare("SELECT id, platform, name, nonce, ciphertext FROM secrets")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Vec<u8>>(3)?,
+                        row.get::<_, Vec<u8>>(4)?,
+                    ))
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(|e| e.to_string())?;
+            for (id, platform, name, nonce, ciphertext) in rows {
+                let aad = associated_data(&platform, &name);
+                let plaintext = self.open(&nonce, &ciphertext, &aad)?;
+                let (nonce, ciphertext) = next.seal(&plaintext, &aad)?;
+                tx.execute(
+                    "UPDATE secrets SET nonce = ?1, ciphertext = ?2 WHERE id = ?3",
+                    rusqlite::params![nonce, ciphertext, id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        next.write_meta(&tx, source.kdf(), &salt)?;
+
+        // The new key is saved only once everything is re-encrypted, and the
+        // old one put back if the commit fails, so the key store always
+        // matches the database
+        if let KeySource::Stored(key_store) = source {
+            key_store.store(&next.key)?;
+        }
+        if let Err(e) = tx.commit() {
+            if let KeySource::Stored(key_store) = source {
+                let _ = key_store.store(&self.key);
+            }
+            return Err(e.to_string());
+        }
+        println!("[secrets] Rotated the secrets key");
+        Ok(next)
+    }
+
+    // A fresh key for `source` with a new salt: derived from the passphrase,
+    // or random for a key store
+    fn create_key(source: &KeySource) -> Result<(Self, Vec<u8>), String> {
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let key = match source {
+            KeySource::Passphrase(passphrase) => derive_key(passphrase, &salt)?,
+            KeySource::Stored(_) => {
+                let mut key = [0u8; KEY_LEN];
+                OsRng.fill_bytes(&mut key);
+                key
+            }
+        };
+        Ok((Self::from_key(key), salt))
+    }
+
+    fn from_key(key: [u8; KEY_LEN]) -> Self {
+        Self { key, cipher: XChaCha20Poly1305::new(Key::from_slice(&key)) }
+    }
+
+    fn write_meta(&self, conn: &Connection, kdf: &str, salt: &[u8]) -> Result<(), String> {
+        let (check_nonce, check_value) = self.seal(CHECK_PLAINTEXT, b"check")?;
+        conn.execute(
+            "INSERT OR REPLACE INTO secrets_meta (id, kdf, salt, check_nonce, check_value) VALUES (1, ?1, ?2, ?3, ?4)",
+            rusqlite::params![kdf, salt, check_nonce, check_value],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad })
+            .map_err(|_| "Encryption failed".to_string())?;
+        Ok((nonce.to_vec(), ciphertext))
+    }
+
+    fn open(&self, nonce: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+        if nonce.len() != 24 {
+            return Err("Corrupt secret".to_string());
+        }
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| "Secret cannot be decrypted with this key".to_string())
+    }
+}
+
+fn invalid_key(key_store: &dyn KeyStore) -> String {
+    format!("Invalid secrets key in the {}", key_store.name())
+}
+
+// Binds a ciphertext to its row, so values cannot be swapped between credentials
+fn associated_data(platform: &str, name: &str) -> Vec<u8> {
+    format!("{}\0{}", platform, name).into_bytes()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN], String> {
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
@@ -0,0 +1,400 @@
+// Embedded HTTP API (JSON in/out) wrapping the parse, download, downloads,
+// favorites and search commands so scripts and other machines on the LAN can
+// use them.
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::{FromRequestParts, Query, Request, State};
+use axum::http::request::Parts;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::auth::{self, constant_time_eq};
+use crate::db::{DbPool, DEFAULT_POOL_SIZE};
+use crate::download_manager::{DownloadManager, DEFAULT_MAX_CONCURRENT_DOWNLOADS};
+use crate::downloader::sanitize_file_name;
+use crate::secrets::{KeySource, SecretStore};
+use crate::parser::{ParseContext, ParseError, ParserRegistry};
+use crate::search::{self, SearchFilters, SearchKind};
+use crate::{cookie_store, downloads, favorites, parsed_items};
+
+/// Settings for [`ApiServer::start`].
+#[derive(Clone)]
+pub struct ApiConfig {
+    /// Address to listen on; use port 0 to pick a free one.
+    pub bind: SocketAddr,
+    /// Required in `Authorization: Bearer <token>` or `X-Api-Token` on every `/api` route but `/api/health`.
+    /// Routes over a user's data also need that user's session token in `X-Session-Token`.
+    pub token: String,
+    /// SQLite database shared with the desktop app.
+    pub db_path: PathBuf,
+    /// Directory that `POST /api/downloads` writes into.
+    pub download_dir: PathBuf,
+    pub ctx: ParseContext,
+    /// Queue that `POST /api/downloads` adds to, e.g. the desktop app's. Without
+    /// one the server starts its own on `db_path`.
+    pub downloads: Option<DownloadManager>,
+}
+
+struct ApiState {
+    token: String,
+    download_dir: PathBuf,
+    registry: ParserRegistry,
+    ctx: ParseContext,
+    db: DbPool,
+    downloads: DownloadManager,
+}
+
+/// A running API server; dropping it without calling [`ApiServer::stop`] leaves it running.
+pub struct ApiServer {
+    addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl ApiServer {
+    pub async fn start(config: ApiConfig) -> std::io::Result<Self> {
+        if config.token.trim().is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "API token must not be empty",
+            ));
+        }
+        let db = DbPool::open(&config.db_path, DEFAULT_POOL_SIZE).map_err(|e| std::io::Error::other(e.to_string()))?;
+        // Cookies imported in the app (or the same database) apply to API requests too
+        let loaded = db.get().and_then(|conn| {
+            let secrets = SecretStore::unlock(&conn, &KeySource::default_for(&config.db_path))?;
+            cookie_store::load_cookies(&conn, &secrets, &config.ctx)
+        });
+        match loaded {
+            Ok(count) if count > 0 => println!("[server] Loaded cookies for {} platforms", count),
+            Ok(_) => {}
+            Err(e) => println!("[server] Failed to load cookies: {}", e),
+        }
+        std::fs::create_dir_all(&config.download_dir)?;
+
+        let downloads = match config.downloads {
+            Some(manager) => manager,
+            None => {
+                let manager = DownloadManager::new(
+                    db.clone(),
+                    config.ctx.client().clone(),
+                    DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+                    |_| {},
+                );
+                manager.start().await.map_err(std::io::Error::other)?;
+                manager
+            }
+        };
+
+        let state = Arc::new(ApiState {
+            token: config.token,
+            download_dir: config.download_dir,
+            registry: ParserRegistry::with_defaults(),
+            ctx: config.ctx,
+            db,
+            downloads,
+        });
+
+        let listener = TcpListener::bind(config.bind).await?;
+        let addr = listener.local_addr()?;
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        let app = router(state);
+        let task = tokio::spawn(async move {
+            let _ = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                })
+                .await;
+        });
+
+        println!("[server] Listening on http://{}", addr);
+        Ok(Self { addr, shutdown, task })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stops accepting connections and waits for in-flight requests to finish.
+    pub async fn stop(self) {
+        let _ = self.shutdown.send(());
+        let _ = self.task.await;
+        println!("[server] Stopped");
+    }
+}
+
+fn router(state: Arc<ApiState>) -> Router {
+    let api = Router::new()
+        .route("/api/login", post(login))
+        .route("/api/logout", post(logout))
+        .route("/api/parse", post(parse))
+        .route("/api/downloads", get(list_downloads).post(download))
+        .route("/api/favorites", get(list_favorites))
+        .route("/api/search", get(search_library))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token));
+
+    Router::new()
+        .route("/api/health", get(health))
+        .merge(api)
+        .with_state(state)
+}
+
+struct ApiError {
+    status: StatusCode,
+    body: Value,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &str, detail: impl Into<String>) -> Self {
+        let retryable = status.is_server_error();
+        Self {
+            status,
+            body: json!({ "code": code, "detail": detail.into(), "retryable": retryable }),
+        }
+    }
+
+    fn internal(detail: impl ToString) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal", detail.to_string())
+    }
+}
+
+impl From<ParseError> for ApiError {
+    fn from(err: ParseError) -> Self {
+        let status = match &err {
+            ParseError::UnsupportedUrl(_) => StatusCode::BAD_REQUEST,
+            ParseError::NotFound(_) => StatusCode::NOT_FOUND,
+            ParseError::LoginRequired(_) | ParseError::RegionBlocked(_) => StatusCode::FORBIDDEN,
+            ParseError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            ParseError::LayoutChanged(_) | ParseError::Network(_) => StatusCode::BAD_GATEWAY,
+            ParseError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self {
+            status,
+            body: serde_json::to_value(&err).unwrap_or_default(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+async fn require_token(State(state): State<Arc<ApiState>>, req: Request, next: Next) -> Response {
+    let headers = req.headers();
+    let supplied = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-api-token").and_then(|v| v.to_str().ok()));
+
+    match supplied {
+        Some(token) if constant_time_eq(token.as_bytes(), state.token.as_bytes()) => next.run(req).await,
+        _ => ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", "missing or invalid API token").into_response(),
+    }
+}
+
+const SESSION_HEADER: &str = "x-session-token";
+
+/// The user whose session token came with the request, from [`auth::login_user`]
+/// or `POST /api/login`.
+struct SessionUser(auth::UserInfo);
+
+impl FromRequestParts<Arc<ApiState>> for SessionUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<ApiState>) -> Result<Self, Self::Rejection> {
+        let unauthorized = |detail: &str| ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", detail);
+        let token = parts
+            .headers
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| unauthorized("missing session token"))?;
+        state
+            .db
+            .run(move |conn| auth::session_user(conn, &token))
+            .await
+            .map(SessionUser)
+            .map_err(|e| unauthorized(&e))
+    }
+}
+
+async fn health() -> Json<Value> {
+    Json(json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }))
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+async fn login(State(state): State<Arc<ApiState>>, Json(req): Json<LoginRequest>) -> ApiResult<auth::Session> {
+    let session = state
+        .db
+        .run(move |conn| auth::login_user(conn, &req.username, &req.password))
+        .await
+        .map_err(|e| ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", e))?;
+    Ok(Json(session))
+}
+
+// Ends the session in `X-Session-Token`; succeeds whether or not it was valid
+async fn logout(State(state): State<Arc<ApiState>>, headers: HeaderMap) -> ApiResult<Value> {
+    if let Some(token) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+        let token = token.to_string();
+        state
+            .db
+            .run(move |conn| auth::logout_session(conn, &token))
+            .await
+            .map_err(ApiError::internal)?;
+    }
+    Ok(Json(json!({})))
+}
+
+#[derive(Deserialize)]
+struct ParseRequest {
+    url: String,
+}
+
+async fn parse(State(state): State<Arc<ApiState>>, Json(req): Json<ParseRequest>) -> ApiResult<Value> {
+    let info = state.registry.parse(&state.ctx, &req.url).await?;
+    Ok(Json(serde_json::to_value(info).map_err(ApiError::internal)?))
+}
+
+async fn list_downloads(
+    State(state): State<Arc<ApiState>>,
+    SessionUser(user): SessionUser,
+) -> ApiResult<Vec<downloads::DownloadRecord>> {
+    let list = state
+        .db
+        .run(move |conn| downloads::list_downloads(conn, user.id).map_err(|e| e.to_string()))
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(list))
+}
+
+#[derive(Deserialize)]
+struct FavoritesQuery {
+    platform: Option<String>,
+}
+
+async fn list_favorites(
+    State(state): State<Arc<ApiState>>,
+    SessionUser(user): SessionUser,
+    Query(q): Query<FavoritesQuery>,
+) -> ApiResult<Vec<favorites::Favorite>> {
+    let list = state
+        .db
+        .run(move |conn| favorites::list_favorites(conn, user.id, q.platform.as_deref()).map_err(|e| e.to_string()))
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(list))
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+    /// Comma-separated `favorite`, `download` and `parsed`; all when missing
+    kind: Option<String>,
+    platform: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+async fn search_library(
+    State(state): State<Arc<ApiState>>,
+    SessionUser(user): SessionUser,
+    Query(q): Query<SearchQuery>,
+) -> ApiResult<search::SearchPage> {
+    let invalid = |detail: String| ApiError::new(StatusCode::BAD_REQUEST, "invalid_request", detail);
+    let kinds = q
+        .kind
+        .iter()
+        .flat_map(|kinds| kinds.split(','))
+        .filter(|kind| !kind.trim().is_empty())
+        .map(|kind| {
+            serde_json::from_value::<SearchKind>(json!(kind.trim())).map_err(|_| invalid(format!("Unknown kind {:?}", kind)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let filters = SearchFilters { kinds, platform: q.platform, since: q.since, until: q.until };
+    filters.validate().map_err(invalid)?;
+
+    let page = state
+        .db
+        .run(move |conn| {
+            search::find_in_library(
+                conn,
+                user.id,
+                &q.q,
+                &filters,
+                q.limit.unwrap_or(search::DEFAULT_SEARCH_PAGE_SIZE),
+                q.offset.unwrap_or(0),
+            )
+        })
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(page))
+}
+
+#[derive(Deserialize)]
+struct DownloadRequest {
+    url: String,
+    title: String,
+    #[serde(default)]
+    cover_url: String,
+    /// File name inside the server's download directory; defaults to the title.
+    file_name: Option<String>,
+    /// Share link the media came from; the download takes its platform.
+    page_url: Option<String>,
+}
+
+async fn download(
+    State(state): State<Arc<ApiState>>,
+    SessionUser(user): SessionUser,
+    Json(req): Json<DownloadRequest>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    // Clients only pick a file name; paths always stay inside download_dir
+    let file_name = match &req.file_name {
+        Some(name) => sanitize_file_name(name),
+        None => format!("{}.mp4", sanitize_file_name(&req.title)),
+    };
+    let save_path = state.download_dir.join(file_name).to_string_lossy().to_string();
+
+    let id = state
+        .downloads
+        .enqueue(user.id, &req.url, None, &save_path, &req.title, &req.cover_url)
+        .await
+        .map_err(ApiError::internal)?;
+    if let Some(page_url) = req.page_url.filter(|u| !u.is_empty()) {
+        let platform = state.registry.find(&page_url).map(|p| p.platform());
+        state
+            .db
+            .run(move |conn| {
+                if let Some(platform) = platform {
+                    downloads::set_download_platform(conn, id, platform).map_err(|e| e.to_string())?;
+                }
+                parsed_items::link_download(conn, id, &page_url)
+            })
+            .await
+            .map_err(ApiError::internal)?;
+    }
+    // The transfer continues in the queue; `GET /api/downloads` reports its progress
+    Ok((StatusCode::ACCEPTED, Json(json!({ "id": id, "path": save_path, "status": "queued" }))))
+}
@@ -0,0 +1,296 @@
+// Conversion of Bilibili subtitle tracks (JSON) to SRT/WebVTT and of danmaku
+// (bullet comment XML) to ASS, so both can be saved next to a video.
+use std::fmt::Write as _;
+use std::io::Read;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+
+/// File format a subtitle or danmaku download is converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// SubRip, from a subtitle track
+    Srt,
+    /// WebVTT, from a subtitle track
+    Vtt,
+    /// Advanced SubStation Alpha with scrolling comments, from danmaku
+    Ass,
+    /// The danmaku XML as served, decompressed
+    Xml,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Srt => "srt",
+            ExportFormat::Vtt => "vtt",
+            ExportFormat::Ass => "ass",
+            ExportFormat::Xml => "xml",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "srt" => Ok(ExportFormat::Srt),
+            "vtt" | "webvtt" => Ok(ExportFormat::Vtt),
+            "ass" => Ok(ExportFormat::Ass),
+            "xml" => Ok(ExportFormat::Xml),
+            other => Err(format!("Unknown export format: {}", other)),
+        }
+    }
+}
+
+/// Converts a downloaded subtitle track or danmaku file to `format`.
+pub fn convert(input: &[u8], format: ExportFormat) -> Result<Vec<u8>> {
+    Ok(match format {
+        ExportFormat::Srt => to_srt(&parse_bilibili_subtitle(input)?).into_bytes(),
+        ExportFormat::Vtt => to_vtt(&parse_bilibili_subtitle(input)?).into_bytes(),
+        ExportFormat::Ass => danmaku_to_ass(&parse_danmaku_xml(&danmaku_text(input)?)).into_bytes(),
+        ExportFormat::Xml => danmaku_text(input)?.into_bytes(),
+    })
+}
+
+/// One subtitle line; times in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// Reads the `body` of a Bilibili subtitle JSON file
+/// (`{"body": [{"from": 1.0, "to": 2.5, "content": "..."}]}`).
+pub fn parse_bilibili_subtitle(input: &[u8]) -> Result<Vec<Cue>> {
+    let json: serde_json::Value = serde_json::from_slice(input).context("subtitle is not JSON")?;
+    let Some(body) = json["body"].as_array() else {
+        bail!("subtitle JSON has no body");
+    };
+    Ok(body
+        .iter()
+        .filter_map(|line| {
+            Some(Cue {
+                start: line["from"].as_f64()?,
+                end: line["to"].as_f64()?,
+                text: line["content"].as_str()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+pub fn to_srt(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        let _ = write!(
+            out,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            timestamp(cue.start, ','),
+            timestamp(cue.end, ','),
+            cue.text
+        );
+    }
+    out
+}
+
+pub fn to_vtt(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        // A blank line would end the cue early
+        let text = cue.text.replace("\n\n", "\n");
+        let _ = write!(out, "{} --> {}\n{}\n\n", timestamp(cue.start, '.'), timestamp(cue.end, '.'), text);
+    }
+    out
+}
+
+// HH:MM:SS,mmm (SRT) or HH:MM:SS.mmm (WebVTT)
+fn timestamp(seconds: f64, separator: char) -> String {
+    let ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+/// One bullet comment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Danmaku {
+    /// Seconds into the video
+    pub time: f64,
+    /// 1-3 scroll, 4 bottom, 5 top, 6 reverse scroll, 7+ special
+    pub mode: u8,
+    /// Font size; 25 is the regular size
+    pub size: u32,
+    /// 0xRRGGBB
+    pub color: u32,
+    pub text: String,
+}
+
+// The danmaku endpoints send raw deflate data, usually without asking
+fn danmaku_text(input: &[u8]) -> Result<String> {
+    if input.trim_ascii_start().starts_with(b"<") {
+        return Ok(String::from_utf8_lossy(input).into_owned());
+    }
+    let mut xml = String::new();
+    flate2::read::DeflateDecoder::new(input)
+        .read_to_string(&mut xml)
+        .context("danmaku is neither XML nor deflate data")?;
+    Ok(xml)
+}
+
+/// Reads the `<d p="time,mode,size,color,...">text</d>` entries of a
+/// danmaku XML file, ordered by time.
+pub fn parse_danmaku_xml(xml: &str) -> Vec<Danmaku> {
+    let re = Regex::new(r#"<d p="([^"]*)">([^<]*)</d>"#).unwrap();
+    let mut comments: Vec<Danmaku> = re
+        .captures_iter(xml)
+        .filter_map(|cap| {
+            let mut p = cap[1].split(',');
+            Some(Danmaku {
+                time: p.next()?.parse().ok()?,
+                mode: p.next()?.parse().ok()?,
+                size: p.next()?.parse().ok()?,
+                color: p.next()?.parse().ok()?,
+                text: unescape_xml(&cap[2]),
+            })
+        })
+        .collect();
+    comments.sort_by(|a, b| a.time.total_cmp(&b.time));
+    comments
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+const PLAY_RES_X: f64 = 1920.0;
+const PLAY_RES_Y: f64 = 1080.0;
+const FONT_SIZE: f64 = 50.0;
+const SCROLL_SECONDS: f64 = 8.0;
+const FIXED_SECONDS: f64 = 4.0;
+
+/// Renders danmaku as ASS subtitles: scrolling comments move right to left
+/// across the top of a 1920x1080 frame, top and bottom comments stay put.
+/// Comments are spread over lanes so they overlap as little as possible.
+/// Special (mode 7+) comments are skipped.
+pub fn danmaku_to_ass(comments: &[Danmaku]) -> String {
+    let mut out = format!(
+        "[Script Info]\n\
+         ScriptType: v4.00+\n\
+         PlayResX: {x}\n\
+         PlayResY: {y}\n\
+         WrapStyle: 2\n\
+         ScaledBorderAndShadow: yes\n\
+         \n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Danmaku,sans-serif,{size},&H00FFFFFF,&H00FFFFFF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,1.5,0,8,0,0,0,1\n\
+         \n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        x = PLAY_RES_X,
+        y = PLAY_RES_Y,
+        size = FONT_SIZE
+    );
+
+    let lane_height = FONT_SIZE + 4.0;
+    let lanes = ((PLAY_RES_Y / lane_height) as usize).max(1);
+    // When each lane can take its next comment
+    let mut scroll_free = vec![0f64; lanes];
+    let mut top_free = vec![0f64; lanes];
+    let mut bottom_free = vec![0f64; lanes];
+
+    for comment in comments {
+        let font = FONT_SIZE * comment.size as f64 / 25.0;
+        let width = comment.text.chars().map(|c| if c.is_ascii() { 0.5 } else { 1.0 }).sum::<f64>() * font;
+        let text = escape_ass(&comment.text);
+        let color = if comment.color == 0xFFFFFF {
+            String::new()
+        } else {
+            let (r, g, b) = (comment.color >> 16 & 0xFF, comment.color >> 8 & 0xFF, comment.color & 0xFF);
+            format!("\\c&H{:02X}{:02X}{:02X}&", b, g, r)
+        };
+        let size = if comment.size == 25 { String::new() } else { format!("\\fs{}", font.round()) };
+
+        let (start, end, position) = match comment.mode {
+            1..=3 | 6 => {
+                let lane = pick_lane(&mut scroll_free, comment.time);
+                // The lane frees up once this comment's tail has entered the screen
+                let speed = (PLAY_RES_X + width) / SCROLL_SECONDS;
+                scroll_free[lane] = comment.time + width / speed;
+                let y = lane as f64 * lane_height;
+                let (from, to) = if comment.mode == 6 {
+                    (-width / 2.0, PLAY_RES_X + width / 2.0)
+                } else {
+                    (PLAY_RES_X + width / 2.0, -width / 2.0)
+                };
+                (comment.time, comment.time + SCROLL_SECONDS, format!("\\move({:.0},{:.0},{:.0},{:.0})", from, y, to, y))
+            }
+            5 => {
+                let lane = pick_lane(&mut top_free, comment.time);
+                top_free[lane] = comment.time + FIXED_SECONDS;
+                let y = lane as f64 * lane_height;
+                (comment.time, comment.time + FIXED_SECONDS, format!("\\an8\\pos({:.0},{:.0})", PLAY_RES_X / 2.0, y))
+            }
+            4 => {
+                let lane = pick_lane(&mut bottom_free, comment.time);
+                bottom_free[lane] = comment.time + FIXED_SECONDS;
+                let y = PLAY_RES_Y - lane as f64 * lane_height;
+                (comment.time, comment.time + FIXED_SECONDS, format!("\\an2\\pos({:.0},{:.0})", PLAY_RES_X / 2.0, y))
+            }
+            _ => continue,
+        };
+        let _ = writeln!(
+            out,
+            "Dialogue: 0,{},{},Danmaku,,0,0,0,,{{{}{}{}}}{}",
+            ass_time(start),
+            ass_time(end),
+            position,
+            color,
+            size,
+            text
+        );
+    }
+    out
+}
+
+// First lane free at `time`, or the one that frees up soonest
+fn pick_lane(free_at: &mut [f64], time: f64) -> usize {
+    free_at
+        .iter()
+        .position(|&free| free <= time)
+        .unwrap_or_else(|| {
+            free_at
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(i, _)| i)
+                .unwrap_or(0)
+        })
+}
+
+// H:MM:SS.cc
+fn ass_time(seconds: f64) -> String {
+    let cs = (seconds.max(0.0) * 100.0).round() as u64;
+    format!("{}:{:02}:{:02}.{:02}", cs / 360_000, cs / 6000 % 60, cs / 100 % 60, cs % 100)
+}
+
+// ASS has no escape for a backslash, so it becomes a full-width one
+fn escape_ass(text: &str) -> String {
+    text.replace('\\', "＼")
+        .replace('{', "\\{")
+        .replace('}', "\\}")
+        .replace('\n', "\\N")
+}
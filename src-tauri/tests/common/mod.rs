@@ -0,0 +1,300 @@
+// Shared helpers for the offline tests: fixture loading, scratch directories
+// and databases, a tiny HTTP/1.1 stand-in server so network steps run without
+// touching real hosts, and a sample parse result.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "db")]
+use app_lib::db::DbPool;
+use app_lib::models::{Author, VideoParseInfo};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+pub fn fixture(name: &str) -> String {
+    std::fs::read_to_string(fixture_path(name))
+        .unwrap_or_else(|e| panic!("failed to read fixture {}: {}", name, e))
+}
+
+pub fn fixture_json(name: &str) -> serde_json::Value {
+    serde_json::from_str(&fixture(name))
+        .unwrap_or_else(|e| panic!("invalid JSON in fixture {}: {}", name, e))
+}
+
+/// An empty directory for test `name`, unique to this test binary and process.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vp-{}-{}-{}", env!("CARGO_CRATE_NAME"), name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A migrated `app.db` in a fresh [`temp_dir`]; removing the directory
+/// cleans up the database too.
+#[cfg(feature = "db")]
+pub fn temp_db(name: &str) -> (PathBuf, DbPool) {
+    let dir = temp_dir(name);
+    let db = DbPool::open(&dir.join("app.db"), 4).unwrap();
+    (dir, db)
+}
+
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Send only this many body bytes, then hold the connection open.
+    pub stall_after: Option<usize>,
+    /// Honour `Range: bytes=a-b` / `bytes=a-` (and `If-Range`) requests.
+    pub ranges: bool,
+}
+
+impl MockResponse {
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self { status: 200, headers: Vec::new(), body: body.into(), stall_after: None, ranges: false }
+    }
+
+    pub fn json(body: impl Into<Vec<u8>>) -> Self {
+        Self::ok(body).with_header("Content-Type", "application/json")
+    }
+
+    pub fn html(body: impl Into<Vec<u8>>) -> Self {
+        Self::ok(body).with_header("Content-Type", "text/html; charset=utf-8")
+    }
+
+    pub fn redirect(location: &str) -> Self {
+        Self::status(302).with_header("Location", location)
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self { status, headers: Vec::new(), body: Vec::new(), stall_after: None, ranges: false }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_ranges(mut self) -> Self {
+        self.ranges = true;
+        self.with_header("Accept-Ranges", "bytes")
+    }
+
+    /// Simulates a transfer that hangs after `bytes` of the body.
+    pub fn stalled_after(mut self, bytes: usize) -> Self {
+        self.stall_after = Some(bytes);
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path including the query string.
+    pub target: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or("")
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(|s| s.as_str())
+    }
+}
+
+type Routes = Arc<Mutex<HashMap<(String, String), MockResponse>>>;
+
+/// Serves canned responses keyed by method and path (query string ignored).
+/// Unknown routes answer 404.
+pub struct MockServer {
+    addr: SocketAddr,
+    routes: Routes,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let routes: Routes = Arc::default();
+        let requests: Arc<Mutex<Vec<RecordedRequest>>> = Arc::default();
+
+        let (r, q) = (routes.clone(), requests.clone());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (r, q) = (r.clone(), q.clone());
+                tokio::spawn(async move {
+                    let _ = handle(stream, r, q).await;
+                });
+            }
+        });
+
+        Self { addr, routes, requests }
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url(), path)
+    }
+
+    pub fn mock(&self, method: &str, path: &str, response: MockResponse) {
+        self.routes
+            .lock()
+            .unwrap()
+            .insert((method.to_uppercase(), path.to_string()), response);
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    routes: Routes,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("").to_string();
+    let target = request_line.next().unwrap_or("/").to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = buf[head_end..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    let request = RecordedRequest { method, target, headers, body };
+    let response = routes
+        .lock()
+        .unwrap()
+        .get(&(request.method.clone(), request.path().to_string()))
+        .cloned()
+        .unwrap_or_else(|| MockResponse::status(404));
+    let response = match request.header("range") {
+        Some(range) if response.ranges && response.status == 200 => ranged(response, range, request.header("if-range")),
+        _ => response,
+    };
+    requests.lock().unwrap().push(request);
+
+    let mut out = format!("HTTP/1.1 {} Mock\r\n", response.status);
+    for (k, v) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    out.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", response.body.len()));
+    stream.write_all(out.as_bytes()).await?;
+    match response.stall_after {
+        Some(n) => {
+            stream.write_all(&response.body[..n.min(response.body.len())]).await?;
+            stream.flush().await?;
+            tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+            Ok(())
+        }
+        None => {
+            stream.write_all(&response.body).await?;
+            stream.shutdown().await
+        }
+    }
+}
+
+fn ranged(full: MockResponse, range: &str, if_range: Option<&str>) -> MockResponse {
+    let validator_matches = |v: &str| {
+        full.headers
+            .iter()
+            .any(|(k, value)| (k.eq_ignore_ascii_case("etag") || k.eq_ignore_ascii_case("last-modified")) && value == v)
+    };
+    if if_range.is_some_and(|v| !validator_matches(v)) {
+        return full;
+    }
+    let Some((start, end)) = range.strip_prefix("bytes=").and_then(|r| r.split_once('-')) else {
+        return full;
+    };
+    let Ok(start) = start.parse::<usize>() else { return full };
+    let len = full.body.len();
+    let end = match end {
+        "" => len.saturating_sub(1),
+        end => match end.parse::<usize>() {
+            Ok(end) => end.min(len.saturating_sub(1)),
+            Err(_) => return full,
+        },
+    };
+
+    if start >= len || start > end {
+        let mut res = MockResponse::status(416).with_header("Content-Range", &format!("bytes */{}", len));
+        res.headers.extend(full.headers);
+        return res;
+    }
+    MockResponse {
+        status: 206,
+        body: full.body[start..=end].to_vec(),
+        ..full
+    }
+    .with_header("Content-Range", &format!("bytes {}-{}/{}", start, end, len))
+}
+
+/// A minimal parse result, as the cache and history tests store them.
+pub fn video_info(title: &str, platform: &str, author: &str) -> VideoParseInfo {
+    VideoParseInfo {
+        author: Author { uid: String::new(), name: author.to_string(), avatar: String::new() },
+        title: title.to_string(),
+        video_url: format!("https://cdn.example.com/{}.mp4", platform),
+        music_url: String::new(),
+        cover_url: String::new(),
+        images: vec![],
+        platform: platform.to_string(),
+        video_qualities: vec![],
+        statistics: None,
+        tags: None,
+        music_info: None,
+        create_time: None,
+        duration: None,
+        description: None,
+        parts: vec![],
+        subtitles: vec![],
+        danmaku_url: None,
+        page_url: None,
+    }
+}
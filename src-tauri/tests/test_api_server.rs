@@ -0,0 +1,415 @@
+#![cfg(feature = "server")]
+
+mod common;
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use app_lib::auth::{register_user, session_user};
+use app_lib::db::{open_db, DbPool};
+use app_lib::download_manager::DownloadManager;
+use app_lib::downloads::DownloadProgress;
+use app_lib::favorites::insert_favorite;
+use app_lib::parser::ParseContext;
+use app_lib::server::{ApiConfig, ApiServer};
+use common::{temp_dir, MockResponse, MockServer};
+use serde_json::{json, Value};
+
+const TOKEN: &str = "test-token";
+
+async fn start(dir: &std::path::Path) -> (ApiServer, String) {
+    let server = ApiServer::start(ApiConfig {
+        bind: "127.0.0.1:0".parse().unwrap(),
+        token: TOKEN.to_string(),
+        db_path: dir.join("api.db"),
+        download_dir: dir.join("downloads"),
+        ctx: ParseContext::new(),
+        downloads: None,
+    })
+    .await
+    .unwrap();
+    let base = format!("http://{}", server.local_addr());
+    (server, base)
+}
+
+// Registers `username` in the server's database and returns a session token
+fn session(dir: &std::path::Path, username: &str) -> String {
+    let conn = open_db(&dir.join("api.db")).unwrap();
+    register_user(&conn, username, "pw", &format!("{}@example.com", username)).unwrap().token
+}
+
+#[tokio::test]
+async fn test_token_is_required() {
+    let dir = temp_dir("auth");
+    let (server, base) = start(&dir).await;
+    let client = reqwest::Client::new();
+
+    let res = client.get(format!("{}/api/health", base)).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let alice = session(&dir, "alice");
+    let res = client
+        .get(format!("{}/api/downloads", base))
+        .header("X-Session-Token", &alice)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["code"], "unauthorized");
+
+    let res = client
+        .get(format!("{}/api/downloads", base))
+        .header("Authorization", "Bearer wrong-token")
+        .header("X-Session-Token", &alice)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+
+    let res = client
+        .get(format!("{}/api/downloads", base))
+        .header("X-Api-Token", TOKEN)
+        .header("X-Session-Token", &alice)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+
+    // The API token alone does not say whose data to use
+    let res = client.get(format!("{}/api/downloads", base)).bearer_auth(TOKEN).send().await.unwrap();
+    assert_eq!(res.status(), 401);
+    let res = client
+        .get(format!("{}/api/downloads", base))
+        .bearer_auth(TOKEN)
+        .header("X-Session-Token", "not-a-session")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+
+    server.stop().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_empty_token_is_rejected() {
+    let dir = temp_dir("empty-token");
+    let result = ApiServer::start(ApiConfig {
+        bind: "127.0.0.1:0".parse().unwrap(),
+        token: "  ".to_string(),
+        db_path: dir.join("api.db"),
+        download_dir: dir.join("downloads"),
+        ctx: ParseContext::new(),
+        downloads: None,
+    })
+    .await;
+    assert!(result.is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_parse_error_maps_to_status() {
+    let dir = temp_dir("parse");
+    let (server, base) = start(&dir).await;
+
+    let res = reqwest::Client::new()
+        .post(format!("{}/api/parse", base))
+        .bearer_auth(TOKEN)
+        .json(&json!({ "url": "https://example.com/not-a-video" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["code"], "unsupported_url");
+    assert_eq!(body["retryable"], false);
+
+    server.stop().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// Polls `GET /api/downloads` until download `id` reaches `status`
+async fn wait_for_download(base: &str, session: &str, id: &Value, status: &str) -> Value {
+    let client = reqwest::Client::new();
+    for _ in 0..200 {
+        let list: Value = client
+            .get(format!("{}/api/downloads", base))
+            .bearer_auth(TOKEN)
+            .header("X-Session-Token", session)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if let Some(record) = list.as_array().unwrap().iter().find(|r| &r["id"] == id) {
+            if record["status"] == status {
+                return record.clone();
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("download {} never reached {}", id, status);
+}
+
+#[tokio::test]
+async fn test_download_and_list() {
+    let dir = temp_dir("download");
+    let (server, base) = start(&dir).await;
+    let source = MockServer::start().await;
+    source.mock("GET", "/v.mp4", MockResponse::ok(vec![1u8; 1024]));
+    let client = reqwest::Client::new();
+    let alice = session(&dir, "alice");
+
+    // Downloads are queued and the request returns before the transfer
+    let res = client
+        .post(format!("{}/api/downloads", base))
+        .bearer_auth(TOKEN)
+        .header("X-Session-Token", &alice)
+        .json(&json!({
+            "url": source.url("/v.mp4"),
+            "title": "../../escape",
+            "page_url": "https://v.douyin.com/iRNBho6u/",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 202);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["status"], "queued");
+    let record = wait_for_download(&base, &alice, &body["id"], "completed").await;
+    assert_eq!(record["downloaded_size"], 1024);
+    // The share link gives the download its platform, with or without a cached parse
+    assert_eq!(record["platform"], "douyin");
+
+    // The title cannot move the file out of the download directory
+    let path = PathBuf::from(body["path"].as_str().unwrap());
+    assert_eq!(path.parent().unwrap(), dir.join("downloads"));
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 1024);
+
+    // Failed downloads are recorded
+    let res = client
+        .post(format!("{}/api/downloads", base))
+        .bearer_auth(TOKEN)
+        .header("X-Session-Token", &alice)
+        .json(&json!({ "url": source.url("/missing.mp4"), "title": "missing" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 202);
+    let body: Value = res.json().await.unwrap();
+    wait_for_download(&base, &alice, &body["id"], "failed").await;
+
+    server.stop().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_downloads_join_the_shared_queue() {
+    let dir = temp_dir("shared-queue");
+    let source = MockServer::start().await;
+    source.mock("GET", "/stall.mp4", MockResponse::ok(vec![1u8; 512]).stalled_after(10));
+
+    // One worker, as configured in the app; the server must not start more
+    let events: Arc<Mutex<Vec<DownloadProgress>>> = Arc::default();
+    let sink = events.clone();
+    let pool = DbPool::open(&dir.join("api.db"), 4).unwrap();
+    let manager = DownloadManager::new(pool, reqwest::Client::new(), 1, move |p| sink.lock().unwrap().push(p.clone()));
+    let server = ApiServer::start(ApiConfig {
+        bind: "127.0.0.1:0".parse().unwrap(),
+        token: TOKEN.to_string(),
+        db_path: dir.join("api.db"),
+        download_dir: dir.join("downloads"),
+        ctx: ParseContext::new(),
+        downloads: Some(manager.clone()),
+    })
+    .await
+    .unwrap();
+    let base = format!("http://{}", server.local_addr());
+    let alice = session(&dir, "alice");
+
+    let mut ids = Vec::new();
+    for name in ["a", "b"] {
+        let body: Value = reqwest::Client::new()
+            .post(format!("{}/api/downloads", base))
+            .bearer_auth(TOKEN)
+            .header("X-Session-Token", &alice)
+            .json(&json!({ "url": source.url("/stall.mp4"), "title": name }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        ids.push(body["id"].clone());
+    }
+    wait_for_download(&base, &alice, &ids[0], "downloading").await;
+    wait_for_download(&base, &alice, &ids[1], "queued").await;
+    assert_eq!(manager.active_count().await, 1);
+    assert!(events.lock().unwrap().iter().any(|p| json!(p.id) == ids[0]));
+
+    // Queued API downloads can be paused like any other
+    let id = ids[0].as_i64().unwrap();
+    manager.pause(id).await.unwrap();
+    wait_for_download(&base, &alice, &ids[0], "paused").await;
+    manager.cancel(ids[1].as_i64().unwrap()).await.unwrap();
+
+    server.stop().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_list_favorites() {
+    let dir = temp_dir("favorites");
+    let (server, base) = start(&dir).await;
+    let alice = session(&dir, "alice");
+    let bob = session(&dir, "bob");
+
+    // The server shares its database with the desktop app
+    let conn = open_db(&dir.join("api.db")).unwrap();
+    let alice_id = session_user(&conn, &alice).unwrap().id;
+    let bob_id = session_user(&conn, &bob).unwrap().id;
+    insert_favorite(&conn, alice_id, "https://a", "A", "douyin", "", "").unwrap();
+    insert_favorite(&conn, alice_id, "https://b", "B", "bilibili", "", "").unwrap();
+    insert_favorite(&conn, bob_id, "https://c", "C", "douyin", "", "").unwrap();
+
+    let client = reqwest::Client::new();
+    let favorites = |session: &str, query: &str| {
+        client
+            .get(format!("{}/api/favorites{}", base, query))
+            .bearer_auth(TOKEN)
+            .header("X-Session-Token", session)
+            .send()
+    };
+    let all: Value = favorites(&alice, "").await.unwrap().json().await.unwrap();
+    assert_eq!(all.as_array().unwrap().len(), 2);
+
+    let douyin: Value = favorites(&alice, "?platform=douyin").await.unwrap().json().await.unwrap();
+    assert_eq!(douyin.as_array().unwrap().len(), 1);
+    assert_eq!(douyin[0]["title"], "A");
+
+    // A user id in the query no longer picks whose favorites are listed
+    let bobs: Value = favorites(&bob, &format!("?user_id={}", alice_id)).await.unwrap().json().await.unwrap();
+    assert_eq!(bobs.as_array().unwrap().len(), 1);
+    assert_eq!(bobs[0]["title"], "C");
+
+    server.stop().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_downloads_are_scoped_to_the_session() {
+    let dir = temp_dir("scoped");
+    let (server, base) = start(&dir).await;
+    let source = MockServer::start().await;
+    source.mock("GET", "/v.mp4", MockResponse::ok(vec![1u8; 16]));
+    let client = reqwest::Client::new();
+    let conn = open_db(&dir.join("api.db")).unwrap();
+    register_user(&conn, "alice", "pw", "alice@example.com").unwrap();
+    register_user(&conn, "bob", "pw", "bob@example.com").unwrap();
+
+    let login = |username: &str, password: &str| {
+        client
+            .post(format!("{}/api/login", base))
+            .bearer_auth(TOKEN)
+            .json(&json!({ "username": username, "password": password }))
+            .send()
+    };
+    let res = login("alice", "wrong").await.unwrap();
+    assert_eq!(res.status(), 401);
+    let alice: Value = login("alice", "pw").await.unwrap().json().await.unwrap();
+    let alice = alice["token"].as_str().unwrap().to_string();
+    let bob: Value = login("bob", "pw").await.unwrap().json().await.unwrap();
+    let bob = bob["token"].as_str().unwrap().to_string();
+
+    // A `user_id` in the body is ignored; the download belongs to the session's user
+    let bob_id = session_user(&conn, &bob).unwrap().id;
+    let res = client
+        .post(format!("{}/api/downloads", base))
+        .bearer_auth(TOKEN)
+        .header("X-Session-Token", &alice)
+        .json(&json!({ "user_id": bob_id, "url": source.url("/v.mp4"), "title": "mine" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 202);
+
+    let downloads = |session: &str| {
+        client
+            .get(format!("{}/api/downloads?user_id={}", base, bob_id))
+            .bearer_auth(TOKEN)
+            .header("X-Session-Token", session)
+            .send()
+    };
+    let list: Value = downloads(&alice).await.unwrap().json().await.unwrap();
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["title"], "mine");
+    let list: Value = downloads(&bob).await.unwrap().json().await.unwrap();
+    assert!(list.as_array().unwrap().is_empty());
+
+    // A logged-out session is refused
+    let res = client
+        .post(format!("{}/api/logout", base))
+        .bearer_auth(TOKEN)
+        .header("X-Session-Token", &bob)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(downloads(&bob).await.unwrap().status(), 401);
+
+    server.stop().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_search() {
+    let dir = temp_dir("search");
+    let (server, base) = start(&dir).await;
+    let alice = session(&dir, "alice");
+    let bob = session(&dir, "bob");
+    let conn = open_db(&dir.join("api.db")).unwrap();
+    let alice_id = session_user(&conn, &alice).unwrap().id;
+    let bob_id = session_user(&conn, &bob).unwrap().id;
+    conn.execute_batch(&format!(
+        "INSERT INTO favorites (user_id, url, title, platform) VALUES ({alice_id}, 'https://a', 'Morning yoga', 'douyin');
+         INSERT INTO favorites (user_id, url, title, platform) VALUES ({alice_id}, 'https://b', 'Evening run', 'bilibili');
+         INSERT INTO downloads (user_id, url, title, file_path, status) VALUES ({alice_id}, 'https://cdn/y.mp4', 'yoga mat review', '/tmp/y.mp4', 'completed');
+         INSERT INTO favorites (user_id, url, title, platform) VALUES ({bob_id}, 'https://c', 'Yoga for two', 'douyin');",
+    ))
+    .unwrap();
+
+    let client = reqwest::Client::new();
+    let search_as = |session: &str, query: &str| {
+        client
+            .get(format!("{}/api/search?{}", base, query))
+            .bearer_auth(TOKEN)
+            .header("X-Session-Token", session)
+            .send()
+    };
+    let search = |query: &str| search_as(&alice, query);
+    let page: Value = search("q=yoga").await.unwrap().json().await.unwrap();
+    assert_eq!(page["total"], 2);
+    let kinds: Vec<&str> = page["hits"].as_array().unwrap().iter().map(|h| h["kind"].as_str().unwrap()).collect();
+    assert!(kinds.contains(&"favorite") && kinds.contains(&"download"), "{:?}", kinds);
+
+    let page: Value = search("q=yoga&kind=favorite").await.unwrap().json().await.unwrap();
+    assert_eq!(page["hits"][0]["title"], "Morning yoga");
+    assert_eq!(page["total"], 1);
+
+    let res = search("q=yoga&since=yesterday").await.unwrap();
+    assert_eq!(res.status(), 400);
+    let res = search("kind=playlist").await.unwrap();
+    assert_eq!(res.status(), 400);
+
+    // Bob only finds his own favorite, whatever user id he passes
+    let page: Value = search_as(&bob, &format!("q=yoga&user_id={}", alice_id)).await.unwrap().json().await.unwrap();
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["hits"][0]["title"], "Yoga for two");
+    let res = client.get(format!("{}/api/search?q=yoga", base)).bearer_auth(TOKEN).send().await.unwrap();
+    assert_eq!(res.status(), 401);
+
+    server.stop().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "db")]
+
+mod common;
+
+use app_lib::auth::{login_user, logout_session, register_user, reset_user_password, session_user, update_user};
+use common::temp_db;
+use rusqlite::Connection;
+
+fn stored_password(conn: &Connection, username: &str) -> String {
+    conn.query_row("SELECT password FROM users WHERE username = ?1", [username], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn test_register_hashes_password_and_logs_in() {
+    let (dir, db) = temp_db("register");
+    let conn = db.get().unwrap();
+
+    let session = register_user(&conn, "alice", "s3cret", "alice@example.com").unwrap();
+    assert!(session.recovery_code.is_some());
+    assert_eq!(session_user(&conn, &session.token).unwrap().username, "alice");
+    let stored = stored_password(&conn, "alice");
+    assert!(stored.starts_with("$argon2id$"), "{}", stored);
+    assert!(!stored.contains("s3cret"));
+
+    assert!(register_user(&conn, "alice", "other", "a@example.com").is_err());
+    assert!(login_user(&conn, "alice", "wrong").is_err());
+    assert!(login_user(&conn, "bob", "s3cret").is_err());
+
+    let second = login_user(&conn, "alice", "s3cret").unwrap();
+    assert_ne!(second.token, session.token);
+    // Only a hash of the token is kept
+    let hashes: i64 = conn
+        .query_row("SELECT COUNT(*) FROM sessions WHERE token_hash = ?1", [&second.token], |row| row.get(0))
+        .unwrap();
+    assert_eq!(hashes, 0);
+
+    logout_session(&conn, &second.token).unwrap();
+    assert!(session_user(&conn, &second.token).is_err());
+    assert!(session_user(&conn, &session.token).is_ok());
+    assert!(session_user(&conn, "made-up").is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_unknown_user_takes_as_long_as_wrong_password() {
+    let (dir, db) = temp_db("timing");
+    let conn = db.get().unwrap();
+    register_user(&conn, "alice", "s3cret", "alice@example.com").unwrap();
+
+    let time = |username: &str| {
+        let start = std::time::Instant::now();
+        let err = login_user(&conn, username, "wrong").unwrap_err();
+        (start.elapsed(), err)
+    };
+    let (known, known_err) = time("alice");
+    let (unknown, unknown_err) = time("bob");
+    assert_eq!(known_err, unknown_err);
+    // Both paths run one Argon2 verification
+    assert!(unknown * 3 > known, "unknown user {:?}, wrong password {:?}", unknown, known);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_plaintext_password_is_migrated_on_login() {
+    let (dir, db) = temp_db("migrate");
+    let conn = db.get().unwrap();
+    // An account from before password hashing
+    conn.execute(
+        "INSERT INTO users (username, password, email) VALUES ('carol', 'hunter2', 'carol@example.com')",
+        [],
+    )
+    .unwrap();
+
+    assert!(login_user(&conn, "carol", "hunter3").is_err());
+    assert_eq!(stored_password(&conn, "carol"), "hunter2");
+
+    let session = login_user(&conn, "carol", "hunter2").unwrap();
+    assert!(stored_password(&conn, "carol").starts_with("$argon2id$"));
+    // Accounts without a recovery code are issued one, once
+    let code = session.recovery_code.unwrap();
+    assert!(login_user(&conn, "carol", "hunter2").unwrap().recovery_code.is_none());
+
+    reset_user_password(&conn, "carol", &code.to_lowercase(), "new-pass").unwrap();
+    assert!(login_user(&conn, "carol", "new-pass").is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_expired_session_is_rejected() {
+    let (dir, db) = temp_db("expired");
+    let conn = db.get().unwrap();
+    let session = register_user(&conn, "dave", "pw", "dave@example.com").unwrap();
+    conn.execute("UPDATE sessions SET expires_at = datetime('now', '-1 minute')", [])
+        .unwrap();
+
+    let err = session_user(&conn, &session.token).unwrap_err();
+    assert!(err.contains("session has expired"), "{}", err);
+    assert!(update_user(&conn, &session.token, Some("eve"), None).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_password_change_and_reset_end_sessions() {
+    let (dir, db) = temp_db("reset");
+    let conn = db.get().unwrap();
+    let first = register_user(&conn, "frank", "old", "frank@example.com").unwrap();
+    let code = first.recovery_code.clone().unwrap();
+    let other = login_user(&conn, "frank", "old").unwrap();
+
+    // A new password keeps the session that changed it and ends the others
+    let user = update_user(&conn, &first.token, Some("frankie"), Some("newer")).unwrap();
+    assert_eq!(user.username, "frankie");
+    assert!(session_user(&conn, &first.token).is_ok());
+    assert!(session_user(&conn, &other.token).is_err());
+    assert!(login_user(&conn, "frankie", "old").is_err());
+
+    // The email alone no longer resets a password
+    assert!(reset_user_password(&conn, "frankie", "frank@example.com", "x").is_err());
+    let next_code = reset_user_password(&conn, "frankie", &code, "newest").unwrap();
+    assert!(session_user(&conn, &first.token).is_err());
+    assert!(login_user(&conn, "frankie", "newest").is_ok());
+    // Each code works once
+    assert!(reset_user_password(&conn, "frankie", &code, "again").is_err());
+    assert!(reset_user_password(&conn, "frankie", &next_code, "again").is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 
 #[tokio::test]
+#[ignore = "requires network"]
 async fn debug_douyin_mobile_ua() -> Result<()> {
     let video_id = "7227408198167186721";
     let mobile_ua = "Mozilla/5.0 (iPhone; CPU iPhone OS 16_6 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.6 Mobile/15E148 Safari/604.1";
@@ -33,7 +34,7 @@ async fn debug_douyin_mobile_ua() -> Result<()> {
                             if let Some(item) = value.get("videoInfoRes")
                                 .and_then(|v| v.get("item_list"))
                                 .and_then(|v| v.as_array())
-                                .and_then(|arr| arr.get(0))
+                                .and_then(|arr| arr.first())
                             {
                                 println!("SUCCESS!");
                                 println!("desc: {}", item.get("desc").and_then(|v| v.as_str()).unwrap_or("N/A"));
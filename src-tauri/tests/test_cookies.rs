@@ -0,0 +1,132 @@
+mod common;
+
+use app_lib::parser::cookies::{self, parse_header, parse_netscape};
+use app_lib::parser::weibo::Weibo;
+use app_lib::parser::xhs::Xiaohongshu;
+use app_lib::parser::{ParseContext, ParserRegistry};
+use common::{fixture, MockResponse, MockServer};
+use reqwest::header::{HeaderValue, COOKIE};
+
+#[test]
+fn test_parse_netscape_cookies_txt() {
+    let cookies = parse_netscape(&fixture("cookies/cookies.txt"));
+    let names: Vec<&str> = cookies.iter().map(|c| c.name.as_str()).collect();
+    // HttpOnly lines are read, expired cookies and malformed lines are not
+    assert_eq!(names, ["buvid3", "SESSDATA", "bili_jct", "SUB", "web_session", "tracking"]);
+    assert_eq!(cookies[1].domain, "bilibili.com");
+    assert_eq!(cookies[1].expires, Some(4102444800));
+    assert_eq!(cookies[2].expires, None);
+}
+
+#[test]
+fn test_parse_pasted_header() {
+    let pairs = parse_header("Cookie: SESSDATA=abc; bili_jct=def ;  empty=; =bad");
+    let expected = [("SESSDATA", "abc"), ("bili_jct", "def"), ("empty", "")];
+    let expected: Vec<(String, String)> = expected.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect();
+    assert_eq!(pairs, expected);
+}
+
+#[test]
+fn test_import_splits_by_platform() {
+    let registry = ParserRegistry::with_defaults();
+    let headers = cookies::import(&registry, &fixture("cookies/cookies.txt"), None).unwrap();
+    let platforms: Vec<&str> = headers.keys().map(String::as_str).collect();
+    assert_eq!(platforms, ["bilibili", "weibo", "xhs"]);
+    assert_eq!(headers["bilibili"], "buvid3=4A1B2C3D-infoc; SESSDATA=abc123%2C4102444800%2Cxyz; bili_jct=0123456789abcdef");
+
+    let only = cookies::import(&registry, &fixture("cookies/cookies.txt"), Some("weibo")).unwrap();
+    assert_eq!(only.len(), 1);
+    assert_eq!(only["weibo"], "SUB=_2A25weibo_session");
+
+    let pasted = cookies::import(&registry, "SESSDATA=abc; bili_jct=def", Some("bilibili")).unwrap();
+    assert_eq!(pasted["bilibili"], "SESSDATA=abc; bili_jct=def");
+    assert!(cookies::import(&registry, "SESSDATA=abc", None).is_err());
+    assert!(cookies::import(&registry, "SESSDATA=abc", Some("youtube")).is_err());
+}
+
+#[test]
+fn test_context_cookies_are_shared_and_overridable() {
+    let ctx = ParseContext::new();
+    let clone = ctx.clone();
+    ctx.set_cookies("bilibili", "SESSDATA=abc").unwrap();
+    assert_eq!(clone.headers("bilibili").get(COOKIE).unwrap(), "SESSDATA=abc");
+    assert!(clone.headers("weibo").get(COOKIE).is_none());
+    assert_eq!(clone.cookie_platforms(), ["bilibili"]);
+    assert!(ctx.set_cookies("bilibili", "bad\nvalue").is_err());
+
+    // An explicitly configured header still wins
+    let configured = ctx.clone().with_header("bilibili", COOKIE, HeaderValue::from_static("fixed=1"));
+    assert_eq!(configured.headers("bilibili").get(COOKIE).unwrap(), "fixed=1");
+
+    clone.remove_cookies("bilibili");
+    assert!(ctx.headers("bilibili").get(COOKIE).is_none());
+}
+
+#[tokio::test]
+async fn test_parsers_send_stored_cookies() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/explore/abc", MockResponse::html(fixture("xhs/note_video.html")));
+    let ctx = ParseContext::new();
+
+    // No made-up cookies without a login
+    Xiaohongshu::parse_share_url(&ctx, &server.url("/explore/abc")).await.unwrap();
+    assert_eq!(server.requests()[0].header("cookie"), None);
+
+    ctx.set_cookies("xhs", "web_session=040069xhs").unwrap();
+    Xiaohongshu::parse_share_url(&ctx, &server.url("/explore/abc")).await.unwrap();
+    assert_eq!(server.requests()[1].header("cookie"), Some("web_session=040069xhs"));
+}
+
+#[tokio::test]
+async fn test_weibo_video_needs_login_cookies() {
+    let server = MockServer::start().await;
+    server.mock("POST", "/api/component", MockResponse::html("<html><title>Sina Visitor System</title></html>"));
+    let ctx = ParseContext::new().with_base_url("https://h5.video.weibo.com", server.base_url());
+    let url = "https://video.weibo.com/show?fid=1034:4830000000000001";
+
+    let err = Weibo::parse_share_url(&ctx, url).await.unwrap_err();
+    assert_eq!(err.code(), "login_required");
+
+    server.mock("POST", "/api/component", MockResponse::json(fixture("weibo/video_component.json")));
+    ctx.set_cookies("weibo", "SUB=_2A25weibo_session").unwrap();
+    Weibo::parse_share_url(&ctx, url).await.unwrap();
+    assert_eq!(server.requests()[1].header("cookie"), Some("SUB=_2A25weibo_session"));
+}
+
+#[cfg(feature = "db")]
+#[test]
+fn test_cookie_store_roundtrip() {
+    use app_lib::cookie_store;
+    use app_lib::secrets::{FileKeyStore, KeySource, SecretStore};
+
+    let path = std::env::temp_dir().join(format!("vp-cookies-{}.db", std::process::id()));
+    let key_path = path.with_extension("key");
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&key_path);
+    let conn = app_lib::db::open_db(&path).unwrap();
+    let secrets = SecretStore::unlock(&conn, &KeySource::Stored(Box::new(FileKeyStore::new(&key_path)))).unwrap();
+    let registry = ParserRegistry::with_defaults();
+    let ctx = ParseContext::new();
+
+    let updated = cookie_store::import(&conn, &secrets, &ctx, &registry, &fixture("cookies/cookies.txt"), None).unwrap();
+    assert_eq!(updated, ["bilibili", "weibo", "xhs"]);
+    cookie_store::import(&conn, &secrets, &ctx, &registry, "SESSDATA=new", Some("bilibili")).unwrap();
+
+    let stored = cookie_store::list_cookies(&conn, &secrets).unwrap();
+    assert_eq!(stored.len(), 3);
+    assert_eq!(stored[0].platform, "bilibili");
+    assert_eq!(stored[0].names, ["SESSDATA"]);
+
+    // A new session picks the cookies up from the database
+    let restarted = ParseContext::new();
+    assert_eq!(cookie_store::load_cookies(&conn, &secrets, &restarted).unwrap(), 3);
+    assert_eq!(restarted.headers("bilibili").get(COOKIE).unwrap(), "SESSDATA=new");
+
+    cookie_store::remove_cookies(&conn, &secrets, &restarted, "weibo").unwrap();
+    assert!(restarted.headers("weibo").get(COOKIE).is_none());
+    assert_eq!(cookie_store::list_cookies(&conn, &secrets).unwrap().len(), 2);
+
+    drop(conn);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&key_path).unwrap();
+}
@@ -0,0 +1,220 @@
+#![cfg(feature = "db")]
+
+use app_lib::db::{open_db, DbPool};
+use app_lib::downloads::{create_download_record, update_download_progress};
+
+#[test]
+fn test_open_db_and_track_download() {
+    let path = std::env::temp_dir().join(format!("vp-test-db-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let conn = open_db(&path).unwrap();
+    let id = create_download_record(&conn, 1, "https://cdn/v.mp4", "title", "", "/tmp/v.mp4", "downloading").unwrap();
+    update_download_progress(&conn, id, 50, 100, "completed").unwrap();
+
+    let (downloaded, total, status): (i64, i64, String) = conn
+        .query_row(
+            "SELECT downloaded_size, total_size, status FROM downloads WHERE id = ?",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!((downloaded, total, status.as_str()), (50, 100, "completed"));
+
+    // Reopening an existing database keeps its rows
+    drop(conn);
+    let conn = open_db(&path).unwrap();
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM downloads", [], |row| row.get(0)).unwrap();
+    assert_eq!(count, 1);
+
+    drop(conn);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_db_adds_resume_columns() {
+    let path = std::env::temp_dir().join(format!("vp-test-db-resume-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    // Downloads table as created before resumable downloads
+    {
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE downloads (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL DEFAULT 0,
+                url TEXT NOT NULL,
+                title TEXT NOT NULL,
+                cover_url TEXT DEFAULT '',
+                file_path TEXT NOT NULL,
+                status TEXT NOT NULL,
+                total_size INTEGER DEFAULT 0,
+                downloaded_size INTEGER DEFAULT 0,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            INSERT INTO downloads (user_id, url, title, file_path, status) VALUES (1, 'https://cdn/v.mp4', 't', '/tmp/v.mp4', 'failed');",
+        )
+        .unwrap();
+    }
+
+    let conn = open_db(&path).unwrap();
+    let validators = app_lib::downloads::get_download_validators(&conn, 1).unwrap();
+    assert_eq!(validators, app_lib::downloader::Validators::default());
+
+    drop(conn);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_db_upgrades_unversioned_schema_without_losing_rows() {
+    use app_lib::db::{backup_path, schema_version, SCHEMA_VERSION};
+
+    let path = std::env::temp_dir().join(format!("vp-test-db-upgrade-{}.db", std::process::id()));
+    let backup = backup_path(&path, 0);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&backup);
+
+    // Favorites and downloads as created before accounts existed
+    {
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE favorites (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL UNIQUE,
+                title TEXT NOT NULL,
+                platform TEXT NOT NULL,
+                cover_url TEXT DEFAULT '',
+                author_name TEXT DEFAULT '',
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE users (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                username TEXT NOT NULL UNIQUE,
+                password TEXT NOT NULL,
+                email TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE downloads (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL,
+                title TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                status TEXT NOT NULL
+            );
+            INSERT INTO favorites (url, title, platform) VALUES ('https://b23.tv/x', 'Video', 'bilibili');
+            INSERT INTO users (username, password, email) VALUES ('alice', 'pw', 'a@example.com');
+            INSERT INTO downloads (url, title, file_path, status) VALUES ('https://cdn/v.mp4', 't', '/tmp/v.mp4', 'completed');",
+        )
+        .unwrap();
+    }
+
+    let conn = open_db(&path).unwrap();
+    assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+
+    let favorites = app_lib::favorites::list_favorites(&conn, 0, None).unwrap();
+    assert_eq!(favorites.len(), 1);
+    assert_eq!(favorites[0].title, "Video");
+    let downloads = app_lib::downloads::list_downloads(&conn, 0).unwrap();
+    assert_eq!(downloads.len(), 1);
+    assert_eq!((downloads[0].status.as_str(), downloads[0].audio_url.as_str()), ("completed", ""));
+    let recovery: String = conn
+        .query_row("SELECT recovery_code FROM users WHERE username = 'alice'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(recovery, "");
+
+    // The copy taken before migrating still has the old schema and rows
+    {
+        let old = rusqlite::Connection::open(&backup).unwrap();
+        assert_eq!(schema_version(&old).unwrap(), 0);
+        let count: i64 = old.query_row("SELECT COUNT(*) FROM favorites", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    // An up-to-date database is neither migrated nor backed up again
+    std::fs::remove_file(&backup).unwrap();
+    drop(conn);
+    let conn = open_db(&path).unwrap();
+    assert!(!backup.exists());
+    assert_eq!(app_lib::favorites::list_favorites(&conn, 0, None).unwrap().len(), 1);
+
+    drop(conn);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_db_fresh_database_is_not_backed_up() {
+    use app_lib::db::{backup_path, schema_version, SCHEMA_VERSION};
+
+    let path = std::env::temp_dir().join(format!("vp-test-db-fresh-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let conn = open_db(&path).unwrap();
+    assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    assert!(!backup_path(&path, 0).exists());
+
+    drop(conn);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_db_refuses_newer_schema() {
+    use app_lib::db::SCHEMA_VERSION;
+
+    let path = std::env::temp_dir().join(format!("vp-test-db-newer-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    {
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+    }
+
+    let err = open_db(&path).unwrap_err().to_string();
+    assert!(err.contains("newer than this build"), "{}", err);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_pool_shares_wal_database() {
+    let path = std::env::temp_dir().join(format!("vp-test-db-pool-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let pool = DbPool::open(&path, 2).unwrap();
+
+    let mode: String = pool.get().unwrap().query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+    assert_eq!(mode, "wal");
+
+    // A reader is not blocked by another connection's open write transaction
+    let mut writer = pool.get().unwrap();
+    let tx = writer.transaction().unwrap();
+    create_download_record(&tx, 1, "https://cdn/v.mp4", "title", "", "/tmp/v.mp4", "queued").unwrap();
+    let count = pool
+        .run(|conn| conn.query_row("SELECT COUNT(*) FROM downloads", [], |row| row.get::<_, i64>(0)).map_err(|e| e.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+    tx.commit().unwrap();
+    drop(writer);
+
+    // Many more tasks than connections all get their turn
+    let tasks: Vec<_> = (0..16)
+        .map(|i| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                pool.run(move |conn| {
+                    create_download_record(conn, 1, &format!("https://cdn/{}.mp4", i), "t", "", "", "queued")
+                        .map_err(|e| e.to_string())
+                })
+                .await
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+    let count: i64 = pool.get().unwrap().query_row("SELECT COUNT(*) FROM downloads", [], |row| row.get(0)).unwrap();
+    assert_eq!(count, 17);
+
+    drop(pool);
+    let _ = std::fs::remove_file(path.with_extension("db-wal"));
+    let _ = std::fs::remove_file(path.with_extension("db-shm"));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -1,10 +1,12 @@
 use app_lib::parser::douyin::DouYin;
+use app_lib::parser::ParseContext;
 use app_lib::models::VideoParseInfo;
-use anyhow::Result;
+use app_lib::parser::Result;
 
 #[tokio::test]
+#[ignore = "requires network"]
 async fn test_douyin_parsing() {
-    let res: Result<VideoParseInfo> = DouYin::parse_share_url("https://v.douyin.com/aiozpqHvSIg/").await;
+    let res: Result<VideoParseInfo> = DouYin::parse_share_url(&ParseContext::new(), "https://v.douyin.com/aiozpqHvSIg/").await;
     println!("RESULT: {:?}", res);
     assert!(res.is_ok());
 }
@@ -2,6 +2,7 @@ use reqwest::Client;
 use anyhow::Result;
 
 #[tokio::test]
+#[ignore = "requires network"]
 async fn test_redirect() -> Result<()> {
     let client = Client::builder()
         .redirect(reqwest::redirect::Policy::none())
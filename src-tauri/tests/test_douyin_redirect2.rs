@@ -2,6 +2,7 @@ use reqwest::{Client, header};
 use anyhow::Result;
 
 #[tokio::test]
+#[ignore = "requires network"]
 async fn test_redirect_real_browser() -> Result<()> {
     let mut headers = header::HeaderMap::new();
     headers.insert(header::USER_AGENT, header::HeaderValue::from_static("Mozilla/5.0 (iPhone; CPU iPhone OS 16_6 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.6 Mobile/15E148 Safari/604.1"));
@@ -1,11 +1,13 @@
 use app_lib::parser::douyin::DouYin;
+use app_lib::parser::ParseContext;
 use app_lib::models::VideoParseInfo;
-use anyhow::Result;
+use app_lib::parser::Result;
 
 #[tokio::test]
+#[ignore = "requires network"]
 async fn test_douyin_video_parsing() {
     let url = "https://v.douyin.com/iANyYmXn/";
-    let res: Result<VideoParseInfo> = DouYin::parse_share_url(url).await;
+    let res: Result<VideoParseInfo> = DouYin::parse_share_url(&ParseContext::new(), url).await;
     println!("RESULT: {:?}", res);
     assert!(res.is_ok());
     let info = res.unwrap();
@@ -1,8 +1,10 @@
 use app_lib::parser::douyin::DouYin;
+use app_lib::parser::ParseContext;
 
 #[tokio::test]
+#[ignore = "requires network"]
 async fn test_parse_id() {
-    let res = DouYin::parse_video_id("7464082269229042971").await;
+    let res = DouYin::parse_video_id(&ParseContext::new(), "7464082269229042971").await;
     println!("RESULT: {:?}", res);
     assert!(res.is_ok());
     let info = res.unwrap();
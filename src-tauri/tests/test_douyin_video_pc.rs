@@ -1,9 +1,11 @@
 use app_lib::parser::douyin::DouYin;
+use app_lib::parser::ParseContext;
 
 #[tokio::test]
+#[ignore = "requires network"]
 async fn test_pc_url() {
     let url = "https://www.douyin.com/video/7464082269229042971";
-    let res = DouYin::parse_share_url(url).await;
+    let res = DouYin::parse_share_url(&ParseContext::new(), url).await;
     println!("RESULT: {:?}", res);
     assert!(res.is_ok());
 }
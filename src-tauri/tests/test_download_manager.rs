@@ -0,0 +1,263 @@
+#![cfg(feature = "db")]
+
+mod common;
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use app_lib::db::{open_db, DbPool};
+use app_lib::download_manager::DownloadManager;
+use app_lib::downloads::{get_download, DownloadProgress};
+use app_lib::subtitles::ExportFormat;
+use common::{temp_dir, MockResponse, MockServer};
+
+fn manager(db: &Path, max_concurrent: usize) -> (DownloadManager, Arc<Mutex<Vec<DownloadProgress>>>) {
+    let events: Arc<Mutex<Vec<DownloadProgress>>> = Arc::default();
+    let sink = events.clone();
+    let manager = DownloadManager::new(DbPool::open(db, 4).unwrap(), reqwest::Client::new(), max_concurrent, move |p| {
+        sink.lock().unwrap().push(p.clone())
+    });
+    (manager, events)
+}
+
+fn status(db: &Path, id: i64) -> String {
+    get_download(&open_db(db).unwrap(), id).unwrap().unwrap().status
+}
+
+async fn wait_for(db: &Path, id: i64, expected: &str) {
+    for _ in 0..200 {
+        if status(db, id) == expected {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("download {} never reached {}, still {}", id, expected, status(db, id));
+}
+
+#[tokio::test]
+async fn test_queue_pause_resume_cancel_retry() {
+    let dir = temp_dir("queue");
+    let db = dir.join("app.db");
+    let source = MockServer::start().await;
+    source.mock("GET", "/slow.mp4", MockResponse::ok(vec![1u8; 4096]).stalled_after(1024));
+    source.mock("GET", "/fast.mp4", MockResponse::ok(vec![2u8; 2048]));
+    let (manager, events) = manager(&db, 1);
+
+    let slow_path = dir.join("slow.mp4");
+    let slow = manager
+        .enqueue(1, &source.url("/slow.mp4"), None, slow_path.to_str().unwrap(), "slow", "")
+        .await
+        .unwrap();
+    let fast = manager
+        .enqueue(1, &source.url("/fast.mp4"), None, dir.join("fast.mp4").to_str().unwrap(), "fast", "")
+        .await
+        .unwrap();
+
+    // Only one worker: the second download waits behind the stalled first one
+    wait_for(&db, slow, "downloading").await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(status(&db, fast), "queued");
+    assert_eq!(manager.active_count().await, 1);
+
+    manager.pause(slow).await.unwrap();
+    wait_for(&db, slow, "paused").await;
+    wait_for(&db, fast, "completed").await;
+    assert_eq!(std::fs::metadata(dir.join("fast.mp4")).unwrap().len(), 2048);
+    assert!(manager.pause(fast).await.is_err());
+
+    manager.resume(slow).await.unwrap();
+    wait_for(&db, slow, "downloading").await;
+    manager.cancel(slow).await.unwrap();
+    wait_for(&db, slow, "cancelled").await;
+    assert!(!slow_path.exists());
+    assert!(manager.resume(slow).await.is_err());
+
+    manager.retry(slow).await.unwrap();
+    wait_for(&db, slow, "downloading").await;
+    manager.cancel(slow).await.unwrap();
+    wait_for(&db, slow, "cancelled").await;
+
+    let slow_events: Vec<String> = events
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|p| p.id == slow)
+        .map(|p| p.status.clone())
+        .fold(Vec::new(), |mut acc, s| {
+            if acc.last() != Some(&s) {
+                acc.push(s);
+            }
+            acc
+        });
+    assert_eq!(
+        slow_events,
+        [
+            "queued", "downloading", "paused", "queued", "downloading", "cancelled", "queued", "downloading",
+            "cancelled"
+        ]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_resume_continues_partial_file() {
+    let dir = temp_dir("resume");
+    let db = dir.join("app.db");
+    let source = MockServer::start().await;
+    let body: Vec<u8> = (0..8192u32).map(|i| (i % 256) as u8).collect();
+    let stalled = MockResponse::ok(body.clone()).with_header("ETag", "\"abc\"").with_ranges().stalled_after(3000);
+    source.mock("GET", "/v.mp4", stalled);
+    let (manager, _) = manager(&db, 1);
+
+    let path = dir.join("v.mp4");
+    let id = manager.enqueue(1, &source.url("/v.mp4"), None, path.to_str().unwrap(), "v", "").await.unwrap();
+    for _ in 0..200 {
+        if std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0) == 3000 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    manager.pause(id).await.unwrap();
+    wait_for(&db, id, "paused").await;
+
+    source.mock("GET", "/v.mp4", MockResponse::ok(body.clone()).with_header("ETag", "\"abc\"").with_ranges());
+    manager.resume(id).await.unwrap();
+    wait_for(&db, id, "completed").await;
+
+    assert_eq!(std::fs::read(&path).unwrap(), body);
+    // Too small to split: each attempt is a range probe plus one transfer
+    let ranges: Vec<_> = source.requests().iter().map(|r| r.header("range").map(str::to_string)).collect();
+    assert_eq!(ranges.len(), 4);
+    assert_eq!(ranges[3].as_deref(), Some("bytes=3000-"));
+    let record = get_download(&open_db(&db).unwrap(), id).unwrap().unwrap();
+    assert_eq!((record.downloaded_size, record.total_size), (8192, 8192));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_progress_is_saved_in_batches() {
+    let dir = temp_dir("progress");
+    let db = dir.join("app.db");
+    let source = MockServer::start().await;
+    source.mock("GET", "/v.mp4", MockResponse::ok(vec![3u8; 4096]).stalled_after(1024));
+    let (manager, events) = manager(&db, 1);
+
+    let id = manager.enqueue(1, &source.url("/v.mp4"), None, dir.join("v.mp4").to_str().unwrap(), "v", "").await.unwrap();
+    for _ in 0..200 {
+        if events.lock().unwrap().iter().any(|p| p.id == id && p.downloaded == 1024) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // Chunks only reach the database when progress is flushed
+    let record = get_download(&open_db(&db).unwrap(), id).unwrap().unwrap();
+    assert_eq!((record.status.as_str(), record.downloaded_size), ("downloading", 0));
+    manager.flush_progress().await.unwrap();
+    let record = get_download(&open_db(&db).unwrap(), id).unwrap().unwrap();
+    assert_eq!((record.downloaded_size, record.total_size), (1024, 4096));
+
+    // A flush after the download stopped leaves its final numbers alone
+    manager.cancel(id).await.unwrap();
+    wait_for(&db, id, "cancelled").await;
+    manager.flush_progress().await.unwrap();
+    let record = get_download(&open_db(&db).unwrap(), id).unwrap().unwrap();
+    assert_eq!(record.status, "cancelled");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_queue_survives_restart_and_limits_workers() {
+    let dir = temp_dir("restart");
+    let db = dir.join("app.db");
+    let source = MockServer::start().await;
+    source.mock("GET", "/v.mp4", MockResponse::ok(vec![3u8; 512]));
+    source.mock("GET", "/stall.mp4", MockResponse::ok(vec![3u8; 512]).stalled_after(10));
+
+    // A download left running by a previous session and one still queued
+    {
+        let conn = open_db(&db).unwrap();
+        for (name, status) in [("a", "downloading"), ("b", "queued")] {
+            app_lib::downloads::create_download_record(
+                &conn,
+                1,
+                &source.url("/v.mp4"),
+                name,
+                "",
+                dir.join(format!("{}.mp4", name)).to_str().unwrap(),
+                status,
+            )
+            .unwrap();
+        }
+    }
+
+    let (manager, _) = manager(&db, 2);
+    manager.start().await.unwrap();
+    wait_for(&db, 1, "completed").await;
+    wait_for(&db, 2, "completed").await;
+
+    let mut stalled = Vec::new();
+    for i in 0..3 {
+        let path = dir.join(format!("stall-{}.mp4", i));
+        let id = manager.enqueue(1, &source.url("/stall.mp4"), None, path.to_str().unwrap(), "stall", "").await;
+        stalled.push(id.unwrap());
+    }
+    wait_for(&db, stalled[1], "downloading").await;
+    assert_eq!(manager.active_count().await, 2);
+    assert_eq!(status(&db, stalled[2]), "queued");
+
+    manager.set_max_concurrent(3).await;
+    wait_for(&db, stalled[2], "downloading").await;
+    for id in stalled {
+        manager.cancel(id).await.unwrap();
+        wait_for(&db, id, "cancelled").await;
+    }
+    assert_eq!(manager.active_count().await, 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_export_download_is_converted() {
+    let dir = temp_dir("export");
+    let db = dir.join("app.db");
+    let source = MockServer::start().await;
+    source.mock("GET", "/list.so", MockResponse::ok(common::fixture("bilibili/danmaku.xml")));
+    let (manager, _) = manager(&db, 1);
+
+    let path = dir.join("v.danmaku.ass");
+    let id = manager
+        .enqueue_export(1, &source.url("/list.so?oid=62131"), ExportFormat::Ass, path.to_str().unwrap(), "v", "")
+        .await
+        .unwrap();
+    wait_for(&db, id, "completed").await;
+
+    assert!(std::fs::read_to_string(&path).unwrap().starts_with("[Script Info]\n"));
+    let record = get_download(&open_db(&db).unwrap(), id).unwrap().unwrap();
+    assert_eq!(record.export_format, "ass");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_waiting_for_a_connection_does_not_block_the_runtime() {
+    let dir = temp_dir("busy-pool");
+    let db = dir.join("app.db");
+    let pool = DbPool::open(&db, 1).unwrap();
+    let manager = DownloadManager::new(pool.clone(), reqwest::Client::new(), 1, |_| {});
+
+    // The only connection is handed back by a task on this single-threaded
+    // runtime, which can only run while the manager waits asynchronously
+    let busy = pool.get().unwrap();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(busy);
+    });
+    let id = manager.enqueue(1, "http://127.0.0.1:9/v.mp4", None, dir.join("v.mp4").to_str().unwrap(), "v", "").await;
+    assert!(id.is_ok(), "{:?}", id);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,307 @@
+mod common;
+
+use app_lib::downloader::{
+    download_resumable, download_segmented, download_to_file, sanitize_file_name, video_url_for_quality, Validators,
+    MIN_SEGMENT_SIZE,
+};
+use app_lib::parser::douyin::DouYin;
+use common::{fixture, MockResponse, MockServer};
+
+#[tokio::test]
+async fn test_download_to_file_reports_progress() {
+    let server = MockServer::start().await;
+    let body = vec![7u8; 64 * 1024];
+    server.mock("GET", "/video.mp4", MockResponse::ok(body.clone()));
+
+    let dir = std::env::temp_dir().join(format!("vp-download-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("video.mp4");
+
+    let mut last = (0, None);
+    let written = download_to_file(&reqwest::Client::new(), &server.url("/video.mp4"), &path, |done, total| {
+        last = (done, total);
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(written, body.len() as u64);
+    assert_eq!(last, (body.len() as u64, Some(body.len() as u64)));
+    assert_eq!(std::fs::read(&path).unwrap(), body);
+    assert!(server.requests()[0].header("user-agent").is_some());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_download_to_file_rejects_error_status() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/gone.mp4", MockResponse::status(404));
+
+    let path = std::env::temp_dir().join(format!("vp-gone-{}.mp4", std::process::id()));
+    let result = download_to_file(&reqwest::Client::new(), &server.url("/gone.mp4"), &path, |_, _| {}).await;
+
+    assert!(result.is_err());
+    assert!(!path.exists());
+}
+
+fn body() -> Vec<u8> {
+    (0..10_000u32).map(|i| (i % 251) as u8).collect()
+}
+
+fn validators(etag: &str, total: u64) -> Validators {
+    Validators { etag: Some(etag.to_string()), last_modified: None, total: Some(total) }
+}
+
+// Writes the first `len` bytes of `body()` as if an earlier attempt was interrupted
+fn partial_file(name: &str, len: usize) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("vp-resume-{}-{}.mp4", name, std::process::id()));
+    std::fs::write(&path, &body()[..len]).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn test_download_resumes_with_range() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/v.mp4", MockResponse::ok(body()).with_header("ETag", "\"v1\"").with_ranges());
+    let path = partial_file("range", 4000);
+
+    let mut started = None;
+    let mut first_progress = None;
+    let written = download_resumable(
+        &reqwest::Client::new(),
+        &server.url("/v.mp4"),
+        &path,
+        Some(&validators("\"v1\"", 10_000)),
+        |v| started = Some(v.clone()),
+        |done, _| {
+            first_progress.get_or_insert(done);
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(written, 10_000);
+    assert_eq!(std::fs::read(&path).unwrap(), body());
+    assert_eq!(started, Some(validators("\"v1\"", 10_000)));
+    assert!(first_progress.unwrap() > 4000);
+    let req = &server.requests()[0];
+    assert_eq!(req.header("range"), Some("bytes=4000-"));
+    assert_eq!(req.header("if-range"), Some("\"v1\""));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_download_restarts_when_file_changed() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/v.mp4", MockResponse::ok(body()).with_header("ETag", "\"v2\"").with_ranges());
+    // Garbage from an older version of the file must not survive
+    let path = partial_file("changed", 4000);
+    std::fs::write(&path, vec![0u8; 4000]).unwrap();
+
+    let mut started = None;
+    let written = download_resumable(
+        &reqwest::Client::new(),
+        &server.url("/v.mp4"),
+        &path,
+        Some(&validators("\"v1\"", 10_000)),
+        |v| started = Some(v.clone()),
+        |_, _| {},
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(written, 10_000);
+    assert_eq!(std::fs::read(&path).unwrap(), body());
+    assert_eq!(started.unwrap().etag.as_deref(), Some("\"v2\""));
+    assert_eq!(server.requests().len(), 1);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_download_restarts_without_range_support_or_validators() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/v.mp4", MockResponse::ok(body()).with_header("ETag", "\"v1\""));
+    let client = reqwest::Client::new();
+
+    // Server answers 200 to the range request: the body replaces the partial file
+    let path = partial_file("no-ranges", 4000);
+    let prev = validators("\"v1\"", 10_000);
+    download_resumable(&client, &server.url("/v.mp4"), &path, Some(&prev), |_| {}, |_, _| {})
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), body());
+
+    // Nothing to validate against: no range is attempted
+    std::fs::write(&path, &body()[..4000]).unwrap();
+    let weak = Validators { etag: Some("W/\"v1\"".to_string()), last_modified: None, total: Some(10_000) };
+    download_resumable(&client, &server.url("/v.mp4"), &path, Some(&weak), |_| {}, |_, _| {})
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), body());
+    assert!(server.requests()[1].header("range").is_none());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_download_already_complete() {
+    let server = MockServer::start().await;
+    server.mock(
+        "GET",
+        "/v.mp4",
+        MockResponse::ok(body()).with_header("Last-Modified", "Wed, 01 Jan 2025 00:00:00 GMT").with_ranges(),
+    );
+    let path = partial_file("complete", 10_000);
+    let prev = Validators {
+        etag: None,
+        last_modified: Some("Wed, 01 Jan 2025 00:00:00 GMT".to_string()),
+        total: Some(10_000),
+    };
+
+    let written = download_resumable(&reqwest::Client::new(), &server.url("/v.mp4"), &path, Some(&prev), |_| {}, |_, _| {})
+        .await
+        .unwrap();
+
+    assert_eq!(written, 10_000);
+    assert_eq!(std::fs::read(&path).unwrap(), body());
+    assert_eq!(server.requests().len(), 1);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+fn large_body() -> Vec<u8> {
+    (0..(3 * MIN_SEGMENT_SIZE + 123)).map(|i| (i % 253) as u8).collect()
+}
+
+fn segment_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("vp-segments-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn test_segmented_download_uses_parallel_ranges() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/big.mp4", MockResponse::ok(large_body()).with_header("ETag", "\"big\"").with_ranges());
+    let dir = segment_dir("parallel");
+    let path = dir.join("big.mp4");
+
+    let mut last = (0, None);
+    let written = download_segmented(&reqwest::Client::new(), &server.url("/big.mp4"), &path, 8, None, |_| {}, |done, total| {
+        last = (done, total);
+    })
+    .await
+    .unwrap();
+
+    let len = large_body().len() as u64;
+    assert_eq!(written, len);
+    assert_eq!(last, (len, Some(len)));
+    assert_eq!(std::fs::read(&path).unwrap(), large_body());
+    // Only the joined file is left behind
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    // A probe, then one range per MiB-sized segment (capped below the 8 asked for)
+    let mut ranges: Vec<String> = server.requests().iter().filter_map(|r| r.header("range").map(str::to_string)).collect();
+    assert_eq!(ranges.remove(0), "bytes=0-0");
+    ranges.sort();
+    let seg = len.div_ceil(3);
+    assert_eq!(
+        ranges,
+        [
+            format!("bytes=0-{}", seg - 1),
+            format!("bytes={}-{}", seg, 2 * seg - 1),
+            format!("bytes={}-{}", 2 * seg, len - 1),
+        ]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_segmented_download_falls_back_without_ranges() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/big.mp4", MockResponse::ok(large_body()));
+    let dir = segment_dir("fallback");
+    let path = dir.join("big.mp4");
+
+    download_segmented(&reqwest::Client::new(), &server.url("/big.mp4"), &path, 4, None, |_| {}, |_, _| {})
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), large_body());
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].header("range").is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_segmented_download_continues_parts() {
+    let server = MockServer::start().await;
+    let full = MockResponse::ok(large_body()).with_header("ETag", "\"big\"").with_ranges();
+    server.mock("GET", "/big.mp4", full.clone().stalled_after(100_000));
+    let dir = segment_dir("continue");
+    let path = dir.join("big.mp4");
+    let client = reqwest::Client::new();
+
+    // Every segment stalls part way; give up as if the app was paused
+    let mut started = None;
+    let interrupted = tokio::time::timeout(
+        std::time::Duration::from_millis(500),
+        download_segmented(&client, &server.url("/big.mp4"), &path, 3, None, |v| started = Some(v.clone()), |_, _| {}),
+    )
+    .await;
+    assert!(interrupted.is_err());
+    assert!(!path.exists());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+    server.mock("GET", "/big.mp4", full);
+    let before = server.requests().len();
+    download_segmented(&client, &server.url("/big.mp4"), &path, 3, started.as_ref(), |_| {}, |_, _| {})
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), large_body());
+    let seg = (large_body().len() as u64).div_ceil(3);
+    let mut starts: Vec<u64> = server.requests()[before + 1..]
+        .iter()
+        .map(|r| {
+            assert_eq!(r.header("if-range"), Some("\"big\""));
+            let range = r.header("range").unwrap();
+            range["bytes=".len()..range.find('-').unwrap()].parse().unwrap()
+        })
+        .collect();
+    starts.sort();
+    assert_eq!(starts, [100_000, seg + 100_000, 2 * seg + 100_000]);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_video_url_for_quality() {
+    let mut info = DouYin::parse_video_data_from_json(&fixture("douyin/router_video.json")).unwrap();
+    info.video_qualities = vec![
+        app_lib::models::VideoQuality { quality: "1080p".to_string(), video_url: "https://cdn/1080.mp4".to_string(), size: None, ..Default::default() },
+        app_lib::models::VideoQuality { quality: "720p".to_string(), video_url: "https://cdn/720.mp4".to_string(), size: None, ..Default::default() },
+    ];
+
+    assert_eq!(video_url_for_quality(&info, Some("720P")), Some("https://cdn/720.mp4"));
+    assert_eq!(video_url_for_quality(&info, Some("4k")), None);
+    assert_eq!(video_url_for_quality(&info, None), Some(info.video_url.as_str()));
+
+    info.video_url.clear();
+    assert_eq!(video_url_for_quality(&info, None), Some("https://cdn/1080.mp4"));
+}
+
+#[test]
+fn test_sanitize_file_name() {
+    assert_eq!(sanitize_file_name("a/b:c*d?"), "a_b_c_d_");
+    assert_eq!(sanitize_file_name("  ..  "), "video");
+    assert_eq!(sanitize_file_name("标题 #话题"), "标题 #话题");
+    assert_eq!(sanitize_file_name(&"长".repeat(200)).chars().count(), 80);
+}
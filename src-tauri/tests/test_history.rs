@@ -0,0 +1,107 @@
+#![cfg(feature = "db")]
+
+mod common;
+
+use app_lib::auth::{register_user, session_user};
+use app_lib::history::{delete_all_history, delete_history_entry, list_history, owned_history_entry, record_history};
+use app_lib::parsed_items::{owned_parsed_item, save_parsed_item};
+use app_lib::search::TextQuery;
+use common::{temp_db, video_info};
+use rusqlite::Connection;
+
+fn user(conn: &Connection, name: &str) -> i64 {
+    let session = register_user(conn, name, "pw", &format!("{}@example.com", name)).unwrap();
+    session_user(conn, &session.token).unwrap().id
+}
+
+fn titles(conn: &Connection, user_id: i64, query: &str) -> Vec<String> {
+    list_history(conn, user_id, Some(query), 20, 0)
+        .unwrap()
+        .entries
+        .into_iter()
+        .map(|e| e.title)
+        .collect()
+}
+
+#[test]
+fn test_text_query_splits_indexed_and_short_terms() {
+    let query = TextQuery::parse("  猫咪 \"cute\" cats 5%");
+    assert_eq!(query.fts.as_deref(), Some("\"\"\"cute\"\"\" AND \"cats\""));
+    assert_eq!(query.like, ["%猫咪%", "%5\\%%"]);
+    assert_eq!(query.like_sql(&["a", "b"]), "(a LIKE ? ESCAPE '\\' OR b LIKE ? ESCAPE '\\') AND (a LIKE ? ESCAPE '\\' OR b LIKE ? ESCAPE '\\')");
+    assert_eq!(query.like_params(2).len(), 4);
+    assert!(TextQuery::parse(" \t").is_empty());
+}
+
+#[test]
+fn test_history_is_recorded_searched_and_paged() {
+    let (dir, db) = temp_db("search");
+    let conn = db.get().unwrap();
+    let alice = user(&conn, "alice");
+    let bob = user(&conn, "bob");
+
+    let share = "3.14 复制打开抖音，看看【小猫钓鱼】 https://v.douyin.com/abc/ 05/11";
+    let item = save_parsed_item(&conn, share, &video_info("小猫钓鱼", "douyin", "渔夫")).unwrap();
+    let id = record_history(&conn, alice, share, &video_info("小猫钓鱼", "douyin", "渔夫"), Some(item)).unwrap();
+    record_history(&conn, alice, "https://b23.tv/x", &video_info("Rust async explained", "bilibili", "ferris"), None).unwrap();
+    record_history(&conn, alice, "https://b23.tv/y", &video_info("Cooking with cats", "bilibili", "chef"), None).unwrap();
+    record_history(&conn, bob, "https://b23.tv/z", &video_info("Rust for cats", "bilibili", "bob"), None).unwrap();
+
+    let entry = owned_history_entry(&conn, alice, id).unwrap();
+    assert_eq!(entry.input, share);
+    assert_eq!(entry.url, "https://v.douyin.com/abc/");
+    assert_eq!((entry.platform.as_str(), entry.author_name.as_str()), ("douyin", "渔夫"));
+    // The history entry is enough to open the cached result
+    assert_eq!(owned_parsed_item(&conn, alice, item).unwrap().info.title, "小猫钓鱼");
+    assert!(owned_parsed_item(&conn, bob, item).is_err());
+
+    // Newest first, one page at a time, each user only sees their own
+    let page = list_history(&conn, alice, None, 2, 0).unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.entries.iter().map(|e| e.title.as_str()).collect::<Vec<_>>(), ["Cooking with cats", "Rust async explained"]);
+    let rest = list_history(&conn, alice, Some(""), 2, 2).unwrap();
+    assert_eq!(rest.entries.len(), 1);
+    assert_eq!(rest.entries[0].id, id);
+
+    // Parts of words, authors, platforms, links and short CJK terms all match
+    assert_eq!(titles(&conn, alice, "cat"), ["Cooking with cats"]);
+    assert_eq!(titles(&conn, alice, "ferris"), ["Rust async explained"]);
+    assert_eq!(titles(&conn, alice, "BILIBILI rust"), ["Rust async explained"]);
+    assert_eq!(titles(&conn, alice, "钓鱼"), ["小猫钓鱼"]);
+    assert_eq!(titles(&conn, alice, "v.douyin.com/abc"), ["小猫钓鱼"]);
+    assert!(titles(&conn, alice, "bob").is_empty());
+    assert_eq!(list_history(&conn, alice, Some("cats"), 20, 0).unwrap().total, 1);
+    // Query syntax is searched for, not interpreted
+    assert!(titles(&conn, alice, "\"rust OR* NEAR(").is_empty());
+
+    // Deleting keeps the index in step
+    assert_eq!(delete_history_entry(&conn, bob, id).unwrap_err(), format!("History entry {} not found", id));
+    delete_history_entry(&conn, alice, id).unwrap();
+    assert!(titles(&conn, alice, "钓鱼").is_empty());
+    assert_eq!(delete_all_history(&conn, alice).unwrap(), 2);
+    assert!(titles(&conn, alice, "cat").is_empty());
+    assert_eq!(titles(&conn, bob, "cat"), ["Rust for cats"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_history_keeps_the_resolved_link() {
+    let (dir, db) = temp_db("resolved");
+    let conn = db.get().unwrap();
+    let alice = user(&conn, "alice");
+
+    let share = "【Rust async explained】 https://b23.tv/x 复制";
+    let mut info = video_info("Rust async explained", "bilibili", "ferris");
+    info.page_url = Some("https://www.bilibili.com/video/BV1xx411c7mD?p=2".to_string());
+    let id = record_history(&conn, alice, share, &info, None).unwrap();
+
+    let entry = owned_history_entry(&conn, alice, id).unwrap();
+    assert_eq!(entry.url, "https://www.bilibili.com/video/BV1xx411c7mD?p=2");
+    assert_eq!(entry.input, share);
+    // Both the short link and the post it led to find the entry
+    assert_eq!(titles(&conn, alice, "BV1xx411c7mD"), ["Rust async explained"]);
+    assert_eq!(titles(&conn, alice, "b23.tv/x"), ["Rust async explained"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
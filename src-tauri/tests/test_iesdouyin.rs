@@ -1,6 +1,7 @@
-use reqwest::{Client, header};
+use reqwest::Client;
 
 #[tokio::test]
+#[ignore = "requires network"]
 async fn test_iesdouyin_redirect() {
     let client = Client::builder()
         .redirect(reqwest::redirect::Policy::none())
@@ -0,0 +1,283 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use app_lib::downloader::download_muxed;
+use app_lib::mux::mux_av;
+use common::{temp_dir, MockResponse, MockServer};
+
+fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    [&(body.len() as u32 + 8).to_be_bytes()[..], kind, body].concat()
+}
+
+fn full_box(kind: &[u8; 4], version: u8, flags: u32, body: &[u8]) -> Vec<u8> {
+    let head = (version as u32) << 24 | flags;
+    mp4_box(kind, &[&head.to_be_bytes()[..], body].concat())
+}
+
+// A single-track fragmented MP4 as served for DASH: ftyp, moov with mvex, then
+// one moof/mdat pair per (decode time, payload). `absolute_offsets` makes the
+// fragments point at their data with an explicit tfhd base_data_offset.
+fn dash_stream(track_id: u32, timescale: u32, duration: u32, fragments: &[(u64, &[u8])], absolute_offsets: bool) -> Vec<u8> {
+    let u32s = |values: &[u32]| values.iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<u8>>();
+    let mvhd = full_box(b"mvhd", 0, 0, &[u32s(&[0, 0, timescale, duration]), vec![0; 76], u32s(&[track_id + 1])].concat());
+    let tkhd = full_box(b"tkhd", 0, 3, &[u32s(&[0, 0, track_id]), vec![0; 68]].concat());
+    let mdhd = full_box(b"mdhd", 0, 0, &u32s(&[0, 0, timescale, 0, 0]));
+    let trak = mp4_box(b"trak", &[tkhd, mp4_box(b"mdia", &mdhd)].concat());
+    let trex = full_box(b"trex", 0, 0, &u32s(&[track_id, 1, 0, 0, 0]));
+    let moov = mp4_box(b"moov", &[mvhd, trak, mp4_box(b"mvex", &trex)].concat());
+
+    let mut out = [mp4_box(b"ftyp", b"iso6\0\0\0\0iso6dash"), moov].concat();
+    for (i, (decode_time, payload)) in fragments.iter().enumerate() {
+        let moof_pos = out.len() as u64;
+        let tfhd = if absolute_offsets {
+            full_box(b"tfhd", 0, 0x1, &[&track_id.to_be_bytes()[..], &moof_pos.to_be_bytes()].concat())
+        } else {
+            full_box(b"tfhd", 0, 0x020000, &track_id.to_be_bytes())
+        };
+        let tfdt = full_box(b"tfdt", 1, 0, &decode_time.to_be_bytes());
+        // data offset = moof size + mdat header; the moof is 96 or 88 bytes
+        let moof_len = if absolute_offsets { 96 } else { 88 };
+        let trun = full_box(b"trun", 0, 0x1, &u32s(&[1, moof_len + 8]));
+        let traf = mp4_box(b"traf", &[tfhd, tfdt, trun].concat());
+        let moof = mp4_box(b"moof", &[full_box(b"mfhd", 0, 0, &(i as u32 + 1).to_be_bytes()), traf].concat());
+        assert_eq!(moof.len() as u32, moof_len);
+        out.extend_from_slice(&moof);
+        out.extend_from_slice(&mp4_box(b"mdat", payload));
+    }
+    out
+}
+
+// (kind, start, end) of the boxes directly inside data[start..end]
+fn boxes(data: &[u8], start: usize, end: usize) -> Vec<([u8; 4], usize, usize)> {
+    let mut out = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        out.push((data[pos + 4..pos + 8].try_into().unwrap(), pos, pos + size));
+        pos += size;
+    }
+    out
+}
+
+fn find(data: &[u8], start: usize, end: usize, kind: &[u8; 4]) -> (usize, usize) {
+    let (_, s, e) = boxes(data, start, end).into_iter().find(|(k, _, _)| k == kind).unwrap();
+    (s, e)
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+fn sample_streams() -> (Vec<u8>, Vec<u8>) {
+    // Video in milliseconds, fragments at 0s and 2s; audio at 48 kHz, at 0s, 1s and 2s
+    let video = dash_stream(1, 1000, 4000, &[(0, b"video-0"), (2000, b"video-1")], false);
+    let audio = dash_stream(1, 48000, 240_000, &[(0, b"audio-0"), (48000, b"audio-1"), (96000, b"audio-2")], true);
+    (video, audio)
+}
+
+#[test]
+fn test_mux_interleaves_fragments_of_both_tracks() {
+    let dir = temp_dir("interleave");
+    let (video, audio) = sample_streams();
+    std::fs::write(dir.join("v.m4s"), &video).unwrap();
+    std::fs::write(dir.join("a.m4s"), &audio).unwrap();
+
+    let size = mux_av(&dir.join("v.m4s"), &dir.join("a.m4s"), &dir.join("out.mp4")).unwrap();
+    let out = std::fs::read(dir.join("out.mp4")).unwrap();
+    assert_eq!(size, out.len() as u64);
+
+    let top = boxes(&out, 0, out.len());
+    let kinds: Vec<&[u8]> = top.iter().map(|(k, _, _)| &k[..]).collect();
+    assert_eq!(&kinds[..2], [b"ftyp", b"moov"]);
+    assert_eq!(kinds.len(), 2 + 5 * 2);
+
+    // Both tracks with fresh IDs; the movie lasts as long as the longer stream
+    let (moov, moov_end) = find(&out, 0, out.len(), b"moov");
+    let (mvhd, _) = find(&out, moov + 8, moov_end, b"mvhd");
+    assert_eq!(u32_at(&out, mvhd + 8 + 16), 5000);
+    assert_eq!(u32_at(&out, mvhd + 8 + 96), 3);
+    let track_ids: Vec<u32> = boxes(&out, moov + 8, moov_end)
+        .into_iter()
+        .filter(|(k, _, _)| k == b"trak")
+        .map(|(_, s, e)| u32_at(&out, find(&out, s + 8, e, b"tkhd").0 + 8 + 12))
+        .collect();
+    assert_eq!(track_ids, [1, 2]);
+    let (mvex, mvex_end) = find(&out, moov + 8, moov_end, b"mvex");
+    let trex_ids: Vec<u32> = boxes(&out, mvex + 8, mvex_end).iter().map(|(_, s, _)| u32_at(&out, s + 12)).collect();
+    assert_eq!(trex_ids, [1, 2]);
+
+    // Fragments ordered by start time, renumbered, data untouched
+    let mut fragments = Vec::new();
+    for pair in top[2..].chunks(2) {
+        let (moof, moof_end) = (pair[0].1, pair[0].2);
+        let (mdat, mdat_end) = (pair[1].1, pair[1].2);
+        let sequence = u32_at(&out, find(&out, moof + 8, moof_end, b"mfhd").0 + 12);
+        let (traf, traf_end) = find(&out, moof + 8, moof_end, b"traf");
+        let (tfhd, _) = find(&out, traf + 8, traf_end, b"tfhd");
+        let track = u32_at(&out, tfhd + 12);
+        if u32_at(&out, tfhd + 8) & 0x1 != 0 {
+            // Explicit base offsets follow the fragment to its new position
+            assert_eq!(u64_at(&out, tfhd + 16), moof as u64);
+        }
+        fragments.push((sequence, track, String::from_utf8(out[mdat + 8..mdat_end].to_vec()).unwrap()));
+    }
+    let expected = [
+        (1, 1, "video-0"),
+        (2, 2, "audio-0"),
+        (3, 2, "audio-1"),
+        (4, 1, "video-1"),
+        (5, 2, "audio-2"),
+    ];
+    let expected: Vec<(u32, u32, String)> = expected.iter().map(|(s, t, d)| (*s, *t, d.to_string())).collect();
+    assert_eq!(fragments, expected);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_mux_rejects_progressive_mp4() {
+    let dir = temp_dir("progressive");
+    let (video, _) = sample_streams();
+    // A plain file with media data but no fragments or moov
+    let plain = [mp4_box(b"ftyp", b"isom\0\0\0\0isom"), mp4_box(b"mdat", b"data")].concat();
+    std::fs::write(dir.join("v.m4s"), &video).unwrap();
+    std::fs::write(dir.join("a.mp4"), &plain).unwrap();
+
+    let err = mux_av(&dir.join("v.m4s"), &dir.join("a.mp4"), &dir.join("out.mp4")).unwrap_err();
+    assert!(format!("{:#}", err).contains("audio stream"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// `data` with the children of its first `kind` box (inside `parent`) rebuilt by `rebuild`
+fn rewrite_box(data: &[u8], parent: (usize, usize), kind: &[u8; 4], rebuild: impl Fn(&[u8], usize, usize) -> Vec<u8>) -> Vec<u8> {
+    let (start, end) = find(data, parent.0, parent.1, kind);
+    [&data[..start], &mp4_box(kind, &rebuild(data, start + 8, end)), &data[end..]].concat()
+}
+
+// The children of data[start..end] without `kind`, then an empty `kind` box
+fn empty_last(data: &[u8], start: usize, end: usize, kind: &[u8; 4]) -> Vec<u8> {
+    let mut body: Vec<u8> =
+        boxes(data, start, end).into_iter().filter(|(k, _, _)| k != kind).flat_map(|(_, s, e)| data[s..e].to_vec()).collect();
+    body.extend_from_slice(&mp4_box(kind, &[]));
+    body
+}
+
+#[test]
+fn test_mux_rejects_truncated_boxes() {
+    let dir = temp_dir("truncated");
+    let (video, audio) = sample_streams();
+    std::fs::write(dir.join("a.m4s"), &audio).unwrap();
+
+    // A full box cut down to its header, at the very end of its parent
+    let no_mvhd = rewrite_box(&video, (0, video.len()), b"moov", |d, s, e| empty_last(d, s, e, b"mvhd"));
+    std::fs::write(dir.join("v.m4s"), &no_mvhd).unwrap();
+    let err = mux_av(&dir.join("v.m4s"), &dir.join("a.m4s"), &dir.join("out.mp4")).unwrap_err();
+    assert!(format!("{:#}", err).contains("mvhd box is too short"), "{:#}", err);
+
+    let no_tfdt = rewrite_box(&video, (0, video.len()), b"moof", |d, s, e| {
+        let (traf, traf_end) = find(d, s, e, b"traf");
+        let others: Vec<u8> = boxes(d, s, e).into_iter().filter(|(k, _, _)| k != b"traf").flat_map(|(_, s, e)| d[s..e].to_vec()).collect();
+        [others, mp4_box(b"traf", &empty_last(d, traf + 8, traf_end, b"tfdt"))].concat()
+    });
+    std::fs::write(dir.join("v.m4s"), &no_tfdt).unwrap();
+    let err = mux_av(&dir.join("v.m4s"), &dir.join("a.m4s"), &dir.join("out.mp4")).unwrap_err();
+    assert!(format!("{:#}", err).contains("tfdt box is too short"), "{:#}", err);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_download_muxed() {
+    let dir = temp_dir("download");
+    let server = MockServer::start().await;
+    let (video, audio) = sample_streams();
+    server.mock("GET", "/v.m4s", MockResponse::ok(video.clone()));
+    server.mock("GET", "/a.m4s", MockResponse::ok(audio.clone()));
+
+    let path = dir.join("out.mp4");
+    let mut last = (0, None);
+    let size = download_muxed(
+        &reqwest::Client::new(),
+        &server.url("/v.m4s"),
+        &server.url("/a.m4s"),
+        &path,
+        4,
+        &HashMap::new(),
+        |_, _| {},
+        |done, total| last = (done, total),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), size);
+    let total = (video.len() + audio.len()) as u64;
+    assert_eq!(last, (total, Some(total)));
+    // Only the muxed file is left behind
+    let names: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(names, ["out.mp4"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_download_muxed_resumes_each_stream() {
+    let dir = temp_dir("resume");
+    let server = MockServer::start().await;
+    let (video, audio) = sample_streams();
+    let tagged = |body: &[u8], etag: &str| MockResponse::ok(body.to_vec()).with_header("ETag", etag).with_ranges();
+    server.mock("GET", "/v.m4s", tagged(&video, "\"v\""));
+    server.mock("GET", "/a.m4s", tagged(&audio, "\"a\"").stalled_after(audio.len() / 2));
+
+    let client = reqwest::Client::new();
+    let (video_url, audio_url) = (server.url("/v.m4s"), server.url("/a.m4s"));
+    let path = dir.join("out.mp4");
+    let started = Mutex::new(HashMap::new());
+    let none = HashMap::new();
+    let first = download_muxed(
+        &client,
+        &video_url,
+        &audio_url,
+        &path,
+        4,
+        &none,
+        |stream, validators| {
+            started.lock().unwrap().insert(stream.to_string(), validators.clone());
+        },
+        |_, _| {},
+    );
+    // The audio transfer hangs, so the attempt is abandoned like a paused job
+    assert!(tokio::time::timeout(Duration::from_millis(500), first).await.is_err());
+    let previous = started.into_inner().unwrap();
+    assert_eq!(previous["video"].etag.as_deref(), Some("\"v\""));
+    assert_eq!(previous["audio"].total, Some(audio.len() as u64));
+
+    server.mock("GET", "/a.m4s", tagged(&audio, "\"a\""));
+    let before = server.requests().len();
+    let mut last = (0, None);
+    download_muxed(&client, &video_url, &audio_url, &path, 4, &previous, |_, _| {}, |done, total| last = (done, total))
+        .await
+        .unwrap();
+
+    let total = (video.len() + audio.len()) as u64;
+    assert_eq!(last, (total, Some(total)));
+    // The finished video is kept and the audio continues where it stopped
+    let resumed = &server.requests()[before..];
+    assert!(resumed.iter().all(|r| r.path() == "/a.m4s"));
+    let ranges: Vec<_> = resumed.iter().filter_map(|r| r.header("range")).collect();
+    assert_eq!(ranges.last(), Some(&format!("bytes={}-", audio.len() / 2).as_str()));
+    let muxed = std::fs::read(&path).unwrap();
+    assert!(muxed.windows(7).any(|w| w == b"audio-2"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "db")]
+
+mod common;
+
+use app_lib::auth::{register_user, session_user};
+use app_lib::downloads::{create_download_record, delete_download, list_downloads, owned_download};
+use app_lib::favorites::{delete_favorite, insert_favorite, is_favorite, list_favorites};
+use common::temp_db;
+
+#[test]
+fn test_favorites_are_scoped_to_their_user() {
+    let (dir, db) = temp_db("favorites");
+    let conn = db.get().unwrap();
+    let alice = register_user(&conn, "alice", "pw", "a@example.com").unwrap();
+    let bob = register_user(&conn, "bob", "pw", "b@example.com").unwrap();
+    let alice_id = session_user(&conn, &alice.token).unwrap().id;
+    let bob_id = session_user(&conn, &bob.token).unwrap().id;
+
+    let fav = insert_favorite(&conn, alice_id, "https://b23.tv/x", "Video", "bilibili", "", "up").unwrap();
+    // The same link is a separate favorite for each user
+    let bobs = insert_favorite(&conn, bob_id, "https://b23.tv/x", "Video", "bilibili", "", "up").unwrap();
+    assert_ne!(fav.id, bobs.id);
+    assert_eq!(insert_favorite(&conn, alice_id, "https://b23.tv/x", "Video", "bilibili", "", "up").unwrap().id, fav.id);
+
+    assert_eq!(list_favorites(&conn, alice_id, None).unwrap().len(), 1);
+    assert!(is_favorite(&conn, bob_id, "https://b23.tv/x").unwrap());
+
+    // Bob cannot remove Alice's favorite, and learns nothing about it
+    let err = delete_favorite(&conn, bob_id, fav.id).unwrap_err();
+    assert_eq!(err, format!("Favorite {} not found", fav.id));
+    assert_eq!(list_favorites(&conn, alice_id, None).unwrap().len(), 1);
+
+    delete_favorite(&conn, alice_id, fav.id).unwrap();
+    assert!(!is_favorite(&conn, alice_id, "https://b23.tv/x").unwrap());
+    assert!(is_favorite(&conn, bob_id, "https://b23.tv/x").unwrap());
+    assert!(delete_favorite(&conn, alice_id, fav.id).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_downloads_are_scoped_to_their_user() {
+    let (dir, db) = temp_db("downloads");
+    let conn = db.get().unwrap();
+    let file = dir.join("v.mp4");
+    std::fs::write(&file, b"video").unwrap();
+
+    let id = create_download_record(&conn, 1, "https://cdn/v.mp4", "title", "", &file.to_string_lossy(), "completed").unwrap();
+    assert_eq!(list_downloads(&conn, 1).unwrap().len(), 1);
+    assert!(list_downloads(&conn, 2).unwrap().is_empty());
+    assert_eq!(owned_download(&conn, 1, id).unwrap().id, id);
+    assert_eq!(owned_download(&conn, 2, id).unwrap_err(), format!("Download {} not found", id));
+
+    // Another user can neither remove the record nor have the file deleted
+    assert!(delete_download(&conn, 2, id, true).is_err());
+    assert!(file.exists());
+    assert_eq!(list_downloads(&conn, 1).unwrap().len(), 1);
+
+    delete_download(&conn, 1, id, true).unwrap();
+    assert!(!file.exists());
+    assert!(list_downloads(&conn, 1).unwrap().is_empty());
+    assert!(delete_download(&conn, 1, id, false).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
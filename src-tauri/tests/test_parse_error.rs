@@ -0,0 +1,39 @@
+use app_lib::parser::ParseError;
+use reqwest::StatusCode;
+
+#[test]
+fn test_parse_error_serializes_code_and_detail() {
+    let err = ParseError::RateLimited("too many requests".to_string());
+    let json = serde_json::to_value(&err).unwrap();
+    assert_eq!(json["code"], "rate_limited");
+    assert_eq!(json["detail"], "too many requests");
+    assert_eq!(json["retryable"], true);
+
+    let err = ParseError::NotFound("deleted".to_string());
+    let json = serde_json::to_value(&err).unwrap();
+    assert_eq!(json["code"], "not_found");
+    assert_eq!(json["retryable"], false);
+}
+
+#[test]
+fn test_parse_error_from_status() {
+    let cases = [
+        (StatusCode::NOT_FOUND, "not_found"),
+        (StatusCode::GONE, "not_found"),
+        (StatusCode::UNAUTHORIZED, "login_required"),
+        (StatusCode::FORBIDDEN, "login_required"),
+        (StatusCode::TOO_MANY_REQUESTS, "rate_limited"),
+        (StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS, "region_blocked"),
+        (StatusCode::BAD_GATEWAY, "network"),
+    ];
+    for (status, code) in cases {
+        assert_eq!(ParseError::from_status(status, "test").code(), code, "{}", status);
+    }
+    assert!(!ParseError::from_status(StatusCode::FORBIDDEN, "test").is_retryable());
+}
+
+#[test]
+fn test_parse_error_from_json_error() {
+    let err: ParseError = serde_json::from_str::<serde_json::Value>("<html>").unwrap_err().into();
+    assert_eq!(err.code(), "layout_changed");
+}
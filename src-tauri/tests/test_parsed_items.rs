@@ -0,0 +1,59 @@
+#![cfg(feature = "db")]
+
+mod common;
+
+use app_lib::auth::{register_user, session_user};
+use app_lib::downloads::{create_download_record, list_downloads};
+use app_lib::favorites::{insert_favorite, list_favorites};
+use app_lib::models::VideoStatistics;
+use app_lib::parsed_items::{find_parsed_item, link_download, owned_parsed_item, save_parsed_item};
+use common::{temp_db, video_info};
+
+#[test]
+fn test_parse_result_is_cached_and_linked() {
+    let (dir, db) = temp_db("linked");
+    let conn = db.get().unwrap();
+    let alice = session_user(&conn, &register_user(&conn, "alice", "pw", "a@example.com").unwrap().token).unwrap().id;
+    let bob = session_user(&conn, &register_user(&conn, "bob", "pw", "b@example.com").unwrap().token).unwrap().id;
+    let url = "https://b23.tv/x";
+
+    // A favorite saved before the link was ever cached gets linked by the next parse
+    let early = insert_favorite(&conn, alice, url, "Video", "bilibili", "", "up").unwrap();
+    assert_eq!(early.parsed_item_id, None);
+
+    let mut info = video_info("Video", "bilibili", "up");
+    info.tags = Some(vec!["music".to_string()]);
+    info.statistics = Some(VideoStatistics { likes: Some(7), views: Some(100), favorites: None, shares: None, comments: None, coins: None });
+    info.create_time = Some(1_700_000_000);
+    let id = save_parsed_item(&conn, url, &info).unwrap();
+    assert_eq!(list_favorites(&conn, alice, None).unwrap()[0].parsed_item_id, Some(id));
+
+    // Everything the parser returned comes back
+    let cached = owned_parsed_item(&conn, alice, id).unwrap();
+    assert_eq!(cached.url, url);
+    assert_eq!(cached.info.tags, Some(vec!["music".to_string()]));
+    assert_eq!(cached.info.statistics.unwrap().likes, Some(7));
+    assert_eq!(cached.info.create_time, Some(1_700_000_000));
+
+    // A newer parse replaces the cached one in place
+    info.title = "Video (edited)".to_string();
+    assert_eq!(save_parsed_item(&conn, url, &info).unwrap(), id);
+    assert_eq!(find_parsed_item(&conn, url).unwrap().unwrap().info.title, "Video (edited)");
+
+    // Items are only handed to users who saved or downloaded them
+    assert_eq!(owned_parsed_item(&conn, bob, id).unwrap_err(), format!("Parsed item {} not found", id));
+    let download = create_download_record(&conn, bob, "https://cdn/x.mp4", "Video", "", "/tmp/x.mp4", "queued").unwrap();
+    link_download(&conn, download, url).unwrap();
+    assert_eq!(list_downloads(&conn, bob).unwrap()[0].parsed_item_id, Some(id));
+    assert_eq!(owned_parsed_item(&conn, bob, id).unwrap().info.title, "Video (edited)");
+
+    // New favorites of a cached link are linked right away
+    assert_eq!(insert_favorite(&conn, bob, url, "Video", "bilibili", "", "up").unwrap().parsed_item_id, Some(id));
+    let other = create_download_record(&conn, bob, "https://cdn/y.mp4", "Other", "", "/tmp/y.mp4", "queued").unwrap();
+    link_download(&conn, other, "https://b23.tv/never-parsed").unwrap();
+    let unlinked = list_downloads(&conn, bob).unwrap().into_iter().find(|d| d.id == other).unwrap();
+    assert_eq!(unlinked.parsed_item_id, None);
+    assert!(find_parsed_item(&conn, "https://b23.tv/never-parsed").unwrap().is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
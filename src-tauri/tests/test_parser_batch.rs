@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use app_lib::models::{Author, VideoParseInfo};
+use app_lib::parser::utils::regexp_match_urls_from_string;
+use app_lib::parser::{ParseContext, ParseError, Parser, ParserRegistry, Result};
+use async_trait::async_trait;
+
+// Stand-in parser that tracks how many parses run at once
+struct SlowParser {
+    in_flight: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Parser for SlowParser {
+    fn platform(&self) -> &'static str {
+        "slow"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["slow.test"]
+    }
+
+    async fn parse(&self, _ctx: &ParseContext, share_text: &str) -> Result<VideoParseInfo> {
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        if share_text.contains("missing") {
+            return Err(ParseError::NotFound(share_text.to_string()));
+        }
+        Ok(VideoParseInfo {
+            author: Author { uid: String::new(), name: String::new(), avatar: String::new() },
+            title: share_text.to_string(),
+            video_url: String::new(),
+            music_url: String::new(),
+            cover_url: String::new(),
+            images: vec![],
+            platform: "slow".to_string(),
+            video_qualities: vec![],
+            statistics: None,
+            tags: None,
+            music_info: None,
+            create_time: None,
+            duration: None,
+            description: None,
+            parts: vec![],
+            subtitles: vec![],
+            danmaku_url: None,
+            page_url: None,
+        })
+    }
+}
+
+#[test]
+fn test_extract_all_urls() {
+    let text = "1. 看看 https://v.douyin.com/abc/ 复制打开\n\
+                2. https://www.bilibili.com/video/BV1xx411c7mD.\n\
+                3. again https://v.douyin.com/abc/\thttps://example.com/x?a=1&b=2";
+    assert_eq!(
+        regexp_match_urls_from_string(text),
+        vec![
+            "https://v.douyin.com/abc/",
+            "https://www.bilibili.com/video/BV1xx411c7mD",
+            "https://example.com/x?a=1&b=2",
+        ]
+    );
+    assert!(regexp_match_urls_from_string("no links here").is_empty());
+}
+
+#[tokio::test]
+async fn test_parse_batch_respects_concurrency() {
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let mut registry = ParserRegistry::new();
+    registry.register(SlowParser { in_flight: in_flight.clone(), peak: peak.clone() });
+
+    let text: String = (0..8)
+        .map(|i| format!("item {} http://slow.test/{}\n", i, i))
+        .chain(["http://slow.test/missing\n".to_string(), "https://unknown.test/x\n".to_string()])
+        .collect();
+
+    let mut items = Vec::new();
+    let summary = registry
+        .parse_batch(&ParseContext::new(), &text, 3, |item| items.push(item))
+        .await;
+
+    assert_eq!(peak.load(Ordering::SeqCst), 3);
+    assert_eq!(summary.urls.len(), 9);
+    assert_eq!(summary.skipped, vec!["https://unknown.test/x"]);
+    assert_eq!((summary.succeeded, summary.failed), (8, 1));
+
+    assert_eq!(items.len(), 9);
+    let failed: Vec<_> = items.iter().filter(|i| i.error.is_some()).collect();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].url, "http://slow.test/missing");
+    assert_eq!(failed[0].index, 8);
+    assert_eq!(failed[0].error.as_ref().unwrap().code(), "not_found");
+    assert!(items.iter().all(|i| i.info.is_some() != i.error.is_some()));
+    // A parser that resolves nothing leaves the link it was given as the page
+    let first = items.iter().find(|i| i.index == 0).unwrap();
+    assert_eq!(first.info.as_ref().unwrap().page_url.as_deref(), Some("http://slow.test/0"));
+}
+
+#[tokio::test]
+async fn test_parse_batch_clamps_concurrency() {
+    let peak = Arc::new(AtomicUsize::new(0));
+    let mut registry = ParserRegistry::new();
+    registry.register(SlowParser { in_flight: Arc::new(AtomicUsize::new(0)), peak: peak.clone() });
+
+    let summary = registry
+        .parse_batch(&ParseContext::new(), "http://slow.test/a http://slow.test/b", 0, |_| {})
+        .await;
+
+    assert_eq!(summary.succeeded, 2);
+    assert_eq!(peak.load(Ordering::SeqCst), 1);
+}
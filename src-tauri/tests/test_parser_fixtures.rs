@@ -0,0 +1,506 @@
+// Offline tests for each parser's pure extraction step, driven by recorded
+// fixtures in tests/fixtures/<platform>/.
+mod common;
+
+use app_lib::parser::bilibili::{BangumiId, Bilibili};
+use app_lib::parser::douyin::DouYin;
+use app_lib::parser::kuaishou::Kuaishou;
+use app_lib::parser::pipixia::PiPiXia;
+use app_lib::parser::weibo::Weibo;
+use app_lib::parser::xhs::Xiaohongshu;
+use app_lib::parser::xigua::XiGua;
+use app_lib::models::StreamKind;
+use common::{fixture, fixture_json};
+
+#[test]
+fn test_douyin_video_from_router_data() {
+    let info = DouYin::parse_video_data_from_json(&fixture("douyin/router_video.json")).unwrap();
+
+    assert_eq!(info.platform, "douyin");
+    assert_eq!(info.title, "周末去海边 #旅行 #日落");
+    assert_eq!(info.author.name, "海边的阿杰");
+    assert_eq!(info.author.uid, "MS4wLjABAAAA_test_sec_uid");
+    // playwm (watermarked) is rewritten to play
+    assert_eq!(
+        info.video_url,
+        "https://aweme.snssdk.com/aweme/v1/play/?video_id=v0200fg10000abc&ratio=720p&line=0"
+    );
+    // webp covers are skipped in favour of jpeg
+    assert_eq!(info.cover_url, "https://p3.douyinpic.com/obj/cover.jpeg");
+    assert!(info.images.is_empty());
+
+    assert_eq!(info.video_qualities.len(), 2);
+    assert_eq!(info.video_qualities[0].quality, "normal_1080_0");
+    assert_eq!(info.video_qualities[0].size, Some(10485760));
+    assert!(!info.video_qualities[0].video_url.contains("playwm"));
+
+    let stats = info.statistics.unwrap();
+    assert_eq!(stats.likes, Some(1200));
+    assert_eq!(stats.comments, Some(42));
+    assert_eq!(info.tags.unwrap(), vec!["旅行", "日落"]);
+    assert_eq!(info.create_time, Some(1737890000));
+    assert_eq!(info.music_info.unwrap().url, "https://sf3.douyinvod.com/obj/music.mp3");
+}
+
+#[test]
+fn test_douyin_note_from_router_data() {
+    let info = DouYin::parse_video_data_from_json(&fixture("douyin/router_note.json")).unwrap();
+
+    assert_eq!(info.title, "今日穿搭分享");
+    assert!(info.video_url.is_empty());
+    assert!(info.video_qualities.is_empty());
+    assert_eq!(info.images.len(), 2);
+    assert_eq!(info.images[0].url, "https://p3.douyinpic.com/tos/img1.jpeg");
+    assert_eq!(info.images[0].live_photo_url, None);
+    assert_eq!(
+        info.images[1].live_photo_url.as_deref(),
+        Some("https://aweme.snssdk.com/live_photo2.mp4")
+    );
+}
+
+#[test]
+fn test_douyin_share_page_router_data() {
+    let router = DouYin::extract_router_data(&fixture("douyin/share_page.html")).unwrap();
+    let info = DouYin::parse_video_data_from_json(&router).unwrap();
+    assert_eq!(info.author.name, "海边的阿杰");
+
+    assert!(DouYin::extract_router_data("<html></html>").is_none());
+    let err = DouYin::parse_video_data_from_json(r#"{"loaderData":{}}"#).unwrap_err();
+    assert_eq!(err.code(), "layout_changed");
+}
+
+#[test]
+fn test_douyin_video_id_from_path() {
+    let cases = [
+        ("https://www.douyin.com/video/7464082269229042971", "7464082269229042971"),
+        ("https://www.iesdouyin.com/share/video/7464082269229042971/?region=CN", "7464082269229042971"),
+        ("https://www.douyin.com/discover?modal_id=7300000000000000001", "7300000000000000001"),
+    ];
+    for (url, id) in cases {
+        assert_eq!(DouYin::parse_video_id_from_path(url).unwrap(), id);
+    }
+}
+
+#[test]
+fn test_weibo_mobile_api_video() {
+    let json = fixture_json("weibo/mobile_status_video.json");
+    let info = Weibo::parse_mobile_api_data(&json["data"]).unwrap();
+
+    assert_eq!(info.platform, "weibo");
+    assert_eq!(info.title, "春天来了#踏青# 分享视频");
+    assert_eq!(info.author.name, "微博用户A");
+    assert_eq!(info.author.avatar, "https://ww1.sinaimg.cn/large/abcdef.jpg");
+    assert_eq!(info.video_url, "https://f.video.weibocdn.com/o0/hd.mp4");
+    assert_eq!(info.cover_url, "https://ww1.sinaimg.cn/large/cover123.jpg");
+    assert!(info.images.is_empty());
+}
+
+#[test]
+fn test_weibo_mobile_api_pics() {
+    let json = fixture_json("weibo/mobile_status_pics.json");
+    let info = Weibo::parse_mobile_api_data(&json["data"]).unwrap();
+
+    assert_eq!(info.title, "三张图");
+    assert!(info.video_url.is_empty());
+    let urls: Vec<&str> = info.images.iter().map(|i| i.url.as_str()).collect();
+    assert_eq!(
+        urls,
+        vec![
+            "https://ww1.sinaimg.cn/large/p1.jpg",
+            "https://ww1.sinaimg.cn/large/p2.jpg",
+            "https://ww1.sinaimg.cn/large/p3.jpg",
+        ]
+    );
+}
+
+#[test]
+fn test_weibo_video_component() {
+    let info = Weibo::parse_component_data(&fixture_json("weibo/video_component.json")).unwrap();
+
+    assert_eq!(info.title, "一段测试视频");
+    assert_eq!(info.author.name, "视频作者");
+    assert_eq!(info.video_url, "https://f.video.weibocdn.com/o0/1080.mp4");
+    assert_eq!(info.cover_url, "https://wx1.sinaimg.cn/orj480/video_cover.jpg");
+
+    let err = Weibo::parse_component_data(&serde_json::json!({"data": {}})).unwrap_err();
+    assert_eq!(err.code(), "not_found");
+}
+
+#[test]
+fn test_weibo_desktop_page() {
+    let info = Weibo::parse_html_page(&fixture("weibo/desktop_page.html")).unwrap();
+
+    assert_eq!(info.title, "桌面版@某人 文本");
+    assert_eq!(info.author.name, "桌面用户");
+    assert_eq!(info.images.len(), 1);
+    assert_eq!(info.images[0].url, "https://wx1.sinaimg.cn/large/d1.jpg");
+
+    let err = Weibo::parse_html_page("<html></html>").unwrap_err();
+    assert_eq!(err.code(), "layout_changed");
+}
+
+#[test]
+fn test_weibo_desktop_page_render_data_layouts() {
+    // Pretty-printed render data spans several lines
+    let html = "<script>var $render_data = [{\n  \"status\": {\n    \"text\": \"多行\",\n    \"user\": {\"screen_name\": \"作者\"}\n  }\n}][0] || {};</script>";
+    let info = Weibo::parse_html_page(html).unwrap();
+    assert_eq!(info.title, "多行");
+    assert_eq!(info.author.name, "作者");
+
+    // Undecodable render data is an error, not a post with no content
+    let err = Weibo::parse_html_page("<script>var $render_data = [{\"status\": }][0] || {};</script>").unwrap_err();
+    assert_eq!(err.code(), "layout_changed");
+}
+
+#[test]
+fn test_kuaishou_photo_page() {
+    let info = Kuaishou::parse_html(&fixture("kuaishou/photo_page.html")).unwrap();
+
+    assert_eq!(info.platform, "kuaishou");
+    assert_eq!(info.title, "快手测试视频");
+    assert_eq!(info.author.name, "快手用户");
+    assert_eq!(info.video_url, "https://txmov2.a.kwimgs.com/upic/video.mp4");
+    assert_eq!(info.cover_url, "https://p2.a.yximgs.com/upic/cover.jpg");
+    assert!(info.images.is_empty());
+}
+
+#[test]
+fn test_kuaishou_atlas_page() {
+    let info = Kuaishou::parse_html(&fixture("kuaishou/atlas_page.html")).unwrap();
+
+    assert_eq!(info.title, "快手图集");
+    assert!(info.video_url.is_empty());
+    let urls: Vec<&str> = info.images.iter().map(|i| i.url.as_str()).collect();
+    assert_eq!(
+        urls,
+        vec![
+            "https://p1.a.yximgs.com/ufile/atlas/1.jpg",
+            "https://p1.a.yximgs.com/ufile/atlas/2.jpg",
+        ]
+    );
+
+    let err = Kuaishou::parse_html("<html></html>").unwrap_err();
+    assert_eq!(err.code(), "layout_changed");
+}
+
+#[test]
+fn test_xhs_image_note() {
+    let info = Xiaohongshu::parse_html(&fixture("xhs/note_images.html")).unwrap();
+
+    assert_eq!(info.platform, "xhs");
+    // Empty title falls back to the first 30 chars of desc
+    assert_eq!(info.title, "这是一篇没有标题的图文笔记，描述会被截断为标题使用的内容，超");
+    assert_eq!(info.author.name, "小红薯A");
+    assert!(info.video_url.is_empty());
+    assert_eq!(info.images.len(), 2);
+    assert!(info.images.iter().all(|i| i.url.starts_with("https://")));
+    assert_eq!(info.cover_url, info.images[0].url);
+}
+
+#[test]
+fn test_xhs_video_note() {
+    let info = Xiaohongshu::parse_html(&fixture("xhs/note_video.html")).unwrap();
+
+    assert_eq!(info.title, "视频笔记标题");
+    assert_eq!(info.video_url, "https://sns-video-bd.xhscdn.com/stream/110/258/01e5.mp4");
+    assert!(info.images.is_empty());
+}
+
+#[test]
+fn test_xhs_captcha_page() {
+    let err = Xiaohongshu::parse_html(&fixture("xhs/captcha_page.html")).unwrap_err();
+    assert_eq!(err.code(), "layout_changed");
+}
+
+#[test]
+fn test_pipixia_video_prefers_author_comment() {
+    let info = PiPiXia::parse_api_response(&fixture_json("pipixia/cell_comment_video.json")).unwrap();
+
+    assert_eq!(info.platform, "pipixia");
+    assert_eq!(info.title, "皮皮虾测试视频");
+    assert_eq!(info.author.uid, "111");
+    assert_eq!(info.author.name, "皮友A");
+    assert_eq!(info.video_url, "https://v3.pipix.com/no_watermark.mp4");
+    assert_eq!(info.cover_url, "https://p3.pipix.com/cover/c.jpeg");
+}
+
+#[test]
+fn test_pipixia_images() {
+    let info = PiPiXia::parse_api_response(&fixture_json("pipixia/cell_comment_images.json")).unwrap();
+
+    assert!(info.video_url.is_empty());
+    assert_eq!(info.images.len(), 2);
+
+    let err = PiPiXia::parse_api_response(&fixture_json("pipixia/cell_comment_empty.json")).unwrap_err();
+    assert_eq!(err.code(), "not_found");
+}
+
+#[test]
+fn test_bilibili_view_and_playurl() {
+    let info = Bilibili::parse_api_responses(
+        &fixture_json("bilibili/view.json"),
+        &fixture_json("bilibili/playurl.json"),
+    )
+    .unwrap();
+
+    assert_eq!(info.platform, "bilibili");
+    assert_eq!(info.title, "字幕君交流场所");
+    assert_eq!(info.author.uid, "2");
+    assert_eq!(info.author.name, "碧诗");
+    assert_eq!(info.cover_url, "http://i0.hdslb.com/bfs/archive/cover.jpg");
+    assert!(info.video_url.starts_with("https://upos-sz-mirrorcos.bilivideo.com/"));
+
+    let stats = info.statistics.unwrap();
+    assert_eq!(stats.views, Some(3210000));
+    assert_eq!(stats.likes, Some(120000));
+    assert_eq!(stats.coins, Some(20000));
+    assert_eq!(stats.favorites, Some(60000));
+    assert_eq!(stats.shares, Some(9000));
+    assert_eq!(stats.comments, Some(180000));
+    assert_eq!(info.create_time, Some(1252458549));
+    assert_eq!(info.duration, Some(2233));
+    assert_eq!(info.description.as_deref(), Some("字幕君交流场所"));
+
+    let tags = Bilibili::parse_tags(&fixture_json("bilibili/tags.json")).unwrap();
+    assert_eq!(tags, ["字幕", "交流"]);
+}
+
+#[test]
+fn test_bilibili_subtitles_and_danmaku() {
+    let info = Bilibili::parse_api_responses(
+        &fixture_json("bilibili/view.json"),
+        &fixture_json("bilibili/playurl.json"),
+    )
+    .unwrap();
+    assert_eq!(info.danmaku_url.as_deref(), Some("https://api.bilibili.com/x/v1/dm/list.so?oid=62131"));
+
+    let tracks = Bilibili::parse_subtitle_tracks(&fixture_json("bilibili/player_v2.json")).unwrap();
+    // The locked track has no URL and is left out
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[0].lang, "zh-CN");
+    assert_eq!(tracks[0].label, "中文（中国）");
+    assert_eq!(tracks[0].url, "https://aisubtitle.hdslb.com/bfs/subtitle/zh-cn.json");
+    assert!(!tracks[0].ai_generated);
+    assert_eq!(tracks[1].lang, "ai-en");
+    assert!(tracks[1].ai_generated);
+}
+
+#[test]
+fn test_bilibili_dash_qualities() {
+    let qualities = Bilibili::parse_dash_qualities(&fixture_json("bilibili/playurl_dash.json")).unwrap();
+    let labels: Vec<&str> = qualities.iter().map(|q| q.quality.as_str()).collect();
+    assert_eq!(
+        labels,
+        ["1080P", "1080P HEVC", "720P", "720P HEVC", "480P", "Audio 192K", "Audio 64K"]
+    );
+
+    let best = &qualities[0];
+    assert_eq!(best.kind, StreamKind::Video);
+    assert_eq!(best.codec.as_deref(), Some("AVC"));
+    assert_eq!((best.width, best.height, best.bitrate), (Some(1920), Some(1080), Some(3100000)));
+    assert!(best.video_url.ends_with("62131-1-100026.m4s?e=ig8"));
+    // Every video stream gets the best AAC track
+    assert!(qualities[..5]
+        .iter()
+        .all(|q| q.audio_url.as_deref().is_some_and(|a| a.contains("62131-1-30280.m4s"))));
+    // base_url is accepted as well as baseUrl
+    assert!(qualities[2].video_url.ends_with("62131-1-100023.m4s?e=ig8"));
+    assert_eq!(qualities[4].codec.as_deref(), Some("AV1"));
+
+    let audio = &qualities[5];
+    assert_eq!(audio.kind, StreamKind::Audio);
+    assert_eq!(audio.audio_url, None);
+    assert_eq!(audio.codec.as_deref(), Some("mp4a.40.2"));
+
+    // html5 responses without DASH streams are reported as a layout change
+    let err = Bilibili::parse_dash_qualities(&fixture_json("bilibili/playurl.json")).unwrap_err();
+    assert_eq!(err.code(), "layout_changed");
+}
+
+#[test]
+fn test_bilibili_multipart_pages() {
+    let view = fixture_json("bilibili/view_multipart.json");
+    let info = Bilibili::parse_part_responses(&view, &fixture_json("bilibili/playurl.json"), Some(2)).unwrap();
+    assert_eq!(info.title, "Rust 入门教程 P2 所有权");
+
+    let parts: Vec<(u32, &str, &str, u64, bool)> = info
+        .parts
+        .iter()
+        .map(|p| (p.index, p.title.as_str(), p.id.as_str(), p.duration, p.current))
+        .collect();
+    assert_eq!(
+        parts,
+        [
+            (1, "安装", "700001", 600, false),
+            (2, "所有权", "700002", 1500, true),
+            (3, "生命周期", "700003", 1500, false),
+        ]
+    );
+    assert_eq!(info.parts[2].url, "https://www.bilibili.com/video/BV1mp4y1X7tK?p=3");
+    // The duration is the part's, not the whole upload's
+    assert_eq!(info.duration, Some(1500));
+
+    // Without ?p= the first part is the one described
+    let info = Bilibili::parse_api_responses(&view, &fixture_json("bilibili/playurl.json")).unwrap();
+    assert_eq!(info.title, "Rust 入门教程 P1 安装");
+    assert!(info.parts[0].current);
+}
+
+#[test]
+fn test_bilibili_collection_episodes() {
+    let info = Bilibili::parse_api_responses(
+        &fixture_json("bilibili/view_season.json"),
+        &fixture_json("bilibili/playurl.json"),
+    )
+    .unwrap();
+    // A single-page video keeps its own title
+    assert_eq!(info.title, "旅行日记 第二集");
+
+    let parts: Vec<(u32, &str, &str, u64, bool)> = info
+        .parts
+        .iter()
+        .map(|p| (p.index, p.url.as_str(), p.id.as_str(), p.duration, p.current))
+        .collect();
+    assert_eq!(
+        parts,
+        [
+            (1, "https://www.bilibili.com/video/BV1se411A7bA", "800001", 360, false),
+            (2, "https://www.bilibili.com/video/BV1se411A7bB", "800002", 420, true),
+            (3, "https://www.bilibili.com/video/BV1se411A7bC", "800003", 95, false),
+        ]
+    );
+
+    // Lone videos list no parts
+    let info = Bilibili::parse_api_responses(&fixture_json("bilibili/view.json"), &fixture_json("bilibili/playurl.json"))
+        .unwrap();
+    assert!(info.parts.is_empty());
+}
+
+#[test]
+fn test_bilibili_bangumi_episode() {
+    let season = fixture_json("bilibili/pgc_season.json");
+    let play = fixture_json("bilibili/pgc_playurl.json");
+    let info = Bilibili::parse_bangumi_responses(&season, &play, BangumiId::Episode(327108)).unwrap();
+
+    assert_eq!(info.platform, "bilibili");
+    assert_eq!(info.title, "测试番剧 第2话 重逢");
+    assert_eq!(info.author.name, "哔哩哔哩番剧");
+    // Episodes without their own cover use the season's
+    assert_eq!(info.cover_url, "http://i0.hdslb.com/bfs/bangumi/season.jpg");
+    assert!(info.video_url.contains("900002-1-80.mp4"));
+    let stats = info.statistics.as_ref().unwrap();
+    assert_eq!((stats.views, stats.coins, stats.comments), (Some(52000000), Some(180000), Some(98000)));
+    assert_eq!(info.tags.as_deref(), Some(&["奇幻".to_string(), "冒险".to_string()][..]));
+    assert_eq!(info.create_time, Some(1712340000));
+    assert_eq!(info.duration, Some(1435));
+    assert_eq!(info.description.as_deref(), Some("一部用于测试的番剧。"));
+
+    let parts: Vec<(&str, &str, u64, bool)> = info
+        .parts
+        .iter()
+        .map(|p| (p.title.as_str(), p.url.as_str(), p.duration, p.current))
+        .collect();
+    assert_eq!(
+        parts,
+        [
+            ("第1话 出发", "https://www.bilibili.com/bangumi/play/ep327107", 1420, false),
+            ("第2话 重逢", "https://www.bilibili.com/bangumi/play/ep327108", 1435, true),
+        ]
+    );
+
+    // A season link starts at its first episode
+    let info = Bilibili::parse_bangumi_responses(&season, &play, BangumiId::Season(33378)).unwrap();
+    assert_eq!(info.title, "测试番剧 第1话 出发");
+
+    let err = Bilibili::parse_bangumi_responses(&season, &play, BangumiId::Episode(1)).unwrap_err();
+    assert_eq!(err.code(), "not_found");
+    let err = Bilibili::parse_bangumi_responses(
+        &season,
+        &fixture_json("bilibili/pgc_playurl_region.json"),
+        BangumiId::Episode(327108),
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), "region_blocked");
+}
+
+#[test]
+fn test_bilibili_bangumi_and_av_links() {
+    let cases = [
+        ("https://www.bilibili.com/bangumi/play/ep327107", Some(BangumiId::Episode(327107))),
+        ("https://m.bilibili.com/bangumi/play/ss33378?from=search", Some(BangumiId::Season(33378))),
+        ("https://www.bilibili.com/bangumi/media/md28229899", None),
+        ("https://www.bilibili.com/video/BV1xx411c7mD", None),
+    ];
+    for (url, id) in cases {
+        assert_eq!(Bilibili::bangumi_id_from_url(url), id, "{}", url);
+    }
+
+    assert_eq!(Bilibili::av_to_bv(170001), "BV17x411w7KC");
+    assert_eq!(Bilibili::av_to_bv(2), "BV1xx411c7mD");
+    // aids past 2^30 use the extended encoding
+    assert_eq!(Bilibili::av_to_bv(1054803170), "BV1mH4y1u7UA");
+    assert_eq!(Bilibili::get_bvid_from_url("https://www.bilibili.com/video/av170001/").unwrap(), "BV17x411w7KC");
+    assert_eq!(Bilibili::get_bvid_from_url("https://m.bilibili.com/video/AV2?p=1").unwrap(), "BV1xx411c7mD");
+}
+
+#[test]
+fn test_bilibili_api_error_codes() {
+    let err = Bilibili::parse_api_responses(
+        &fixture_json("bilibili/view_not_found.json"),
+        &fixture_json("bilibili/playurl.json"),
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), "not_found");
+
+    let err = Bilibili::parse_api_responses(
+        &fixture_json("bilibili/view.json"),
+        &fixture_json("bilibili/playurl_rate_limited.json"),
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), "rate_limited");
+    assert!(err.is_retryable());
+}
+
+#[test]
+fn test_bilibili_bvid_from_url() {
+    let cases = [
+        ("https://www.bilibili.com/video/BV1xx411c7mD", "BV1xx411c7mD"),
+        ("https://www.bilibili.com/video/BV1xx411c7mD/?spm_id_from=333.1007", "BV1xx411c7mD"),
+        ("https://m.bilibili.com/BV1xx411c7mD", "BV1xx411c7mD"),
+    ];
+    for (url, bvid) in cases {
+        assert_eq!(Bilibili::get_bvid_from_url(url).unwrap(), bvid);
+    }
+    assert!(Bilibili::get_bvid_from_url("https://www.bilibili.com/").is_err());
+
+    assert_eq!(Bilibili::page_from_url("https://www.bilibili.com/video/BV1xx411c7mD?p=3&t=10"), Some(3));
+    assert_eq!(Bilibili::page_from_url("https://www.bilibili.com/video/BV1xx411c7mD?p=0"), None);
+    assert_eq!(Bilibili::page_from_url("https://www.bilibili.com/video/BV1xx411c7mD"), None);
+}
+
+#[test]
+fn test_xigua_info_and_vod() {
+    let info = XiGua::parse_api_responses(
+        &fixture_json("xigua/info.json"),
+        Some(&fixture_json("xigua/vod.json")),
+    )
+    .unwrap();
+
+    assert_eq!(info.platform, "xigua");
+    assert_eq!(info.title, "西瓜视频测试");
+    assert_eq!(info.author.name, "西瓜用户");
+    assert_eq!(info.author.uid, "123456");
+    assert_eq!(info.video_qualities.len(), 3);
+    // Highest resolution becomes the default
+    assert_eq!(info.video_url, "https://v3-xg.ixigua.com/1080.mp4");
+}
+
+#[test]
+fn test_xigua_without_vod() {
+    let info = XiGua::parse_api_responses(&fixture_json("xigua/info.json"), None).unwrap();
+    assert!(info.video_url.is_empty());
+    assert!(info.video_qualities.is_empty());
+
+    let err = XiGua::parse_api_responses(&fixture_json("xigua/info_failed.json"), None).unwrap_err();
+    assert_eq!(err.code(), "not_found");
+}
@@ -0,0 +1,202 @@
+// Network steps of the parsers exercised against a local stand-in server.
+mod common;
+
+use app_lib::parser::bilibili::Bilibili;
+use app_lib::parser::kuaishou::Kuaishou;
+use app_lib::parser::pipixia::PiPiXia;
+use app_lib::parser::xhs::Xiaohongshu;
+use app_lib::parser::{ParseContext, ParserRegistry};
+use common::{fixture, fixture_json, MockResponse, MockServer};
+use reqwest::header::{HeaderName, HeaderValue, USER_AGENT};
+
+#[tokio::test]
+async fn test_kuaishou_follows_redirects_to_photo_page() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/f/abc", MockResponse::redirect(&server.url("/fw/long-video/3xabcdef")));
+    server.mock("GET", "/fw/photo/3xabcdef", MockResponse::html(fixture("kuaishou/photo_page.html")));
+
+    let share_text = format!("看看这个作品 {} 复制此链接", server.url("/f/abc"));
+    let info = Kuaishou::parse_share_url(&ParseContext::new(), &share_text).await.unwrap();
+    assert_eq!(info.title, "快手测试视频");
+    assert_eq!(info.page_url, Some(server.url("/fw/photo/3xabcdef")));
+
+    // long-video links are rewritten to the photo page
+    let paths: Vec<String> = server.requests().iter().map(|r| r.path().to_string()).collect();
+    assert_eq!(paths.first().map(|s| s.as_str()), Some("/f/abc"));
+    assert!(paths.iter().all(|p| !p.contains("long-video")));
+    assert!(paths.iter().any(|p| p == "/fw/photo/3xabcdef"));
+}
+
+#[tokio::test]
+async fn test_xhs_share_url() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/explore/64f0000000000000000000bb", MockResponse::html(fixture("xhs/note_video.html")));
+
+    let info = Xiaohongshu::parse_share_url(&ParseContext::new(), &server.url("/explore/64f0000000000000000000bb"))
+        .await
+        .unwrap();
+    assert_eq!(info.title, "视频笔记标题");
+    assert_eq!(info.page_url, Some(server.url("/explore/64f0000000000000000000bb")));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].header("user-agent").is_some());
+}
+
+#[tokio::test]
+async fn test_http_status_maps_to_parse_error() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/gone", MockResponse::status(404));
+    server.mock("GET", "/busy", MockResponse::status(429));
+
+    let err = Xiaohongshu::parse_share_url(&ParseContext::new(), &server.url("/gone")).await.unwrap_err();
+    assert_eq!(err.code(), "not_found");
+
+    let err = Xiaohongshu::parse_share_url(&ParseContext::new(), &server.url("/busy")).await.unwrap_err();
+    assert_eq!(err.code(), "rate_limited");
+    assert!(err.is_retryable());
+}
+
+#[tokio::test]
+async fn test_bilibili_api_base_url_override() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/x/web-interface/view", MockResponse::json(fixture("bilibili/view.json")));
+    server.mock("GET", "/x/player/playurl", MockResponse::json(fixture("bilibili/playurl.json")));
+
+    let ctx = ParseContext::new().with_base_url("https://api.bilibili.com", server.base_url());
+    let info = Bilibili::parse_share_url(&ctx, "https://www.bilibili.com/video/BV1xx411c7mD")
+        .await
+        .unwrap();
+    assert_eq!(info.title, "字幕君交流场所");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 5);
+    assert_eq!(requests[0].target, "/x/web-interface/view?bvid=BV1xx411c7mD");
+    assert!(requests[1].target.contains("cid=62131"));
+    // Tags, DASH streams and subtitles are optional: none failing breaks the parse
+    assert_eq!(requests[2].target, "/x/tag/archive/tags?bvid=BV1xx411c7mD");
+    assert!(info.tags.is_none());
+    assert!(requests[3].target.contains("fnval=4048"));
+    assert!(info.video_qualities.is_empty());
+    assert_eq!(requests[4].target, "/x/player/v2?bvid=BV1xx411c7mD&cid=62131");
+    assert!(info.subtitles.is_empty());
+}
+
+#[tokio::test]
+async fn test_bilibili_dash_qualities_from_api() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/x/web-interface/view", MockResponse::json(fixture("bilibili/view.json")));
+    // One route serves both playurl requests, so give the DASH answer the html5 MP4 too
+    let mut playurl = fixture_json("bilibili/playurl_dash.json");
+    playurl["data"]["durl"] = fixture_json("bilibili/playurl.json")["data"]["durl"].clone();
+    server.mock("GET", "/x/player/playurl", MockResponse::json(playurl.to_string()));
+    server.mock("GET", "/x/tag/archive/tags", MockResponse::json(fixture("bilibili/tags.json")));
+    server.mock("GET", "/x/player/v2", MockResponse::json(fixture("bilibili/player_v2.json")));
+
+    let ctx = ParseContext::new().with_base_url("https://api.bilibili.com", server.base_url());
+    let info = Bilibili::parse_share_url(&ctx, "https://www.bilibili.com/video/BV1xx411c7mD")
+        .await
+        .unwrap();
+    assert_eq!(info.video_qualities.len(), 7);
+    assert_eq!(info.video_qualities[0].quality, "1080P");
+    assert_eq!(info.tags.unwrap(), ["字幕", "交流"]);
+    assert_eq!(info.subtitles.len(), 2);
+}
+
+#[tokio::test]
+async fn test_bilibili_part_from_query() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/x/web-interface/view", MockResponse::json(fixture("bilibili/view_multipart.json")));
+    server.mock("GET", "/x/player/playurl", MockResponse::json(fixture("bilibili/playurl.json")));
+    let ctx = ParseContext::new().with_base_url("https://api.bilibili.com", server.base_url());
+
+    let info = Bilibili::parse_share_url(&ctx, "https://www.bilibili.com/video/BV1mp4y1X7tK?p=3")
+        .await
+        .unwrap();
+    assert_eq!(info.title, "Rust 入门教程 P3 生命周期");
+    assert_eq!(info.page_url.as_deref(), Some("https://www.bilibili.com/video/BV1mp4y1X7tK?p=3"));
+    assert_eq!(info.parts.len(), 3);
+    assert!(server.requests()[1].target.contains("cid=700003"));
+
+    let err = Bilibili::parse_share_url(&ctx, "https://www.bilibili.com/video/BV1mp4y1X7tK?p=9")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), "not_found");
+}
+
+#[tokio::test]
+async fn test_bilibili_bangumi_episode_link() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/pgc/view/web/season", MockResponse::json(fixture("bilibili/pgc_season.json")));
+    server.mock("GET", "/pgc/player/web/playurl", MockResponse::json(fixture("bilibili/pgc_playurl.json")));
+    let ctx = ParseContext::new().with_base_url("https://api.bilibili.com", server.base_url());
+
+    let info = ParserRegistry::with_defaults()
+        .parse(&ctx, "【番剧】 https://www.bilibili.com/bangumi/play/ep327108")
+        .await
+        .unwrap();
+    assert_eq!(info.title, "测试番剧 第2话 重逢");
+    assert_eq!(info.page_url.as_deref(), Some("https://www.bilibili.com/bangumi/play/ep327108"));
+
+    let requests = server.requests();
+    assert_eq!(requests[0].target, "/pgc/view/web/season?ep_id=327108");
+    assert!(requests[1].target.contains("ep_id=327108&cid=900002"));
+}
+
+#[tokio::test]
+async fn test_pipixia_redirect_and_api() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/s/abc", MockResponse::redirect("https://h5.pipix.com/item/7000000000000000001"));
+    server.mock(
+        "GET",
+        "/bds/cell/cell_comment/",
+        MockResponse::json(fixture("pipixia/cell_comment_video.json")),
+    );
+
+    let ctx = ParseContext::new().with_base_url("https://api.pipix.com", server.base_url());
+    let info = PiPiXia::parse_share_url(&ctx, &server.url("/s/abc")).await.unwrap();
+    assert_eq!(info.title, "皮皮虾测试视频");
+    assert_eq!(info.page_url.as_deref(), Some("https://h5.pipix.com/item/7000000000000000001"));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].target.contains("cell_id=7000000000000000001"));
+}
+
+#[tokio::test]
+async fn test_platform_headers_replace_defaults() {
+    let server = MockServer::start().await;
+    server.mock("GET", "/x/web-interface/view", MockResponse::json(fixture("bilibili/view.json")));
+    server.mock("GET", "/x/player/playurl", MockResponse::json(fixture("bilibili/playurl.json")));
+
+    let ctx = ParseContext::new()
+        .with_base_url("https://api.bilibili.com/", server.base_url())
+        .with_header("bilibili", USER_AGENT, HeaderValue::from_static("mirror-agent"))
+        .with_header("bilibili", HeaderName::from_static("x-mirror-token"), HeaderValue::from_static("t0k"))
+        .with_header("douyin", HeaderName::from_static("x-other"), HeaderValue::from_static("no"));
+
+    let registry = ParserRegistry::with_defaults();
+    registry
+        .parse(&ctx, "【标题】 https://www.bilibili.com/video/BV1xx411c7mD")
+        .await
+        .unwrap();
+
+    for request in server.requests() {
+        assert_eq!(request.header("user-agent"), Some("mirror-agent"));
+        assert_eq!(request.header("x-mirror-token"), Some("t0k"));
+        assert_eq!(request.header("x-other"), None);
+    }
+}
+
+#[test]
+fn test_context_url_rewrite() {
+    let ctx = ParseContext::new().with_base_url("https://api.bilibili.com", "http://127.0.0.1:8080/mirror/");
+
+    assert_eq!(
+        ctx.url("https://api.bilibili.com/x/web-interface/view?bvid=BV1"),
+        "http://127.0.0.1:8080/mirror/x/web-interface/view?bvid=BV1"
+    );
+    // Only whole origins are rewritten
+    assert_eq!(ctx.url("https://api.bilibili.com.cn/x"), "https://api.bilibili.com.cn/x");
+    assert_eq!(ctx.url("https://m.weibo.cn/statuses/show"), "https://m.weibo.cn/statuses/show");
+}
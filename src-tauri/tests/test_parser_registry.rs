@@ -0,0 +1,51 @@
+use app_lib::parser::utils::regexp_match_url_from_string;
+use app_lib::parser::ParserRegistry;
+
+#[test]
+fn test_registry_matches_share_text() {
+    let registry = ParserRegistry::with_defaults();
+
+    let cases = [
+        ("7.43 复制打开抖音 https://v.douyin.com/aiozpqHvSIg/ abc", "douyin"),
+        ("https://www.iesdouyin.com/share/video/7464082269229042971/", "douyin"),
+        ("http://xhslink.com/a/abcdef", "xhs"),
+        ("https://h5.pipix.com/s/abcdef/", "pipixia"),
+        ("https://m.weibo.cn/status/5011234567890", "weibo"),
+        ("https://v.kuaishou.com/abcdef", "kuaishou"),
+        ("https://b23.tv/abcdef", "bilibili"),
+        ("https://www.bilibili.com/video/BV1xx411c7mD", "bilibili"),
+        ("https://www.ixigua.com/7123456789", "xigua"),
+    ];
+
+    for (text, platform) in cases {
+        let parser = registry.find(text).unwrap_or_else(|| panic!("no parser for {}", text));
+        assert_eq!(parser.platform(), platform, "{}", text);
+    }
+
+    assert!(registry.find("https://example.com/video/1").is_none());
+    assert!(registry.find("https://notdouyin.com/video/1").is_none());
+    assert!(registry.find("no url here").is_none());
+}
+
+#[test]
+fn test_registry_unregister() {
+    let mut registry = ParserRegistry::with_defaults();
+    assert!(registry.unregister("weibo"));
+    assert!(!registry.unregister("weibo"));
+    assert!(!registry.platforms().contains(&"weibo"));
+    assert!(registry.find("https://weibo.com/123/abc").is_none());
+}
+
+#[test]
+fn test_share_url_extraction() {
+    let cases = [
+        ("7.43 复制打开抖音 https://v.douyin.com/aiozpqHvSIg/ 复制此链接", "https://v.douyin.com/aiozpqHvSIg/"),
+        ("看看 https://b23.tv/abcdef?p=2&t=10，好看", "https://b23.tv/abcdef?p=2&t=10"),
+        // An explicit port stays part of the link instead of ending it at the host
+        ("试试 http://127.0.0.1:8080/f/abc 复制", "http://127.0.0.1:8080/f/abc"),
+    ];
+    for (text, url) in cases {
+        assert_eq!(regexp_match_url_from_string(text).as_deref(), Some(url), "{}", text);
+    }
+    assert_eq!(regexp_match_url_from_string("no url here"), None);
+}
@@ -0,0 +1,186 @@
+#![cfg(feature = "db")]
+
+mod common;
+
+use app_lib::auth::{register_user, session_user};
+use app_lib::db::open_db;
+use app_lib::downloads::{create_download_record, delete_download, set_download_platform};
+use app_lib::favorites::{delete_favorite, insert_favorite};
+use app_lib::history::record_history;
+use app_lib::parsed_items::{link_download, save_parsed_item};
+use app_lib::search::{find_in_library, SearchFilters, SearchKind, SearchPage};
+use common::{temp_db, video_info};
+use rusqlite::Connection;
+
+fn user(conn: &Connection, name: &str) -> i64 {
+    let session = register_user(conn, name, "pw", &format!("{}@example.com", name)).unwrap();
+    session_user(conn, &session.token).unwrap().id
+}
+
+fn search(conn: &Connection, user_id: i64, query: &str, filters: &SearchFilters) -> SearchPage {
+    find_in_library(conn, user_id, query, filters, 20, 0).unwrap()
+}
+
+fn hits(page: &SearchPage) -> Vec<(SearchKind, String)> {
+    page.hits.iter().map(|h| (h.kind, h.title.clone())).collect()
+}
+
+#[test]
+fn test_search_finds_favorites_downloads_and_parses_by_tags_and_description() {
+    let (dir, db) = temp_db("library");
+    let conn = db.get().unwrap();
+    let alice = user(&conn, "alice");
+    let bob = user(&conn, "bob");
+
+    let mut cat = video_info("小猫钓鱼", "douyin", "渔夫");
+    cat.tags = Some(vec!["pets".to_string(), "fishing".to_string()]);
+    cat.description = Some("A kitten learns patience by the river".to_string());
+    let cat_item = save_parsed_item(&conn, "https://v.douyin.com/cat/", &cat).unwrap();
+    let fav = insert_favorite(&conn, alice, "https://v.douyin.com/cat/", "小猫钓鱼", "douyin", "", "渔夫").unwrap();
+
+    let mut talk = video_info("Rust in production", "bilibili", "ferris");
+    talk.description = Some("Lessons from shipping async services".to_string());
+    let talk_item = save_parsed_item(&conn, "https://b23.tv/talk", &talk).unwrap();
+    let download = create_download_record(&conn, alice, "https://cdn/talk.mp4", "talk.mp4", "", "/tmp/talk.mp4", "completed").unwrap();
+    link_download(&conn, download, "https://b23.tv/talk").unwrap();
+    record_history(&conn, alice, "https://b23.tv/talk", &talk, Some(talk_item)).unwrap();
+    insert_favorite(&conn, bob, "https://b23.tv/bob", "Rust for bob", "bilibili", "", "bob").unwrap();
+    let all = SearchFilters::default();
+
+    // Tags and description of the linked parse result find the favorite
+    assert_eq!(hits(&search(&conn, alice, "fishing", &all)), [(SearchKind::Favorite, "小猫钓鱼".to_string())]);
+    assert_eq!(hits(&search(&conn, alice, "kitten river", &all)), [(SearchKind::Favorite, "小猫钓鱼".to_string())]);
+    assert_eq!(search(&conn, alice, "钓鱼", &all).hits[0].parsed_item_id, Some(cat_item));
+
+    // The download has the author and description of the page it came from,
+    // and the history brings in the parse result itself
+    let page = search(&conn, alice, "async", &all);
+    assert_eq!(page.total, 2);
+    let kinds: Vec<SearchKind> = page.hits.iter().map(|h| h.kind).collect();
+    assert!(kinds.contains(&SearchKind::Download) && kinds.contains(&SearchKind::Parsed), "{:?}", kinds);
+    let hit = page.hits.iter().find(|h| h.kind == SearchKind::Download).unwrap();
+    assert_eq!((hit.id, hit.platform.as_str(), hit.author_name.as_str()), (download, "bilibili", "ferris"));
+    assert!(page.hits.iter().all(|h| h.score < 0.0));
+
+    // Filters
+    let favorites = SearchFilters { kinds: vec![SearchKind::Favorite], ..Default::default() };
+    assert!(search(&conn, alice, "async", &favorites).hits.is_empty());
+    let douyin = SearchFilters { platform: Some("douyin".to_string()), ..Default::default() };
+    assert_eq!(search(&conn, alice, "", &douyin).total, 1);
+    let future = SearchFilters { since: Some("2999-01-01".to_string()), ..Default::default() };
+    assert_eq!(search(&conn, alice, "", &future).total, 0);
+    let past = SearchFilters { until: Some("2000-01-01".to_string()), ..Default::default() };
+    assert_eq!(search(&conn, alice, "", &past).total, 0);
+    assert_eq!(search(&conn, alice, "", &all).total, 3);
+    let bad = SearchFilters { since: Some("01/02/2024".to_string()), ..Default::default() };
+    assert!(find_in_library(&conn, alice, "", &bad, 20, 0).unwrap_err().contains("YYYY-MM-DD"));
+    assert!(bad.validate().is_err());
+
+    // Paging, and other users' items stay out
+    let first = find_in_library(&conn, alice, "", &all, 2, 0).unwrap();
+    let second = find_in_library(&conn, alice, "", &all, 2, 2).unwrap();
+    assert_eq!((first.hits.len(), second.hits.len(), first.total), (2, 1, 3));
+    assert!(search(&conn, alice, "bob", &all).hits.is_empty());
+    assert_eq!(search(&conn, bob, "rust", &all).total, 1);
+
+    // A new parse updates the indexes of everything linked to it
+    cat.tags = Some(vec!["cats".to_string()]);
+    save_parsed_item(&conn, "https://v.douyin.com/cat/", &cat).unwrap();
+    assert!(search(&conn, alice, "fishing", &all).hits.is_empty());
+    assert_eq!(search(&conn, alice, "cats", &all).total, 1);
+
+    // Removed items leave the index
+    delete_favorite(&conn, alice, fav.id).unwrap();
+    delete_download(&conn, alice, download, false).unwrap();
+    assert!(search(&conn, alice, "cats", &all).hits.is_empty());
+    assert_eq!(hits(&search(&conn, alice, "async", &all)), [(SearchKind::Parsed, "Rust in production".to_string())]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_platform_filter_keeps_downloads_without_a_parse_result() {
+    let (dir, db) = temp_db("platform");
+    let conn = db.get().unwrap();
+    let alice = user(&conn, "alice");
+    // Queued from a page whose parse was never cached, so nothing is linked
+    let download = create_download_record(&conn, alice, "https://cdn/cat.mp4", "Cat video", "", "/tmp/cat.mp4", "completed").unwrap();
+    set_download_platform(&conn, download, "douyin").unwrap();
+    link_download(&conn, download, "https://v.douyin.com/uncached/").unwrap();
+    create_download_record(&conn, alice, "https://cdn/dog.mp4", "Dog video", "", "/tmp/dog.mp4", "completed").unwrap();
+
+    let douyin = SearchFilters { platform: Some("douyin".to_string()), ..Default::default() };
+    let page = search(&conn, alice, "video", &douyin);
+    assert_eq!(hits(&page), [(SearchKind::Download, "Cat video".to_string())]);
+    assert_eq!((page.hits[0].platform.as_str(), page.hits[0].parsed_item_id), ("douyin", None));
+    let bilibili = SearchFilters { platform: Some("bilibili".to_string()), ..Default::default() };
+    assert!(search(&conn, alice, "video", &bilibili).hits.is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_search_interleaves_the_best_matches_of_each_kind() {
+    let (dir, db) = temp_db("ranking");
+    let conn = db.get().unwrap();
+    let alice = user(&conn, "alice");
+    insert_favorite(&conn, alice, "https://a", "Rust rust rust", "bilibili", "", "").unwrap();
+    insert_favorite(&conn, alice, "https://b", "Rust and a long list of other things to watch", "bilibili", "", "").unwrap();
+    create_download_record(&conn, alice, "https://cdn/c.mp4", "Rust async rust", "", "/tmp/c.mp4", "completed").unwrap();
+    create_download_record(&conn, alice, "https://cdn/d.mp4", "Rust talk from a conference far away", "", "/tmp/d.mp4", "completed")
+        .unwrap();
+    // Ties between kinds go to the newest item
+    conn.execute_batch(
+        "UPDATE favorites SET created_at = '2024-05-02 10:00:00';
+         UPDATE downloads SET created_at = '2024-05-01 10:00:00';",
+    )
+    .unwrap();
+
+    // BM25 scores of different indexes are not comparable, so each kind is
+    // ranked on its own and the ranks are merged
+    let page = search(&conn, alice, "rust", &SearchFilters::default());
+    let expected = [
+        (SearchKind::Favorite, "Rust rust rust"),
+        (SearchKind::Download, "Rust async rust"),
+        (SearchKind::Favorite, "Rust and a long list of other things to watch"),
+        (SearchKind::Download, "Rust talk from a conference far away"),
+    ];
+    let expected: Vec<(SearchKind, String)> = expected.iter().map(|(k, t)| (*k, t.to_string())).collect();
+    assert_eq!(hits(&page), expected);
+    let middle = find_in_library(&conn, alice, "rust", &SearchFilters::default(), 2, 1).unwrap();
+    assert_eq!(hits(&middle), expected[1..3]);
+    assert_eq!(middle.total, 4);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_search_indexes_existing_rows_when_upgrading() {
+    let (dir, db) = temp_db("upgrade");
+    let conn = db.get().unwrap();
+    let item = save_parsed_item(&conn, "https://b23.tv/old", &video_info("Old talk", "bilibili", "ferris")).unwrap();
+    // Stand in for a database from before the search indexes and download platforms
+    conn.execute_batch(&format!(
+        "DROP TRIGGER favorites_ai;
+         DELETE FROM favorites_fts;
+         INSERT INTO favorites (user_id, url, title, platform) VALUES (1, 'https://a', 'Old favorite', 'xhs');
+         INSERT INTO downloads (user_id, url, title, file_path, status, parsed_item_id, platform)
+             VALUES (1, 'https://cdn/old.mp4', 'Old download', '/tmp/old.mp4', 'completed', {}, '');
+         DELETE FROM downloads_fts;
+         DELETE FROM parsed_items_fts;
+         PRAGMA user_version = 7;",
+        item
+    ))
+    .unwrap();
+    drop((conn, db));
+
+    let conn = open_db(&dir.join("app.db")).unwrap();
+    let page = search(&conn, 1, "favorite", &SearchFilters::default());
+    assert_eq!(hits(&page), [(SearchKind::Favorite, "Old favorite".to_string())]);
+    assert_eq!(page.total, 1);
+    // Linked downloads take the platform of their parse result
+    let bilibili = SearchFilters { platform: Some("bilibili".to_string()), ..Default::default() };
+    assert_eq!(hits(&search(&conn, 1, "download", &bilibili)), [(SearchKind::Download, "Old download".to_string())]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
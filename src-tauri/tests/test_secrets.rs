@@ -0,0 +1,153 @@
+#![cfg(feature = "db")]
+
+use std::path::PathBuf;
+
+use app_lib::cookie_store;
+use app_lib::db::open_db;
+use app_lib::parser::ParseContext;
+use app_lib::secrets::{FileKeyStore, KeySource, SecretStore};
+use reqwest::header::COOKIE;
+
+fn temp_paths(name: &str) -> (PathBuf, PathBuf) {
+    let db = std::env::temp_dir().join(format!("vp-secrets-{}-{}.db", name, std::process::id()));
+    let key = db.with_extension("key");
+    let _ = std::fs::remove_file(&db);
+    let _ = std::fs::remove_file(&key);
+    (db, key)
+}
+
+fn cleanup(paths: &(PathBuf, PathBuf)) {
+    let _ = std::fs::remove_file(&paths.0);
+    let _ = std::fs::remove_file(&paths.1);
+}
+
+fn file_key(path: &PathBuf) -> KeySource {
+    KeySource::Stored(Box::new(FileKeyStore::new(path)))
+}
+
+#[test]
+fn test_credentials_add_list_rotate_delete() {
+    let paths = temp_paths("crud");
+    let conn = open_db(&paths.0).unwrap();
+    let secrets = SecretStore::unlock(&conn, &file_key(&paths.1)).unwrap();
+
+    let id = secrets.add(&conn, "bilibili", "cookie", "SESSDATA=abc").unwrap();
+    secrets.add(&conn, "weibo", "cookie", "SUB=xyz").unwrap();
+    secrets.add(&conn, "bilibili", "token", "t0ken").unwrap();
+    // Adding again replaces the value and keeps the row
+    assert_eq!(secrets.add(&conn, "bilibili", "cookie", "SESSDATA=def").unwrap(), id);
+    assert_eq!(secrets.get(&conn, "bilibili", "cookie").unwrap().as_deref(), Some("SESSDATA=def"));
+
+    let all: Vec<(String, String)> = secrets.list(&conn, None).unwrap().into_iter().map(|s| (s.platform, s.name)).collect();
+    assert_eq!(
+        all,
+        [
+            ("bilibili".to_string(), "cookie".to_string()),
+            ("bilibili".to_string(), "token".to_string()),
+            ("weibo".to_string(), "cookie".to_string()),
+        ]
+    );
+    assert_eq!(secrets.list(&conn, Some("cookie")).unwrap().len(), 2);
+
+    secrets.rotate(&conn, "weibo", "cookie", "SUB=new").unwrap();
+    assert_eq!(secrets.get(&conn, "weibo", "cookie").unwrap().as_deref(), Some("SUB=new"));
+    assert!(secrets.rotate(&conn, "xhs", "cookie", "x").is_err());
+
+    assert!(secrets.delete(&conn, "bilibili", "token").unwrap());
+    assert!(!secrets.delete(&conn, "bilibili", "token").unwrap());
+    assert_eq!(secrets.get(&conn, "bilibili", "token").unwrap(), None);
+
+    // Only ciphertext reaches the database
+    let blobs: Vec<Vec<u8>> = conn
+        .prepare("SELECT ciphertext FROM secrets")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(blobs.len(), 2);
+    for blob in &blobs {
+        assert!(!blob.windows(3).any(|w| w == b"SUB" || w == b"SES"));
+    }
+
+    // A restart reads the same key back from the key file
+    drop(conn);
+    let conn = open_db(&paths.0).unwrap();
+    let secrets = SecretStore::unlock(&conn, &file_key(&paths.1)).unwrap();
+    assert_eq!(secrets.get(&conn, "bilibili", "cookie").unwrap().as_deref(), Some("SESSDATA=def"));
+
+    drop(conn);
+    cleanup(&paths);
+}
+
+#[test]
+fn test_passphrase_must_match() {
+    let paths = temp_paths("passphrase");
+    let conn = open_db(&paths.0).unwrap();
+    let secrets = SecretStore::unlock(&conn, &KeySource::Passphrase("correct horse".into())).unwrap();
+    secrets.add(&conn, "xhs", "cookie", "web_session=1").unwrap();
+
+    let err = SecretStore::unlock(&conn, &KeySource::Passphrase("battery staple".into())).unwrap_err();
+    assert!(err.contains("Wrong passphrase"), "{}", err);
+    // A key file is no substitute for the passphrase
+    assert!(SecretStore::unlock(&conn, &file_key(&paths.1)).is_err());
+
+    let secrets = SecretStore::unlock(&conn, &KeySource::Passphrase("correct horse".into())).unwrap();
+    assert_eq!(secrets.get(&conn, "xhs", "cookie").unwrap().as_deref(), Some("web_session=1"));
+
+    drop(conn);
+    cleanup(&paths);
+}
+
+#[test]
+fn test_rotate_key_reencrypts_credentials() {
+    let paths = temp_paths("rotate-key");
+    let mut conn = open_db(&paths.0).unwrap();
+    let secrets = SecretStore::unlock(&conn, &file_key(&paths.1)).unwrap();
+    secrets.add(&conn, "bilibili", "cookie", "SESSDATA=abc").unwrap();
+
+    let secrets = secrets.rotate_key(&mut conn, &KeySource::Passphrase("hunter2".into())).unwrap();
+    assert_eq!(secrets.get(&conn, "bilibili", "cookie").unwrap().as_deref(), Some("SESSDATA=abc"));
+    assert!(SecretStore::unlock(&conn, &file_key(&paths.1)).is_err());
+    let unlocked = SecretStore::unlock(&conn, &KeySource::Passphrase("hunter2".into())).unwrap();
+    assert_eq!(unlocked.get(&conn, "bilibili", "cookie").unwrap().as_deref(), Some("SESSDATA=abc"));
+
+    // And back to a stored key
+    let _ = std::fs::remove_file(&paths.1);
+    let secrets = unlocked.rotate_key(&mut conn, &file_key(&paths.1)).unwrap();
+    assert!(paths.1.exists());
+    assert_eq!(secrets.get(&conn, "bilibili", "cookie").unwrap().as_deref(), Some("SESSDATA=abc"));
+    assert!(SecretStore::unlock(&conn, &file_key(&paths.1)).is_ok());
+
+    drop(conn);
+    cleanup(&paths);
+}
+
+#[test]
+fn test_plaintext_cookies_are_migrated() {
+    let paths = temp_paths("migrate");
+    let conn = open_db(&paths.0).unwrap();
+    // Cookies as stored before they were encrypted
+    conn.execute_batch(
+        "CREATE TABLE platform_cookies (
+            platform TEXT PRIMARY KEY,
+            cookie TEXT NOT NULL,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        INSERT INTO platform_cookies (platform, cookie) VALUES ('weibo', 'SUB=legacy');",
+    )
+    .unwrap();
+
+    let secrets = SecretStore::unlock(&conn, &file_key(&paths.1)).unwrap();
+    let ctx = ParseContext::new();
+    assert_eq!(cookie_store::load_cookies(&conn, &secrets, &ctx).unwrap(), 1);
+    assert_eq!(ctx.headers("weibo").get(COOKIE).unwrap(), "SUB=legacy");
+    assert_eq!(secrets.get(&conn, "weibo", "cookie").unwrap().as_deref(), Some("SUB=legacy"));
+    let tables: i64 = conn
+        .query_row("SELECT COUNT(*) FROM sqlite_master WHERE name = 'platform_cookies'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(tables, 0);
+
+    drop(conn);
+    cleanup(&paths);
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use std::io::Write;
+
+use app_lib::downloader::download_converted;
+use app_lib::subtitles::{convert, parse_danmaku_xml, ExportFormat};
+use common::{fixture, temp_dir, MockResponse, MockServer};
+
+fn convert_fixture(name: &str, format: ExportFormat) -> String {
+    String::from_utf8(convert(fixture(name).as_bytes(), format).unwrap()).unwrap()
+}
+
+#[test]
+fn test_subtitle_to_srt_and_vtt() {
+    let srt = convert_fixture("bilibili/subtitle.json", ExportFormat::Srt);
+    assert_eq!(
+        srt,
+        "1\n00:00:00,500 --> 00:00:02,250\n大家好\n\n\
+         2\n00:00:02,250 --> 00:00:05,000\n欢迎来到\n字幕君交流场所\n\n\
+         3\n01:01:01,200 --> 01:01:03,000\n再见\n\n"
+    );
+
+    let vtt = convert_fixture("bilibili/subtitle.json", ExportFormat::Vtt);
+    assert!(vtt.starts_with("WEBVTT\n\n00:00:00.500 --> 00:00:02.250\n大家好\n\n"));
+    assert!(vtt.ends_with("01:01:01.200 --> 01:01:03.000\n再见\n\n"));
+
+    assert!(convert(b"<i></i>", ExportFormat::Srt).is_err());
+}
+
+#[test]
+fn test_danmaku_to_ass() {
+    let comments = parse_danmaku_xml(&fixture("bilibili/danmaku.xml"));
+    let times: Vec<f64> = comments.iter().map(|c| c.time).collect();
+    assert_eq!(times, [3.25, 3.5, 12.5, 20.0, 30.0]);
+    assert_eq!(comments[0].text, "前排 & <打卡>");
+
+    let ass = convert_fixture("bilibili/danmaku.xml", ExportFormat::Ass);
+    assert!(ass.starts_with("[Script Info]\n"));
+    assert!(ass.contains("PlayResX: 1920\nPlayResY: 1080\n"));
+    let events: Vec<&str> = ass.lines().filter(|l| l.starts_with("Dialogue:")).collect();
+    // The special (mode 7) comment is left out
+    assert_eq!(events.len(), 4);
+    assert!(events[0].starts_with("Dialogue: 0,0:00:03.25,0:00:11.25,Danmaku,,0,0,0,,{\\move("));
+    assert!(events[0].ends_with("}前排 & <打卡>"));
+    // Overlapping scrolling comments take separate lanes
+    assert!(events[1].contains(",54)\\fs72}\\{大字\\}"));
+    assert!(events[2].ends_with("{\\an8\\pos(960,0)\\c&H0000FF&}顶部红字"));
+    assert!(events[3].starts_with("Dialogue: 0,0:00:20.00,0:00:24.00,"));
+    assert!(events[3].ends_with("{\\an2\\pos(960,1080)\\c&H00FF00&}底部绿字"));
+}
+
+#[test]
+fn test_danmaku_deflate_input() {
+    let xml = fixture("bilibili/danmaku.xml");
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(xml.as_bytes()).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    assert_eq!(convert(&compressed, ExportFormat::Xml).unwrap(), xml.as_bytes());
+    assert_eq!(
+        convert(&compressed, ExportFormat::Ass).unwrap(),
+        convert(xml.as_bytes(), ExportFormat::Ass).unwrap()
+    );
+    assert!(convert(b"\x00\x01 not deflate", ExportFormat::Ass).is_err());
+}
+
+#[test]
+fn test_export_format_names() {
+    assert_eq!("WebVTT".parse::<ExportFormat>(), Ok(ExportFormat::Vtt));
+    assert_eq!("ass".parse::<ExportFormat>().unwrap().as_str(), "ass");
+    assert!("mp4".parse::<ExportFormat>().is_err());
+}
+
+#[tokio::test]
+async fn test_download_converted() {
+    let dir = temp_dir("download");
+    let server = MockServer::start().await;
+    server.mock("GET", "/subtitle.json", MockResponse::json(fixture("bilibili/subtitle.json")));
+
+    let path = dir.join("video.zh-CN.srt");
+    let size = download_converted(
+        &reqwest::Client::new(),
+        &server.url("/subtitle.json"),
+        &path,
+        ExportFormat::Srt,
+        |_, _| {},
+    )
+    .await
+    .unwrap();
+
+    let srt = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(size, srt.len() as u64);
+    assert!(srt.starts_with("1\n00:00:00,500 --> 00:00:02,250\n"));
+    // The file as served is not kept
+    let names: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(names, ["video.zh-CN.srt"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
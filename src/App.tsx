@@ -2,22 +2,31 @@ import { useState, useEffect } from "react";
 import { invoke, convertFileSrc } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { motion, AnimatePresence } from "framer-motion";
-import { Search, Loader2, Download, User, ImageIcon, Languages, Star, LogOut, Copy, Clock, Cloud, Sun, Moon, Monitor, Settings, ChevronDown, Heart, Eye, Share2 } from "lucide-react";
+import { Search, Loader2, Download, User, ImageIcon, Languages, Star, LogOut, Copy, Clock, Cloud, Sun, Moon, Monitor, Settings, ChevronDown, Heart, Eye, Share2, Coins, Cookie, History as HistoryIcon } from "lucide-react";
 import { save, open } from "@tauri-apps/plugin-dialog";
 import { useTranslation } from "react-i18next";
 import { PhotoProvider, PhotoView } from 'react-photo-view';
 import 'react-photo-view/dist/react-photo-view.css';
 import "./i18n";
 import Favorites from "./Favorites";
-import Login, { type UserInfo } from "./Login";
+import History from "./History";
+import Login, { type UserInfo, type Session } from "./Login";
 import Profile from "./Profile";
 import Downloads from "./Downloads";
+import Cookies from "./Cookies";
 
 
 export interface VideoQuality {
   quality: string;
   video_url: string;
   size?: number | null;
+  // DASH streams: video-only entries carry the audio to mux in
+  kind?: 'video' | 'audio';
+  audio_url?: string;
+  codec?: string;
+  width?: number;
+  height?: number;
+  bitrate?: number;
 }
 
 interface VideoStatistics {
@@ -26,6 +35,7 @@ interface VideoStatistics {
   favorites?: number;
   shares?: number;
   comments?: number;
+  coins?: number;
 }
 
 interface MusicInfo {
@@ -35,6 +45,22 @@ interface MusicInfo {
   cover_url: string;
 }
 
+interface VideoPart {
+  index: number;
+  title: string;
+  id: string;
+  duration: number;
+  url: string;
+  current?: boolean;
+}
+
+interface SubtitleTrack {
+  lang: string;
+  label: string;
+  url: string;
+  ai_generated?: boolean;
+}
+
 interface VideoParseInfo {
   video_url: string;
   cover_url: string;
@@ -51,13 +77,55 @@ interface VideoParseInfo {
   tags?: string[];
   music_info?: MusicInfo;
   create_time?: number;
+  duration?: number;
+  description?: string;
+  parts?: VideoPart[];
+  subtitles?: SubtitleTrack[];
+  danmaku_url?: string;
+  page_url?: string;
+}
+
+// A parse result cached with the link it came from
+interface ParsedItem {
+  id: number;
+  url: string;
+  info: VideoParseInfo;
+  parsed_at: string;
+}
+
+interface ParseError {
+  code: string;
+  detail: string;
+  retryable: boolean;
 }
 
 function App() {
   const { t, i18n } = useTranslation();
 
+  const formatParseError = (err: unknown): string => {
+    if (err && typeof err === "object" && "code" in err) {
+      const e = err as ParseError;
+      return `${t(`parse_error_${e.code}`)}: ${e.detail}`;
+    }
+    return String(err);
+  };
+
   // Auth state
   const [currentUser, setCurrentUser] = useState<UserInfo | null>(null);
+  // Session token passed to every command acting for the user
+  const [sessionToken, setSessionToken] = useState("");
+
+  // Restore a remembered session
+  useEffect(() => {
+    const token = localStorage.getItem("vp_session");
+    if (!token) return;
+    invoke<UserInfo>("current_user", { token })
+      .then((user) => {
+        setSessionToken(token);
+        setCurrentUser(user);
+      })
+      .catch(() => localStorage.removeItem("vp_session"));
+  }, []);
 
   // Theme state
   const [theme, setTheme] = useState<'light' | 'dark' | 'system'>(() => {
@@ -103,9 +171,11 @@ function App() {
 
   // Selected video quality
   const [selectedQualityUrl, setSelectedQualityUrl] = useState<string | null>(null);
+  const selectedQuality = result?.video_qualities?.find((q) => q.video_url === selectedQualityUrl);
 
   // Favorites state
   const [showFavorites, setShowFavorites] = useState(false);
+  const [showHistory, setShowHistory] = useState(false);
   const [isFavorited, setIsFavorited] = useState(false);
   const [favRefreshKey, setFavRefreshKey] = useState(0);
 
@@ -116,6 +186,7 @@ function App() {
   const [showProfile, setShowProfile] = useState(false);
   const [showUserMenu, setShowUserMenu] = useState(false);
   const [showSettingsMenu, setShowSettingsMenu] = useState(false);
+  const [showCookies, setShowCookies] = useState(false);
 
   // Time state
   const [currentTime, setCurrentTime] = useState(new Date());
@@ -195,58 +266,103 @@ function App() {
     }
   }, [result]);
 
-  const handleParse = async (urlArg?: string) => {
-    const targetUrl = urlArg || url;
-    if (!targetUrl) return;
-    setLoading(true);
+  const resetResult = () => {
     setError(null);
     setResult(null);
     setProxiedImages({});
     setProxiedAvatar(null);
     setIsFavorited(false);
+  };
+
+  const showResult = async (res: VideoParseInfo, targetUrl: string) => {
+    // Pre-select best quality if available, otherwise fallback to default video_url
+    const playable = res.video_qualities?.filter((q) => q.kind !== 'audio') ?? [];
+    if (playable.length > 0) {
+      setSelectedQualityUrl(playable[0].video_url);
+    } else {
+      setSelectedQualityUrl(res.video_url);
+    }
+
+    setResult(res);
+    setUrl(targetUrl);
+    // Check if this URL is already favorited
+    if (currentUser) {
+      const favorited = await invoke<boolean>("is_favorited", { token: sessionToken, url: targetUrl });
+      setIsFavorited(favorited);
+    }
+  };
+
+  const handleParse = async (urlArg?: string) => {
+    const targetUrl = urlArg || url;
+    if (!targetUrl) return;
+    setLoading(true);
+    resetResult();
 
     try {
-      const res = await invoke<VideoParseInfo>("parse_video", { url: targetUrl });
-      // Pre-select best quality if available, otherwise fallback to default video_url
-      if (res.video_qualities && res.video_qualities.length > 0) {
-        setSelectedQualityUrl(res.video_qualities[0].video_url);
-      } else {
-        setSelectedQualityUrl(res.video_url);
-      }
+      const res = await invoke<VideoParseInfo>("parse_video", { url: targetUrl, token: sessionToken || null });
+      await showResult(res, targetUrl);
+    } catch (err: any) {
+      console.error(err);
+      setError(formatParseError(err));
+    } finally {
+      setLoading(false);
+    }
+  };
 
-      setResult(res);
-      // Update input if called programmatically
-      if (urlArg) setUrl(targetUrl);
-      // Check if this URL is already favorited
-      if (currentUser) {
-        const favorited = await invoke<boolean>("is_favorited", { userId: currentUser.id, url: targetUrl });
-        setIsFavorited(favorited);
-      }
+  const handleReparse = async (id: number, input: string) => {
+    setLoading(true);
+    resetResult();
+    try {
+      const res = await invoke<VideoParseInfo>("reparse_history_entry", { token: sessionToken, id });
+      await showResult(res, input);
     } catch (err: any) {
       console.error(err);
-      setError(err as string);
+      setError(String(err));
     } finally {
       setLoading(false);
     }
   };
 
+  // Favorites, downloads and history reopen from their cached parse result, without
+  // going back to the network; links never cached are parsed again
+  const handleOpenSaved = async (targetUrl: string, parsedItemId: number | null) => {
+    if (parsedItemId == null) {
+      setUrl(targetUrl);
+      handleParse(targetUrl);
+      return;
+    }
+    resetResult();
+    try {
+      const item = await invoke<ParsedItem>("get_parsed_item", { token: sessionToken, id: parsedItemId });
+      await showResult(item.info, item.url);
+    } catch (err) {
+      console.error('Failed to open cached result:', err);
+      if (targetUrl) {
+        setUrl(targetUrl);
+        handleParse(targetUrl);
+      } else {
+        setError(String(err));
+      }
+    }
+  };
+
   const handleToggleFavorite = async () => {
     if (!result || !currentUser) return;
     const targetUrl = url;
     try {
       if (isFavorited) {
         // Need to get favorites and find the matching one to remove
-        const favs = await invoke<any[]>("get_favorites", { userId: currentUser.id, platform: null });
+        const favs = await invoke<any[]>("get_favorites", { token: sessionToken, platform: null });
         const match = favs.find((f: any) => f.url === targetUrl);
         if (match) {
-          await invoke("remove_favorite", { id: match.id });
+          await invoke("remove_favorite", { token: sessionToken, id: match.id });
           setIsFavorited(false);
           showToast(t('favorite_removed'), 'success');
           setFavRefreshKey((k) => k + 1);
         }
       } else {
         await invoke("add_favorite", {
-          userId: currentUser.id,
+          token: sessionToken,
           url: targetUrl,
           title: result.title || '',
           platform: result.platform || '',
@@ -280,7 +396,7 @@ function App() {
   };
 
 
-  const handleDownload = async (fileUrl: string, type: 'video' | 'image' | 'audio', index?: number) => {
+  const handleDownload = async (fileUrl: string, type: 'video' | 'image' | 'audio', index?: number, audioUrl?: string) => {
     try {
       // Auto-detect extension from URL for audio, fallback to defaults
       let ext = { video: 'mp4', image: 'jpeg', audio: 'mp3' }[type];
@@ -311,15 +427,15 @@ function App() {
 
       showToast(t('toast_downloading'), 'success');
 
-      // Do not await, let it run in the background
+      // Queued in the background; progress shows up in the downloads panel
       invoke('download_file', {
-        userId: currentUser?.id || 0,
+        token: sessionToken,
         url: fileUrl,
+        audioUrl: audioUrl ?? null,
         savePath,
         title: result?.title || '',
-        coverUrl: result?.cover_url || ''
-      }).then(() => {
-        showToast(t('toast_saved'), 'success');
+        coverUrl: result?.cover_url || '',
+        pageUrl: url
       }).catch((err) => {
         showToast(t('error_download', { error: err }), 'error');
       });
@@ -366,11 +482,12 @@ function App() {
         const savePath = `${selectedPath}${typeof selectedPath === 'string' && selectedPath.endsWith(separator) ? '' : separator}${filename}`;
 
         invoke('download_file', {
-          userId: currentUser?.id || 0,
+          token: sessionToken,
           url: img.url,
           savePath,
           title: result.title || `Image ${i + 1}`,
-          coverUrl: img.url
+          coverUrl: img.url,
+          pageUrl: url
         }).catch((err) => {
           console.error(`Failed to download image ${i}:`, err);
         });
@@ -384,6 +501,65 @@ function App() {
     }
   };
 
+  const handleDownloadParts = async () => {
+    if (!result?.parts?.length) return;
+    try {
+      const dir = await open({ directory: true, multiple: false });
+      if (!dir || typeof dir !== 'string') return;
+
+      showToast(t('toast_downloading'), 'success');
+      const current = result.parts.find((p) => p.current) ?? result.parts[0];
+      invoke('download_parts', {
+        token: sessionToken,
+        url: current.url,
+        dir,
+        quality: selectedQuality?.quality ?? null
+      }).catch((err) => {
+        showToast(t('error_download', { error: err }), 'error');
+      });
+      setShowDownloads(true);
+    } catch (err: any) {
+      console.error(err);
+      showToast(t('error_download', { error: err }), 'error');
+    }
+  };
+
+  // Subtitles are saved as srt/vtt, danmaku as ass/xml, converted by the backend
+  const handleDownloadExport = async (fileUrl: string, format: 'srt' | 'vtt' | 'ass' | 'xml', suffix: string) => {
+    try {
+      let safeTitle = result?.title ? result.title.replace(/[\\/:*?"<>|\r\n]/g, '').trim() : '';
+      if (safeTitle.length > 40) safeTitle = safeTitle.substring(0, 40);
+      const savePath = await save({
+        defaultPath: `${safeTitle || result?.platform || 'media'}.${suffix}.${format}`,
+        filters: [{ name: format.toUpperCase(), extensions: [format] }]
+      });
+      if (!savePath) return;
+
+      showToast(t('toast_downloading'), 'success');
+      invoke('download_export', {
+        token: sessionToken,
+        url: fileUrl,
+        format,
+        savePath,
+        title: result?.title || '',
+        coverUrl: result?.cover_url || '',
+        pageUrl: url
+      }).catch((err) => {
+        showToast(t('error_download', { error: err }), 'error');
+      });
+      setShowDownloads(true);
+    } catch (err: any) {
+      console.error(err);
+      showToast(t('error_download', { error: err }), 'error');
+    }
+  };
+
+  const formatDuration = (seconds: number) => {
+    const m = Math.floor(seconds / 60);
+    const s = seconds % 60;
+    return `${m}:${s.toString().padStart(2, '0')}`;
+  };
+
   const toggleLanguage = () => {
     // If current is Chinese (zh or zh-CN), switch to English
     const newLang = i18n.language.startsWith('zh') ? 'en' : 'zh';
@@ -391,6 +567,9 @@ function App() {
   };
 
   const handleLogout = () => {
+    invoke("logout", { token: sessionToken }).catch(() => { });
+    localStorage.removeItem("vp_session");
+    setSessionToken("");
     setCurrentUser(null);
     setResult(null);
     setUrl("");
@@ -399,7 +578,14 @@ function App() {
 
   // Show login page if not authenticated
   if (!currentUser) {
-    return <Login onLoginSuccess={(user) => setCurrentUser(user)} />;
+    return (
+      <Login
+        onLoginSuccess={(session: Session) => {
+          setSessionToken(session.token);
+          setCurrentUser(session.user);
+        }}
+      />
+    );
   }
 
   return (
@@ -434,6 +620,13 @@ function App() {
               <Download size={18} />
               <span className="hidden sm:inline">{t('downloads')}</span>
             </button>
+            <button
+              onClick={() => setShowHistory(true)}
+              className="bg-white dark:bg-gray-800 p-2 rounded-lg shadow-sm hover:bg-indigo-50 dark:hover:bg-gray-700 flex items-center gap-2 text-sm font-medium text-gray-600 dark:text-gray-300 hover:text-indigo-600 dark:hover:text-indigo-400 transition-colors cursor-pointer whitespace-nowrap"
+            >
+              <HistoryIcon size={18} />
+              <span className="hidden sm:inline">{t('history')}</span>
+            </button>
             <button
               onClick={() => setShowFavorites(true)}
               className="bg-white dark:bg-gray-800 p-2 rounded-lg shadow-sm hover:bg-amber-50 dark:hover:bg-gray-700 flex items-center gap-2 text-sm font-medium text-gray-600 dark:text-gray-300 hover:text-amber-600 dark:hover:text-amber-400 transition-colors cursor-pointer whitespace-nowrap"
@@ -479,6 +672,13 @@ function App() {
                           <Languages size={16} />
                           {i18n.language.startsWith('zh') ? 'English' : '中文'}
                         </button>
+                        <button
+                          onClick={() => { setShowCookies(true); setShowSettingsMenu(false); }}
+                          className="w-full text-left px-3 py-2 rounded-lg hover:bg-gray-50 dark:hover:bg-gray-700 flex items-center gap-3 text-sm transition-colors cursor-pointer"
+                        >
+                          <Cookie size={16} />
+                          {t('cookies')}
+                        </button>
                       </div>
                     </motion.div>
                   </>
@@ -616,6 +816,9 @@ function App() {
                         {new Date(result.create_time * 1000).toLocaleString(i18n.language.startsWith('zh') ? 'zh-CN' : 'en-US')}
                       </span>
                     )}
+                    {result.duration !== undefined && result.duration > 0 && (
+                      <span className="opacity-80">{formatDuration(result.duration)}</span>
+                    )}
                   </div>
                 </div>
               </div>
@@ -638,6 +841,11 @@ function App() {
                       <Star size={22} className={isFavorited ? 'fill-amber-500' : ''} />
                     </button>
                   </div>
+                  {result.description && result.description !== result.title && (
+                    <p className="text-gray-500 dark:text-gray-400 text-sm leading-relaxed whitespace-pre-wrap line-clamp-4">
+                      {result.description}
+                    </p>
+                  )}
                   {result.tags && result.tags.length > 0 && (
                     <div className="flex flex-wrap gap-2 mt-2">
                       {result.tags.map((tag, idx) => (
@@ -667,6 +875,12 @@ function App() {
                         <span>{result.statistics.favorites.toLocaleString()}</span>
                       </div>
                     )}
+                    {result.statistics.coins !== undefined && (
+                      <div className="flex items-center gap-1.5 bg-yellow-50 dark:bg-yellow-900/20 text-yellow-600 dark:text-yellow-400 px-3 py-1.5 rounded-full border border-yellow-100 dark:border-yellow-900/30 text-sm font-medium transition-colors">
+                        <Coins size={14} className="opacity-80" />
+                        <span>{result.statistics.coins.toLocaleString()}</span>
+                      </div>
+                    )}
                     {result.statistics.shares !== undefined && (
                       <div className="flex items-center gap-1.5 bg-emerald-50 dark:bg-emerald-900/20 text-emerald-600 dark:text-emerald-400 px-3 py-1.5 rounded-full border border-emerald-100 dark:border-emerald-900/30 text-sm font-medium transition-colors">
                         <Share2 size={14} className="opacity-80" />
@@ -722,7 +936,7 @@ function App() {
                         <video
                           {...({ referrerPolicy: "no-referrer" } as any)}
                           controls
-                          src={cachedVideo || selectedQualityUrl || result.video_url}
+                          src={cachedVideo || (selectedQuality?.audio_url ? result.video_url : selectedQualityUrl) || result.video_url}
                           className="w-full h-full object-contain cursor-pointer"
                           poster={result.cover_url}
                           onClick={(e) => e.currentTarget.requestFullscreen()}
@@ -736,9 +950,9 @@ function App() {
                               onChange={(e) => setSelectedQualityUrl(e.target.value)}
                               className="bg-gray-50 dark:bg-gray-700 border border-gray-300 dark:border-gray-600 text-gray-900 dark:text-gray-100 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full sm:w-auto p-2.5 outline-none shadow-sm cursor-pointer transition-colors"
                             >
-                              {result.video_qualities.map((q: VideoQuality, idx: number) => (
+                              {result.video_qualities.filter((q) => q.kind !== 'audio').map((q: VideoQuality, idx: number) => (
                                 <option key={idx} value={q.video_url}>
-                                  {q.quality} {q.size ? `(${((q.size) / (1024 * 1024)).toFixed(2)} MB)` : ''}
+                                  {q.quality} {q.codec && !q.quality.includes(q.codec) ? q.codec : ''} {q.size ? `(${((q.size) / (1024 * 1024)).toFixed(2)} MB)` : ''}
                                 </option>
                               ))}
                             </select>
@@ -755,7 +969,7 @@ function App() {
                             <span>{t('copy_link')}</span>
                           </button>
                           <button
-                            onClick={() => handleDownload(selectedQualityUrl || result.video_url, 'video')}
+                            onClick={() => handleDownload(selectedQualityUrl || result.video_url, 'video', undefined, selectedQuality?.audio_url)}
                             className="w-full sm:w-auto inline-flex items-center justify-center space-x-2 bg-blue-600 hover:bg-blue-700 text-white px-6 py-2.5 rounded-lg font-semibold transition-colors shadow-md cursor-pointer"
                           >
                             <Download size={18} />
@@ -766,6 +980,75 @@ function App() {
                     </div>
                   )}
 
+                  {/* Subtitle tracks and danmaku */}
+                  {(result.subtitles?.length || result.danmaku_url) && (
+                    <div className="space-y-3 pt-4 border-t border-gray-100 dark:border-gray-700">
+                      <h3 className="font-semibold text-gray-700 dark:text-gray-300">{t('subtitles_and_danmaku')}</h3>
+                      <div className="flex flex-wrap gap-2">
+                        {result.subtitles?.map((track) => (
+                          <div key={track.url} className="flex items-center gap-1">
+                            <span className="text-sm text-gray-600 dark:text-gray-400">
+                              {track.label}{track.ai_generated ? ` (${t('ai_generated')})` : ''}
+                            </span>
+                            {(['srt', 'vtt'] as const).map((format) => (
+                              <button
+                                key={format}
+                                onClick={() => handleDownloadExport(track.url, format, track.lang)}
+                                className="px-2 py-1 bg-blue-50 dark:bg-blue-900/40 hover:bg-blue-100 dark:hover:bg-blue-800/60 text-blue-600 dark:text-blue-400 border border-blue-200 dark:border-blue-800 rounded-md text-xs font-medium transition-colors cursor-pointer"
+                              >
+                                {format.toUpperCase()}
+                              </button>
+                            ))}
+                          </div>
+                        ))}
+                        {result.danmaku_url && (
+                          <div className="flex items-center gap-1">
+                            <span className="text-sm text-gray-600 dark:text-gray-400">{t('danmaku')}</span>
+                            {(['ass', 'xml'] as const).map((format) => (
+                              <button
+                                key={format}
+                                onClick={() => handleDownloadExport(result.danmaku_url!, format, 'danmaku')}
+                                className="px-2 py-1 bg-blue-50 dark:bg-blue-900/40 hover:bg-blue-100 dark:hover:bg-blue-800/60 text-blue-600 dark:text-blue-400 border border-blue-200 dark:border-blue-800 rounded-md text-xs font-medium transition-colors cursor-pointer"
+                              >
+                                {format.toUpperCase()}
+                              </button>
+                            ))}
+                          </div>
+                        )}
+                      </div>
+                    </div>
+                  )}
+
+                  {/* Parts of a multi-part video or collection */}
+                  {result.parts && result.parts.length > 0 && (
+                    <div className="space-y-3 pt-4 border-t border-gray-100 dark:border-gray-700">
+                      <div className="flex items-center justify-between">
+                        <h3 className="font-semibold text-gray-700 dark:text-gray-300">
+                          {t('parts')} ({result.parts.length})
+                        </h3>
+                        <button
+                          onClick={handleDownloadParts}
+                          className="flex items-center gap-2 px-3 py-1.5 bg-blue-50 dark:bg-blue-900/40 hover:bg-blue-100 dark:hover:bg-blue-800/60 text-blue-600 dark:text-blue-400 border border-blue-200 dark:border-blue-800 rounded-md text-sm font-medium transition-colors shadow-sm cursor-pointer"
+                        >
+                          <Download size={14} />
+                          <span>{t('download_all_parts')}</span>
+                        </button>
+                      </div>
+                      <div className="max-h-64 overflow-y-auto rounded-lg border border-gray-100 dark:border-gray-700 divide-y divide-gray-100 dark:divide-gray-700">
+                        {result.parts.map((part) => (
+                          <button
+                            key={part.url}
+                            onClick={() => !part.current && handleParse(part.url)}
+                            className={`w-full flex items-center justify-between gap-3 px-3 py-2 text-left text-sm transition-colors ${part.current ? 'bg-blue-50 dark:bg-blue-900/30 text-blue-600 dark:text-blue-400 font-medium' : 'text-gray-700 dark:text-gray-300 hover:bg-gray-50 dark:hover:bg-gray-800 cursor-pointer'}`}
+                          >
+                            <span className="truncate">P{part.index} {part.title}</span>
+                            <span className="shrink-0 text-xs text-gray-400">{formatDuration(part.duration)}</span>
+                          </button>
+                        ))}
+                      </div>
+                    </div>
+                  )}
+
                   {/* Image Gallery */}
                   {result.images.length > 0 && (
                     <div className="space-y-4 pt-4 border-t border-gray-100 dark:border-gray-700">
@@ -861,12 +1144,22 @@ function App() {
         <Favorites
           visible={showFavorites}
           onClose={() => setShowFavorites(false)}
-          onSelect={(selectedUrl: string) => {
-            setUrl(selectedUrl);
-            handleParse(selectedUrl);
+          onSelect={(selectedUrl: string, parsedItemId: number | null) => {
+            handleOpenSaved(selectedUrl, parsedItemId);
           }}
           refreshKey={favRefreshKey}
-          userId={currentUser.id}
+          token={sessionToken}
+        />
+      </AnimatePresence >
+
+      {/* History Panel */}
+      < AnimatePresence >
+        <History
+          visible={showHistory}
+          onClose={() => setShowHistory(false)}
+          onOpen={(input: string, parsedItemId: number | null) => handleOpenSaved(input, parsedItemId)}
+          onReparse={handleReparse}
+          token={sessionToken}
         />
       </AnimatePresence >
 
@@ -875,7 +1168,8 @@ function App() {
         <Downloads
           visible={showDownloads}
           onClose={() => setShowDownloads(false)}
-          userId={currentUser.id}
+          onOpen={(parsedItemId: number) => handleOpenSaved('', parsedItemId)}
+          token={sessionToken}
         />
       </AnimatePresence >
 
@@ -884,10 +1178,20 @@ function App() {
         <Profile
           visible={showProfile}
           user={currentUser}
+          token={sessionToken}
           onClose={() => setShowProfile(false)}
           onUpdated={(updatedUser: UserInfo) => setCurrentUser(updatedUser)}
         />
       </AnimatePresence >
+
+      {/* Login Cookies Modal */}
+      < AnimatePresence >
+        <Cookies
+          visible={showCookies}
+          onClose={() => setShowCookies(false)}
+          token={sessionToken}
+        />
+      </AnimatePresence >
     </div >
   );
 }
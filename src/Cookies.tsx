@@ -0,0 +1,265 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { motion, AnimatePresence } from "framer-motion";
+import { X, Cookie, Trash2, Upload, Loader2, Lock, KeyRound } from "lucide-react";
+import { useTranslation } from "react-i18next";
+
+interface StoredCookies {
+    platform: string;
+    names: string[];
+    updated_at: string;
+}
+
+interface CookiesProps {
+    visible: boolean;
+    onClose: () => void;
+    token: string;
+}
+
+const PLATFORMS = ["bilibili", "douyin", "xhs", "weibo", "kuaishou", "xigua", "pipixia"];
+
+export default function Cookies({ visible, onClose, token }: CookiesProps) {
+    const { t } = useTranslation();
+    const [stored, setStored] = useState<StoredCookies[]>([]);
+    const [text, setText] = useState("");
+    // Empty: detect from a cookies.txt export
+    const [platform, setPlatform] = useState("");
+    const [loading, setLoading] = useState(false);
+    const [message, setMessage] = useState<{ text: string; type: "success" | "error" } | null>(null);
+    // Secrets keyed by a passphrase stay locked until it is entered
+    const [locked, setLocked] = useState(false);
+    const [passphrase, setPassphrase] = useState("");
+
+    const loadCookies = () =>
+        invoke<StoredCookies[]>("get_cookies", { token })
+            .then((cookies) => {
+                setStored(cookies);
+                setLocked(false);
+            })
+            .catch((err) => {
+                if (String(err).includes("locked")) setLocked(true);
+                else setMessage({ text: String(err), type: "error" });
+            });
+
+    useEffect(() => {
+        if (!visible) return;
+        setMessage(null);
+        loadCookies();
+    }, [visible, token]);
+
+    const handleUnlock = async (e: React.FormEvent) => {
+        e.preventDefault();
+        setLoading(true);
+        setMessage(null);
+        try {
+            await invoke("unlock_secrets", { passphrase });
+            setPassphrase("");
+            await loadCookies();
+        } catch (err: any) {
+            setMessage({ text: String(err), type: "error" });
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    const handleSetPassphrase = async () => {
+        setMessage(null);
+        try {
+            await invoke("set_secrets_passphrase", { token, passphrase: passphrase || null });
+            setPassphrase("");
+            setMessage({ text: t(passphrase ? "passphrase_set" : "passphrase_removed"), type: "success" });
+        } catch (err: any) {
+            setMessage({ text: String(err), type: "error" });
+        }
+    };
+
+    const handleImport = async (e: React.FormEvent) => {
+        e.preventDefault();
+        if (!text.trim()) return;
+        setLoading(true);
+        setMessage(null);
+        try {
+            const updated = await invoke<StoredCookies[]>("import_cookies", { token, text, platform: platform || null });
+            setStored(updated);
+            setText("");
+            setMessage({ text: t("cookies_imported"), type: "success" });
+        } catch (err: any) {
+            setMessage({ text: String(err), type: "error" });
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    const handleDelete = async (name: string) => {
+        try {
+            await invoke("delete_cookies", { token, platform: name });
+            setStored((prev) => prev.filter((s) => s.platform !== name));
+        } catch (err: any) {
+            setMessage({ text: String(err), type: "error" });
+        }
+    };
+
+    if (!visible) return null;
+
+    return (
+        <motion.div
+            initial={{ opacity: 0 }}
+            animate={{ opacity: 1 }}
+            exit={{ opacity: 0 }}
+            className="fixed inset-0 z-50 flex items-center justify-center bg-black/40 backdrop-blur-sm"
+            onClick={onClose}
+        >
+            <motion.div
+                initial={{ opacity: 0, scale: 0.95, y: 20 }}
+                animate={{ opacity: 1, scale: 1, y: 0 }}
+                exit={{ opacity: 0, scale: 0.95, y: 20 }}
+                transition={{ type: "spring", duration: 0.4 }}
+                className="bg-white dark:bg-gray-800 rounded-2xl shadow-2xl w-[90vw] max-w-lg overflow-hidden transition-colors"
+                onClick={(e) => e.stopPropagation()}
+            >
+                {/* Header */}
+                <div className="flex items-center justify-between px-6 py-4 border-b border-gray-100 dark:border-gray-700 transition-colors">
+                    <h2 className="text-xl font-bold text-gray-800 dark:text-gray-100 transition-colors">{t("cookies")}</h2>
+                    <button
+                        onClick={onClose}
+                        className="p-2 rounded-lg hover:bg-gray-100 dark:hover:bg-gray-700 text-gray-500 dark:text-gray-400 transition-colors cursor-pointer"
+                    >
+                        <X size={20} />
+                    </button>
+                </div>
+
+                {locked ? (
+                    <form onSubmit={handleUnlock} className="p-6 space-y-3">
+                        <p className="flex items-center gap-2 text-sm text-gray-500 dark:text-gray-400">
+                            <Lock size={14} />
+                            {t("secrets_locked")}
+                        </p>
+                        <input
+                            type="password"
+                            value={passphrase}
+                            onChange={(e) => setPassphrase(e.target.value)}
+                            placeholder={t("passphrase")}
+                            className="w-full px-4 py-2.5 bg-gray-50 dark:bg-gray-900 border border-gray-200 dark:border-gray-700 rounded-xl outline-none focus:ring-2 focus:ring-blue-500 text-gray-800 dark:text-gray-100"
+                        />
+                        {message && (
+                            <div className="text-sm px-4 py-2.5 rounded-lg border text-red-500 dark:text-red-400 bg-red-50 dark:bg-red-900/30 border-red-100 dark:border-red-800">
+                                {message.text}
+                            </div>
+                        )}
+                        <button
+                            type="submit"
+                            disabled={loading || !passphrase}
+                            className="w-full bg-blue-600 hover:bg-blue-700 active:bg-blue-800 text-white font-semibold py-3 px-6 rounded-xl shadow-md shadow-blue-200 dark:shadow-none transition-all disabled:opacity-50 disabled:cursor-not-allowed flex items-center justify-center gap-2 cursor-pointer"
+                        >
+                            {loading ? <Loader2 className="animate-spin" size={20} /> : t("unlock")}
+                        </button>
+                    </form>
+                ) : (
+                <div className="p-6 space-y-5">
+                    {/* Stored platforms */}
+                    <div className="space-y-2">
+                        {stored.length === 0 ? (
+                            <p className="text-sm text-gray-400 dark:text-gray-500">{t("no_cookies")}</p>
+                        ) : (
+                            stored.map((s) => (
+                                <div
+                                    key={s.platform}
+                                    className="flex items-center justify-between gap-3 px-4 py-2.5 bg-gray-50 dark:bg-gray-900 border border-gray-200 dark:border-gray-700 rounded-xl"
+                                >
+                                    <div className="min-w-0">
+                                        <div className="flex items-center gap-2 font-medium text-gray-800 dark:text-gray-100">
+                                            <Cookie size={14} />
+                                            {s.platform}
+                                        </div>
+                                        <p className="text-xs text-gray-400 dark:text-gray-500 truncate">
+                                            {s.names.join(", ")} · {s.updated_at}
+                                        </p>
+                                    </div>
+                                    <button
+                                        onClick={() => handleDelete(s.platform)}
+                                        title={t("remove_cookies")}
+                                        className="p-2 rounded-lg hover:bg-red-50 dark:hover:bg-red-900/30 text-gray-400 hover:text-red-500 transition-colors cursor-pointer"
+                                    >
+                                        <Trash2 size={16} />
+                                    </button>
+                                </div>
+                            ))
+                        )}
+                    </div>
+
+                    {/* Import */}
+                    <form onSubmit={handleImport} className="space-y-3">
+                        <select
+                            value={platform}
+                            onChange={(e) => setPlatform(e.target.value)}
+                            className="w-full px-4 py-2.5 bg-gray-50 dark:bg-gray-900 border border-gray-200 dark:border-gray-700 rounded-xl outline-none focus:ring-2 focus:ring-blue-500 text-gray-800 dark:text-gray-100"
+                        >
+                            <option value="">{t("cookies_detect_platform")}</option>
+                            {PLATFORMS.map((p) => (
+                                <option key={p} value={p}>{p}</option>
+                            ))}
+                        </select>
+                        <textarea
+                            rows={5}
+                            value={text}
+                            onChange={(e) => setText(e.target.value)}
+                            placeholder={t("cookies_placeholder")}
+                            className="w-full px-4 py-3 bg-gray-50 dark:bg-gray-900 border border-gray-200 dark:border-gray-700 rounded-xl outline-none focus:ring-2 focus:ring-blue-500 focus:border-transparent transition-all placeholder-gray-400 dark:placeholder-gray-500 text-gray-800 dark:text-gray-100 font-mono text-xs"
+                        />
+
+                        <AnimatePresence>
+                            {message && (
+                                <motion.div
+                                    initial={{ opacity: 0, y: -5 }}
+                                    animate={{ opacity: 1, y: 0 }}
+                                    exit={{ opacity: 0, y: -5 }}
+                                    className={`text-sm px-4 py-2.5 rounded-lg border transition-colors ${message.type === "success"
+                                        ? "text-green-700 dark:text-green-400 bg-green-50 dark:bg-green-900/30 border-green-100 dark:border-green-800"
+                                        : "text-red-500 dark:text-red-400 bg-red-50 dark:bg-red-900/30 border-red-100 dark:border-red-800"
+                                        }`}
+                                >
+                                    {message.text}
+                                </motion.div>
+                            )}
+                        </AnimatePresence>
+
+                        <button
+                            type="submit"
+                            disabled={loading || !text.trim()}
+                            className="w-full bg-blue-600 hover:bg-blue-700 active:bg-blue-800 text-white font-semibold py-3 px-6 rounded-xl shadow-md shadow-blue-200 dark:shadow-none transition-all disabled:opacity-50 disabled:cursor-not-allowed flex items-center justify-center gap-2 cursor-pointer"
+                        >
+                            {loading ? (
+                                <Loader2 className="animate-spin" size={20} />
+                            ) : (
+                                <>
+                                    <Upload size={18} />
+                                    <span>{t("import_cookies")}</span>
+                                </>
+                            )}
+                        </button>
+                    </form>
+
+                    {/* Encryption passphrase; empty keeps the key in the keyring again */}
+                    <div className="flex gap-2 pt-4 border-t border-gray-100 dark:border-gray-700">
+                        <input
+                            type="password"
+                            value={passphrase}
+                            onChange={(e) => setPassphrase(e.target.value)}
+                            placeholder={t("passphrase_optional")}
+                            className="flex-1 min-w-0 px-4 py-2 bg-gray-50 dark:bg-gray-900 border border-gray-200 dark:border-gray-700 rounded-xl outline-none focus:ring-2 focus:ring-blue-500 text-sm text-gray-800 dark:text-gray-100"
+                        />
+                        <button
+                            type="button"
+                            onClick={handleSetPassphrase}
+                            className="flex items-center gap-1.5 px-4 py-2 rounded-xl text-sm border border-gray-200 dark:border-gray-700 text-gray-600 dark:text-gray-300 hover:bg-gray-100 dark:hover:bg-gray-700 transition-colors cursor-pointer"
+                        >
+                            <KeyRound size={14} />
+                            {t("set_passphrase")}
+                        </button>
+                    </div>
+                </div>
+                )}
+            </motion.div>
+        </motion.div>
+    );
+}
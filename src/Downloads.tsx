@@ -2,7 +2,7 @@ import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { motion, AnimatePresence } from "framer-motion";
-import { X, Trash2, FolderOpen, Play, Loader2, Download as DownloadIcon } from "lucide-react";
+import { X, Trash2, FolderOpen, Play, Pause, RotateCw, Loader2, FileSearch, Download as DownloadIcon } from "lucide-react";
 import { useTranslation } from "react-i18next";
 
 interface DownloadRecord {
@@ -15,7 +15,9 @@ interface DownloadRecord {
     status: string;
     total_size: number;
     downloaded_size: number;
+    parsed_item_id: number | null;
     created_at: string;
+    platform: string;
 }
 
 interface DownloadProgressPayload {
@@ -28,10 +30,12 @@ interface DownloadProgressPayload {
 interface DownloadsProps {
     visible: boolean;
     onClose: () => void;
-    userId: number;
+    // Shows the cached parse result a download came from
+    onOpen: (parsedItemId: number) => void;
+    token: string;
 }
 
-export default function Downloads({ visible, onClose, userId }: DownloadsProps) {
+export default function Downloads({ visible, onClose, onOpen, token }: DownloadsProps) {
     const { t } = useTranslation();
     const [downloads, setDownloads] = useState<DownloadRecord[]>([]);
     const [loading, setLoading] = useState(false);
@@ -58,7 +62,7 @@ export default function Downloads({ visible, onClose, userId }: DownloadsProps)
     const removeSelectedDownloads = async (delete_file: boolean) => {
         if (selectedIds.length === 0) return;
         try {
-            await Promise.all(selectedIds.map(id => invoke("remove_download_record", { id, deleteFile: delete_file })));
+            await Promise.all(selectedIds.map(id => invoke("remove_download_record", { token, id, deleteFile: delete_file })));
             setDownloads((prev) => prev.filter((dl) => !selectedIds.includes(dl.id)));
             setSelectedIds([]);
             setBatchDeleting(false);
@@ -72,15 +76,15 @@ export default function Downloads({ visible, onClose, userId }: DownloadsProps)
 
     // Poll for downloads on mount and when opening
     useEffect(() => {
-        if (visible && userId) {
+        if (visible && token) {
             fetchDownloads();
         }
-    }, [visible, userId]);
+    }, [visible, token]);
 
     const fetchDownloads = async () => {
         try {
             setLoading(true);
-            const res = await invoke<DownloadRecord[]>("get_downloads", { userId });
+            const res = await invoke<DownloadRecord[]>("get_downloads", { token });
             setDownloads(res);
         } catch (err) {
             console.error("Failed to fetch downloads:", err);
@@ -148,11 +152,11 @@ export default function Downloads({ visible, onClose, userId }: DownloadsProps)
         return () => {
             unlistenPromise.then((unlisten) => unlisten());
         };
-    }, [userId]); // Rebind if user changes
+    }, [token]); // Rebind if user changes
 
     const removeDownload = async (id: number, delete_file: boolean) => {
         try {
-            await invoke("remove_download_record", { id, deleteFile: delete_file });
+            await invoke("remove_download_record", { token, id, deleteFile: delete_file });
             setDownloads((prev) => prev.filter((dl) => dl.id !== id));
             setDeletingId(null);
         } catch (err) {
@@ -160,6 +164,15 @@ export default function Downloads({ visible, onClose, userId }: DownloadsProps)
         }
     };
 
+    // Status changes come back through download://progress
+    const controlDownload = async (command: string, id: number) => {
+        try {
+            await invoke(command, { token, id });
+        } catch (err) {
+            console.error(`Failed to ${command}:`, err);
+        }
+    };
+
     const openFile = async (path: string) => {
         try {
             await invoke("open_path", { path });
@@ -256,6 +269,9 @@ export default function Downloads({ visible, onClose, userId }: DownloadsProps)
                                 const isDownloading = dl.status === "downloading";
                                 const isFailed = dl.status === "failed";
                                 const isCompleted = dl.status === "completed";
+                                const isQueued = dl.status === "queued";
+                                const isPaused = dl.status === "paused";
+                                const isCancelled = dl.status === "cancelled";
                                 const progress =
                                     dl.total_size > 0
                                         ? Math.round((dl.downloaded_size / dl.total_size) * 100)
@@ -345,10 +361,10 @@ export default function Downloads({ visible, onClose, userId }: DownloadsProps)
                                                 </h3>
                                                 <div className="flex items-center gap-2 text-xs mb-3">
                                                     <span className={`px-2 py-0.5 rounded-full font-medium transition-colors ${isCompleted ? 'bg-green-100 dark:bg-green-900/30 text-green-700 dark:text-green-400' :
-                                                        isFailed ? 'bg-red-100 dark:bg-red-900/30 text-red-700 dark:text-red-400' :
+                                                        isFailed || isCancelled ? 'bg-red-100 dark:bg-red-900/30 text-red-700 dark:text-red-400' :
                                                             'bg-blue-100 dark:bg-blue-900/30 text-blue-700 dark:text-blue-400'
                                                         }`}>
-                                                        {t(isCompleted ? 'completed' : isFailed ? 'failed' : 'downloading')}
+                                                        {t(dl.status)}
                                                     </span>
                                                     <span className="text-gray-500 dark:text-gray-400 transition-colors">
                                                         {new Date(dl.created_at).toLocaleString()}
@@ -390,6 +406,60 @@ export default function Downloads({ visible, onClose, userId }: DownloadsProps)
                                                             <FolderOpen size={14} />
                                                             {t('open_folder')}
                                                         </button>
+                                                        {dl.parsed_item_id != null && (
+                                                            <button
+                                                                onClick={() => {
+                                                                    onOpen(dl.parsed_item_id!);
+                                                                    onClose();
+                                                                }}
+                                                                className="text-sm flex items-center gap-1.5 text-gray-600 dark:text-gray-300 hover:text-gray-800 dark:hover:text-gray-100 font-medium bg-gray-100 dark:bg-gray-700 px-3 py-1.5 rounded-lg transition-colors cursor-pointer"
+                                                            >
+                                                                <FileSearch size={14} />
+                                                                {t('open_details')}
+                                                            </button>
+                                                        )}
+                                                    </div>
+                                                )}
+
+                                                {/* Queue controls */}
+                                                {(isDownloading || isQueued || isPaused || isFailed || isCancelled) && (
+                                                    <div className="flex items-center gap-3 mt-3">
+                                                        {(isDownloading || isQueued) && (
+                                                            <button
+                                                                onClick={() => controlDownload("pause_download", dl.id)}
+                                                                className="text-sm flex items-center gap-1.5 text-gray-600 dark:text-gray-300 hover:text-gray-800 dark:hover:text-gray-100 font-medium bg-gray-100 dark:bg-gray-700 px-3 py-1.5 rounded-lg transition-colors cursor-pointer"
+                                                            >
+                                                                <Pause size={14} />
+                                                                {t('pause')}
+                                                            </button>
+                                                        )}
+                                                        {isPaused && (
+                                                            <button
+                                                                onClick={() => controlDownload("resume_download", dl.id)}
+                                                                className="text-sm flex items-center gap-1.5 text-blue-600 dark:text-blue-400 hover:text-blue-700 dark:hover:text-blue-300 font-medium bg-blue-50 dark:bg-blue-900/30 px-3 py-1.5 rounded-lg transition-colors cursor-pointer"
+                                                            >
+                                                                <Play size={14} />
+                                                                {t('resume')}
+                                                            </button>
+                                                        )}
+                                                        {(isFailed || isCancelled) && (
+                                                            <button
+                                                                onClick={() => controlDownload("retry_download", dl.id)}
+                                                                className="text-sm flex items-center gap-1.5 text-blue-600 dark:text-blue-400 hover:text-blue-700 dark:hover:text-blue-300 font-medium bg-blue-50 dark:bg-blue-900/30 px-3 py-1.5 rounded-lg transition-colors cursor-pointer"
+                                                            >
+                                                                <RotateCw size={14} />
+                                                                {t('retry')}
+                                                            </button>
+                                                        )}
+                                                        {(isDownloading || isQueued || isPaused) && (
+                                                            <button
+                                                                onClick={() => controlDownload("cancel_download", dl.id)}
+                                                                className="text-sm flex items-center gap-1.5 text-red-600 dark:text-red-400 hover:text-red-700 dark:hover:text-red-300 font-medium bg-red-50 dark:bg-red-900/30 px-3 py-1.5 rounded-lg transition-colors cursor-pointer"
+                                                            >
+                                                                <X size={14} />
+                                                                {t('cancel')}
+                                                            </button>
+                                                        )}
                                                     </div>
                                                 )}
 
@@ -1,7 +1,7 @@
 import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { motion, AnimatePresence } from "framer-motion";
-import { Star, Trash2, ExternalLink, X } from "lucide-react";
+import { Star, Trash2, ExternalLink, Search, X } from "lucide-react";
 import { useTranslation } from "react-i18next";
 
 interface Favorite {
@@ -11,9 +11,16 @@ interface Favorite {
   platform: string;
   cover_url: string;
   author_name: string;
+  parsed_item_id: number | null;
   created_at: string;
 }
 
+// A favorite found by `search_library`
+interface SearchHit extends Favorite {
+  kind: string;
+  score: number;
+}
+
 const PLATFORMS = [
   { key: "all", i18nKey: "platform_all" },
   { key: "douyin", i18nKey: "platform_douyin" },
@@ -38,31 +45,48 @@ const PLATFORM_COLORS: Record<string, string> = {
 interface FavoritesProps {
   visible: boolean;
   onClose: () => void;
-  onSelect: (url: string) => void;
+  onSelect: (url: string, parsedItemId: number | null) => void;
   refreshKey?: number;
-  userId: number;
+  token: string;
 }
 
-export default function Favorites({ visible, onClose, onSelect, refreshKey, userId }: FavoritesProps) {
+export default function Favorites({ visible, onClose, onSelect, refreshKey, token }: FavoritesProps) {
   const { t } = useTranslation();
   const [favorites, setFavorites] = useState<Favorite[]>([]);
   const [activePlatform, setActivePlatform] = useState("all");
   const [loading, setLoading] = useState(false);
+  const [query, setQuery] = useState("");
+  const [debouncedQuery, setDebouncedQuery] = useState("");
+
+  useEffect(() => {
+    const timer = setTimeout(() => setDebouncedQuery(query.trim()), 300);
+    return () => clearTimeout(timer);
+  }, [query]);
 
   const loadFavorites = useCallback(async () => {
     setLoading(true);
+    const platform = activePlatform === "all" ? null : activePlatform;
     try {
-      const data = await invoke<Favorite[]>("get_favorites", {
-        userId: userId,
-        platform: activePlatform === "all" ? null : activePlatform,
-      });
-      setFavorites(data);
+      if (debouncedQuery) {
+        // Searches titles, authors and the tags and description of the cached parse, best matches first
+        const page = await invoke<{ hits: SearchHit[]; total: number }>("search_library", {
+          token,
+          query: debouncedQuery,
+          filters: { kinds: ["favorite"], platform },
+          limit: 100,
+          offset: 0,
+        });
+        setFavorites(page.hits);
+      } else {
+        const data = await invoke<Favorite[]>("get_favorites", { token, platform });
+        setFavorites(data);
+      }
     } catch (err) {
       console.error("Failed to load favorites:", err);
     } finally {
       setLoading(false);
     }
-  }, [activePlatform]);
+  }, [activePlatform, debouncedQuery, token]);
 
   useEffect(() => {
     if (visible) {
@@ -73,15 +97,15 @@ export default function Favorites({ visible, onClose, onSelect, refreshKey, user
   const handleRemove = async (id: number, e: React.MouseEvent) => {
     e.stopPropagation();
     try {
-      await invoke("remove_favorite", { id });
+      await invoke("remove_favorite", { token, id });
       setFavorites((prev) => prev.filter((f) => f.id !== id));
     } catch (err) {
       console.error("Failed to remove favorite:", err);
     }
   };
 
-  const handleSelect = (url: string) => {
-    onSelect(url);
+  const handleSelect = (fav: Favorite) => {
+    onSelect(fav.url, fav.parsed_item_id);
     onClose();
   };
 
@@ -121,6 +145,19 @@ export default function Favorites({ visible, onClose, onSelect, refreshKey, user
           </button>
         </div>
 
+        {/* Search */}
+        <div className="px-4 pt-3 transition-colors">
+          <div className="relative">
+            <Search size={16} className="absolute left-3 top-1/2 -translate-y-1/2 text-gray-400" />
+            <input
+              value={query}
+              onChange={(e) => setQuery(e.target.value)}
+              placeholder={t("favorites_search_placeholder")}
+              className="w-full pl-9 pr-3 py-2 rounded-lg bg-gray-100 dark:bg-gray-700 text-sm text-gray-800 dark:text-gray-100 outline-none focus:ring-2 focus:ring-blue-500 transition-colors"
+            />
+          </div>
+        </div>
+
         {/* Platform Tabs */}
         <div className="px-4 py-3 border-b border-gray-100 dark:border-gray-700 overflow-x-auto transition-colors">
           <div className="flex gap-2 min-w-max">
@@ -148,8 +185,8 @@ export default function Favorites({ visible, onClose, onSelect, refreshKey, user
           ) : favorites.length === 0 ? (
             <div className="text-center py-16 text-gray-400">
               <Star size={48} className="mx-auto mb-4 opacity-30" />
-              <p className="text-lg font-medium">{t("no_favorites")}</p>
-              <p className="text-sm mt-1">{t("no_favorites_hint")}</p>
+              <p className="text-lg font-medium">{debouncedQuery ? t("favorites_no_matches") : t("no_favorites")}</p>
+              {!debouncedQuery && <p className="text-sm mt-1">{t("no_favorites_hint")}</p>}
             </div>
           ) : (
             <AnimatePresence>
@@ -160,7 +197,7 @@ export default function Favorites({ visible, onClose, onSelect, refreshKey, user
                   initial={{ opacity: 0, y: 10 }}
                   animate={{ opacity: 1, y: 0 }}
                   exit={{ opacity: 0, x: -100 }}
-                  onClick={() => handleSelect(fav.url)}
+                  onClick={() => handleSelect(fav)}
                   className="flex items-center gap-4 p-4 bg-gray-50 dark:bg-gray-800/50 hover:bg-blue-50 dark:hover:bg-gray-700 rounded-xl border border-gray-100 dark:border-gray-700 hover:border-blue-200 dark:hover:border-gray-600 cursor-pointer transition-all group"
                 >
                   {/* Cover thumbnail */}
@@ -0,0 +1,230 @@
+import { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { motion, AnimatePresence } from "framer-motion";
+import { History as HistoryIcon, Trash2, RotateCw, Search, X } from "lucide-react";
+import { useTranslation } from "react-i18next";
+
+interface HistoryEntry {
+  id: number;
+  input: string;
+  url: string;
+  platform: string;
+  title: string;
+  author_name: string;
+  parsed_item_id: number | null;
+  created_at: string;
+}
+
+interface HistoryPage {
+  entries: HistoryEntry[];
+  total: number;
+}
+
+const PAGE_SIZE = 20;
+
+interface HistoryProps {
+  visible: boolean;
+  onClose: () => void;
+  // Shows the entry's cached result, parsing `input` again when there is none
+  onOpen: (input: string, parsedItemId: number | null) => void;
+  onReparse: (id: number, input: string) => void;
+  refreshKey?: number;
+  token: string;
+}
+
+export default function History({ visible, onClose, onOpen, onReparse, refreshKey, token }: HistoryProps) {
+  const { t } = useTranslation();
+  const [entries, setEntries] = useState<HistoryEntry[]>([]);
+  const [total, setTotal] = useState(0);
+  const [query, setQuery] = useState("");
+  const [debouncedQuery, setDebouncedQuery] = useState("");
+  const [loading, setLoading] = useState(false);
+
+  useEffect(() => {
+    const timer = setTimeout(() => setDebouncedQuery(query.trim()), 300);
+    return () => clearTimeout(timer);
+  }, [query]);
+
+  const loadPage = useCallback(async (offset: number) => {
+    setLoading(true);
+    try {
+      const page = await invoke<HistoryPage>("get_history", {
+        token,
+        query: debouncedQuery || null,
+        limit: PAGE_SIZE,
+        offset,
+      });
+      setEntries((prev) => (offset === 0 ? page.entries : [...prev, ...page.entries]));
+      setTotal(page.total);
+    } catch (err) {
+      console.error("Failed to load history:", err);
+    } finally {
+      setLoading(false);
+    }
+  }, [debouncedQuery, token]);
+
+  useEffect(() => {
+    if (visible) {
+      loadPage(0);
+    }
+  }, [visible, loadPage, refreshKey]);
+
+  const handleRemove = async (id: number, e: React.MouseEvent) => {
+    e.stopPropagation();
+    try {
+      await invoke("remove_history_entry", { token, id });
+      setEntries((prev) => prev.filter((entry) => entry.id !== id));
+      setTotal((n) => n - 1);
+    } catch (err) {
+      console.error("Failed to remove history entry:", err);
+    }
+  };
+
+  const handleClear = async () => {
+    if (!window.confirm(t("history_clear_confirm"))) return;
+    try {
+      await invoke("clear_history", { token });
+      setEntries([]);
+      setTotal(0);
+    } catch (err) {
+      console.error("Failed to clear history:", err);
+    }
+  };
+
+  const handleOpen = (entry: HistoryEntry) => {
+    onOpen(entry.input, entry.parsed_item_id);
+    onClose();
+  };
+
+  const handleReparse = (entry: HistoryEntry, e: React.MouseEvent) => {
+    e.stopPropagation();
+    onReparse(entry.id, entry.input);
+    onClose();
+  };
+
+  if (!visible) return null;
+
+  return (
+    <motion.div
+      initial={{ opacity: 0 }}
+      animate={{ opacity: 1 }}
+      exit={{ opacity: 0 }}
+      className="fixed inset-0 z-50 flex items-center justify-center bg-black/40 backdrop-blur-sm"
+      onClick={onClose}
+    >
+      <motion.div
+        initial={{ opacity: 0, scale: 0.95, y: 20 }}
+        animate={{ opacity: 1, scale: 1, y: 0 }}
+        exit={{ opacity: 0, scale: 0.95, y: 20 }}
+        transition={{ type: "spring", duration: 0.4 }}
+        className="bg-white dark:bg-gray-800 rounded-2xl shadow-2xl w-[90vw] max-w-2xl max-h-[80vh] flex flex-col overflow-hidden transition-colors"
+        onClick={(e) => e.stopPropagation()}
+      >
+        {/* Header */}
+        <div className="flex items-center justify-between px-6 py-4 border-b border-gray-100 dark:border-gray-700 transition-colors">
+          <h2 className="text-xl font-bold text-gray-800 dark:text-gray-100 flex items-center gap-2 transition-colors">
+            <HistoryIcon size={22} className="text-indigo-500" />
+            {t("history_title")}
+          </h2>
+          <div className="flex items-center gap-2">
+            {entries.length > 0 && (
+              <button
+                onClick={handleClear}
+                className="px-3 py-1.5 rounded-lg text-sm font-medium text-red-500 hover:bg-red-50 dark:hover:bg-red-900/30 transition-colors cursor-pointer"
+              >
+                {t("history_clear")}
+              </button>
+            )}
+            <button
+              onClick={onClose}
+              className="p-2 rounded-lg hover:bg-gray-100 dark:hover:bg-gray-700 text-gray-500 dark:text-gray-400 transition-colors cursor-pointer"
+            >
+              <X size={20} />
+            </button>
+          </div>
+        </div>
+
+        {/* Search */}
+        <div className="px-4 py-3 border-b border-gray-100 dark:border-gray-700 transition-colors">
+          <div className="relative">
+            <Search size={16} className="absolute left-3 top-1/2 -translate-y-1/2 text-gray-400" />
+            <input
+              value={query}
+              onChange={(e) => setQuery(e.target.value)}
+              placeholder={t("history_search_placeholder")}
+              className="w-full pl-9 pr-3 py-2 rounded-lg bg-gray-100 dark:bg-gray-700 text-sm text-gray-800 dark:text-gray-100 outline-none focus:ring-2 focus:ring-indigo-500 transition-colors"
+            />
+          </div>
+        </div>
+
+        {/* History List */}
+        <div className="flex-1 overflow-y-auto p-4 space-y-3">
+          {loading && entries.length === 0 ? (
+            <div className="flex items-center justify-center py-16">
+              <div className="w-8 h-8 border-4 border-blue-200 border-t-blue-600 rounded-full animate-spin" />
+            </div>
+          ) : entries.length === 0 ? (
+            <div className="text-center py-16 text-gray-400">
+              <HistoryIcon size={48} className="mx-auto mb-4 opacity-30" />
+              <p className="text-lg font-medium">{debouncedQuery ? t("history_no_matches") : t("no_history")}</p>
+            </div>
+          ) : (
+            <>
+              <AnimatePresence>
+                {entries.map((entry) => (
+                  <motion.div
+                    key={entry.id}
+                    layout
+                    initial={{ opacity: 0, y: 10 }}
+                    animate={{ opacity: 1, y: 0 }}
+                    exit={{ opacity: 0, x: -100 }}
+                    onClick={() => handleOpen(entry)}
+                    className="flex items-center gap-4 p-4 bg-gray-50 dark:bg-gray-800/50 hover:bg-blue-50 dark:hover:bg-gray-700 rounded-xl border border-gray-100 dark:border-gray-700 hover:border-blue-200 dark:hover:border-gray-600 cursor-pointer transition-all group"
+                  >
+                    <div className="flex-1 min-w-0">
+                      <p className="text-sm font-semibold text-gray-800 dark:text-gray-200 truncate transition-colors">
+                        {entry.title || entry.url}
+                      </p>
+                      <p className="text-xs text-gray-400 dark:text-gray-500 truncate mt-0.5" title={entry.input}>
+                        {entry.url}
+                      </p>
+                      <div className="flex items-center gap-2 mt-1 text-xs text-gray-500 dark:text-gray-400">
+                        <span className="font-semibold">{entry.platform}</span>
+                        {entry.author_name && <span className="truncate">{entry.author_name}</span>}
+                        <span className="ml-auto whitespace-nowrap">{entry.created_at}</span>
+                      </div>
+                    </div>
+
+                    <button
+                      onClick={(e) => handleReparse(entry, e)}
+                      className="p-2 rounded-lg text-gray-400 dark:text-gray-500 hover:text-blue-500 dark:hover:text-blue-400 hover:bg-blue-50 dark:hover:bg-blue-900/30 transition-colors opacity-0 group-hover:opacity-100 flex-shrink-0 cursor-pointer"
+                      title={t("history_reparse")}
+                    >
+                      <RotateCw size={18} />
+                    </button>
+                    <button
+                      onClick={(e) => handleRemove(entry.id, e)}
+                      className="p-2 rounded-lg text-gray-400 dark:text-gray-500 hover:text-red-500 dark:hover:text-red-400 hover:bg-red-50 dark:hover:bg-red-900/30 transition-colors opacity-0 group-hover:opacity-100 flex-shrink-0 cursor-pointer"
+                      title={t("history_remove")}
+                    >
+                      <Trash2 size={18} />
+                    </button>
+                  </motion.div>
+                ))}
+              </AnimatePresence>
+              {entries.length < total && (
+                <button
+                  onClick={() => loadPage(entries.length)}
+                  disabled={loading}
+                  className="w-full py-2 rounded-lg text-sm font-medium text-gray-600 dark:text-gray-300 bg-gray-100 dark:bg-gray-700 hover:bg-gray-200 dark:hover:bg-gray-600 transition-colors cursor-pointer disabled:opacity-50"
+                >
+                  {t("history_load_more", { count: total - entries.length })}
+                </button>
+              )}
+            </>
+          )}
+        </div>
+      </motion.div>
+    </motion.div>
+  );
+}
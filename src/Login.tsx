@@ -1,7 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { motion, AnimatePresence } from "framer-motion";
-import { User, Mail, Lock, ArrowRight, Loader2, Languages, ArrowLeft } from "lucide-react";
+import { User, Mail, Lock, ArrowRight, Loader2, Languages, ArrowLeft, KeyRound } from "lucide-react";
 import { useTranslation } from "react-i18next";
 
 interface UserInfo {
@@ -10,8 +10,16 @@ interface UserInfo {
     email: string;
 }
 
+interface Session {
+    token: string;
+    user: UserInfo;
+    expires_at: string;
+    // Set when a new recovery code was issued; shown once
+    recovery_code: string | null;
+}
+
 interface LoginProps {
-    onLoginSuccess: (user: UserInfo) => void;
+    onLoginSuccess: (session: Session) => void;
 }
 
 type Mode = "login" | "register" | "forgot";
@@ -23,21 +31,32 @@ export default function Login({ onLoginSuccess }: LoginProps) {
     const [password, setPassword] = useState("");
     const [email, setEmail] = useState("");
     const [newPassword, setNewPassword] = useState("");
+    const [recoveryCode, setRecoveryCode] = useState("");
+    // A recovery code to show before going on, and the session to go on with
+    const [issued, setIssued] = useState<{ code: string; session: Session | null } | null>(null);
     const [loading, setLoading] = useState(false);
     const [error, setError] = useState<string | null>(null);
     const [success, setSuccess] = useState<string | null>(null);
     const [rememberMe, setRememberMe] = useState(false);
 
     useEffect(() => {
+        // Passwords used to be remembered in plain text
+        localStorage.removeItem("vp_password");
         const savedUsername = localStorage.getItem("vp_username");
-        const savedPassword = localStorage.getItem("vp_password");
-        if (savedUsername && savedPassword) {
+        if (savedUsername) {
             setUsername(savedUsername);
-            setPassword(savedPassword);
             setRememberMe(true);
         }
     }, []);
 
+    const finish = (session: Session) => {
+        if (session.recovery_code) {
+            setIssued({ code: session.recovery_code, session });
+        } else {
+            onLoginSuccess(session);
+        }
+    };
+
     const toggleLanguage = () => {
         const newLang = i18n.language.startsWith("zh") ? "en" : "zh";
         i18n.changeLanguage(newLang);
@@ -55,15 +74,15 @@ export default function Login({ onLoginSuccess }: LoginProps) {
             }
             setLoading(true);
             try {
-                const user = await invoke<UserInfo>("login", { username, password });
+                const session = await invoke<Session>("login", { username, password });
                 if (rememberMe) {
                     localStorage.setItem("vp_username", username);
-                    localStorage.setItem("vp_password", password);
+                    localStorage.setItem("vp_session", session.token);
                 } else {
                     localStorage.removeItem("vp_username");
-                    localStorage.removeItem("vp_password");
+                    localStorage.removeItem("vp_session");
                 }
-                onLoginSuccess(user);
+                finish(session);
             } catch (err: any) {
                 const errStr = String(err);
                 if (errStr.includes("Invalid")) {
@@ -81,8 +100,8 @@ export default function Login({ onLoginSuccess }: LoginProps) {
             }
             setLoading(true);
             try {
-                const user = await invoke<UserInfo>("register", { username, password, email });
-                onLoginSuccess(user);
+                const session = await invoke<Session>("register", { username, password, email });
+                finish(session);
             } catch (err: any) {
                 const errStr = String(err);
                 if (errStr.includes("already exists")) {
@@ -94,17 +113,19 @@ export default function Login({ onLoginSuccess }: LoginProps) {
                 setLoading(false);
             }
         } else if (mode === "forgot") {
-            if (!username.trim() || !email.trim() || !newPassword.trim()) {
+            if (!username.trim() || !recoveryCode.trim() || !newPassword.trim()) {
                 setError(t("fields_required"));
                 return;
             }
             setLoading(true);
             try {
-                await invoke("reset_password", { username, email, newPassword });
+                const code = await invoke<string>("reset_password", { username, recoveryCode, newPassword });
                 setMode("login");
                 setSuccess(t("reset_success"));
+                setIssued({ code, session: null });
                 // Clear fields
                 setNewPassword("");
+                setRecoveryCode("");
                 setPassword("");
             } catch (err: any) {
                 const errStr = String(err);
@@ -181,6 +202,30 @@ export default function Login({ onLoginSuccess }: LoginProps) {
 
                 {/* Card */}
                 <div className="bg-white dark:bg-gray-800 rounded-2xl shadow-xl shadow-gray-200/50 dark:shadow-none border border-gray-100 dark:border-gray-700 p-8 transition-colors duration-300">
+                    {issued ? (
+                        <div className="space-y-4">
+                            <h2 className="text-xl font-bold text-gray-800 dark:text-gray-100 transition-colors">{t("recovery_code")}</h2>
+                            {success && (
+                                <p className="text-green-700 dark:text-green-400 text-sm">{success}</p>
+                            )}
+                            <p className="text-sm text-gray-500 dark:text-gray-400">{t("recovery_code_hint")}</p>
+                            <div className="px-4 py-3 bg-gray-50 dark:bg-gray-900 border border-gray-200 dark:border-gray-700 rounded-xl font-mono text-lg text-center tracking-wider text-gray-800 dark:text-gray-100 select-all">
+                                {issued.code}
+                            </div>
+                            <button
+                                onClick={() => {
+                                    const session = issued.session;
+                                    setIssued(null);
+                                    if (session) onLoginSuccess(session);
+                                }}
+                                className="w-full bg-blue-600 hover:bg-blue-700 active:bg-blue-800 text-white font-semibold py-3 px-6 rounded-xl shadow-md shadow-blue-200 dark:shadow-none transition-all flex items-center justify-center gap-2 cursor-pointer"
+                            >
+                                <span>{t("recovery_code_saved")}</span>
+                                <ArrowRight size={18} />
+                            </button>
+                        </div>
+                    ) : (
+                    <>
                     <AnimatePresence mode="wait">
                         <motion.div
                             key={mode}
@@ -217,9 +262,9 @@ export default function Login({ onLoginSuccess }: LoginProps) {
                                     />
                                 </div>
 
-                                {/* Email (register + forgot) */}
+                                {/* Email (register) */}
                                 <AnimatePresence>
-                                    {(mode === "register" || mode === "forgot") && (
+                                    {mode === "register" && (
                                         <motion.div
                                             initial={{ opacity: 0, height: 0 }}
                                             animate={{ opacity: 1, height: "auto" }}
@@ -270,6 +315,20 @@ export default function Login({ onLoginSuccess }: LoginProps) {
                                     </div>
                                 )}
 
+                                {/* Recovery code (forgot mode) */}
+                                {mode === "forgot" && (
+                                    <div className="relative">
+                                        <KeyRound size={18} className="absolute left-3.5 top-1/2 -translate-y-1/2 text-gray-400" />
+                                        <input
+                                            type="text"
+                                            placeholder={t("recovery_code")}
+                                            value={recoveryCode}
+                                            onChange={(e) => setRecoveryCode(e.target.value)}
+                                            className="w-full pl-11 pr-4 py-3 bg-gray-50 dark:bg-gray-900 border border-gray-200 dark:border-gray-700 rounded-xl outline-none focus:ring-2 focus:ring-blue-500 focus:border-transparent transition-all placeholder-gray-400 dark:placeholder-gray-500 text-gray-800 dark:text-gray-100 font-mono"
+                                        />
+                                    </div>
+                                )}
+
                                 {/* New password (forgot mode) */}
                                 {mode === "forgot" && (
                                     <div className="relative">
@@ -356,10 +415,12 @@ export default function Login({ onLoginSuccess }: LoginProps) {
                             </button>
                         </div>
                     )}
+                    </>
+                    )}
                 </div>
             </motion.div>
         </div>
     );
 }
 
-export type { UserInfo };
+export type { UserInfo, Session };
@@ -13,11 +13,12 @@ interface UserInfo {
 interface ProfileProps {
     visible: boolean;
     user: UserInfo;
+    token: string;
     onClose: () => void;
     onUpdated: (user: UserInfo) => void;
 }
 
-export default function Profile({ visible, user, onClose, onUpdated }: ProfileProps) {
+export default function Profile({ visible, user, token, onClose, onUpdated }: ProfileProps) {
     const { t } = useTranslation();
     const [newUsername, setNewUsername] = useState("");
     const [newPassword, setNewPassword] = useState("");
@@ -39,7 +40,7 @@ export default function Profile({ visible, user, onClose, onUpdated }: ProfilePr
         setLoading(true);
         try {
             const updated = await invoke<UserInfo>("update_profile", {
-                id: user.id,
+                token,
                 newUsername: hasUsername ? newUsername.trim() : null,
                 newPassword: hasPassword ? newPassword.trim() : null,
             });